@@ -0,0 +1,23 @@
+//! Compares the allocation-free precheck with decoding the hex just to size it
+//!
+//! Run with `cargo bench --bench precheck`.
+
+use bitcoin_nostr_relay::validation::precheck_hex;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn bench_precheck(c: &mut Criterion) {
+    let mut group = c.benchmark_group("precheck");
+    for byte_len in [250, 10_000, 400_000] {
+        let tx_hex = "ab".repeat(byte_len);
+        group.bench_with_input(BenchmarkId::new("single_pass", byte_len), &tx_hex, |b, tx_hex| {
+            b.iter(|| precheck_hex(black_box(tx_hex)))
+        });
+        group.bench_with_input(BenchmarkId::new("decode", byte_len), &tx_hex, |b, tx_hex| {
+            b.iter(|| hex::decode(black_box(tx_hex)).map(|bytes| bytes.len()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_precheck);
+criterion_main!(benches);
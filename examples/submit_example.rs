@@ -0,0 +1,91 @@
+use bitcoin_nostr_relay::{RelaySubmitClient, Result};
+use std::io::Read;
+use std::time::Duration;
+
+const USAGE: &str = "\
+Submit a raw transaction to a running bitcoin-nostr relay
+
+Usage: cargo run --example submit_example -- [OPTIONS] [TX_HEX]
+
+Reads the transaction hex from TX_HEX, from --file, or from stdin when neither is given.
+
+Options:
+  --relay <URL>      Relay WebSocket URL [default: ws://127.0.0.1:7779]
+  --file <PATH>      Read the transaction hex from a file (\"-\" for stdin)
+  --timeout <SECS>   Seconds to wait for the relay's response [default: 10]";
+
+struct Args {
+    relay_url: String,
+    timeout: Duration,
+    tx_hex: String,
+}
+
+fn parse_args() -> std::result::Result<Args, String> {
+    let mut relay_url = "ws://127.0.0.1:7779".to_string();
+    let mut timeout = Duration::from_secs(10);
+    let mut file = None;
+    let mut tx_hex = None;
+    
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--relay" => relay_url = args.next().ok_or("--relay needs a URL")?,
+            "--file" => file = Some(args.next().ok_or("--file needs a path")?),
+            "--timeout" => {
+                let secs = args.next().ok_or("--timeout needs a number of seconds")?;
+                timeout = Duration::from_secs(secs.parse().map_err(|_| format!("invalid timeout: {}", secs))?);
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if tx_hex.is_none() && file.is_none() => tx_hex = Some(arg),
+            _ => return Err(format!("unexpected argument: {}\n\n{}", arg, USAGE)),
+        }
+    }
+    
+    let tx_hex = match (tx_hex, file.as_deref()) {
+        (Some(tx_hex), None) => tx_hex,
+        (None, Some(path)) if path != "-" => {
+            std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?
+        }
+        (None, _) => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input).map_err(|e| format!("failed to read stdin: {}", e))?;
+            input
+        }
+        (Some(_), Some(_)) => return Err("give either TX_HEX or --file, not both".to_string()),
+    };
+    let tx_hex = tx_hex.trim().to_string();
+    if tx_hex.is_empty() {
+        return Err(format!("no transaction given\n\n{}", USAGE));
+    }
+    
+    Ok(Args { relay_url, timeout, tx_hex })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    println!("Connecting to {}", args.relay_url);
+    let client = RelaySubmitClient::connect(&args.relay_url).await?.with_timeout(args.timeout);
+    
+    println!("Submitting {}-byte transaction", args.tx_hex.len() / 2);
+    let response = client.submit(&args.tx_hex).await?;
+    
+    if response.success {
+        println!("✅ Accepted: {} (txid {})", response.message, response.txid);
+    } else {
+        println!("❌ Rejected: {}", response.message);
+        if !response.txid.is_empty() {
+            println!("   txid {}", response.txid);
+        }
+        std::process::exit(1);
+    }
+    
+    Ok(())
+}
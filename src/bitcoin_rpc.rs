@@ -1,9 +1,250 @@
 use crate::error::BitcoinRpcError;
+use crate::relay::{BitcoinNodeConfig, HttpPoolConfig};
 use crate::Result;
-use bitcoin::{Block, BlockHash};
+use bitcoin::{Block, BlockHash, OutPoint, Txid};
+use futures_util::future::BoxFuture;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+/// JSON-RPC error code for an unknown method
+const RPC_METHOD_NOT_FOUND: i32 = -32601;
+
+/// Bitcoin Core error code for an invalid address or key (also used for unknown transactions)
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+
+/// Verbose `getrawtransaction` result with block context
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawTxInfo {
+    pub txid: String,
+    /// Witness transaction ID
+    pub hash: String,
+    pub hex: String,
+    pub size: usize,
+    pub vsize: usize,
+    pub weight: u64,
+    pub version: i32,
+    pub locktime: u32,
+    /// Number of confirmations (absent for mempool transactions)
+    pub confirmations: Option<u64>,
+    pub blockhash: Option<String>,
+    pub blocktime: Option<u64>,
+    pub vin: Vec<RawTxInput>,
+    pub vout: Vec<RawTxOutput>,
+}
+
+/// Decoded transaction input from a verbose `getrawtransaction` result
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawTxInput {
+    /// Previous output txid (absent for coinbase inputs)
+    pub txid: Option<String>,
+    pub vout: Option<u32>,
+    /// Coinbase script hex (coinbase inputs only)
+    pub coinbase: Option<String>,
+    pub sequence: u32,
+    #[serde(default)]
+    pub txinwitness: Vec<String>,
+}
+
+/// Decoded transaction output from a verbose `getrawtransaction` result
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawTxOutput {
+    /// Output value in BTC
+    pub value: f64,
+    pub n: u32,
+    #[serde(rename = "scriptPubKey")]
+    pub script_pub_key: RawScriptPubKey,
+}
+
+/// Decoded output script from a verbose `getrawtransaction` result
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawScriptPubKey {
+    pub hex: String,
+    #[serde(rename = "type")]
+    pub script_type: String,
+    pub address: Option<String>,
+}
+
+/// `getrawmempool false true` result: mempool txids and the sequence number they were read at
+///
+/// The sequence number changes whenever a transaction enters or leaves the mempool.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MempoolSequence {
+    pub txids: Vec<String>,
+    pub mempool_sequence: u64,
+}
+
+/// `getmempoolentry` result
+#[derive(Debug, Clone, Deserialize)]
+pub struct MempoolEntry {
+    pub vsize: usize,
+    pub weight: Option<u64>,
+    /// Time the transaction entered the mempool (seconds since epoch)
+    pub time: u64,
+    pub ancestorcount: u64,
+    pub descendantcount: u64,
+    pub fees: MempoolEntryFees,
+    /// Unconfirmed parent transactions in the mempool
+    pub depends: Vec<String>,
+    #[serde(rename = "spentby", default)]
+    pub spent_by: Vec<String>,
+}
+
+/// Fee information of a `getmempoolentry` result, in BTC
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MempoolEntryFees {
+    pub base: f64,
+    pub modified: f64,
+    pub ancestor: f64,
+    pub descendant: f64,
+}
+
+/// `getnetworkinfo` result
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NetworkInfo {
+    pub version: u64,
+    pub subversion: String,
+    /// Number of p2p peers, inbound and outbound
+    pub connections: u32,
+    /// Absent before Bitcoin Core 21
+    #[serde(default)]
+    pub connections_in: Option<u32>,
+    #[serde(default)]
+    pub connections_out: Option<u32>,
+    /// False when p2p networking was disabled with `setnetworkactive`
+    #[serde(rename = "networkactive")]
+    pub network_active: bool,
+}
+
+/// Header fields of a `getblock` verbosity 1 result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeaderInfo {
+    pub hash: BlockHash,
+    pub height: u64,
+    pub version: i32,
+    /// Absent for the genesis block
+    pub previous_block_hash: Option<BlockHash>,
+    pub merkle_root: String,
+    /// Block timestamp (seconds since epoch)
+    pub time: u64,
+    /// Number of confirmations, -1 if the block is not on the main chain
+    pub confirmations: i64,
+}
+
+/// `submitpackage` result
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageResult {
+    /// "success" when the whole package was accepted, otherwise the package-level failure
+    #[serde(rename = "package_msg", alias = "package-msg", default)]
+    pub package_msg: String,
+    /// Per-transaction results keyed by wtxid
+    #[serde(rename = "tx-results")]
+    pub tx_results: HashMap<String, PackageTxResult>,
+    #[serde(rename = "replaced-transactions", default)]
+    pub replaced_transactions: Vec<String>,
+}
+
+/// Result for one transaction of a submitted package
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageTxResult {
+    pub txid: String,
+    /// Wtxid of a same-txid transaction already in the mempool, if this one was not used
+    #[serde(rename = "other-wtxid")]
+    pub other_wtxid: Option<String>,
+    pub vsize: Option<usize>,
+    pub fees: Option<PackageTxFees>,
+    /// Why this transaction was rejected
+    pub error: Option<String>,
+}
+
+/// Fee information of a package transaction, in BTC
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageTxFees {
+    pub base: f64,
+    /// Feerate (BTC/kvB) the transaction was evaluated at, possibly with other package members
+    #[serde(rename = "effective-feerate")]
+    pub effective_feerate: Option<f64>,
+    /// Wtxids of the transactions whose fees and vsizes are included in the effective feerate
+    #[serde(rename = "effective-includes", default)]
+    pub effective_includes: Vec<String>,
+}
+
+/// Fee estimation mode for `estimatesmartfee`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EstimateMode {
+    /// The node's default mode
+    #[default]
+    Unset,
+    /// Responds faster to short-term drops in fee rates
+    Economical,
+    /// Considers a longer history, less likely to underpay
+    Conservative,
+}
+
+impl EstimateMode {
+    /// Mode name as accepted by `estimatesmartfee`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstimateMode::Unset => "unset",
+            EstimateMode::Economical => "economical",
+            EstimateMode::Conservative => "conservative",
+        }
+    }
+}
+
+/// `estimatesmartfee` result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    /// Estimated fee rate in BTC/kvB, `None` when the node lacks data (e.g. on regtest)
+    #[serde(rename = "feerate", default)]
+    pub fee_rate: Option<f64>,
+    /// Confirmation target the estimate was found for
+    pub blocks: u32,
+    /// Why no estimate is available
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+impl FeeEstimate {
+    /// Estimated fee rate in sat/vB
+    pub fn sat_per_vbyte(&self) -> Option<f64> {
+        self.fee_rate.map(|btc_per_kvb| btc_per_kvb * 100_000_000.0 / 1000.0)
+    }
+}
+
+impl PackageResult {
+    /// Whether every transaction of the package was accepted
+    pub fn is_success(&self) -> bool {
+        self.package_msg == "success" && self.tx_results.values().all(|r| r.error.is_none())
+    }
+    
+    /// Txids of the transactions that were rejected, with their errors
+    pub fn rejected(&self) -> Vec<(&str, &str)> {
+        let mut rejected: Vec<(&str, &str)> = self
+            .tx_results
+            .values()
+            .filter_map(|r| Some((r.txid.as_str(), r.error.as_deref()?)))
+            .collect();
+        rejected.sort();
+        rejected
+    }
+}
+
+impl RawTxInfo {
+    /// Whether the transaction is included in a block
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmations.unwrap_or(0) > 0
+    }
+}
+
+/// `User-Agent` sent with every RPC request
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
 #[derive(Clone)]
 pub struct BitcoinRpcClient {
@@ -11,6 +252,10 @@ pub struct BitcoinRpcClient {
     url: String,
     username: String,
     password: String,
+    user_agent: String,
+    pool: HttpPoolConfig,
+    /// Shared across clones so request ids stay unique per client
+    next_id: Arc<AtomicU64>,
 }
 
 impl BitcoinRpcClient {
@@ -20,13 +265,37 @@ impl BitcoinRpcClient {
             url,
             username,
             password,
+            user_agent: USER_AGENT.to_string(),
+            pool: HttpPoolConfig::default(),
+            next_id: Arc::new(AtomicU64::new(1)),
         }
     }
     
+    /// Create a client whose HTTP connection pool uses the given settings
+    pub fn with_pool(url: String, username: String, password: String, pool: HttpPoolConfig) -> Result<Self> {
+        let client = pool.apply(Client::builder())?.build()?;
+        Ok(Self {
+            client,
+            pool,
+            ..Self::new(url, username, password)
+        })
+    }
+    
+    /// Connection pool settings the HTTP client was built with
+    pub fn pool_config(&self) -> &HttpPoolConfig {
+        &self.pool
+    }
+    
+    /// Identify requests from this relay in `bitcoind`'s debug log via the `User-Agent`
+    pub fn with_relay_id(mut self, relay_id: &str) -> Self {
+        self.user_agent = format!("{} (relay {})", USER_AGENT, relay_id);
+        self
+    }
+    
     async fn rpc_call(&self, method: &str, params: &Value) -> Result<Value> {
         let request = json!({
             "jsonrpc": "2.0",
-            "id": 1,
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
             "method": method,
             "params": params
         });
@@ -35,6 +304,7 @@ impl BitcoinRpcClient {
             .client
             .post(&self.url)
             .basic_auth(&self.username, Some(&self.password))
+            .header(reqwest::header::USER_AGENT, &self.user_agent)
             .json(&request)
             .send()
             .await?
@@ -43,7 +313,7 @@ impl BitcoinRpcClient {
         
         if let Some(error) = response.get("error") {
             if !error.is_null() {
-                return Err(BitcoinRpcError::request_failed(format!("RPC error: {}", error)).into());
+                return Err(rpc_error(error).into());
             }
         }
         
@@ -57,52 +327,425 @@ impl BitcoinRpcClient {
         let result = self.rpc_call("getbestblockhash", &json!([])).await?;
         let hash_str = result
             .as_str()
-            .ok_or_else(|| BitcoinRpcError::InvalidResponse)?;
+            .ok_or(BitcoinRpcError::InvalidResponse)?;
+        BlockHash::from_str(hash_str).map_err(|e| BitcoinRpcError::request_failed(format!("Failed to parse block hash: {}", e)).into())
+    }
+    
+    /// Height of the most-work fully validated chain
+    pub async fn get_block_count(&self) -> Result<u64> {
+        let result = self.rpc_call("getblockcount", &json!([])).await?;
+        result.as_u64().ok_or_else(|| BitcoinRpcError::InvalidResponse.into())
+    }
+    
+    /// Hash of the best-chain block at `height`
+    pub async fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        let result = self.rpc_call("getblockhash", &json!([height])).await?;
+        let hash_str = result
+            .as_str()
+            .ok_or(BitcoinRpcError::InvalidResponse)?;
         BlockHash::from_str(hash_str).map_err(|e| BitcoinRpcError::request_failed(format!("Failed to parse block hash: {}", e)).into())
     }
     
+    /// Get a block's header fields and txids without downloading and decoding the whole block
+    pub async fn get_block_txids(&self, block_hash: &BlockHash) -> Result<(BlockHeaderInfo, Vec<Txid>)> {
+        let result = self
+            .rpc_call("getblock", &json!([block_hash.to_string(), 1]))
+            .await?;
+        parse_block_txids(result)
+    }
+    
     pub async fn get_block(&self, block_hash: &BlockHash) -> Result<Block> {
         let result = self
             .rpc_call("getblock", &json!([block_hash.to_string(), 0]))
             .await?;
         let block_hex = result
             .as_str()
-            .ok_or_else(|| BitcoinRpcError::InvalidResponse)?;
+            .ok_or(BitcoinRpcError::InvalidResponse)?;
         let block_bytes = hex::decode(block_hex)?;
         bitcoin::consensus::deserialize(&block_bytes)
             .map_err(|e| BitcoinRpcError::request_failed(format!("Failed to deserialize block: {}", e)).into())
     }
 
+    /// Get the chain name reported by `getblockchaininfo` (e.g. "main", "regtest")
+    pub async fn get_chain(&self) -> Result<String> {
+        let result = self.rpc_call("getblockchaininfo", &json!([])).await?;
+        result
+            .get("chain")
+            .and_then(Value::as_str)
+            .map(|s| s.to_string())
+            .ok_or_else(|| BitcoinRpcError::InvalidResponse.into())
+    }
+
+    /// Get the node's p2p networking state, including its number of peer connections
+    pub async fn get_network_info(&self) -> Result<NetworkInfo> {
+        let result = self.rpc_call("getnetworkinfo", &json!([])).await?;
+        serde_json::from_value(result).map_err(|_| BitcoinRpcError::InvalidResponse.into())
+    }
+
     pub async fn send_raw_transaction(&self, tx_hex: &str) -> Result<String> {
         let result = self.rpc_call("sendrawtransaction", &json!([tx_hex])).await?;
         result
             .as_str()
-            .ok_or_else(|| BitcoinRpcError::InvalidResponse)
+            .ok_or(BitcoinRpcError::InvalidResponse)
             .map(|s| s.to_string())
             .map_err(|e| e.into())
     }
 
     pub async fn get_raw_mempool(&self) -> Result<Vec<String>> {
         let result = self.rpc_call("getrawmempool", &json!([])).await?;
-        let txids: Vec<String> = result
+        result
             .as_array()
-            .unwrap_or(&vec![])
+            .ok_or(BitcoinRpcError::InvalidResponse)?
             .iter()
-            .map(|v| v.as_str().unwrap_or("").to_string())
-            .collect();
-        Ok(txids)
+            .map(|v| v.as_str().map(|s| s.to_string()).ok_or_else(|| BitcoinRpcError::InvalidResponse.into()))
+            .collect()
     }
 
+    /// Get the mempool txids along with the mempool sequence number
+    /// 
+    /// Requires Bitcoin Core 0.21 or newer.
+    pub async fn get_raw_mempool_sequence(&self) -> Result<MempoolSequence> {
+        let result = self.rpc_call("getrawmempool", &json!([false, true])).await?;
+        serde_json::from_value(result).map_err(|_| BitcoinRpcError::InvalidResponse.into())
+    }
+
+    /// Get the raw hex of a transaction
+    /// 
+    /// Without `-txindex` only mempool transactions can be found; confirmed ones yield
+    /// `BitcoinRpcError::TxIndexRequired`. A null or empty result is an error rather
+    /// than an empty transaction.
     pub async fn get_raw_transaction(&self, txid: &str) -> Result<String> {
-        let result = self.rpc_call("getrawtransaction", &json!([txid])).await?;
-        result
-            .as_str()
-            .ok_or_else(|| BitcoinRpcError::InvalidResponse)
-            .map(|s| s.to_string())
-            .map_err(|e| e.into())
+        let result = self
+            .rpc_call("getrawtransaction", &json!([txid]))
+            .await
+            .map_err(|e| map_tx_lookup_error(e, txid))?;
+        match result.as_str() {
+            Some(tx_hex) if !tx_hex.is_empty() => Ok(tx_hex.to_string()),
+            _ => Err(BitcoinRpcError::InvalidResponse.into()),
+        }
+    }
+    
+    /// Estimate the fee rate needed to confirm within `conf_target` blocks
+    /// 
+    /// A node without enough fee data (always the case on regtest) returns an
+    /// estimate with no `fee_rate` and the reason in `errors`.
+    pub async fn estimate_smart_fee(&self, conf_target: u16, mode: EstimateMode) -> Result<FeeEstimate> {
+        let result = self
+            .rpc_call("estimatesmartfee", &json!([conf_target, mode.as_str()]))
+            .await?;
+        serde_json::from_value(result).map_err(|_| BitcoinRpcError::InvalidResponse.into())
+    }
+    
+    /// Get mempool data (ancestors, fees) for a transaction in the mempool
+    pub async fn get_mempool_entry(&self, txid: &str) -> Result<MempoolEntry> {
+        let result = self.rpc_call("getmempoolentry", &json!([txid])).await?;
+        serde_json::from_value(result).map_err(|_| BitcoinRpcError::InvalidResponse.into())
+    }
+    
+    /// Submit a package of related transactions (parents first) for atomic acceptance
+    /// 
+    /// Requires Bitcoin Core 26 or newer; older nodes yield `BitcoinRpcError::UnsupportedMethod`.
+    pub async fn submit_package(&self, tx_hexes: &[String]) -> Result<PackageResult> {
+        let result = self
+            .rpc_call("submitpackage", &json!([tx_hexes]))
+            .await
+            .map_err(|e| map_unsupported_method(e, "submitpackage", "requires Bitcoin Core 26 or newer"))?;
+        parse_package_result(result)
+    }
+    
+    /// Find the mempool transactions spending each outpoint, in order (`None` where nothing spends it)
+    /// 
+    /// Requires Bitcoin Core 24 or newer; older nodes yield `BitcoinRpcError::UnsupportedMethod`.
+    pub async fn get_tx_spending_prevout(&self, outpoints: &[OutPoint]) -> Result<Vec<Option<String>>> {
+        let prevouts: Vec<Value> = outpoints
+            .iter()
+            .map(|outpoint| json!({"txid": outpoint.txid.to_string(), "vout": outpoint.vout}))
+            .collect();
+        let result = self
+            .rpc_call("gettxspendingprevout", &json!([prevouts]))
+            .await
+            .map_err(|e| map_unsupported_method(e, "gettxspendingprevout", "requires Bitcoin Core 24 or newer"))?;
+        parse_spending_txids(result, outpoints.len())
+    }
+    
+    /// Get a decoded transaction with confirmation and block context
+    /// 
+    /// Confirmed transactions can only be looked up this way when the node runs with
+    /// `-txindex`; otherwise `BitcoinRpcError::TxIndexRequired` is returned and the
+    /// caller should retry with [`get_raw_transaction_verbose_in_block`](Self::get_raw_transaction_verbose_in_block).
+    pub async fn get_raw_transaction_verbose(&self, txid: &str) -> Result<RawTxInfo> {
+        let result = self
+            .rpc_call("getrawtransaction", &json!([txid, true]))
+            .await
+            .map_err(|e| map_tx_lookup_error(e, txid))?;
+        parse_raw_tx_info(result)
+    }
+    
+    /// Get a decoded transaction from a known block (works without `-txindex`)
+    pub async fn get_raw_transaction_verbose_in_block(&self, txid: &str, block_hash: &BlockHash) -> Result<RawTxInfo> {
+        let result = self
+            .rpc_call("getrawtransaction", &json!([txid, true, block_hash.to_string()]))
+            .await?;
+        parse_raw_tx_info(result)
     }
 }
 
+/// A further node transactions are fanned out to, labelled for logging
+pub type AdditionalNode = (String, Arc<dyn BitcoinRpc>);
+
+/// Build clients for the configured additional nodes
+pub fn additional_node_clients(nodes: &[BitcoinNodeConfig], pool: &HttpPoolConfig, relay_id: &str) -> Result<Vec<AdditionalNode>> {
+    nodes
+        .iter()
+        .map(|node| {
+            let client = BitcoinRpcClient::with_pool(
+                node.url.clone(),
+                node.auth.username.clone(),
+                node.auth.password().to_string(),
+                pool.clone(),
+            )?
+            .with_relay_id(relay_id);
+            Ok((node.url.clone(), Arc::new(client) as Arc<dyn BitcoinRpc>))
+        })
+        .collect()
+}
+
+/// Submit a transaction to the primary node and every additional node concurrently
+/// 
+/// Succeeds if any node accepts the transaction. Rejections from individual nodes are
+/// logged; when every node rejects, the primary node's error is returned.
+pub async fn send_raw_transaction_to_all(primary: &dyn BitcoinRpc, additional: &[AdditionalNode], tx_hex: &str) -> Result<String> {
+    if additional.is_empty() {
+        return primary.send_raw_transaction(tx_hex).await;
+    }
+    
+    let (primary_result, additional_results) = futures_util::future::join(
+        primary.send_raw_transaction(tx_hex),
+        futures_util::future::join_all(additional.iter().map(|(_, node)| node.send_raw_transaction(tx_hex))),
+    ).await;
+    
+    let mut accepted = None;
+    for ((label, _), result) in additional.iter().zip(additional_results) {
+        match result {
+            Ok(txid) => {
+                accepted.get_or_insert(txid);
+            }
+            Err(e) => warn!("Bitcoin node {} rejected transaction: {}", label, e),
+        }
+    }
+    
+    match (primary_result, accepted) {
+        (Ok(txid), _) => Ok(txid),
+        (Err(e), Some(txid)) => {
+            warn!("Primary Bitcoin node rejected transaction {}: {}", txid, e);
+            Ok(txid)
+        }
+        (Err(e), None) => Err(e),
+    }
+}
+
+/// Bitcoin node RPC surface used by the relay
+/// 
+/// Implemented by [`BitcoinRpcClient`] for real nodes and by `MockBitcoinRpc`
+/// (behind the `testing` feature) for tests. Methods added after the first release
+/// have default bodies failing with `BitcoinRpcError::UnsupportedMethod`, so existing
+/// implementors keep compiling.
+pub trait BitcoinRpc: Send + Sync {
+    fn get_best_block_hash(&self) -> BoxFuture<'_, Result<BlockHash>>;
+    fn get_block<'a>(&'a self, block_hash: &'a BlockHash) -> BoxFuture<'a, Result<Block>>;
+    fn get_chain(&self) -> BoxFuture<'_, Result<String>>;
+    fn send_raw_transaction<'a>(&'a self, tx_hex: &'a str) -> BoxFuture<'a, Result<String>>;
+    fn get_raw_mempool(&self) -> BoxFuture<'_, Result<Vec<String>>>;
+    fn get_raw_transaction<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<String>>;
+    fn get_raw_transaction_verbose<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<RawTxInfo>>;
+    fn get_mempool_entry<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<MempoolEntry>>;
+    fn submit_package<'a>(&'a self, tx_hexes: &'a [String]) -> BoxFuture<'a, Result<PackageResult>>;
+    
+    fn get_block_count(&self) -> BoxFuture<'_, Result<u64>> {
+        not_implemented("getblockcount")
+    }
+    
+    fn get_block_hash(&self, _height: u64) -> BoxFuture<'_, Result<BlockHash>> {
+        not_implemented("getblockhash")
+    }
+    
+    fn estimate_smart_fee(&self, _conf_target: u16, _mode: EstimateMode) -> BoxFuture<'_, Result<FeeEstimate>> {
+        not_implemented("estimatesmartfee")
+    }
+    
+    fn get_tx_spending_prevout<'a>(&'a self, _outpoints: &'a [OutPoint]) -> BoxFuture<'a, Result<Vec<Option<String>>>> {
+        not_implemented("gettxspendingprevout")
+    }
+    
+    fn get_block_txids<'a>(&'a self, _block_hash: &'a BlockHash) -> BoxFuture<'a, Result<(BlockHeaderInfo, Vec<Txid>)>> {
+        not_implemented("getblock")
+    }
+    
+    fn get_raw_mempool_sequence(&self) -> BoxFuture<'_, Result<MempoolSequence>> {
+        not_implemented("getrawmempool")
+    }
+    
+    fn get_network_info(&self) -> BoxFuture<'_, Result<NetworkInfo>> {
+        not_implemented("getnetworkinfo")
+    }
+}
+
+/// Default body for [`BitcoinRpc`] methods an implementor doesn't provide
+fn not_implemented<'a, T: Send + 'a>(method: &'static str) -> BoxFuture<'a, Result<T>> {
+    Box::pin(async move { Err(BitcoinRpcError::unsupported_method(method, "not implemented by this client").into()) })
+}
+
+impl BitcoinRpc for BitcoinRpcClient {
+    fn get_best_block_hash(&self) -> BoxFuture<'_, Result<BlockHash>> {
+        Box::pin(BitcoinRpcClient::get_best_block_hash(self))
+    }
+    
+    fn get_block<'a>(&'a self, block_hash: &'a BlockHash) -> BoxFuture<'a, Result<Block>> {
+        Box::pin(BitcoinRpcClient::get_block(self, block_hash))
+    }
+    
+    fn get_block_txids<'a>(&'a self, block_hash: &'a BlockHash) -> BoxFuture<'a, Result<(BlockHeaderInfo, Vec<Txid>)>> {
+        Box::pin(BitcoinRpcClient::get_block_txids(self, block_hash))
+    }
+    
+    fn get_chain(&self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(BitcoinRpcClient::get_chain(self))
+    }
+    
+    fn get_network_info(&self) -> BoxFuture<'_, Result<NetworkInfo>> {
+        Box::pin(BitcoinRpcClient::get_network_info(self))
+    }
+    
+    fn send_raw_transaction<'a>(&'a self, tx_hex: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(BitcoinRpcClient::send_raw_transaction(self, tx_hex))
+    }
+    
+    fn get_raw_mempool(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        Box::pin(BitcoinRpcClient::get_raw_mempool(self))
+    }
+    
+    fn get_raw_mempool_sequence(&self) -> BoxFuture<'_, Result<MempoolSequence>> {
+        Box::pin(BitcoinRpcClient::get_raw_mempool_sequence(self))
+    }
+    
+    fn get_raw_transaction<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(BitcoinRpcClient::get_raw_transaction(self, txid))
+    }
+    
+    fn get_raw_transaction_verbose<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<RawTxInfo>> {
+        Box::pin(BitcoinRpcClient::get_raw_transaction_verbose(self, txid))
+    }
+    
+    fn get_mempool_entry<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<MempoolEntry>> {
+        Box::pin(BitcoinRpcClient::get_mempool_entry(self, txid))
+    }
+    
+    fn submit_package<'a>(&'a self, tx_hexes: &'a [String]) -> BoxFuture<'a, Result<PackageResult>> {
+        Box::pin(BitcoinRpcClient::submit_package(self, tx_hexes))
+    }
+    
+    fn estimate_smart_fee(&self, conf_target: u16, mode: EstimateMode) -> BoxFuture<'_, Result<FeeEstimate>> {
+        Box::pin(BitcoinRpcClient::estimate_smart_fee(self, conf_target, mode))
+    }
+    
+    fn get_tx_spending_prevout<'a>(&'a self, outpoints: &'a [OutPoint]) -> BoxFuture<'a, Result<Vec<Option<String>>>> {
+        Box::pin(BitcoinRpcClient::get_tx_spending_prevout(self, outpoints))
+    }
+    
+    fn get_block_count(&self) -> BoxFuture<'_, Result<u64>> {
+        Box::pin(BitcoinRpcClient::get_block_count(self))
+    }
+    
+    fn get_block_hash(&self, height: u64) -> BoxFuture<'_, Result<BlockHash>> {
+        Box::pin(BitcoinRpcClient::get_block_hash(self, height))
+    }
+}
+
+/// Convert a JSON-RPC error object into a `BitcoinRpcError`
+fn rpc_error(error: &Value) -> BitcoinRpcError {
+    match (error.get("code").and_then(Value::as_i64), error.get("message").and_then(Value::as_str)) {
+        (Some(code), Some(message)) => BitcoinRpcError::bitcoin_core(code as i32, message),
+        _ => BitcoinRpcError::request_failed(format!("RPC error: {}", error)),
+    }
+}
+
+/// Translate Core's "use -txindex" lookup failure into a dedicated error
+fn map_tx_lookup_error(err: crate::RelayError, txid: &str) -> crate::RelayError {
+    match err {
+        crate::RelayError::BitcoinRpc(BitcoinRpcError::BitcoinCore { code, message })
+            if code == RPC_INVALID_ADDRESS_OR_KEY && message.contains("txindex") =>
+        {
+            BitcoinRpcError::tx_index_required(txid).into()
+        }
+        other => other,
+    }
+}
+
+/// Translate "method not found" into a dedicated error naming the missing method
+fn map_unsupported_method(err: crate::RelayError, method: &str, requirement: &str) -> crate::RelayError {
+    match err {
+        crate::RelayError::BitcoinRpc(BitcoinRpcError::BitcoinCore { code, .. }) if code == RPC_METHOD_NOT_FOUND => {
+            BitcoinRpcError::unsupported_method(method, requirement).into()
+        }
+        other => other,
+    }
+}
+
+/// Spending txids from a `gettxspendingprevout` result, which must cover every queried outpoint
+fn parse_spending_txids(result: Value, expected: usize) -> Result<Vec<Option<String>>> {
+    #[derive(Deserialize)]
+    struct Spend {
+        spendingtxid: Option<String>,
+    }
+    
+    let spends: Vec<Spend> = serde_json::from_value(result).map_err(|_| BitcoinRpcError::InvalidResponse)?;
+    if spends.len() != expected {
+        return Err(BitcoinRpcError::InvalidResponse.into());
+    }
+    Ok(spends.into_iter().map(|spend| spend.spendingtxid).collect())
+}
+
+/// Header fields and txids of a `getblock` verbosity 1 result
+fn parse_block_txids(result: Value) -> Result<(BlockHeaderInfo, Vec<Txid>)> {
+    #[derive(Deserialize)]
+    struct RawBlock {
+        hash: String,
+        height: u64,
+        version: i32,
+        previousblockhash: Option<String>,
+        merkleroot: String,
+        time: u64,
+        confirmations: i64,
+        tx: Vec<String>,
+    }
+    
+    let raw: RawBlock = serde_json::from_value(result).map_err(|_| BitcoinRpcError::InvalidResponse)?;
+    let block_hash = |hash: &str| BlockHash::from_str(hash).map_err(|_| BitcoinRpcError::InvalidResponse);
+    let header = BlockHeaderInfo {
+        hash: block_hash(&raw.hash)?,
+        height: raw.height,
+        version: raw.version,
+        previous_block_hash: raw.previousblockhash.as_deref().map(block_hash).transpose()?,
+        merkle_root: raw.merkleroot,
+        time: raw.time,
+        confirmations: raw.confirmations,
+    };
+    let txids = raw
+        .tx
+        .iter()
+        .map(|txid| Txid::from_str(txid).map_err(|_| BitcoinRpcError::InvalidResponse))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok((header, txids))
+}
+
+fn parse_raw_tx_info(result: Value) -> Result<RawTxInfo> {
+    serde_json::from_value(result).map_err(|_| BitcoinRpcError::InvalidResponse.into())
+}
+
+fn parse_package_result(result: Value) -> Result<PackageResult> {
+    serde_json::from_value(result).map_err(|_| BitcoinRpcError::InvalidResponse.into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,6 +779,139 @@ mod tests {
         assert_eq!(client1.password, client2.password);
     }
 
+    #[test]
+    fn test_client_with_pool_config() {
+        let pool = HttpPoolConfig {
+            pool_max_idle_per_host: 2,
+            pool_idle_timeout: Some(std::time::Duration::from_secs(10)),
+            tcp_keepalive: None,
+            socks_proxy: None,
+        };
+        let client = BitcoinRpcClient::with_pool(
+            "http://127.0.0.1:18332".to_string(),
+            "user".to_string(),
+            "password".to_string(),
+            pool.clone(),
+        ).unwrap();
+        
+        assert_eq!(client.pool_config(), &pool);
+        assert_eq!(client.clone().pool_config(), &pool);
+        assert_eq!(BitcoinRpcClient::new("http://127.0.0.1:18332".to_string(), "u".to_string(), "p".to_string()).pool_config(), &HttpPoolConfig::default());
+    }
+
+    #[tokio::test]
+    async fn test_client_with_socks_proxy() {
+        let rpc_port = crate::test_utils::spawn_mock_rpc(json!({"result": {"chain": "regtest"}, "error": null, "id": 1})).await;
+        let (proxy_port, targets) = crate::test_utils::spawn_socks5_proxy().await;
+        let pool = HttpPoolConfig {
+            socks_proxy: Some(format!("127.0.0.1:{}", proxy_port)),
+            ..Default::default()
+        };
+        let client = BitcoinRpcClient::with_pool(
+            format!("http://127.0.0.1:{}", rpc_port),
+            "user".to_string(),
+            "password".to_string(),
+            pool,
+        ).unwrap();
+        
+        assert_eq!(client.get_chain().await.unwrap(), "regtest");
+        assert_eq!(*targets.lock().unwrap(), vec![format!("127.0.0.1:{}", rpc_port)]);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_smart_fee() {
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": {"feerate": 0.00012, "blocks": 2},
+            "error": null,
+            "id": 1
+        })).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        
+        let estimate = client.estimate_smart_fee(2, EstimateMode::Economical).await.unwrap();
+        assert_eq!(estimate.fee_rate, Some(0.00012));
+        assert_eq!(estimate.sat_per_vbyte(), Some(12.0));
+        assert_eq!(estimate.blocks, 2);
+        assert!(estimate.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_smart_fee_insufficient_data() {
+        // What Core returns on regtest or before it has seen enough blocks
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": {"errors": ["Insufficient data or no feerate found"], "blocks": 0},
+            "error": null,
+            "id": 1
+        })).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        
+        let estimate = client.estimate_smart_fee(6, EstimateMode::Unset).await.unwrap();
+        assert_eq!(estimate.fee_rate, None);
+        assert_eq!(estimate.sat_per_vbyte(), None);
+        assert_eq!(estimate.errors, vec!["Insufficient data or no feerate found"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_network_info() {
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": {
+                "version": 260000,
+                "subversion": "/Satoshi:26.0.0/",
+                "protocolversion": 70016,
+                "connections": 10,
+                "connections_in": 2,
+                "connections_out": 8,
+                "networkactive": true
+            },
+            "error": null,
+            "id": 1
+        })).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        
+        let info = client.get_network_info().await.unwrap();
+        assert_eq!(info.connections, 10);
+        assert_eq!(info.connections_in, Some(2));
+        assert_eq!(info.connections_out, Some(8));
+        assert!(info.network_active);
+        assert_eq!(info.subversion, "/Satoshi:26.0.0/");
+        
+        // Nodes before 21 only report the total
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": {"version": 200000, "subversion": "/Satoshi:0.20.0/", "connections": 0, "networkactive": true},
+            "error": null,
+            "id": 1
+        })).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        let info = client.get_network_info().await.unwrap();
+        assert_eq!(info.connections, 0);
+        assert_eq!(info.connections_in, None);
+    }
+
+    #[tokio::test]
+    async fn test_request_ids_and_user_agent() {
+        let (port, requests) = crate::test_utils::spawn_recording_rpc(json!({"result": "regtest", "error": null, "id": 1})).await;
+        let client = BitcoinRpcClient::new(
+            format!("http://127.0.0.1:{}", port),
+            "user".to_string(),
+            "password".to_string(),
+        ).with_relay_id("7");
+        
+        client.rpc_call("getblockchaininfo", &json!([])).await.unwrap();
+        client.clone().rpc_call("getblockchaininfo", &json!([])).await.unwrap();
+        client.rpc_call("getblockchaininfo", &json!([])).await.unwrap();
+        
+        let requests = requests.lock().unwrap();
+        let ids: Vec<u64> = requests
+            .iter()
+            .map(|(_, body)| serde_json::from_str::<Value>(body).unwrap()["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        
+        let expected = format!("user-agent: bitcoin-nostr-relay/{} (relay 7)", env!("CARGO_PKG_VERSION"));
+        for (headers, _) in requests.iter() {
+            assert!(headers.lines().any(|line| line.eq_ignore_ascii_case(&expected)), "{}", headers);
+        }
+    }
+
     // Integration tests that require a running Bitcoin node
     #[tokio::test]
     #[ignore] // Use `cargo test -- --ignored` to run this test
@@ -188,6 +964,286 @@ mod tests {
         }
     }
 
+    fn verbose_tx_json() -> Value {
+        json!({
+            "txid": "b5f2e1b6e7f4c1c1a0a4b0d2d9f4c2e3a1b0c9d8e7f6a5b4c3d2e1f0a9b8c7d6",
+            "hash": "c6a3f2c7f8e5d2d2b1b5c1e3eaf5d3f4b2c1dae9f8e7b6c5d4e3f2a1b0c9d8e7",
+            "version": 2,
+            "size": 222,
+            "vsize": 141,
+            "weight": 561,
+            "locktime": 0,
+            "vin": [{
+                "txid": "a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90",
+                "vout": 1,
+                "scriptSig": {"asm": "", "hex": ""},
+                "txinwitness": ["3044", "02ab"],
+                "sequence": 4294967293u32
+            }],
+            "vout": [{
+                "value": 0.0005,
+                "n": 0,
+                "scriptPubKey": {
+                    "asm": "0 751e76e8199196d454941c45d1b3a323f1433bd6",
+                    "hex": "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                    "address": "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+                    "type": "witness_v0_keyhash"
+                }
+            }],
+            "hex": "02000000000101"
+        })
+    }
+
+    #[test]
+    fn test_parse_verbose_tx_unconfirmed() {
+        let info = parse_raw_tx_info(verbose_tx_json()).unwrap();
+        
+        assert_eq!(info.vsize, 141);
+        assert_eq!(info.weight, 561);
+        assert_eq!(info.confirmations, None);
+        assert_eq!(info.blockhash, None);
+        assert!(!info.is_confirmed());
+        assert_eq!(info.vin.len(), 1);
+        assert_eq!(info.vin[0].vout, Some(1));
+        assert_eq!(info.vin[0].txinwitness.len(), 2);
+        assert_eq!(info.vout[0].script_pub_key.script_type, "witness_v0_keyhash");
+        assert!(info.vout[0].script_pub_key.address.is_some());
+    }
+
+    #[test]
+    fn test_parse_verbose_tx_confirmed() {
+        let mut value = verbose_tx_json();
+        value["confirmations"] = json!(6);
+        value["blockhash"] = json!("0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206");
+        value["blocktime"] = json!(1_700_000_000u64);
+        value["time"] = json!(1_700_000_000u64);
+        
+        let info = parse_raw_tx_info(value).unwrap();
+        assert_eq!(info.confirmations, Some(6));
+        assert!(info.is_confirmed());
+        assert_eq!(info.blocktime, Some(1_700_000_000));
+        assert!(info.blockhash.is_some());
+    }
+
+    #[test]
+    fn test_parse_verbose_tx_invalid() {
+        let result = parse_raw_tx_info(json!("0200000001"));
+        assert!(matches!(result, Err(crate::RelayError::BitcoinRpc(BitcoinRpcError::InvalidResponse))));
+    }
+
+    #[test]
+    fn test_parse_mempool_entry() {
+        let entry: MempoolEntry = serde_json::from_value(json!({
+            "vsize": 141,
+            "weight": 561,
+            "time": 1700000000u64,
+            "height": 101,
+            "descendantcount": 1,
+            "descendantsize": 141,
+            "ancestorcount": 2,
+            "ancestorsize": 282,
+            "wtxid": "c6a3",
+            "fees": {"base": 0.00001, "modified": 0.00001, "ancestor": 0.00002, "descendant": 0.00001},
+            "depends": ["a1b2"],
+            "spentby": [],
+            "bip125-replaceable": false,
+            "unbroadcast": false
+        })).unwrap();
+        
+        assert_eq!(entry.vsize, 141);
+        assert_eq!(entry.ancestorcount, 2);
+        assert_eq!(entry.depends, vec!["a1b2".to_string()]);
+        assert!(entry.spent_by.is_empty());
+        assert_eq!(entry.fees.ancestor, 0.00002);
+    }
+
+    #[test]
+    fn test_parse_successful_package() {
+        let result = parse_package_result(json!({
+            "package_msg": "success",
+            "tx-results": {
+                "wp": {"txid": "parent", "vsize": 110, "fees": {"base": 0.0, "effective-feerate": 0.00025, "effective-includes": ["wp", "wc"]}},
+                "wc": {"txid": "child", "vsize": 110, "fees": {"base": 0.00005, "effective-feerate": 0.00025, "effective-includes": ["wp", "wc"]}}
+            },
+            "replaced-transactions": []
+        })).unwrap();
+        
+        assert!(result.is_success());
+        assert!(result.rejected().is_empty());
+        let child = &result.tx_results["wc"];
+        assert_eq!(child.txid, "child");
+        let fees = child.fees.as_ref().unwrap();
+        assert_eq!(fees.base, 0.00005);
+        assert_eq!(fees.effective_includes, vec!["wp".to_string(), "wc".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_partially_failing_package() {
+        let result = parse_package_result(json!({
+            "package_msg": "transaction failed",
+            "tx-results": {
+                "wp": {"txid": "parent", "other-wtxid": "wp2", "vsize": 110, "fees": {"base": 0.00001}},
+                "wc": {"txid": "child", "error": "min relay fee not met"}
+            }
+        })).unwrap();
+        
+        assert!(!result.is_success());
+        assert_eq!(result.rejected(), vec![("child", "min relay fee not met")]);
+        assert_eq!(result.tx_results["wp"].other_wtxid.as_deref(), Some("wp2"));
+        assert!(result.tx_results["wc"].fees.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_submit_package_unsupported_on_old_node() {
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": null,
+            "error": {"code": -32601, "message": "Method not found"},
+            "id": 1
+        })).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        
+        match client.submit_package(&["00".to_string()]).await {
+            Err(crate::RelayError::BitcoinRpc(BitcoinRpcError::UnsupportedMethod { method, .. })) => {
+                assert_eq!(method, "submitpackage");
+            }
+            other => panic!("Expected unsupported method error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_spending_prevout() {
+        let outpoints = [
+            OutPoint::new(bitcoin::Txid::all_zeros(), 0),
+            OutPoint::new(bitcoin::Txid::all_zeros(), 1),
+        ];
+        let spender = "11".repeat(32);
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": [
+                {"txid": "00".repeat(32), "vout": 0, "spendingtxid": spender},
+                {"txid": "00".repeat(32), "vout": 1}
+            ],
+            "error": null,
+            "id": 1
+        })).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        assert_eq!(client.get_tx_spending_prevout(&outpoints).await.unwrap(), vec![Some(spender), None]);
+        
+        // A result that doesn't cover every outpoint is malformed
+        assert!(matches!(
+            client.get_tx_spending_prevout(&outpoints[..1]).await,
+            Err(crate::RelayError::BitcoinRpc(BitcoinRpcError::InvalidResponse))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_raw_mempool_sequence() {
+        let txid = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        let (port, requests) = crate::test_utils::spawn_recording_rpc(json!({
+            "result": {"txids": [txid], "mempool_sequence": 42},
+            "error": null,
+            "id": 1
+        })).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        
+        let mempool = client.get_raw_mempool_sequence().await.unwrap();
+        assert_eq!(mempool, MempoolSequence { txids: vec![txid.to_string()], mempool_sequence: 42 });
+        let body: Value = serde_json::from_str(&requests.lock().unwrap()[0].1).unwrap();
+        assert_eq!(body["method"], "getrawmempool");
+        assert_eq!(body["params"], json!([false, true]));
+    }
+
+    #[tokio::test]
+    async fn test_get_block_txids() {
+        let block = json!({
+            "hash": "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+            "confirmations": 3,
+            "height": 0,
+            "version": 1,
+            "versionHex": "00000001",
+            "merkleroot": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+            "time": 1296688602,
+            "mediantime": 1296688602,
+            "nonce": 2,
+            "bits": "207fffff",
+            "difficulty": 4.656542373906925e-10,
+            "chainwork": "0000000000000000000000000000000000000000000000000000000000000002",
+            "nTx": 1,
+            "nextblockhash": "1a5f0ae3ba2b1e13c5c3d2e8f0c4d6c2a1e6a7d0c1a0b9f8e7d6c5b4a3928170",
+            "strippedsize": 285,
+            "size": 285,
+            "weight": 1140,
+            "tx": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"]
+        });
+        let (port, requests) = crate::test_utils::spawn_recording_rpc(json!({"result": block, "error": null, "id": 1})).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        let hash = BlockHash::from_str("0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206").unwrap();
+        
+        let (header, txids) = client.get_block_txids(&hash).await.unwrap();
+        assert_eq!(header.hash, hash);
+        assert_eq!(header.height, 0);
+        assert_eq!(header.previous_block_hash, None);
+        assert_eq!(header.time, 1296688602);
+        assert_eq!(header.confirmations, 3);
+        assert_eq!(txids, vec![Txid::from_str("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b").unwrap()]);
+        
+        // Verbosity 1 lists txids rather than returning the serialized block
+        let body: Value = serde_json::from_str(&requests.lock().unwrap()[0].1).unwrap();
+        assert_eq!(body["method"], "getblock");
+        assert_eq!(body["params"][1], 1);
+        
+        let mut child = block.clone();
+        child["previousblockhash"] = json!(hash.to_string());
+        child["tx"] = json!(["not-a-txid"]);
+        assert!(matches!(parse_block_txids(child), Err(crate::RelayError::BitcoinRpc(BitcoinRpcError::InvalidResponse))));
+    }
+
+    #[tokio::test]
+    async fn test_get_raw_transaction_rejects_missing_result() {
+        for result in [json!(null), json!("")] {
+            let port = crate::test_utils::spawn_mock_rpc(json!({"result": result, "error": null, "id": 1})).await;
+            let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+            assert!(matches!(
+                client.get_raw_transaction("abcd").await,
+                Err(crate::RelayError::BitcoinRpc(BitcoinRpcError::InvalidResponse))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_raw_transaction_without_txindex() {
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": null,
+            "error": {"code": -5, "message": "No such mempool transaction. Use -txindex or provide a block hash to enable blockchain transaction queries."},
+            "id": 1
+        })).await;
+        let client = BitcoinRpcClient::new(format!("http://127.0.0.1:{}", port), "user".to_string(), "password".to_string());
+        assert!(matches!(
+            client.get_raw_transaction("abcd").await,
+            Err(crate::RelayError::BitcoinRpc(BitcoinRpcError::TxIndexRequired { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_tx_lookup_txindex_error() {
+        let error = rpc_error(&json!({
+            "code": -5,
+            "message": "No such mempool transaction. Use -txindex or provide a block hash to enable blockchain transaction queries."
+        }));
+        let mapped = map_tx_lookup_error(error.into(), "abcd");
+        assert!(matches!(mapped, crate::RelayError::BitcoinRpc(BitcoinRpcError::TxIndexRequired { ref txid }) if txid == "abcd"));
+        
+        // Other Core errors pass through unchanged
+        let error = rpc_error(&json!({"code": -8, "message": "parameter 1 must be hexadecimal"}));
+        let mapped = map_tx_lookup_error(error.into(), "abcd");
+        assert!(matches!(mapped, crate::RelayError::BitcoinRpc(BitcoinRpcError::BitcoinCore { code: -8, .. })));
+    }
+
+    #[test]
+    fn test_rpc_error_without_code() {
+        let error = rpc_error(&json!("unexpected"));
+        assert!(matches!(error, BitcoinRpcError::RequestFailed { .. }));
+    }
+
     #[test]
     fn test_block_hash_parsing() {
         // Test valid block hash parsing
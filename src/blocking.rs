@@ -0,0 +1,107 @@
+//! Synchronous wrappers for programs that don't run a tokio runtime
+//!
+//! Like reqwest's `blocking` module, each call drives the async implementation
+//! on a private runtime owned by this module. Calling these from inside a
+//! runtime would deadlock or panic, so they return
+//! [`RelayError::BlockingInAsyncContext`] instead.
+
+use crate::{BitcoinNostrRelay, RelayError, Result, SubmitOutcome};
+use std::future::Future;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+
+/// Shared runtime, created on first use
+///
+/// One worker thread keeps pooled RPC connections alive between calls.
+fn runtime() -> Result<&'static Runtime> {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("bitcoin-nostr-relay-blocking")
+        .enable_all()
+        .build()?;
+    // A concurrent first call may have won the race; its runtime is used and ours dropped
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Run `future` to completion on the private runtime
+fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Err(RelayError::BlockingInAsyncContext);
+    }
+    Ok(runtime()?.block_on(future))
+}
+
+impl BitcoinNostrRelay {
+    /// Blocking version of [`BitcoinNostrRelay::validate_transaction`]
+    pub fn validate_transaction_blocking(&self, tx_hex: &str) -> Result<()> {
+        block_on(self.validate_transaction(tx_hex))?.map_err(Into::into)
+    }
+    
+    /// Blocking version of [`BitcoinNostrRelay::submit_transaction`]
+    pub fn submit_transaction_blocking(&self, tx_hex: &str) -> Result<SubmitOutcome> {
+        block_on(self.submit_transaction(tx_hex))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay::RelayConfig;
+    use crate::ValidationConfig;
+    use crate::test_utils::{sample_tx_hex, spawn_mock_rpc};
+
+    fn config_for_port(port: u16) -> RelayConfig {
+        RelayConfig::new(
+            format!("http://127.0.0.1:{}", port),
+            "ws://127.0.0.1:7777",
+            "1",
+            "127.0.0.1:7779".parse().unwrap(),
+        ).unwrap()
+        .with_auth("user".to_string(), "password".to_string())
+    }
+
+    fn relay_for_port(port: u16) -> BitcoinNostrRelay {
+        let config = config_for_port(port).with_validation(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        });
+        BitcoinNostrRelay::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_validate_transaction_blocking() {
+        assert!(relay_for_port(1).validate_transaction_blocking(&sample_tx_hex()).is_ok());
+        
+        // Undecodable input is rejected before any RPC call
+        let validating = BitcoinNostrRelay::new(config_for_port(1)).unwrap();
+        assert!(matches!(
+            validating.validate_transaction_blocking("zz"),
+            Err(RelayError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_submit_transaction_blocking() {
+        let port = runtime()
+            .unwrap()
+            .block_on(spawn_mock_rpc(serde_json::json!({"result": "ok", "error": null, "id": 1})));
+        let relay = relay_for_port(port);
+        
+        let outcome = relay.submit_transaction_blocking(&sample_tx_hex()).unwrap();
+        assert!(outcome.submitted);
+        assert!(!outcome.broadcast);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_call_inside_runtime_is_rejected() {
+        let relay = relay_for_port(1);
+        assert!(matches!(
+            relay.submit_transaction_blocking(&sample_tx_hex()),
+            Err(RelayError::BlockingInAsyncContext)
+        ));
+    }
+}
@@ -48,9 +48,15 @@ pub enum RelayError {
     #[error("Nostr event builder error: {0}")]
     NostrEventBuilder(#[from] nostr::event::builder::Error),
     
+    #[error("Nostr bech32 error: {0}")]
+    NostrBech32(#[from] nostr::nips::nip19::Error),
+    
     #[error("Address parse error: {0}")]
     AddrParse(#[from] std::net::AddrParseError),
     
+    #[error("Blocking API called from within an async runtime; use the async methods instead")]
+    BlockingInAsyncContext,
+    
     #[error("{0}")]
     Other(String),
 }
@@ -72,6 +78,15 @@ pub enum ConfigError {
     
     #[error("Invalid configuration parameter: {param}")]
     InvalidParameter { param: String },
+    
+    #[error("Another relay on the network is using relay_id {relay_id}")]
+    DuplicateRelayId { relay_id: String },
+    
+    #[error("Configured network {configured:?} does not match Bitcoin node chain \"{node}\"")]
+    NetworkMismatch { configured: crate::Network, node: String },
+    
+    #[error("Bitcoin RPC at {url} is unreachable: {reason}")]
+    RpcUnreachable { url: String, reason: String },
 }
 
 /// Bitcoin RPC-specific errors
@@ -91,6 +106,18 @@ pub enum BitcoinRpcError {
     
     #[error("Bitcoin Core error: {code} - {message}")]
     BitcoinCore { code: i32, message: String },
+    
+    #[error("Transaction {txid} not in mempool; looking up confirmed transactions requires -txindex or a block hash")]
+    TxIndexRequired { txid: String },
+    
+    #[error("Transaction submission rate limit exceeded, try again later")]
+    RateLimited,
+    
+    #[error("Server busy: too many transactions are being submitted, try again shortly")]
+    ServerBusy,
+    
+    #[error("Bitcoin node does not support {method} ({requirement})")]
+    UnsupportedMethod { method: String, requirement: String },
 }
 
 /// Nostr-specific errors  
@@ -110,6 +137,36 @@ pub enum NostrError {
     
     #[error("Subscription failed")]
     SubscriptionFailed,
+    
+    #[error("Invalid transaction broadcast: {reason}")]
+    InvalidTxBroadcast { reason: String },
+    
+    #[error("Invalid eviction notice: {reason}")]
+    InvalidTxEviction { reason: String },
+    
+    #[error("Invalid acceptance notice: {reason}")]
+    InvalidTxAcceptance { reason: String },
+    
+    #[error("SOCKS5 proxy {proxy} failed: {reason}")]
+    ProxyFailed { proxy: String, reason: String },
+    
+    #[error("Client {client_id} is no longer connected")]
+    ClientGone { client_id: String },
+    
+    #[error("Strfry outbound queue is full")]
+    OutboundQueueFull,
+    
+    #[error("Invalid transaction response: {reason}")]
+    InvalidTxResponse { reason: String },
+    
+    #[error("No response from relay within {timeout:?}")]
+    ResponseTimeout { timeout: std::time::Duration },
+    
+    #[error("Invalid auth event: {reason}")]
+    InvalidAuth { reason: String },
+    
+    #[error("Broadcast encryption failed: {reason}")]
+    Encryption { reason: String },
 }
 
 /// Transaction validation errors
@@ -127,6 +184,9 @@ pub enum ValidationError {
     #[error("Invalid transaction structure")]
     InvalidStructure,
     
+    #[error("Invalid txid: {value}")]
+    InvalidTxid { value: String },
+    
     #[error("Transaction {txid} recently processed (cached)")]
     RecentlyProcessed { txid: String },
     
@@ -136,8 +196,41 @@ pub enum ValidationError {
     #[error("Validation timeout")]
     Timeout,
     
+    #[error("Bitcoin node unavailable, its circuit breaker is open")]
+    NodeUnavailable,
+    
     #[error("Validation disabled")]
     Disabled,
+    
+    #[error("Replacement transaction underpriced, bump the fee further: {reason}")]
+    ReplacementUnderpriced { reason: String },
+    
+    #[error("Transaction conflicts with a mempool transaction that cannot be replaced: {reason}")]
+    MempoolConflict { reason: String },
+    
+    #[error("Fee rate {fee_rate:.2} sat/vB is below the relay minimum of {min_fee_rate} sat/vB")]
+    FeeRateTooLow { fee_rate: f64, min_fee_rate: f64 },
+    
+    #[error("Transaction rejected by relay output policy: {reason}")]
+    OutputPolicy { reason: String },
+    
+    #[error("Non-standard transaction version {version}, only versions 1 and 2 are relayed")]
+    NonStandardVersion { version: i32 },
+    
+    #[error("Input {input} scriptSig contains operations other than data pushes")]
+    NonPushScriptSig { input: usize },
+    
+    #[error("Output {output} of {value} sat is below the dust threshold of {threshold} sat")]
+    DustOutput { output: usize, value: u64, threshold: u64 },
+    
+    #[error("Input {input} spends a coinbase output with {confirmations} confirmations, which needs 100 to mature")]
+    ImmatureCoinbaseSpend { input: usize, confirmations: u64 },
+    
+    #[error("Transaction has {count} inputs, more than the relay limit of {max}")]
+    TooManyInputs { count: usize, max: usize },
+    
+    #[error("Transaction has {count} outputs, more than the relay limit of {max}")]
+    TooManyOutputs { count: usize, max: usize },
 }
 
 // Add conversion from reqwest::Error to ValidationError for HTTP requests
@@ -189,6 +282,10 @@ impl ValidationError {
         Self::InvalidSize { size }
     }
     
+    pub fn invalid_txid(value: impl Into<String>) -> Self {
+        Self::InvalidTxid { value: value.into() }
+    }
+    
     pub fn recently_processed(txid: impl Into<String>) -> Self {
         Self::RecentlyProcessed { txid: txid.into() }
     }
@@ -196,6 +293,46 @@ impl ValidationError {
     pub fn bitcoin_core_rejection(reason: impl Into<String>) -> Self {
         Self::BitcoinCoreRejection { reason: reason.into() }
     }
+    
+    pub fn replacement_underpriced(reason: impl Into<String>) -> Self {
+        Self::ReplacementUnderpriced { reason: reason.into() }
+    }
+    
+    pub fn mempool_conflict(reason: impl Into<String>) -> Self {
+        Self::MempoolConflict { reason: reason.into() }
+    }
+    
+    pub fn fee_rate_too_low(fee_rate: f64, min_fee_rate: f64) -> Self {
+        Self::FeeRateTooLow { fee_rate, min_fee_rate }
+    }
+    
+    pub fn output_policy(reason: impl Into<String>) -> Self {
+        Self::OutputPolicy { reason: reason.into() }
+    }
+    
+    pub fn non_standard_version(version: i32) -> Self {
+        Self::NonStandardVersion { version }
+    }
+    
+    pub fn non_push_script_sig(input: usize) -> Self {
+        Self::NonPushScriptSig { input }
+    }
+    
+    pub fn dust_output(output: usize, value: u64, threshold: u64) -> Self {
+        Self::DustOutput { output, value, threshold }
+    }
+    
+    pub fn immature_coinbase_spend(input: usize, confirmations: u64) -> Self {
+        Self::ImmatureCoinbaseSpend { input, confirmations }
+    }
+    
+    pub fn too_many_inputs(count: usize, max: usize) -> Self {
+        Self::TooManyInputs { count, max }
+    }
+    
+    pub fn too_many_outputs(count: usize, max: usize) -> Self {
+        Self::TooManyOutputs { count, max }
+    }
 }
 
 impl BitcoinRpcError {
@@ -210,6 +347,14 @@ impl BitcoinRpcError {
     pub fn bitcoin_core(code: i32, message: impl Into<String>) -> Self {
         Self::BitcoinCore { code, message: message.into() }
     }
+    
+    pub fn tx_index_required(txid: impl Into<String>) -> Self {
+        Self::TxIndexRequired { txid: txid.into() }
+    }
+    
+    pub fn unsupported_method(method: impl Into<String>, requirement: impl Into<String>) -> Self {
+        Self::UnsupportedMethod { method: method.into(), requirement: requirement.into() }
+    }
 }
 
 impl ConfigError {
@@ -224,6 +369,18 @@ impl ConfigError {
     pub fn unsupported_configuration(network: crate::Network, relay_id: u16) -> Self {
         Self::UnsupportedConfiguration { network, relay_id }
     }
+    
+    pub fn duplicate_relay_id(relay_id: impl Into<String>) -> Self {
+        Self::DuplicateRelayId { relay_id: relay_id.into() }
+    }
+    
+    pub fn network_mismatch(configured: crate::Network, node: impl Into<String>) -> Self {
+        Self::NetworkMismatch { configured, node: node.into() }
+    }
+    
+    pub fn rpc_unreachable(url: impl Into<String>, reason: impl ToString) -> Self {
+        Self::RpcUnreachable { url: url.into(), reason: reason.to_string() }
+    }
 }
 
 impl NetworkError {
@@ -236,4 +393,40 @@ impl NostrError {
     pub fn connection_failed(url: impl Into<String>) -> Self {
         Self::ConnectionFailed { url: url.into() }
     }
+    
+    pub fn invalid_tx_broadcast(reason: impl Into<String>) -> Self {
+        Self::InvalidTxBroadcast { reason: reason.into() }
+    }
+    
+    pub fn encryption(reason: impl Into<String>) -> Self {
+        Self::Encryption { reason: reason.into() }
+    }
+    
+    pub fn invalid_tx_eviction(reason: impl Into<String>) -> Self {
+        Self::InvalidTxEviction { reason: reason.into() }
+    }
+    
+    pub fn invalid_tx_acceptance(reason: impl Into<String>) -> Self {
+        Self::InvalidTxAcceptance { reason: reason.into() }
+    }
+    
+    pub fn proxy_failed(proxy: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::ProxyFailed { proxy: proxy.into(), reason: reason.into() }
+    }
+    
+    pub fn client_gone(client_id: impl Into<String>) -> Self {
+        Self::ClientGone { client_id: client_id.into() }
+    }
+    
+    pub fn invalid_tx_response(reason: impl Into<String>) -> Self {
+        Self::InvalidTxResponse { reason: reason.into() }
+    }
+    
+    pub fn response_timeout(timeout: std::time::Duration) -> Self {
+        Self::ResponseTimeout { timeout }
+    }
+    
+    pub fn invalid_auth(reason: impl Into<String>) -> Self {
+        Self::InvalidAuth { reason: reason.into() }
+    }
 }
\ No newline at end of file
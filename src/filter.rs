@@ -0,0 +1,126 @@
+use bitcoin::{Address, Script, ScriptBuf, Transaction};
+use std::collections::HashSet;
+
+/// Restricts mempool gossip to transactions touching a set of output scripts
+/// 
+/// An empty filter matches every transaction, so relays gossip their whole
+/// mempool unless scripts are configured.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptFilter {
+    scripts: HashSet<ScriptBuf>,
+    /// Also match transactions spending from a watched script (requires prevout lookups)
+    pub match_inputs: bool,
+}
+
+impl ScriptFilter {
+    /// Create an empty filter that matches everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Watch an output script
+    pub fn with_script(mut self, script: ScriptBuf) -> Self {
+        self.scripts.insert(script);
+        self
+    }
+    
+    /// Watch the output script of an address
+    pub fn with_address(self, address: &Address) -> Self {
+        self.with_script(address.script_pubkey())
+    }
+    
+    /// Also match transactions whose inputs spend a watched script
+    pub fn with_match_inputs(mut self, match_inputs: bool) -> Self {
+        self.match_inputs = match_inputs;
+        self
+    }
+    
+    /// Whether no scripts are configured (everything is relayed)
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+    
+    /// Whether a script is being watched
+    pub fn contains(&self, script: &Script) -> bool {
+        self.scripts.contains(script)
+    }
+    
+    /// Whether any output of the transaction pays to a watched script
+    pub fn matches_outputs(&self, tx: &Transaction) -> bool {
+        tx.output.iter().any(|output| self.contains(&output.script_pubkey))
+    }
+    
+    /// Whether any of the given prevout scripts (the scripts being spent) is watched
+    pub fn matches_prevouts<'a>(&self, prevout_scripts: impl IntoIterator<Item = &'a ScriptBuf>) -> bool {
+        self.match_inputs && prevout_scripts.into_iter().any(|script| self.contains(script))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{absolute::LockTime, OutPoint, Sequence, TxIn, TxOut, Witness};
+    use std::str::FromStr;
+
+    fn tx_paying_to(scripts: &[ScriptBuf]) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: scripts
+                .iter()
+                .map(|script| TxOut { value: 10_000, script_pubkey: script.clone() })
+                .collect(),
+        }
+    }
+
+    fn watched_address() -> Address {
+        Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080")
+            .unwrap()
+            .require_network(bitcoin::Network::Regtest)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_empty_filter_watches_nothing() {
+        let filter = ScriptFilter::new();
+        assert!(filter.is_empty());
+        
+        // The server treats an empty filter as "relay everything"; the filter itself watches nothing
+        let tx = tx_paying_to(&[ScriptBuf::from_bytes(vec![0x51])]);
+        assert!(!filter.matches_outputs(&tx));
+    }
+
+    #[test]
+    fn test_filter_matches_watched_output() {
+        let address = watched_address();
+        let filter = ScriptFilter::new().with_address(&address);
+        
+        let matching = tx_paying_to(&[ScriptBuf::from_bytes(vec![0x51]), address.script_pubkey()]);
+        let other = tx_paying_to(&[ScriptBuf::from_bytes(vec![0x51])]);
+        
+        assert!(!filter.is_empty());
+        assert!(filter.matches_outputs(&matching));
+        assert!(!filter.matches_outputs(&other));
+    }
+
+    #[test]
+    fn test_filter_matches_spent_prevouts() {
+        let watched = watched_address().script_pubkey();
+        let unrelated = ScriptBuf::from_bytes(vec![0x51]);
+        
+        let filter = ScriptFilter::new().with_script(watched.clone());
+        
+        // Input matching is opt-in
+        assert!(!filter.matches_prevouts([&watched]));
+        
+        let filter = filter.with_match_inputs(true);
+        assert!(filter.matches_prevouts([&unrelated, &watched]));
+        assert!(!filter.matches_prevouts([&unrelated]));
+    }
+}
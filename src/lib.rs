@@ -4,77 +4,177 @@ pub mod nostr;
 pub mod relay;
 pub mod networks;
 pub mod error;
+pub mod filter;
+pub mod transport;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod mock_rpc;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 // Re-export core types for easy access
-pub use bitcoin_rpc::BitcoinRpcClient;
-pub use validation::{TransactionValidator, ValidationConfig};
-pub use nostr::NostrClient;
-pub use relay::{RelayServer, RelayConfig};
+pub use bitcoin_rpc::{AdditionalNode, BitcoinRpc, BitcoinRpcClient, BlockHeaderInfo, EstimateMode, FeeEstimate, MempoolEntry, MempoolSequence, NetworkInfo, PackageResult, PackageTxResult, RawTxInfo};
+#[cfg(any(test, feature = "testing"))]
+pub use mock_rpc::MockBitcoinRpc;
+pub use validation::{TransactionValidator, ValidationCheck, ValidationConfig, ValidationReport, ValidationStage};
+pub use nostr::{NostrClient, RelaySubmitClient, SubmitResponse, TagNamespace};
+pub use transport::{MemoryTransport, NostrTransport};
+#[cfg(feature = "prometheus")]
+pub use relay::PrometheusSink;
+pub use relay::{RelayServer, RelayConfig, RpcAuth, BackoffConfig, EventBus, BroadcastCipher, BitcoinNodeConfig, BreakerState, CircuitBreakerConfig, Compression, ConflictPolicy, InflightSubmissionLimit, HandshakeBanPolicy, DeadLetter, DeadLetterLog, DedupSnapshot, HttpPoolConfig, DiscoveryConfig, OverflowPolicy, RelayIdCheck, BroadcastEncoding, Capability, CapabilityAnnouncement, PeerInfo, Shard, SubmissionLimiter, SubmissionRateLimit, TxBroadcastPayload, AdaptivePollInterval, LatencyHistogram, LoopMetrics, Metric, MetricsSink, NoopMetrics, RelayStats, MempoolDiff, MempoolSummary, BitcoinState, ConnectionState, StrfryState, RelayedTx, TxFilter, TxSource, TxSubscription};
 pub use networks::{Network, network_config};
+pub use filter::ScriptFilter;
 pub use error::{RelayError, ConfigError, BitcoinRpcError, NostrError, ValidationError, NetworkError};
 
 /// Library result type using our custom error
 pub type Result<T, E = RelayError> = std::result::Result<T, E>;
 
+/// Outcome of a one-shot [`BitcoinNostrRelay::submit_transaction`] call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmitOutcome {
+    /// Transaction ID of the submitted transaction
+    pub txid: String,
+    /// Whether the transaction passed validation (false when validation is disabled)
+    pub validated: bool,
+    /// Whether the Bitcoin node accepted the transaction (false if it already had it)
+    pub submitted: bool,
+    /// Whether the transaction was broadcast to the connected Nostr relay
+    pub broadcast: bool,
+}
+
+/// A relay server started by [`BitcoinNostrRelay::start`], running in the background
+pub struct RelayHandle {
+    server: RelayServer,
+    task: tokio::task::JoinHandle<Result<()>>,
+}
+
+impl RelayHandle {
+    /// The running server
+    pub fn server(&self) -> &RelayServer {
+        &self.server
+    }
+    
+    /// Validate, submit and gossip a transaction through the running server
+    pub async fn submit_transaction(&self, tx_hex: &str) -> Result<SubmitOutcome> {
+        self.server.submit_transaction(tx_hex).await
+    }
+    
+    /// Gossip a transaction through the running server without submitting it, returning its txid
+    pub async fn broadcast_transaction(&self, tx_hex: &str) -> Result<String> {
+        self.server.gossip_transaction(tx_hex).await
+    }
+    
+    /// Ask the server to stop; [`wait`](Self::wait) returns once it has
+    pub fn shutdown(&self) {
+        self.server.shutdown();
+    }
+    
+    /// Wait for the server to stop, returning the error it stopped with, if any
+    pub async fn wait(&mut self) -> Result<()> {
+        (&mut self.task)
+            .await
+            .map_err(|e| RelayError::Other(format!("Relay server task failed: {}", e)))?
+    }
+}
+
 /// High-level API for Bitcoin-over-Nostr relay functionality
 pub struct BitcoinNostrRelay {
     bitcoin_client: BitcoinRpcClient,
+    additional_nodes: Vec<AdditionalNode>,
     nostr_client: Option<NostrClient>,
     validator: TransactionValidator,
+    submission_limiter: Option<std::sync::Arc<SubmissionLimiter>>,
+    relay_handle: Option<RelayHandle>,
     config: RelayConfig,
 }
 
 impl BitcoinNostrRelay {
     /// Create a new BitcoinNostrRelay instance with the given configuration
     pub fn new(config: RelayConfig) -> Result<Self> {
-        let bitcoin_client = BitcoinRpcClient::new(
+        let bitcoin_client = BitcoinRpcClient::with_pool(
             config.bitcoin_rpc_url.clone(),
             config.bitcoin_rpc_auth.username.clone(),
-            config.bitcoin_rpc_auth.password.clone(),
-        );
+            config.bitcoin_rpc_auth.password().to_string(),
+            config.rpc_pool.clone(),
+        )?
+        .with_relay_id(&config.relay_id);
+        let additional_nodes = bitcoin_rpc::additional_node_clients(&config.additional_bitcoin_nodes, &config.rpc_pool, &config.relay_id)?;
         
-        // Extract port from Bitcoin RPC URL for validator
-        let bitcoin_port = if let Ok(url) = url::Url::parse(&config.bitcoin_rpc_url) {
-            url.port().unwrap_or(18332)
-        } else {
-            18332
-        };
-        
-        let validator = TransactionValidator::new(
+        let validator = TransactionValidator::with_rpc_endpoint(
             config.validation_config.clone(),
-            bitcoin_port,
-        );
+            config.bitcoin_rpc_url.clone(),
+            config.bitcoin_rpc_auth.clone(),
+        )
+        .with_http_pool(&config.rpc_pool)?;
         
         Ok(Self {
             bitcoin_client,
+            additional_nodes,
             nostr_client: None,
             validator,
+            submission_limiter: config.submission_rate_limit.clone().map(|limit| std::sync::Arc::new(SubmissionLimiter::new(limit))),
+            relay_handle: None,
             config,
         })
     }
     
     /// Connect to the Nostr relay
     pub async fn connect_nostr(&mut self, ws_stream: tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>) -> Result<()> {
-        self.nostr_client = Some(NostrClient::new(ws_stream));
+        self.nostr_client = Some(NostrClient::new(ws_stream).with_tag_namespace(self.config.tag_namespace.clone()));
         Ok(())
     }
     
-    /// Start the relay server (monitors mempool and relays transactions)
-    pub async fn start(&mut self) -> Result<()> {
+    /// Start the relay server (monitors mempool and relays transactions) in the background
+    /// 
+    /// Returns a handle to the running server, which stays available from
+    /// [`relay_handle`](Self::relay_handle). Await [`RelayHandle::wait`] to block until it stops.
+    /// From then on [`submit_transaction`](Self::submit_transaction) and
+    /// [`broadcast_transaction`](Self::broadcast_transaction) go through the server.
+    /// 
+    /// The server gossips over its own strfry connection, so a Nostr client from
+    /// [`connect_nostr`](Self::connect_nostr) is no longer used once it runs.
+    pub async fn start(&mut self) -> Result<&mut RelayHandle> {
+        if self.relay_handle.is_some() {
+            return Err(RelayError::Other("Relay server already started".to_string()));
+        }
+        
         let relay_server = RelayServer::new(
             self.bitcoin_client.clone(),
-            self.nostr_client.take(),
+            None,
             self.validator.clone(),
             self.config.clone(),
         )?;
         
-        relay_server.run().await
+        // The server shares our limiter so direct submissions count against the same budget
+        let relay_server = match &self.submission_limiter {
+            Some(limiter) => relay_server.with_submission_limiter(std::sync::Arc::clone(limiter)),
+            None => relay_server,
+        };
+        
+        let task = tokio::spawn(relay_server.clone().run());
+        Ok(self.relay_handle.insert(RelayHandle {
+            server: relay_server,
+            task,
+        }))
+    }
+    
+    /// The server started by [`start`](Self::start), if any
+    pub fn relay_handle(&self) -> Option<&RelayHandle> {
+        self.relay_handle.as_ref()
     }
     
     /// Broadcast a transaction to the Nostr network
+    /// 
+    /// Once the server is running the transaction is gossiped through it, and `block_hash` is unused.
     pub async fn broadcast_transaction(&self, tx_hex: &str, block_hash: &str) -> Result<()> {
-        if let Some(nostr_client) = &self.nostr_client {
-            nostr_client.send_tx_event(tx_hex, block_hash).await.map_err(RelayError::from)
+        if let Some(handle) = &self.relay_handle {
+            handle.broadcast_transaction(tx_hex).await.map(|_| ())
+        } else if let Some(nostr_client) = &self.nostr_client {
+            nostr_client.send_tx_event(tx_hex, block_hash).await
         } else {
             Err(NostrError::Disconnected.into())
         }
@@ -85,6 +185,65 @@ impl BitcoinNostrRelay {
         self.validator.validate(tx_hex).await
     }
     
+    /// Validate a transaction and return a report of the decoded transaction and checks run
+    pub async fn validate_detailed(&self, tx_hex: &str) -> Result<ValidationReport, ValidationError> {
+        self.validator.validate_detailed(tx_hex).await
+    }
+    
+    /// Validate a transaction, submit it to the Bitcoin node and broadcast it to Nostr
+    /// 
+    /// Validation and node submission errors are returned to the caller. Broadcasting is
+    /// best-effort: it is skipped when no Nostr client is connected and a failed broadcast
+    /// is reported through `SubmitOutcome::broadcast` rather than as an error.
+    /// 
+    /// Once the server is running the transaction goes through [`RelayServer::submit_transaction`]
+    /// and is gossiped by it, unless the server gossiped it before.
+    pub async fn submit_transaction(&self, tx_hex: &str) -> Result<SubmitOutcome> {
+        let tx_hex = tx_hex.trim();
+        if let Some(handle) = &self.relay_handle {
+            return handle.submit_transaction(tx_hex).await;
+        }
+        
+        let validated = self.validator.config().enable_validation;
+        self.validator.validate(tx_hex).await?;
+        
+        let tx: bitcoin::Transaction = bitcoin::consensus::deserialize(&hex::decode(tx_hex)?)?;
+        let txid = tx.txid().to_string();
+        
+        if let Some(limiter) = &self.submission_limiter {
+            limiter.acquire().await?;
+        }
+        
+        let submitted = match bitcoin_rpc::send_raw_transaction_to_all(&self.bitcoin_client, &self.additional_nodes, tx_hex).await {
+            Ok(_) => true,
+            Err(e) if nostr::ResponseCode::from(&e) == nostr::ResponseCode::AlreadyKnown => false,
+            Err(e) => {
+                if let Some(rejection) = validation::replacement_rejection(&e) {
+                    return Err(rejection.into());
+                }
+                return Err(e);
+            }
+        };
+        
+        let broadcast = match &self.nostr_client {
+            Some(nostr_client) => match nostr_client.send_tx_event(tx_hex, "").await {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("Failed to broadcast transaction {} to Nostr: {}", txid, e);
+                    false
+                }
+            },
+            None => false,
+        };
+        
+        Ok(SubmitOutcome {
+            txid,
+            validated,
+            submitted,
+            broadcast,
+        })
+    }
+    
     /// Get the relay configuration
     pub fn config(&self) -> &RelayConfig {
         &self.config
@@ -95,6 +254,7 @@ impl BitcoinNostrRelay {
 mod tests {
     use super::*;
     use crate::relay::RelayConfig;
+    use crate::test_utils::{sample_tx_hex, spawn_mock_rpc};
     
     #[test]
     fn test_bitcoin_nostr_relay_creation() {
@@ -222,6 +382,25 @@ mod tests {
         }
     }
     
+    #[tokio::test]
+    async fn test_validate_detailed_reports_txid() {
+        let port = spawn_mock_rpc(serde_json::json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let config = RelayConfig::new(
+            format!("http://127.0.0.1:{}", port),
+            "ws://127.0.0.1:7777",
+            "1",
+            "127.0.0.1:7779".parse().unwrap(),
+        ).unwrap();
+        let relay = BitcoinNostrRelay::new(config).unwrap();
+        
+        let tx_hex = sample_tx_hex();
+        let tx: bitcoin::Transaction = bitcoin::consensus::deserialize(&hex::decode(&tx_hex).unwrap()).unwrap();
+        
+        let report = relay.validate_detailed(&tx_hex).await.unwrap();
+        assert_eq!(report.txid, tx.txid().to_string());
+        assert!(!report.from_cache);
+    }
+    
     #[tokio::test]
     async fn test_broadcast_transaction_without_nostr_client() {
         let config = RelayConfig::for_network(Network::Regtest, 1);
@@ -233,6 +412,139 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("Nostr relay disconnected"));
     }
     
+    fn config_for_port(port: u16) -> RelayConfig {
+        RelayConfig::new(
+            format!("http://127.0.0.1:{}", port),
+            "ws://127.0.0.1:7777",
+            "1",
+            "127.0.0.1:7779".parse().unwrap(),
+        ).unwrap()
+        .with_auth("user".to_string(), "password".to_string())
+        .with_validation(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        })
+    }
+    
+    #[tokio::test]
+    async fn test_submit_transaction_without_nostr_client() {
+        let tx_hex = sample_tx_hex();
+        let port = spawn_mock_rpc(serde_json::json!({"result": "ok", "error": null, "id": 1})).await;
+        let relay = BitcoinNostrRelay::new(config_for_port(port)).unwrap();
+        
+        let outcome = relay.submit_transaction(&tx_hex).await.unwrap();
+        
+        let tx: bitcoin::Transaction = bitcoin::consensus::deserialize(&hex::decode(&tx_hex).unwrap()).unwrap();
+        assert_eq!(outcome.txid, tx.txid().to_string());
+        assert!(!outcome.validated); // Validation disabled
+        assert!(outcome.submitted);
+        assert!(!outcome.broadcast); // No Nostr client connected
+    }
+    
+    #[tokio::test]
+    async fn test_submit_transaction_already_in_mempool() {
+        let port = spawn_mock_rpc(serde_json::json!({
+            "result": null,
+            "error": {"code": -27, "message": "Transaction already in mempool"},
+            "id": 1
+        })).await;
+        let relay = BitcoinNostrRelay::new(config_for_port(port)).unwrap();
+        
+        let outcome = relay.submit_transaction(&sample_tx_hex()).await.unwrap();
+        assert!(!outcome.submitted);
+        assert!(!outcome.broadcast);
+        
+        // A running server reports it the same way rather than failing
+        let mut config = config_for_port(port);
+        config.websocket_listen_addr = "127.0.0.1:0".parse().unwrap();
+        let mut relay = BitcoinNostrRelay::new(config).unwrap();
+        relay.start().await.unwrap();
+        let outcome = relay.submit_transaction(&sample_tx_hex()).await.unwrap();
+        assert!(!outcome.submitted);
+        
+        let handle = relay.relay_handle.as_mut().unwrap();
+        handle.shutdown();
+        handle.wait().await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_submit_transaction_propagates_node_rejection() {
+        let port = spawn_mock_rpc(serde_json::json!({
+            "result": null,
+            "error": {"code": -26, "message": "min relay fee not met"},
+            "id": 1
+        })).await;
+        let relay = BitcoinNostrRelay::new(config_for_port(port)).unwrap();
+        
+        let result = relay.submit_transaction(&sample_tx_hex()).await;
+        assert!(matches!(result, Err(RelayError::BitcoinRpc(_))));
+        assert!(result.unwrap_err().to_string().contains("min relay fee not met"));
+    }
+    
+    #[tokio::test]
+    async fn test_submit_transaction_propagates_decode_error() {
+        let relay = BitcoinNostrRelay::new(config_for_port(1)).unwrap();
+        
+        // Validation is disabled, so malformed input must fail at decode time
+        let result = relay.submit_transaction("not_hex").await;
+        assert!(matches!(result, Err(RelayError::HexDecode(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_submit_through_started_server() {
+        let port = spawn_mock_rpc(serde_json::json!({"result": "ok", "error": null, "id": 1})).await;
+        let mut config = config_for_port(port);
+        config.websocket_listen_addr = "127.0.0.1:0".parse().unwrap();
+        let mut relay = BitcoinNostrRelay::new(config).unwrap();
+        
+        let handle = relay.start().await.unwrap();
+        let mut broadcasts = handle.server().subscribe_transactions(TxFilter::new());
+        let txid = handle.submit_transaction(&sample_tx_hex()).await.unwrap().txid;
+        
+        // Gossiped by the server and remembered so the mempool monitor skips it
+        assert_eq!(broadcasts.recv().await.unwrap().txid.to_string(), txid);
+        assert!(handle.server().dedup_snapshot().await.broadcast_transactions.contains_key(&txid));
+        
+        handle.shutdown();
+        handle.wait().await.unwrap();
+        assert!(relay.relay_handle().is_some());
+        assert!(relay.start().await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_high_level_submit_broadcasts_after_start() {
+        let port = spawn_mock_rpc(serde_json::json!({"result": "ok", "error": null, "id": 1})).await;
+        let mut config = config_for_port(port);
+        config.websocket_listen_addr = "127.0.0.1:0".parse().unwrap();
+        let mut relay = BitcoinNostrRelay::new(config).unwrap();
+        
+        let mut relayed = relay.start().await.unwrap().server().subscribe_transactions(TxFilter::new());
+        let outcome = relay.submit_transaction(&sample_tx_hex()).await.unwrap();
+        assert!(outcome.submitted);
+        assert!(outcome.broadcast);
+        assert_eq!(relayed.recv().await.unwrap().txid.to_string(), outcome.txid);
+        
+        // A plain broadcast goes through the server too rather than failing for want of a Nostr client
+        let mut tx: bitcoin::Transaction = bitcoin::consensus::deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        tx.lock_time = bitcoin::absolute::LockTime::from_consensus(42);
+        relay.broadcast_transaction(&bitcoin::consensus::encode::serialize_hex(&tx), "").await.unwrap();
+        let server = relay.relay_handle().unwrap().server();
+        assert!(server.dedup_snapshot().await.broadcast_transactions.contains_key(&tx.txid().to_string()));
+        
+        let handle = relay.relay_handle.as_mut().unwrap();
+        handle.shutdown();
+        handle.wait().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_submit_transaction_propagates_validation_error() {
+        let config = RelayConfig::for_network(Network::Regtest, 1);
+        let relay = BitcoinNostrRelay::new(config).unwrap();
+        
+        let result = relay.submit_transaction("not_hex").await;
+        assert!(matches!(result, Err(RelayError::Validation(ValidationError::InvalidHex))));
+    }
+    
     #[test]
     fn test_bitcoin_nostr_relay_config_integration() {
         let config = RelayConfig::for_network(Network::Regtest, 1)
@@ -243,7 +555,7 @@ mod tests {
         
         // Config should be properly integrated
         assert_eq!(relay.config.bitcoin_rpc_auth.username, "custom_user");
-        assert_eq!(relay.config.bitcoin_rpc_auth.password, "custom_pass");
+        assert_eq!(relay.config.bitcoin_rpc_auth.password(), "custom_pass");
         assert_eq!(relay.config.mempool_poll_interval.as_secs(), 5);
     }
     
@@ -0,0 +1,554 @@
+use crate::bitcoin_rpc::{BitcoinRpc, BlockHeaderInfo, EstimateMode, FeeEstimate, MempoolEntry, MempoolEntryFees, MempoolSequence, NetworkInfo, PackageResult, PackageTxResult, RawTxInfo};
+use crate::error::BitcoinRpcError;
+use crate::Result;
+use bitcoin::hashes::Hash;
+use bitcoin::{Block, BlockHash, OutPoint, Transaction, Txid};
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct MockState {
+    chain: String,
+    connections: u32,
+    mempool: Vec<String>,
+    /// Bumped whenever a transaction enters or leaves the mempool
+    mempool_sequence: u64,
+    pinned_mempool_sequence: Option<u64>,
+    transactions: HashMap<String, String>,
+    verbose: HashMap<String, RawTxInfo>,
+    fees: HashMap<String, f64>,
+    entry_times: HashMap<String, u64>,
+    fee_rate: Option<f64>,
+    blocks: HashMap<BlockHash, Block>,
+    best_block_hash: Option<BlockHash>,
+    sent: Vec<String>,
+    send_error: Option<(i32, String)>,
+    send_delay: Option<std::time::Duration>,
+    evict_on_send: bool,
+    mempool_entries_unavailable: bool,
+    fetch_delay: Option<std::time::Duration>,
+    fetches_in_flight: usize,
+    max_fetches_in_flight: usize,
+    calls: HashMap<&'static str, usize>,
+}
+
+/// In-memory Bitcoin node with programmable responses, for tests
+/// 
+/// Clones share state, so a test can keep a handle while the relay owns another.
+#[derive(Clone)]
+pub struct MockBitcoinRpc {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Default for MockBitcoinRpc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockBitcoinRpc {
+    /// Create an empty regtest node
+    pub fn new() -> Self {
+        let state = MockState {
+            chain: "regtest".to_string(),
+            ..Default::default()
+        };
+        Self { state: Arc::new(Mutex::new(state)) }
+    }
+    
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock state poisoned")
+    }
+    
+    /// Add a transaction to the mempool, returning its txid
+    pub fn add_mempool_tx(&self, tx_hex: &str) -> String {
+        let txid = txid_of(tx_hex);
+        let mut state = self.state();
+        if !state.mempool.contains(&txid) {
+            state.mempool.push(txid.clone());
+            state.mempool_sequence += 1;
+        }
+        state.transactions.insert(txid.clone(), tx_hex.to_string());
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        state.entry_times.entry(txid.clone()).or_insert(now);
+        txid
+    }
+    
+    /// Make a transaction known to `getrawtransaction` without putting it in the mempool
+    pub fn add_transaction(&self, tx_hex: &str) -> String {
+        let txid = txid_of(tx_hex);
+        self.state().transactions.insert(txid.clone(), tx_hex.to_string());
+        txid
+    }
+    
+    /// Remove a transaction from the mempool (e.g. mined or evicted)
+    pub fn remove_mempool_tx(&self, txid: &str) {
+        let mut state = self.state();
+        let before = state.mempool.len();
+        state.mempool.retain(|t| t != txid);
+        if state.mempool.len() != before {
+            state.mempool_sequence += 1;
+        }
+    }
+    
+    /// Report `sequence` as the mempool sequence number whatever the mempool does (None reports the real one)
+    pub fn pin_mempool_sequence(&self, sequence: Option<u64>) {
+        self.state().pinned_mempool_sequence = sequence;
+    }
+    
+    /// Set the chain reported by `getblockchaininfo`
+    pub fn set_chain(&self, chain: impl Into<String>) {
+        self.state().chain = chain.into();
+    }
+    
+    /// Set the number of p2p peers reported by `getnetworkinfo`
+    pub fn set_connections(&self, connections: u32) {
+        self.state().connections = connections;
+    }
+    
+    /// Program the verbose `getrawtransaction` response for a txid
+    pub fn set_verbose_tx(&self, info: RawTxInfo) {
+        self.state().verbose.insert(info.txid.clone(), info);
+    }
+    
+    /// Set the base fee (in BTC) reported by `getmempoolentry`
+    pub fn set_fee(&self, txid: &str, fee_btc: f64) {
+        self.state().fees.insert(txid.to_string(), fee_btc);
+    }
+    
+    /// Set when a transaction entered the mempool (seconds since epoch), as reported by `getmempoolentry`
+    pub fn set_mempool_time(&self, txid: &str, time: u64) {
+        self.state().entry_times.insert(txid.to_string(), time);
+    }
+    
+    /// Set the fee rate (BTC/kvB) reported by `estimatesmartfee` (None reports insufficient data, as on regtest)
+    pub fn set_fee_rate(&self, fee_rate: Option<f64>) {
+        self.state().fee_rate = fee_rate;
+    }
+    
+    /// Add a block, making it the best block
+    pub fn add_block(&self, block: Block) {
+        let hash = block.block_hash();
+        let mut state = self.state();
+        state.blocks.insert(hash, block);
+        state.best_block_hash = Some(hash);
+    }
+    
+    /// Make `sendrawtransaction` fail with a Bitcoin Core error (None to succeed again)
+    pub fn set_send_error(&self, error: Option<(i32, &str)>) {
+        self.state().send_error = error.map(|(code, message)| (code, message.to_string()));
+    }
+    
+    /// Make `sendrawtransaction` take this long to respond, like a busy node
+    pub fn set_send_delay(&self, delay: Option<std::time::Duration>) {
+        self.state().send_delay = delay;
+    }
+    
+    /// Make `sendrawtransaction` accept transactions without keeping them in the mempool,
+    /// like a full node evicting them straight away
+    pub fn set_evict_on_send(&self, evict: bool) {
+        self.state().evict_on_send = evict;
+    }
+    
+    /// Make `getmempoolentry` fail as if the node couldn't be reached
+    pub fn set_mempool_entries_unavailable(&self, unavailable: bool) {
+        self.state().mempool_entries_unavailable = unavailable;
+    }
+    
+    /// Make `getrawtransaction` take this long to respond, like a node under load
+    pub fn set_fetch_delay(&self, delay: Option<std::time::Duration>) {
+        self.state().fetch_delay = delay;
+    }
+    
+    /// Most `getrawtransaction` calls that were in flight at once while a fetch delay was set
+    pub fn max_concurrent_fetches(&self) -> usize {
+        self.state().max_fetches_in_flight
+    }
+    
+    /// Transactions submitted through `sendrawtransaction`, in order
+    pub fn sent_transactions(&self) -> Vec<String> {
+        self.state().sent.clone()
+    }
+    
+    /// Number of calls made to an RPC method
+    pub fn call_count(&self, method: &str) -> usize {
+        self.state().calls.get(method).copied().unwrap_or(0)
+    }
+    
+    fn record(&self, method: &'static str) {
+        *self.state().calls.entry(method).or_insert(0) += 1;
+    }
+}
+
+fn decode(tx_hex: &str) -> Transaction {
+    let bytes = hex::decode(tx_hex).expect("mock transactions must be valid hex");
+    bitcoin::consensus::deserialize(&bytes).expect("mock transactions must deserialize")
+}
+
+fn txid_of(tx_hex: &str) -> String {
+    decode(tx_hex).txid().to_string()
+}
+
+impl MockState {
+    /// In-mempool parents of a mempool transaction
+    fn depends(&self, txid: &str) -> Vec<String> {
+        let Some(tx_hex) = self.transactions.get(txid) else {
+            return Vec::new();
+        };
+        let mut parents: Vec<String> = Vec::new();
+        for input in decode(tx_hex).input {
+            let parent = input.previous_output.txid.to_string();
+            if self.mempool.contains(&parent) && !parents.contains(&parent) {
+                parents.push(parent);
+            }
+        }
+        parents
+    }
+    
+    /// Number of in-mempool ancestors, including the transaction itself
+    fn ancestor_count(&self, txid: &str) -> u64 {
+        let mut seen: Vec<String> = vec![txid.to_string()];
+        let mut frontier = vec![txid.to_string()];
+        while let Some(current) = frontier.pop() {
+            for parent in self.depends(&current) {
+                if !seen.contains(&parent) {
+                    seen.push(parent.clone());
+                    frontier.push(parent);
+                }
+            }
+        }
+        seen.len() as u64
+    }
+    
+    /// Number of ancestors of a block the mock knows about
+    fn height(&self, block: &Block) -> u64 {
+        let mut height = 0;
+        let mut prev = block.header.prev_blockhash;
+        while let Some(parent) = self.blocks.get(&prev) {
+            height += 1;
+            prev = parent.header.prev_blockhash;
+        }
+        height
+    }
+    
+    /// Mempool transaction spending an outpoint
+    fn spender(&self, outpoint: &OutPoint) -> Option<String> {
+        self.mempool.iter().find(|txid| {
+            self.transactions
+                .get(*txid)
+                .is_some_and(|tx_hex| decode(tx_hex).input.iter().any(|input| input.previous_output == *outpoint))
+        }).cloned()
+    }
+    
+    fn mempool_entry(&self, txid: &str) -> Option<MempoolEntry> {
+        if !self.mempool.iter().any(|t| t == txid) {
+            return None;
+        }
+        let tx = decode(self.transactions.get(txid)?);
+        let fee = self.fees.get(txid).copied().unwrap_or(0.0);
+        let spent_by = self
+            .mempool
+            .iter()
+            .filter(|other| self.depends(other).iter().any(|p| p == txid))
+            .cloned()
+            .collect();
+        Some(MempoolEntry {
+            vsize: tx.vsize(),
+            weight: Some(tx.weight().to_wu()),
+            time: self.entry_times.get(txid).copied().unwrap_or(0),
+            ancestorcount: self.ancestor_count(txid),
+            descendantcount: 1,
+            fees: MempoolEntryFees { base: fee, modified: fee, ancestor: fee, descendant: fee },
+            depends: self.depends(txid),
+            spent_by,
+        })
+    }
+}
+
+fn not_found(what: &str) -> crate::RelayError {
+    BitcoinRpcError::bitcoin_core(-5, format!("{} not found", what)).into()
+}
+
+impl BitcoinRpc for MockBitcoinRpc {
+    fn get_best_block_hash(&self) -> BoxFuture<'_, Result<BlockHash>> {
+        self.record("getbestblockhash");
+        let result = self.state().best_block_hash.ok_or_else(|| not_found("Block"));
+        Box::pin(async move { result })
+    }
+    
+    fn get_block<'a>(&'a self, block_hash: &'a BlockHash) -> BoxFuture<'a, Result<Block>> {
+        self.record("getblock");
+        let result = self.state().blocks.get(block_hash).cloned().ok_or_else(|| not_found("Block"));
+        Box::pin(async move { result })
+    }
+    
+    fn get_block_txids<'a>(&'a self, block_hash: &'a BlockHash) -> BoxFuture<'a, Result<(BlockHeaderInfo, Vec<Txid>)>> {
+        self.record("getblock");
+        let state = self.state();
+        let result = state.blocks.get(block_hash).map(|block| {
+            let header = BlockHeaderInfo {
+                hash: *block_hash,
+                height: state.height(block),
+                version: block.header.version.to_consensus(),
+                previous_block_hash: Some(block.header.prev_blockhash).filter(|prev| *prev != BlockHash::all_zeros()),
+                merkle_root: block.header.merkle_root.to_string(),
+                time: block.header.time as u64,
+                confirmations: 1,
+            };
+            (header, block.txdata.iter().map(Transaction::txid).collect())
+        });
+        let result = result.ok_or_else(|| not_found("Block"));
+        Box::pin(async move { result })
+    }
+    
+    fn get_chain(&self) -> BoxFuture<'_, Result<String>> {
+        self.record("getblockchaininfo");
+        let chain = self.state().chain.clone();
+        Box::pin(async move { Ok(chain) })
+    }
+    
+    fn get_network_info(&self) -> BoxFuture<'_, Result<NetworkInfo>> {
+        self.record("getnetworkinfo");
+        let connections = self.state().connections;
+        let info = NetworkInfo {
+            version: 260000,
+            subversion: "/Satoshi:26.0.0/".to_string(),
+            connections,
+            connections_in: Some(0),
+            connections_out: Some(connections),
+            network_active: true,
+        };
+        Box::pin(async move { Ok(info) })
+    }
+    
+    fn send_raw_transaction<'a>(&'a self, tx_hex: &'a str) -> BoxFuture<'a, Result<String>> {
+        self.record("sendrawtransaction");
+        let (send_error, send_delay, evict_on_send) = {
+            let state = self.state();
+            (state.send_error.clone(), state.send_delay, state.evict_on_send)
+        };
+        let result = match send_error {
+            Some((code, message)) => Err(BitcoinRpcError::bitcoin_core(code, message).into()),
+            None if evict_on_send => {
+                self.state().sent.push(tx_hex.to_string());
+                Ok(self.add_transaction(tx_hex))
+            }
+            None => {
+                self.state().sent.push(tx_hex.to_string());
+                Ok(self.add_mempool_tx(tx_hex))
+            }
+        };
+        Box::pin(async move {
+            if let Some(delay) = send_delay {
+                tokio::time::sleep(delay).await;
+            }
+            result
+        })
+    }
+    
+    fn get_raw_mempool(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        self.record("getrawmempool");
+        let mempool = self.state().mempool.clone();
+        Box::pin(async move { Ok(mempool) })
+    }
+    
+    fn get_raw_mempool_sequence(&self) -> BoxFuture<'_, Result<MempoolSequence>> {
+        self.record("getrawmempool_sequence");
+        let state = self.state();
+        let mempool = MempoolSequence {
+            txids: state.mempool.clone(),
+            mempool_sequence: state.pinned_mempool_sequence.unwrap_or(state.mempool_sequence),
+        };
+        Box::pin(async move { Ok(mempool) })
+    }
+    
+    fn get_raw_transaction<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<String>> {
+        self.record("getrawtransaction");
+        let (result, fetch_delay) = {
+            let mut state = self.state();
+            if state.fetch_delay.is_some() {
+                state.fetches_in_flight += 1;
+                state.max_fetches_in_flight = state.max_fetches_in_flight.max(state.fetches_in_flight);
+            }
+            (state.transactions.get(txid).cloned().ok_or_else(|| not_found("Transaction")), state.fetch_delay)
+        };
+        Box::pin(async move {
+            if let Some(delay) = fetch_delay {
+                tokio::time::sleep(delay).await;
+                self.state().fetches_in_flight -= 1;
+            }
+            result
+        })
+    }
+    
+    fn get_raw_transaction_verbose<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<RawTxInfo>> {
+        self.record("getrawtransaction");
+        let result = self.state().verbose.get(txid).cloned().ok_or_else(|| not_found("Transaction"));
+        Box::pin(async move { result })
+    }
+    
+    fn get_mempool_entry<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<MempoolEntry>> {
+        self.record("getmempoolentry");
+        let state = self.state();
+        let result = if state.mempool_entries_unavailable {
+            Err(BitcoinRpcError::connection_failed("mock").into())
+        } else {
+            state.mempool_entry(txid).ok_or_else(|| not_found("Transaction"))
+        };
+        drop(state);
+        Box::pin(async move { result })
+    }
+    
+    /// Accepts the whole package unless a send error is configured, which rejects every member
+    fn submit_package<'a>(&'a self, tx_hexes: &'a [String]) -> BoxFuture<'a, Result<PackageResult>> {
+        self.record("submitpackage");
+        let send_error = self.state().send_error.clone();
+        let mut tx_results = HashMap::new();
+        for tx_hex in tx_hexes {
+            let tx = decode(tx_hex);
+            let error = send_error.as_ref().map(|(_, message)| message.clone());
+            if error.is_none() {
+                self.state().sent.push(tx_hex.clone());
+                self.add_mempool_tx(tx_hex);
+            }
+            tx_results.insert(tx.wtxid().to_string(), PackageTxResult {
+                txid: tx.txid().to_string(),
+                other_wtxid: None,
+                vsize: Some(tx.vsize()),
+                fees: None,
+                error,
+            });
+        }
+        let package_msg = if send_error.is_some() { "transaction failed" } else { "success" };
+        let result = Ok(PackageResult {
+            package_msg: package_msg.to_string(),
+            tx_results,
+            replaced_transactions: Vec::new(),
+        });
+        Box::pin(async move { result })
+    }
+    
+    fn estimate_smart_fee(&self, conf_target: u16, _mode: EstimateMode) -> BoxFuture<'_, Result<FeeEstimate>> {
+        self.record("estimatesmartfee");
+        let estimate = match self.state().fee_rate {
+            Some(fee_rate) => FeeEstimate {
+                fee_rate: Some(fee_rate),
+                blocks: u32::from(conf_target.max(2)),
+                errors: Vec::new(),
+            },
+            None => FeeEstimate {
+                fee_rate: None,
+                blocks: 0,
+                errors: vec!["Insufficient data or no feerate found".to_string()],
+            },
+        };
+        Box::pin(async move { Ok(estimate) })
+    }
+    
+    fn get_tx_spending_prevout<'a>(&'a self, outpoints: &'a [OutPoint]) -> BoxFuture<'a, Result<Vec<Option<String>>>> {
+        self.record("gettxspendingprevout");
+        let state = self.state();
+        let spenders = outpoints.iter().map(|outpoint| state.spender(outpoint)).collect();
+        Box::pin(async move { Ok(spenders) })
+    }
+    
+    fn get_block_count(&self) -> BoxFuture<'_, Result<u64>> {
+        self.record("getblockcount");
+        let state = self.state();
+        let result = state
+            .best_block_hash
+            .and_then(|best| state.blocks.get(&best))
+            .map(|block| state.height(block))
+            .ok_or_else(|| not_found("Block"));
+        Box::pin(async move { result })
+    }
+    
+    /// Walks back from the best block, so only blocks on the best chain are found
+    fn get_block_hash(&self, height: u64) -> BoxFuture<'_, Result<BlockHash>> {
+        self.record("getblockhash");
+        let state = self.state();
+        let mut hash = state.best_block_hash;
+        let mut found = None;
+        while let Some(block) = hash.and_then(|hash| state.blocks.get(&hash)) {
+            match state.height(block).cmp(&height) {
+                std::cmp::Ordering::Equal => {
+                    found = hash;
+                    break;
+                }
+                std::cmp::Ordering::Less => break,
+                std::cmp::Ordering::Greater => hash = Some(block.header.prev_blockhash),
+            }
+        }
+        let result = found.ok_or_else(|| BitcoinRpcError::bitcoin_core(-8, "Block height out of range").into());
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::sample_tx_hex;
+
+    #[tokio::test]
+    async fn test_mock_mempool_roundtrip() {
+        let mock = MockBitcoinRpc::new();
+        let rpc: &dyn BitcoinRpc = &mock;
+        
+        let tx_hex = sample_tx_hex();
+        let txid = mock.add_mempool_tx(&tx_hex);
+        
+        assert_eq!(rpc.get_raw_mempool().await.unwrap(), vec![txid.clone()]);
+        assert_eq!(rpc.get_raw_transaction(&txid).await.unwrap(), tx_hex);
+        
+        let sequence = rpc.get_raw_mempool_sequence().await.unwrap().mempool_sequence;
+        mock.remove_mempool_tx(&txid);
+        assert!(rpc.get_raw_mempool().await.unwrap().is_empty());
+        assert_eq!(mock.call_count("getrawmempool"), 2);
+        assert_eq!(rpc.get_raw_mempool_sequence().await.unwrap().mempool_sequence, sequence + 1);
+    }
+
+    #[tokio::test]
+    async fn test_mock_send_raw_transaction() {
+        let mock = MockBitcoinRpc::new();
+        let tx_hex = sample_tx_hex();
+        
+        let txid = BitcoinRpc::send_raw_transaction(&mock, &tx_hex).await.unwrap();
+        assert_eq!(mock.sent_transactions(), vec![tx_hex.clone()]);
+        assert_eq!(BitcoinRpc::get_raw_mempool(&mock).await.unwrap(), vec![txid]);
+        
+        mock.set_send_error(Some((-26, "min relay fee not met")));
+        let result = BitcoinRpc::send_raw_transaction(&mock, &tx_hex).await;
+        assert!(matches!(result, Err(crate::RelayError::BitcoinRpc(BitcoinRpcError::BitcoinCore { code: -26, .. }))));
+        assert_eq!(mock.sent_transactions().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mock_mempool_entry_depends() {
+        let mock = MockBitcoinRpc::new();
+        let parent_hex = sample_tx_hex();
+        let parent_txid = mock.add_mempool_tx(&parent_hex);
+        
+        let mut child = decode(&parent_hex);
+        child.input[0].previous_output = bitcoin::OutPoint::new(parent_txid.parse().unwrap(), 0);
+        let child_txid = mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&child)));
+        
+        let entry = BitcoinRpc::get_mempool_entry(&mock, &child_txid).await.unwrap();
+        assert_eq!(entry.depends, vec![parent_txid.clone()]);
+        assert_eq!(entry.ancestorcount, 2);
+        
+        let parent_entry = BitcoinRpc::get_mempool_entry(&mock, &parent_txid).await.unwrap();
+        assert!(parent_entry.depends.is_empty());
+        assert_eq!(parent_entry.spent_by, vec![child_txid]);
+    }
+
+    #[tokio::test]
+    async fn test_mock_unknown_transaction() {
+        let mock = MockBitcoinRpc::new();
+        assert!(BitcoinRpc::get_raw_transaction(&mock, "missing").await.is_err());
+        assert_eq!(BitcoinRpc::get_chain(&mock).await.unwrap(), "regtest");
+    }
+}
@@ -1,5 +1,9 @@
+use crate::error::ConfigError;
 use crate::relay::RelayConfig;
+use crate::validation::ValidationConfig;
+use std::fmt;
 use std::net::SocketAddr;
+use std::str::FromStr;
 
 /// Common Bitcoin network types for convenient relay configuration
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -8,11 +12,54 @@ pub enum Network {
     Testnet4,
 }
 
+impl Network {
+    /// Lowercase network name as used in Nostr event content
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Network::Regtest => "regtest",
+            Network::Testnet4 => "testnet4",
+        }
+    }
+    
+    /// Whether a chain name reported by Bitcoin Core's `getblockchaininfo` is this network
+    pub fn matches_chain(&self, chain: &str) -> bool {
+        match self {
+            Network::Regtest => chain == "regtest",
+            Network::Testnet4 => chain == "testnet4",
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Network {
+    type Err = ConfigError;
+    
+    /// Parse a network name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "regtest" => Ok(Network::Regtest),
+            "testnet4" => Ok(Network::Testnet4),
+            _ => Err(ConfigError::InvalidParameter {
+                param: format!("unknown network {:?}, expected regtest or testnet4", s),
+            }),
+        }
+    }
+}
+
 /// Generate configuration for common network patterns
 /// 
 /// This function provides the convenience layer mentioned in the migration plan,
 /// allowing users to quickly configure relays for common scenarios while still
 /// using the explicit configuration API underneath.
+/// 
+/// Validation starts from [`ValidationConfig::for_network`] and can be replaced
+/// with `with_validation`. The relay listens on IPv4 loopback; use
+/// `with_listen_ip` to listen on `::1`, `[::]` or another address.
 pub fn network_config(network: Network, relay_id: u16) -> RelayConfig {
     let (bitcoin_port, websocket_port, strfry_port) = match (network, relay_id) {
         (Network::Regtest, 1) => (18332, 7779, 7777),
@@ -28,6 +75,8 @@ pub fn network_config(network: Network, relay_id: u16) -> RelayConfig {
         relay_id.to_string(),
         SocketAddr::from(([127, 0, 0, 1], websocket_port)),
     ).expect("Hardcoded network configuration should always be valid")
+    .with_network(network)
+    .with_validation(ValidationConfig::for_network(network))
 }
 
 #[cfg(test)]
@@ -71,10 +120,55 @@ mod tests {
             .with_mempool_poll_interval_secs(5);
             
         assert_eq!(config.bitcoin_rpc_auth.username, "custom_user");
-        assert_eq!(config.bitcoin_rpc_auth.password, "custom_pass");
+        assert_eq!(config.bitcoin_rpc_auth.password(), "custom_pass");
         assert_eq!(config.mempool_poll_interval.as_secs(), 5);
     }
 
+    #[test]
+    fn test_network_config_sets_network() {
+        assert_eq!(network_config(Network::Regtest, 1).network, Some(Network::Regtest));
+        assert_eq!(network_config(Network::Testnet4, 2).network, Some(Network::Testnet4));
+        assert_eq!(Network::Testnet4.as_str(), "testnet4");
+    }
+
+    #[test]
+    fn test_network_validation_defaults() {
+        let regtest = network_config(Network::Regtest, 1).validation_config;
+        let testnet4 = network_config(Network::Testnet4, 1).validation_config;
+        
+        assert_eq!(regtest.min_fee_rate, None);
+        assert_eq!(testnet4.min_fee_rate, Some(1.0));
+        assert!(regtest.cache_ttl_seconds < testnet4.cache_ttl_seconds);
+        assert!(testnet4.use_testmempoolaccept && testnet4.enable_precheck);
+        
+        // Still overridable
+        let custom = network_config(Network::Testnet4, 1).with_validation(ValidationConfig::default());
+        assert_eq!(custom.validation_config.min_fee_rate, None);
+    }
+
+    #[test]
+    fn test_network_matches_chain() {
+        assert!(Network::Regtest.matches_chain("regtest"));
+        assert!(Network::Testnet4.matches_chain("testnet4"));
+        assert!(!Network::Regtest.matches_chain("main"));
+        assert!(!Network::Testnet4.matches_chain("test"));
+    }
+
+    #[test]
+    fn test_network_from_str() {
+        assert_eq!("regtest".parse::<Network>().unwrap(), Network::Regtest);
+        assert_eq!("Testnet4".parse::<Network>().unwrap(), Network::Testnet4);
+        assert_eq!(" REGTEST\n".parse::<Network>().unwrap(), Network::Regtest);
+        
+        for network in [Network::Regtest, Network::Testnet4] {
+            assert_eq!(network.to_string().parse::<Network>().unwrap(), network);
+        }
+        
+        for invalid in ["", "testnet", "main", "regtest4"] {
+            assert!(matches!(invalid.parse::<Network>(), Err(ConfigError::InvalidParameter { .. })));
+        }
+    }
+
     #[test]
     #[should_panic(expected = "Unsupported configuration")]
     fn test_network_config_unsupported() {
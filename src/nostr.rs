@@ -1,27 +1,452 @@
-use crate::Result;
-use futures_util::{SinkExt, StreamExt};
-use nostr::{Event, EventBuilder, Keys, Kind, Tag};
+use crate::error::{BitcoinRpcError, NostrError, ValidationError};
+use crate::networks::Network;
+use crate::relay::{BroadcastEncoding, TxBroadcastPayload};
+use crate::{RelayError, Result};
+use bitcoin::{Transaction, Txid};
+use nostr::prelude::{ToBech32, XOnlyPublicKey};
+use nostr::{Event, EventBuilder, EventId, Keys, Kind, Tag};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
-use tokio_tungstenite::{tungstenite::protocol::Message, WebSocketStream, MaybeTlsStream};
+use crate::transport::{connect_websocket, NostrTransport};
+use tokio_tungstenite::{WebSocketStream, MaybeTlsStream};
 use tracing::{info, warn};
 
-pub struct NostrClient {
-    ws_stream: Arc<Mutex<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>>>,
+/// Event kind of raw transactions submitted by clients
+pub const KIND_SUBMIT_TX: u16 = 20010;
+
+/// Event kind of a relay's response to a client request
+pub const KIND_TX_RESPONSE: u16 = 20011;
+
+/// Event kind of transaction broadcasts between relays
+pub const KIND_TX_BROADCAST: u16 = 20012;
+
+/// Event kind of requests for transactions by txid
+pub const KIND_REQUEST_TX: u16 = 20013;
+
+/// Event kind of notices that transactions left a relay's mempool without confirming
+pub const KIND_TX_EVICTED: u16 = 20020;
+
+/// Event kind of raw transactions forwarded by a peer relay, signed with its identity key
+pub const KIND_FORWARD_TX: u16 = 20022;
+
+/// Event kind of notices that a relay's node accepted transactions broadcast by a peer
+pub const KIND_TX_ACCEPTED: u16 = 20024;
+
+/// Tag of a forwarded transaction counting the relays it passed through before the forwarding relay
+pub const HOPS_TAG: &str = "hops";
+
+/// Default time [`RelaySubmitClient`] waits for a relay's response
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Prefix for the custom tags relays write and read, such as `relay_id`
+///
+/// On a strfry shared with other applications, a prefix like `btcrelay` turns
+/// `relay_id` into `btcrelay:relay_id` so the tags can't collide. The default
+/// namespace leaves tag names unprefixed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagNamespace(Option<String>);
+
+impl TagNamespace {
+    /// Namespace tags with `prefix`; an empty prefix is the default namespace
+    pub fn new(prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        Self((!prefix.is_empty()).then_some(prefix))
+    }
+    
+    /// Full name of the custom tag `name` in this namespace
+    pub fn tag_name(&self, name: &str) -> String {
+        match &self.0 {
+            Some(prefix) => format!("{}:{}", prefix, name),
+            None => name.to_string(),
+        }
+    }
+    
+    /// Custom tag `name` in this namespace carrying `value`
+    pub fn tag(&self, name: &str, value: impl Into<String>) -> Tag {
+        Tag::Generic(nostr::TagKind::Custom(self.tag_name(name)), vec![value.into()])
+    }
+    
+    /// First value of the custom tag `name` in this namespace, if the event has one
+    ///
+    /// Tags are compared in their raw `[name, value, ...]` form, so a match doesn't
+    /// depend on which [`Tag`] variant the nostr crate parses the tag into.
+    pub fn value(&self, event: &Event, name: &str) -> Option<String> {
+        let full_name = self.tag_name(name);
+        event.tags.iter().find_map(|tag| {
+            let mut fields = tag.as_vec().into_iter();
+            (fields.next()? == full_name).then(|| fields.next()).flatten()
+        })
+    }
+}
+
+/// Stable, machine-readable outcome of a submission, sent alongside the human-readable message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ResponseCode {
+    /// The transaction or package was accepted by the node
+    Accepted,
+    /// A transaction or fee estimate request was answered
+    Ok,
+    /// The requested transaction is unknown to the node
+    NotFound,
+    /// The submitter's pubkey is not allowed to submit
+    Unauthorized,
+    /// The request content was malformed
+    InvalidRequest,
+    /// The transaction was not valid hex
+    InvalidHex,
+    /// The transaction could not be decoded or is structurally invalid
+    InvalidTransaction,
+    /// The transaction was already processed or is already in the mempool or chain
+    AlreadyKnown,
+    /// The fee rate is below the relay's or the node's minimum
+    FeeTooLow,
+    /// A replacement did not pay enough to replace the transactions it conflicts with
+    ReplacementUnderpriced,
+    /// The transaction conflicts with a mempool transaction that cannot be replaced
+    MempoolConflict,
+    /// The transaction breaks the relay's standardness or output policy
+    PolicyRejected,
+    /// The submission rate limit was exceeded
+    RateLimited,
+    /// Too many submissions are in flight to the node
+    NodeBusy,
+    /// The Bitcoin node could not be reached
+    NodeUnavailable,
+    /// The Bitcoin node rejected the transaction for another reason
+    NodeRejected,
+    /// The Bitcoin node failed to answer a transaction or fee estimate request
+    NodeError,
+    /// An accepted transaction left the node's mempool without confirming
+    Evicted,
+    /// An accepted transaction was replaced in the node's mempool by a conflicting transaction
+    Replaced,
+    /// A peer relay's node accepted a transaction this relay gossiped
+    PropagationConfirmed,
+    /// No peer relay reported accepting a gossiped transaction in time
+    PropagationTimedOut,
+    /// The relay failed for a reason unrelated to the transaction
+    InternalError,
+    /// A code added by a newer relay
+    #[serde(other)]
+    Unknown,
+}
+
+impl ResponseCode {
+    /// Whether the response reports success
+    pub fn is_success(self) -> bool {
+        matches!(self, Self::Accepted | Self::Ok | Self::PropagationConfirmed)
+    }
+    
+    /// Classify a failure to answer a transaction or fee estimate request
+    pub fn for_lookup_error(err: &RelayError) -> Self {
+        match err {
+            RelayError::BitcoinRpc(BitcoinRpcError::BitcoinCore { code: -5, .. } | BitcoinRpcError::TxIndexRequired { .. }) => Self::NotFound,
+            RelayError::Validation(ValidationError::InvalidTxid { .. }) => Self::InvalidRequest,
+            _ => match Self::from(err) {
+                code @ (Self::NodeUnavailable | Self::NodeBusy | Self::RateLimited) => code,
+                _ => Self::NodeError,
+            },
+        }
+    }
+    
+    /// Classify a rejection reason reported by Bitcoin Core
+    pub fn for_core_reason(reason: &str) -> Self {
+        if reason.contains("txn-already") || reason.contains("already in mempool") || reason.contains("already in block chain") {
+            Self::AlreadyKnown
+        } else if reason.contains("min relay fee not met") || reason.contains("mempool min fee not met") {
+            Self::FeeTooLow
+        } else {
+            Self::NodeRejected
+        }
+    }
+}
+
+impl From<&ValidationError> for ResponseCode {
+    fn from(err: &ValidationError) -> Self {
+        match err {
+            ValidationError::InvalidHex => Self::InvalidHex,
+            ValidationError::EmptyTransaction | ValidationError::InvalidSize { .. } | ValidationError::InvalidStructure => Self::InvalidTransaction,
+            ValidationError::InvalidTxid { .. } => Self::InvalidRequest,
+            ValidationError::RecentlyProcessed { .. } => Self::AlreadyKnown,
+            ValidationError::BitcoinCoreRejection { reason } => Self::for_core_reason(reason),
+            ValidationError::Timeout | ValidationError::NodeUnavailable => Self::NodeUnavailable,
+            ValidationError::Disabled => Self::InternalError,
+            ValidationError::ReplacementUnderpriced { .. } => Self::ReplacementUnderpriced,
+            ValidationError::MempoolConflict { .. } => Self::MempoolConflict,
+            ValidationError::FeeRateTooLow { .. } => Self::FeeTooLow,
+            ValidationError::OutputPolicy { .. }
+            | ValidationError::NonStandardVersion { .. }
+            | ValidationError::NonPushScriptSig { .. }
+            | ValidationError::DustOutput { .. }
+            | ValidationError::ImmatureCoinbaseSpend { .. }
+            | ValidationError::TooManyInputs { .. }
+            | ValidationError::TooManyOutputs { .. } => Self::PolicyRejected,
+        }
+    }
+}
+
+impl From<&BitcoinRpcError> for ResponseCode {
+    fn from(err: &BitcoinRpcError) -> Self {
+        match err {
+            BitcoinRpcError::BitcoinCore { code, message } => (&crate::validation::core_rejection(Some(*code), message)).into(),
+            BitcoinRpcError::RateLimited => Self::RateLimited,
+            BitcoinRpcError::ServerBusy => Self::NodeBusy,
+            BitcoinRpcError::ConnectionFailed { .. }
+            | BitcoinRpcError::RequestFailed { .. }
+            | BitcoinRpcError::InvalidResponse
+            | BitcoinRpcError::AuthenticationFailed => Self::NodeUnavailable,
+            BitcoinRpcError::TxIndexRequired { .. } | BitcoinRpcError::UnsupportedMethod { .. } => Self::InternalError,
+        }
+    }
+}
+
+impl From<&RelayError> for ResponseCode {
+    fn from(err: &RelayError) -> Self {
+        match err {
+            RelayError::Validation(e) => e.into(),
+            RelayError::BitcoinRpc(e) => e.into(),
+            RelayError::Http(_) => Self::NodeUnavailable,
+            RelayError::HexDecode(_) => Self::InvalidHex,
+            RelayError::Bitcoin(_) => Self::InvalidTransaction,
+            _ => Self::InternalError,
+        }
+    }
+}
+
+/// Content of a [`KIND_TX_RESPONSE`] event answering a submission or transaction request
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SubmitResponse {
+    pub success: bool,
+    pub message: String,
+    /// Machine-readable outcome of a submission; absent on transaction lookups and from older relays
+    #[serde(default)]
+    pub code: Option<ResponseCode>,
+    /// Empty when the relay rejected the transaction before decoding it
+    #[serde(default)]
+    pub txid: String,
+    /// Raw transaction, when answering a transaction request
+    #[serde(default)]
+    pub hex: Option<String>,
+    /// Whether an accepted transaction was still in the node's mempool shortly after submission,
+    /// in the follow-up response of a relay that checks
+    #[serde(default)]
+    pub resident: Option<bool>,
+}
+
+/// Build a signed transaction broadcast event for `tx`
+pub fn build_tx_broadcast(tx: &Transaction, relay_id: &str, network: Option<Network>, keys: &Keys) -> Result<Event> {
+    build_tx_broadcast_payload(&TxBroadcastPayload::from_transaction(tx), relay_id, network, keys)
+}
+
+/// Build a signed transaction broadcast event from a prepared payload
+/// 
+/// When `network` is known it is added as a `t` tag so subscribers can filter by chain.
+pub fn build_tx_broadcast_payload(payload: &TxBroadcastPayload, relay_id: &str, network: Option<Network>, keys: &Keys) -> Result<Event> {
+    build_tx_broadcast_encoded(payload, BroadcastEncoding::Verbose, relay_id, network, keys)
+}
+
+/// Build a signed transaction broadcast event, writing the payload in `encoding`
+pub fn build_tx_broadcast_encoded(
+    payload: &TxBroadcastPayload,
+    encoding: BroadcastEncoding,
+    relay_id: &str,
+    network: Option<Network>,
+    keys: &Keys,
+) -> Result<Event> {
+    build_tx_broadcast_encoded_with_namespace(payload, encoding, relay_id, &TagNamespace::default(), network, keys)
+}
+
+/// Like [`build_tx_broadcast_encoded`], writing the `relay_id` tag in `namespace`
+pub fn build_tx_broadcast_encoded_with_namespace(
+    payload: &TxBroadcastPayload,
+    encoding: BroadcastEncoding,
+    relay_id: &str,
+    namespace: &TagNamespace,
+    network: Option<Network>,
+    keys: &Keys,
+) -> Result<Event> {
+    let mut tags = vec![
+        Tag::Hashtag("bitcoin".to_string()),
+        Tag::Hashtag("transaction".to_string()),
+        namespace.tag("relay_id", relay_id),
+    ];
+    if let Some(network) = network {
+        tags.push(Tag::Hashtag(network.as_str().to_string()));
+    }
+    let event = EventBuilder::new(
+        Kind::Ephemeral(KIND_TX_BROADCAST),
+        payload.to_content(encoding)?,
+        &tags,
+    ).to_event(keys)?;
+    Ok(event)
+}
+
+/// `#t` values a relay subscribes to for transaction broadcasts
+/// 
+/// A relay with a configured network only asks for broadcasts tagged with it, so
+/// events from other chains are never delivered.
+pub fn tx_broadcast_topics(network: Option<Network>) -> Vec<&'static str> {
+    match network {
+        Some(network) => vec![network.as_str()],
+        None => vec!["bitcoin", "transaction"],
+    }
+}
+
+/// Parse the payload of a transaction broadcast event
+/// 
+/// Fields left out of a compact broadcast are derived from its hex.
+pub fn parse_tx_broadcast(event: &Event) -> std::result::Result<TxBroadcastPayload, NostrError> {
+    if event.kind != Kind::Ephemeral(KIND_TX_BROADCAST) {
+        return Err(NostrError::invalid_tx_broadcast(format!("unexpected event kind {}", event.kind.as_u32())));
+    }
+    let mut payload: TxBroadcastPayload = serde_json::from_str(&event.content).map_err(|e| NostrError::invalid_tx_broadcast(e.to_string()))?;
+    payload.fill_derived_fields();
+    Ok(payload)
+}
+
+/// Build a signed notice that `txids` left this relay's mempool without confirming
+///
+/// Tagged like a transaction broadcast, so peers can filter it by network.
+pub fn build_tx_eviction(txids: &[Txid], relay_id: &str, network: Option<Network>, keys: &Keys) -> Result<Event> {
+    build_tx_eviction_with_namespace(txids, relay_id, &TagNamespace::default(), network, keys)
+}
+
+/// Like [`build_tx_eviction`], writing the `relay_id` tag in `namespace`
+pub fn build_tx_eviction_with_namespace(txids: &[Txid], relay_id: &str, namespace: &TagNamespace, network: Option<Network>, keys: &Keys) -> Result<Event> {
+    let mut tags = vec![
+        Tag::Hashtag("bitcoin".to_string()),
+        Tag::Hashtag("transaction".to_string()),
+        namespace.tag("relay_id", relay_id),
+    ];
+    if let Some(network) = network {
+        tags.push(Tag::Hashtag(network.as_str().to_string()));
+    }
+    let txids: Vec<String> = txids.iter().map(Txid::to_string).collect();
+    let event = EventBuilder::new(
+        Kind::Ephemeral(KIND_TX_EVICTED),
+        json!({ "txids": txids }).to_string(),
+        &tags,
+    ).to_event(keys)?;
+    Ok(event)
+}
+
+/// Parse the evicted txids out of an eviction notice
+pub fn parse_tx_eviction(event: &Event) -> std::result::Result<Vec<Txid>, NostrError> {
+    #[derive(Deserialize)]
+    struct Content {
+        txids: Vec<String>,
+    }
+    
+    if event.kind != Kind::Ephemeral(KIND_TX_EVICTED) {
+        return Err(NostrError::invalid_tx_eviction(format!("unexpected event kind {}", event.kind.as_u32())));
+    }
+    let content: Content = serde_json::from_str(&event.content).map_err(|e| NostrError::invalid_tx_eviction(e.to_string()))?;
+    content.txids
+        .iter()
+        .map(|txid| crate::validation::parse_txid(txid).map_err(|e| NostrError::invalid_tx_eviction(e.to_string())))
+        .collect()
+}
+
+/// Build a signed notice that this relay's node accepted `txids` broadcast by a peer
+///
+/// Sent for broadcasts that asked for one, and tagged like an eviction notice.
+pub fn build_tx_acceptance_with_namespace(txids: &[Txid], relay_id: &str, namespace: &TagNamespace, network: Option<Network>, keys: &Keys) -> Result<Event> {
+    let mut tags = vec![
+        Tag::Hashtag("bitcoin".to_string()),
+        Tag::Hashtag("transaction".to_string()),
+        namespace.tag("relay_id", relay_id),
+    ];
+    if let Some(network) = network {
+        tags.push(Tag::Hashtag(network.as_str().to_string()));
+    }
+    let txids: Vec<String> = txids.iter().map(Txid::to_string).collect();
+    let event = EventBuilder::new(
+        Kind::Ephemeral(KIND_TX_ACCEPTED),
+        json!({ "txids": txids }).to_string(),
+        &tags,
+    ).to_event(keys)?;
+    Ok(event)
+}
+
+/// Parse the accepted txids out of an acceptance notice
+pub fn parse_tx_acceptance(event: &Event) -> std::result::Result<Vec<Txid>, NostrError> {
+    #[derive(Deserialize)]
+    struct Content {
+        txids: Vec<String>,
+    }
+    
+    if event.kind != Kind::Ephemeral(KIND_TX_ACCEPTED) {
+        return Err(NostrError::invalid_tx_acceptance(format!("unexpected event kind {}", event.kind.as_u32())));
+    }
+    let content: Content = serde_json::from_str(&event.content).map_err(|e| NostrError::invalid_tx_acceptance(e.to_string()))?;
+    content.txids
+        .iter()
+        .map(|txid| crate::validation::parse_txid(txid).map_err(|e| NostrError::invalid_tx_acceptance(e.to_string())))
+        .collect()
+}
+
+/// Parse the content of a transaction response event
+pub fn parse_tx_response(event: &Event) -> std::result::Result<SubmitResponse, NostrError> {
+    if event.kind != Kind::Ephemeral(KIND_TX_RESPONSE) {
+        return Err(NostrError::invalid_tx_response(format!("unexpected event kind {}", event.kind.as_u32())));
+    }
+    serde_json::from_str(&event.content).map_err(|e| NostrError::invalid_tx_response(e.to_string()))
+}
+
+/// Client for publishing events to a Nostr relay over any [`NostrTransport`]
+pub struct NostrClient<T = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>> {
+    transport: Arc<Mutex<T>>,
     keys: Keys,
+    tag_namespace: TagNamespace,
 }
 
-impl NostrClient {
-    pub fn new(ws_stream: WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>) -> Self {
-        // Generate random keys for demonstration - in production, use persistent keys
-        let keys = Keys::generate();
-        
+impl<T: NostrTransport> NostrClient<T> {
+    /// Create a client signing with freshly generated keys
+    ///
+    /// Use [`NostrClient::with_keys`] for a stable identity across restarts.
+    pub fn new(transport: T) -> Self {
+        Self::with_keys(transport, Keys::generate())
+    }
+    
+    /// Create a client signing with `keys`
+    pub fn with_keys(transport: T, keys: Keys) -> Self {
         Self {
-            ws_stream: Arc::new(Mutex::new(ws_stream)),
+            transport: Arc::new(Mutex::new(transport)),
             keys,
+            tag_namespace: TagNamespace::default(),
         }
     }
     
+    /// Write custom tags, such as `block`, in `namespace`
+    pub fn with_tag_namespace(mut self, namespace: TagNamespace) -> Self {
+        self.tag_namespace = namespace;
+        self
+    }
+    
+    /// Keys events are signed with
+    pub fn keys(&self) -> &Keys {
+        &self.keys
+    }
+    
+    /// Public key events are published under
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        self.keys.public_key()
+    }
+    
+    /// Secret key as hex, for storing and reloading with [`NostrClient::with_keys`]
+    pub fn secret_key_hex(&self) -> Result<String> {
+        Ok(self.keys.secret_key()?.display_secret().to_string())
+    }
+    
+    /// Secret key in NIP-19 `nsec` form
+    pub fn secret_key_nsec(&self) -> Result<String> {
+        Ok(self.keys.secret_key()?.to_bech32()?)
+    }
+    
     pub async fn send_tx_event(&self, content: &str, block_hash: &str) -> Result<()> {
         // Create bitcoin transaction event (ephemeral)
         let event = EventBuilder::new(
@@ -30,10 +455,7 @@ impl NostrClient {
             &[
                 Tag::Hashtag("bitcoin".to_string()),
                 Tag::Hashtag("transaction".to_string()),
-                Tag::Generic(
-                    nostr::TagKind::Custom("block".to_string()),
-                    vec![block_hash.to_string()]
-                ),
+                self.tag_namespace.tag("block", block_hash),
             ]
         )
         .to_event(&self.keys)?;
@@ -45,33 +467,434 @@ impl NostrClient {
         let message = serde_json::to_string(&serde_json::json!(["EVENT", event]))?;
         info!("Sending nostr event: {}", event.id);
         
-        let mut ws = self.ws_stream.lock().await;
-        ws.send(Message::Text(message)).await?;
+        let mut transport = self.transport.lock().await;
+        transport.send_message(message).await?;
         
-        // Try to read response (non-blocking)
-        if let Some(msg) = ws.next().await {
-            match msg? {
-                Message::Text(text) => {
-                    info!("Nostr relay response: {}", text);
-                }
-                Message::Binary(_) => {
-                    warn!("Received binary message from nostr relay");
-                }
-                Message::Close(_) => {
-                    warn!("Nostr relay closed connection");
-                }
-                _ => {}
-            }
+        // Wait for the relay's response
+        match transport.recv_message().await {
+            Some(response) => info!("Nostr relay response: {}", response?),
+            None => warn!("Nostr relay closed connection"),
         }
         
         Ok(())
     }
 }
 
+/// Client for submitting transactions to, and requesting them from, a bitcoin-nostr relay
+/// 
+/// Handles the `["EVENT", ...]` framing and waits for the [`KIND_TX_RESPONSE`] that
+/// references each request, skipping broadcasts and other traffic in between.
+pub struct RelaySubmitClient<T = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>> {
+    transport: Mutex<T>,
+    keys: Keys,
+    timeout: Duration,
+}
+
+impl RelaySubmitClient {
+    /// Connect to a relay's WebSocket endpoint, signing with freshly generated keys
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self::new(connect_websocket(url, None, None).await?))
+    }
+}
+
+impl<T: NostrTransport> RelaySubmitClient<T> {
+    /// Create a client signing with freshly generated keys
+    pub fn new(transport: T) -> Self {
+        Self::with_keys(transport, Keys::generate())
+    }
+    
+    /// Create a client signing with `keys`, such as a key on the relay's submitter allowlist
+    pub fn with_keys(transport: T, keys: Keys) -> Self {
+        Self {
+            transport: Mutex::new(transport),
+            keys,
+            timeout: DEFAULT_RESPONSE_TIMEOUT,
+        }
+    }
+    
+    /// Set how long to wait for each response
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    
+    /// Public key requests are signed with
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        self.keys.public_key()
+    }
+    
+    /// Submit a raw transaction and wait for the relay's response
+    pub async fn submit(&self, tx_hex: &str) -> Result<SubmitResponse> {
+        self.request(KIND_SUBMIT_TX, tx_hex.trim().to_string()).await
+    }
+    
+    /// Forward a raw transaction as a peer relay and wait for the relay's response
+    ///
+    /// The client must sign with the forwarding relay's identity keys.
+    pub async fn forward(&self, tx_hex: &str) -> Result<SubmitResponse> {
+        self.request(KIND_FORWARD_TX, tx_hex.trim().to_string()).await
+    }
+    
+    /// Ask the relay for a transaction; if found, its raw hex is in the response's `hex`
+    ///
+    /// `txid` may also be the wtxid of a transaction the relay has handled.
+    pub async fn request_tx(&self, txid: &str) -> Result<SubmitResponse> {
+        self.request(KIND_REQUEST_TX, json!({ "txids": [txid] }).to_string()).await
+    }
+    
+    /// Send a request event and wait for the first response referencing it
+    /// 
+    /// Fails with `NostrError::ResponseTimeout` if none arrives in time.
+    async fn request(&self, kind: u16, content: String) -> Result<SubmitResponse> {
+        let event = EventBuilder::new(Kind::Ephemeral(kind), content, &[]).to_event(&self.keys)?;
+        let request_id = event.id;
+        info!("Sending request {} of kind {}", request_id, kind);
+        
+        let mut transport = self.transport.lock().await;
+        transport.send_message(json!(["EVENT", event]).to_string()).await?;
+        
+        let response = async {
+            while let Some(message) = transport.recv_message().await {
+                if let Some(event) = response_to(&message?, request_id) {
+                    return Ok(parse_tx_response(&event)?);
+                }
+            }
+            Err(NostrError::Disconnected.into())
+        };
+        tokio::time::timeout(self.timeout, response)
+            .await
+            .map_err(|_| NostrError::response_timeout(self.timeout))?
+    }
+}
+
+/// The response event in a relay message, if the message answers `request_id`
+fn response_to(message: &str, request_id: EventId) -> Option<Event> {
+    let parsed: Value = serde_json::from_str(message).ok()?;
+    let [verb, .., event] = parsed.as_array()?.as_slice() else {
+        return None;
+    };
+    if verb != "EVENT" {
+        return None;
+    }
+    let event: Event = serde_json::from_value(event.clone()).ok()?;
+    let answers_request = event.tags.iter().any(|tag| matches!(tag, Tag::Event(id, ..) if *id == request_id));
+    (event.kind == Kind::Ephemeral(KIND_TX_RESPONSE) && answers_request).then_some(event)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use nostr::prelude::SecretKey;
+    use bitcoin::consensus::deserialize;
+    use nostr::prelude::{FromBech32, SecretKey};
+    
+    #[test]
+    fn test_tx_broadcast_round_trip() {
+        let keys = Keys::generate();
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        
+        let event = build_tx_broadcast(&tx, "relay-7", None, &keys).unwrap();
+        assert!(event.verify().is_ok());
+        assert_eq!(event.pubkey, keys.public_key());
+        
+        let payload = parse_tx_broadcast(&event).unwrap();
+        assert_eq!(payload.txid, tx.txid().to_string());
+        let parsed: Transaction = deserialize(&hex::decode(&payload.hex).unwrap()).unwrap();
+        assert_eq!(parsed, tx);
+    }
+    
+    #[test]
+    fn test_tx_broadcast_network_tag() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let hashtags = |event: &Event| -> Vec<String> {
+            event.tags.iter().filter_map(|tag| match tag {
+                Tag::Hashtag(value) => Some(value.clone()),
+                _ => None,
+            }).collect()
+        };
+        
+        let event = build_tx_broadcast(&tx, "relay-7", Some(Network::Testnet4), &Keys::generate()).unwrap();
+        assert_eq!(hashtags(&event), vec!["bitcoin", "transaction", "testnet4"]);
+        assert!(event.tags.iter().any(|tag| tag.as_vec() == vec!["relay_id".to_string(), "relay-7".to_string()]));
+        
+        let untagged = build_tx_broadcast(&tx, "relay-7", None, &Keys::generate()).unwrap();
+        assert_eq!(hashtags(&untagged), vec!["bitcoin", "transaction"]);
+        
+        assert_eq!(tx_broadcast_topics(Some(Network::Testnet4)), vec!["testnet4"]);
+        assert_eq!(tx_broadcast_topics(None), vec!["bitcoin", "transaction"]);
+    }
+    
+    #[test]
+    fn test_tx_eviction_round_trip() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let event = build_tx_eviction(&[tx.txid()], "relay-7", Some(Network::Testnet4), &Keys::generate()).unwrap();
+        assert_eq!(parse_tx_eviction(&event).unwrap(), vec![tx.txid()]);
+        assert!(event.tags.iter().any(|tag| tag.as_vec() == vec!["relay_id".to_string(), "relay-7".to_string()]));
+        
+        let namespace = TagNamespace::new("btcrelay");
+        let event = build_tx_eviction_with_namespace(&[tx.txid()], "relay-7", &namespace, None, &Keys::generate()).unwrap();
+        assert_eq!(namespace.value(&event, "relay_id").as_deref(), Some("relay-7"));
+        assert_eq!(TagNamespace::default().value(&event, "relay_id"), None);
+        
+        let garbage = EventBuilder::new(Kind::Ephemeral(KIND_TX_EVICTED), r#"{"txids":["zz"]}"#, &[]).to_event(&Keys::generate()).unwrap();
+        assert!(matches!(parse_tx_eviction(&garbage), Err(NostrError::InvalidTxEviction { .. })));
+    }
+    
+    #[test]
+    fn test_tx_acceptance_round_trip() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let namespace = TagNamespace::new("btcrelay");
+        let event = build_tx_acceptance_with_namespace(&[tx.txid()], "relay-7", &namespace, Some(Network::Testnet4), &Keys::generate()).unwrap();
+        assert_eq!(event.kind, Kind::Ephemeral(KIND_TX_ACCEPTED));
+        assert_eq!(parse_tx_acceptance(&event).unwrap(), vec![tx.txid()]);
+        assert_eq!(namespace.value(&event, "relay_id").as_deref(), Some("relay-7"));
+        
+        let garbage = EventBuilder::new(Kind::Ephemeral(KIND_TX_ACCEPTED), r#"{"txids":["zz"]}"#, &[]).to_event(&Keys::generate()).unwrap();
+        assert!(matches!(parse_tx_acceptance(&garbage), Err(NostrError::InvalidTxAcceptance { .. })));
+    }
+    
+    #[test]
+    fn test_parse_tx_broadcast_rejects_other_events() {
+        let keys = Keys::generate();
+        let note = EventBuilder::new(Kind::TextNote, "hello", &[]).to_event(&keys).unwrap();
+        assert!(matches!(parse_tx_broadcast(&note), Err(NostrError::InvalidTxBroadcast { .. })));
+        
+        let malformed = EventBuilder::new(Kind::Ephemeral(KIND_TX_BROADCAST), "not json", &[]).to_event(&keys).unwrap();
+        assert!(matches!(parse_tx_broadcast(&malformed), Err(NostrError::InvalidTxBroadcast { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_send_event_over_memory_transport() {
+        let (client_end, mut relay_end) = crate::MemoryTransport::pair();
+        let client = NostrClient::new(client_end);
+        let event = EventBuilder::new(Kind::TextNote, "hello", &[]).to_event(&Keys::generate()).unwrap();
+        let event_id = event.id;
+        
+        // The relay acknowledges whatever it receives
+        let relay = tokio::spawn(async move {
+            let message = relay_end.recv_message().await.unwrap().unwrap();
+            let parsed: serde_json::Value = serde_json::from_str(&message).unwrap();
+            let received: Event = serde_json::from_value(parsed[1].clone()).unwrap();
+            relay_end.send_message(serde_json::json!(["OK", received.id, true, ""]).to_string()).await.unwrap();
+            (parsed[0].clone(), received)
+        });
+        
+        client.send_event(event).await.unwrap();
+        let (verb, received) = relay.await.unwrap();
+        assert_eq!(verb, "EVENT");
+        assert_eq!(received.id, event_id);
+        assert!(received.verify().is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_send_tx_event_round_trip() {
+        let (client_end, mut relay_end) = crate::MemoryTransport::pair();
+        let client = NostrClient::new(client_end);
+        
+        let relay = tokio::spawn(async move {
+            let message = relay_end.recv_message().await.unwrap().unwrap();
+            relay_end.send_message(r#"["OK","",true,""]"#.to_string()).await.unwrap();
+            message
+        });
+        client.send_tx_event("deadbeef", "blockhash").await.unwrap();
+        
+        let parsed: serde_json::Value = serde_json::from_str(&relay.await.unwrap()).unwrap();
+        let event: Event = serde_json::from_value(parsed[1].clone()).unwrap();
+        assert_eq!(event.content, "deadbeef");
+        assert!(event.tags.iter().any(|tag| tag.as_vec() == vec!["block".to_string(), "blockhash".to_string()]));
+    }
+    
+    #[tokio::test]
+    async fn test_namespaced_tags() {
+        let namespace = TagNamespace::new("btcrelay");
+        let (client_end, mut relay_end) = crate::MemoryTransport::pair();
+        let client = NostrClient::new(client_end).with_tag_namespace(namespace.clone());
+        
+        let relay = tokio::spawn(async move {
+            let message = relay_end.recv_message().await.unwrap().unwrap();
+            relay_end.send_message(r#"["OK","",true,""]"#.to_string()).await.unwrap();
+            message
+        });
+        client.send_tx_event("deadbeef", "blockhash").await.unwrap();
+        
+        let parsed: serde_json::Value = serde_json::from_str(&relay.await.unwrap()).unwrap();
+        let event: Event = serde_json::from_value(parsed[1].clone()).unwrap();
+        assert!(event.tags.iter().any(|tag| tag.as_vec() == vec!["btcrelay:block".to_string(), "blockhash".to_string()]));
+        assert_eq!(namespace.value(&event, "block").as_deref(), Some("blockhash"));
+        assert_eq!(TagNamespace::default().value(&event, "block"), None);
+        assert_eq!(TagNamespace::new(""), TagNamespace::default());
+    }
+    
+    #[test]
+    fn test_tag_value_independent_of_tag_variant() {
+        let keys = Keys::generate();
+        let tags = [
+            Tag::Relay(nostr::UncheckedUrl::from("wss://relay.example")),
+            Tag::parse(vec!["btcrelay:relay_id", "2", "extra"]).unwrap(),
+        ];
+        let event = EventBuilder::new_text_note("", &tags).to_event(&keys).unwrap();
+        let event: Event = serde_json::from_str(&serde_json::to_string(&event).unwrap()).unwrap();
+        
+        // "relay" parses into a typed variant but still matches by name
+        assert_eq!(TagNamespace::default().value(&event, "relay").as_deref(), Some("wss://relay.example"));
+        assert_eq!(TagNamespace::new("btcrelay").value(&event, "relay_id").as_deref(), Some("2"));
+        assert_eq!(TagNamespace::default().value(&event, "relay_id"), None);
+    }
+    
+    #[tokio::test]
+    async fn test_with_keys_signs_with_provided_key() {
+        let keys = Keys::new(SecretKey::from_slice(&[7u8; 32]).unwrap());
+        let (client_end, mut relay_end) = crate::MemoryTransport::pair();
+        let client = NostrClient::with_keys(client_end, keys.clone());
+        assert_eq!(client.public_key(), keys.public_key());
+        
+        let relay = tokio::spawn(async move {
+            let message = relay_end.recv_message().await.unwrap().unwrap();
+            relay_end.send_message(r#"["OK","",true,""]"#.to_string()).await.unwrap();
+            message
+        });
+        client.send_tx_event("deadbeef", "blockhash").await.unwrap();
+        
+        let parsed: serde_json::Value = serde_json::from_str(&relay.await.unwrap()).unwrap();
+        let event: Event = serde_json::from_value(parsed[1].clone()).unwrap();
+        assert_eq!(event.pubkey, keys.public_key());
+        assert!(event.verify().is_ok());
+    }
+    
+    #[test]
+    fn test_secret_key_export() {
+        let (client_end, _relay_end) = crate::MemoryTransport::pair();
+        let client = NostrClient::with_keys(client_end, Keys::new(SecretKey::from_slice(&[7u8; 32]).unwrap()));
+        
+        assert_eq!(client.secret_key_hex().unwrap(), "07".repeat(32));
+        let nsec = client.secret_key_nsec().unwrap();
+        assert!(nsec.starts_with("nsec1"));
+        let restored = Keys::new(SecretKey::from_bech32(&nsec).unwrap());
+        assert_eq!(restored.public_key(), client.public_key());
+        
+        // Keys without a secret can't be exported
+        let (client_end, _relay_end) = crate::MemoryTransport::pair();
+        let public_only = NostrClient::with_keys(client_end, Keys::from_public_key(client.public_key()));
+        assert!(public_only.secret_key_hex().is_err());
+    }
+    
+    /// A relay message carrying a response to `request`
+    fn response_message(request: &Event, content: serde_json::Value) -> String {
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_TX_RESPONSE), content.to_string(), &[Tag::Event(request.id, None, None)])
+            .to_event(&Keys::generate())
+            .unwrap();
+        serde_json::json!(["EVENT", "sub_id", event]).to_string()
+    }
+    
+    async fn recv_request(relay_end: &mut crate::MemoryTransport) -> Event {
+        let message = relay_end.recv_message().await.unwrap().unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(parsed[0], "EVENT");
+        serde_json::from_value(parsed[1].clone()).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_submit_waits_for_matching_response() {
+        let (client_end, mut relay_end) = crate::MemoryTransport::pair();
+        let client = RelaySubmitClient::new(client_end);
+        
+        let relay = tokio::spawn(async move {
+            let request = recv_request(&mut relay_end).await;
+            // Unrelated traffic and a response to another request arrive first
+            relay_end.send_message(r#"["NOTICE","hello"]"#.to_string()).await.unwrap();
+            let note = EventBuilder::new(Kind::TextNote, "hi", &[]).to_event(&Keys::generate()).unwrap();
+            relay_end.send_message(serde_json::json!(["EVENT", "sub_id", note.clone()]).to_string()).await.unwrap();
+            let other = serde_json::json!({"success": false, "message": "not yours"});
+            relay_end.send_message(response_message(&note, other)).await.unwrap();
+            let response = serde_json::json!({"success": true, "code": "ACCEPTED", "message": "Transaction accepted", "txid": "ab"});
+            relay_end.send_message(response_message(&request, response)).await.unwrap();
+            (request, relay_end)
+        });
+        
+        let response = client.submit(" deadbeef\n").await.unwrap();
+        assert_eq!(response, SubmitResponse {
+            success: true,
+            message: "Transaction accepted".to_string(),
+            code: Some(ResponseCode::Accepted),
+            txid: "ab".to_string(),
+            hex: None,
+            resident: None,
+        });
+        
+        let (request, _relay_end) = relay.await.unwrap();
+        assert_eq!(request.kind, Kind::Ephemeral(KIND_SUBMIT_TX));
+        assert_eq!(request.content, "deadbeef");
+        assert_eq!(request.pubkey, client.public_key());
+    }
+    
+    #[tokio::test]
+    async fn test_request_tx_returns_hex() {
+        let (client_end, mut relay_end) = crate::MemoryTransport::pair();
+        let client = RelaySubmitClient::new(client_end);
+        
+        let relay = tokio::spawn(async move {
+            let request = recv_request(&mut relay_end).await;
+            let response = serde_json::json!({"success": true, "message": "Transaction found", "txid": "ab", "hex": "0200"});
+            relay_end.send_message(response_message(&request, response)).await.unwrap();
+            (request, relay_end)
+        });
+        
+        let response = client.request_tx("ab").await.unwrap();
+        assert_eq!(response.hex.as_deref(), Some("0200"));
+        
+        let (request, _relay_end) = relay.await.unwrap();
+        assert_eq!(request.kind, Kind::Ephemeral(KIND_REQUEST_TX));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&request.content).unwrap(), serde_json::json!({"txids": ["ab"]}));
+    }
+    
+    #[tokio::test]
+    async fn test_submit_times_out() {
+        let (client_end, _relay_end) = crate::MemoryTransport::pair();
+        let client = RelaySubmitClient::new(client_end).with_timeout(Duration::from_millis(50));
+        
+        let result = client.submit("deadbeef").await;
+        assert!(matches!(result, Err(crate::RelayError::Nostr(NostrError::ResponseTimeout { .. }))));
+    }
+    
+    #[test]
+    fn test_parse_tx_response_without_txid() {
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_TX_RESPONSE), r#"{"success":false,"message":"Invalid hex encoding"}"#, &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        let response = parse_tx_response(&event).unwrap();
+        assert!(!response.success);
+        assert!(response.txid.is_empty());
+        assert_eq!(response.code, None);
+        
+        let note = EventBuilder::new(Kind::TextNote, "hello", &[]).to_event(&Keys::generate()).unwrap();
+        assert!(matches!(parse_tx_response(&note), Err(NostrError::InvalidTxResponse { .. })));
+    }
+    
+    #[test]
+    fn test_response_codes() {
+        let code = |err: RelayError| ResponseCode::from(&err);
+        assert_eq!(code(ValidationError::InvalidHex.into()), ResponseCode::InvalidHex);
+        assert_eq!(code(ValidationError::InvalidStructure.into()), ResponseCode::InvalidTransaction);
+        assert_eq!(code(ValidationError::recently_processed("ab").into()), ResponseCode::AlreadyKnown);
+        assert_eq!(code(BitcoinRpcError::RateLimited.into()), ResponseCode::RateLimited);
+        assert_eq!(code(BitcoinRpcError::ServerBusy.into()), ResponseCode::NodeBusy);
+        assert_eq!(code(BitcoinRpcError::connection_failed("node").into()), ResponseCode::NodeUnavailable);
+        assert_eq!(code(BitcoinRpcError::bitcoin_core(-26, "min relay fee not met").into()), ResponseCode::FeeTooLow);
+        assert_eq!(code(BitcoinRpcError::bitcoin_core(-27, "Transaction already in block chain").into()), ResponseCode::AlreadyKnown);
+        assert_eq!(code(BitcoinRpcError::bitcoin_core(-26, "bad-txns-inputs-missingorspent").into()), ResponseCode::NodeRejected);
+        
+        let lookup = |err: RelayError| ResponseCode::for_lookup_error(&err);
+        assert_eq!(lookup(BitcoinRpcError::bitcoin_core(-5, "No such mempool or blockchain transaction").into()), ResponseCode::NotFound);
+        assert_eq!(lookup(BitcoinRpcError::tx_index_required("ab").into()), ResponseCode::NotFound);
+        assert_eq!(lookup(ValidationError::invalid_txid("zz").into()), ResponseCode::InvalidRequest);
+        assert_eq!(lookup(BitcoinRpcError::connection_failed("node").into()), ResponseCode::NodeUnavailable);
+        assert_eq!(lookup(BitcoinRpcError::bitcoin_core(-1, "unexpected").into()), ResponseCode::NodeError);
+        assert!(ResponseCode::Ok.is_success() && !ResponseCode::NodeError.is_success());
+        
+        assert_eq!(serde_json::to_string(&ResponseCode::FeeTooLow).unwrap(), r#""FEE_TOO_LOW""#);
+        assert_eq!(serde_json::to_string(&ResponseCode::Ok).unwrap(), r#""OK""#);
+        assert_eq!(serde_json::from_str::<ResponseCode>(r#""RATE_LIMITED""#).unwrap(), ResponseCode::RateLimited);
+        assert_eq!(serde_json::from_str::<ResponseCode>(r#""SOMETHING_NEW""#).unwrap(), ResponseCode::Unknown);
+    }
     
     #[test]
     fn test_nostr_client_creation() {
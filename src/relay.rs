@@ -1,5 +1,40 @@
+pub mod auth;
+pub mod backoff;
+pub mod bus;
+pub mod circuit_breaker;
+pub mod compression;
 pub mod config;
+pub mod dead_letter;
+pub mod dedup;
+pub mod discovery;
+pub mod encryption;
+pub mod metrics;
+pub mod outbound;
+pub mod payload;
+pub mod poll;
+pub mod rate_limit;
+pub mod reconcile;
 pub mod server;
+pub mod state;
+pub mod subscription;
 
-pub use config::RelayConfig;
-pub use server::RelayServer;
\ No newline at end of file
+pub use backoff::Backoff;
+pub use bus::{BusTransport, EventBus};
+pub use circuit_breaker::{BreakerRpc, BreakerState, CircuitBreaker};
+pub use compression::Compression;
+pub use config::{BackoffConfig, BitcoinNodeConfig, CircuitBreakerConfig, ConflictPolicy, InflightSubmissionLimit, DiscoveryConfig, HandshakeBanPolicy, HttpPoolConfig, OverflowPolicy, RelayConfig, RelayIdCheck, RpcAuth, Shard, SubmissionRateLimit};
+pub use dead_letter::{DeadLetter, DeadLetterLog};
+pub use dedup::DedupSnapshot;
+pub use discovery::{Capability, CapabilityAnnouncement, PeerInfo, PeerRoster};
+pub use encryption::BroadcastCipher;
+pub use metrics::{LatencyHistogram, LoopMetrics, Metric, MetricsSink, NoopMetrics, RelayStats};
+#[cfg(feature = "prometheus")]
+pub use metrics::PrometheusSink;
+pub use outbound::{Enqueued, OutboundQueue};
+pub use payload::{BroadcastEncoding, TxBroadcastPayload, TX_BROADCAST_SCHEMA_VERSION};
+pub use poll::AdaptivePollInterval;
+pub use rate_limit::{HandshakeBans, SubmissionLimiter};
+pub use reconcile::{MempoolDiff, MempoolSummary};
+pub use server::RelayServer;
+pub use state::{BitcoinState, ConnectionState, StrfryState};
+pub use subscription::{RelayedTx, TxFilter, TxSource, TxSubscription};
\ No newline at end of file
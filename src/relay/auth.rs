@@ -0,0 +1,92 @@
+use crate::error::NostrError;
+use nostr::prelude::XOnlyPublicKey;
+use nostr::{Event, Kind, Tag, Timestamp};
+use serde_json::Value;
+
+/// Furthest an auth event's `created_at` may be from the relay's clock, in seconds
+pub const AUTH_MAX_CLOCK_SKEW_SECS: u64 = 600;
+
+/// Fresh random NIP-42 challenge for a client connection
+pub fn new_challenge() -> String {
+    hex::encode(nostr::secp256k1::rand::random::<[u8; 32]>())
+}
+
+/// The signed event of a client's `["AUTH", <event>]` message, or `None` for other messages
+pub fn parse_auth_message(message: &str) -> Option<Result<Event, NostrError>> {
+    let parsed: Value = serde_json::from_str(message).ok()?;
+    let arr = parsed.as_array()?;
+    if arr.first()?.as_str()? != "AUTH" {
+        return None;
+    }
+    Some(arr.get(1).and_then(|event| serde_json::from_value(event.clone()).ok()).ok_or(NostrError::InvalidEvent))
+}
+
+/// Check a client's NIP-42 auth event against the challenge it was sent, returning the authenticated pubkey
+///
+/// The `relay` tag isn't checked, since clients may reach a relay under several URLs.
+pub fn verify_auth(event: &Event, challenge: &str, now: Timestamp) -> Result<XOnlyPublicKey, NostrError> {
+    if event.kind != Kind::Authentication {
+        return Err(NostrError::invalid_auth(format!("unexpected event kind {}", event.kind.as_u32())));
+    }
+    if !event.tags.iter().any(|tag| matches!(tag, Tag::Challenge(c) if c == challenge)) {
+        return Err(NostrError::invalid_auth("challenge does not match"));
+    }
+    if event.created_at.as_u64().abs_diff(now.as_u64()) > AUTH_MAX_CLOCK_SKEW_SECS {
+        return Err(NostrError::invalid_auth("created_at is too far from the current time"));
+    }
+    event.verify().map_err(|_| NostrError::invalid_auth("bad signature"))?;
+    Ok(event.pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr::{EventBuilder, Keys, Url};
+
+    fn auth_event(challenge: &str, keys: &Keys) -> Event {
+        EventBuilder::auth(challenge, Url::parse("wss://relay.example").unwrap()).to_event(keys).unwrap()
+    }
+
+    #[test]
+    fn test_challenges_are_random() {
+        let challenge = new_challenge();
+        assert_eq!(challenge.len(), 64);
+        assert!(challenge.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(challenge, new_challenge());
+    }
+
+    #[test]
+    fn test_verify_auth() {
+        let keys = Keys::generate();
+        let challenge = new_challenge();
+        let event = auth_event(&challenge, &keys);
+        assert_eq!(verify_auth(&event, &challenge, Timestamp::now()).unwrap(), keys.public_key());
+        
+        // Answering another connection's challenge
+        assert!(matches!(verify_auth(&event, &new_challenge(), Timestamp::now()), Err(NostrError::InvalidAuth { .. })));
+        
+        // Replayed long after it was signed
+        let later = Timestamp::from(Timestamp::now().as_u64() + AUTH_MAX_CLOCK_SKEW_SECS + 1);
+        assert!(verify_auth(&event, &challenge, later).is_err());
+        
+        // Claiming someone else's pubkey
+        let mut forged = event.clone();
+        forged.pubkey = Keys::generate().public_key();
+        assert!(verify_auth(&forged, &challenge, Timestamp::now()).is_err());
+        
+        // Not an auth event
+        let note = EventBuilder::new(Kind::TextNote, "", &[Tag::Challenge(challenge.clone())]).to_event(&keys).unwrap();
+        assert!(verify_auth(&note, &challenge, Timestamp::now()).is_err());
+    }
+
+    #[test]
+    fn test_parse_auth_message() {
+        let event = auth_event("abc", &Keys::generate());
+        let message = serde_json::json!(["AUTH", event]).to_string();
+        assert_eq!(parse_auth_message(&message).unwrap().unwrap().id, event.id);
+        
+        assert!(matches!(parse_auth_message(r#"["AUTH", "not an event"]"#), Some(Err(NostrError::InvalidEvent))));
+        assert!(parse_auth_message(&serde_json::json!(["EVENT", event]).to_string()).is_none());
+        assert!(parse_auth_message("not json").is_none());
+    }
+}
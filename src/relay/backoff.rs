@@ -0,0 +1,135 @@
+use super::config::BackoffConfig;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Exponential reconnect backoff with jitter
+/// 
+/// The delay starts at `base_delay`, doubles after every failed or short-lived
+/// connection up to `max_delay`, and resets once a connection has stayed up for
+/// `reset_after`. Jitter desynchronizes relays that lost the same upstream at once.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    config: BackoffConfig,
+    attempt: u32,
+    rng_state: u64,
+}
+
+impl Backoff {
+    /// Create a backoff seeded from process-local randomness
+    pub fn new(config: BackoffConfig) -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        Self::with_seed(config, seed)
+    }
+    
+    /// Create a backoff with a fixed RNG seed (deterministic jitter)
+    pub fn with_seed(config: BackoffConfig, seed: u64) -> Self {
+        Self {
+            config,
+            attempt: 0,
+            // xorshift must not start from zero
+            rng_state: seed.max(1),
+        }
+    }
+    
+    /// Compute the delay before the next attempt, given how long the last connection stayed up
+    pub fn next_delay(&mut self, uptime: Duration) -> Duration {
+        if uptime >= self.config.reset_after {
+            self.reset();
+        }
+        
+        let exponential = self
+            .config
+            .base_delay
+            .checked_mul(1u32.checked_shl(self.attempt).unwrap_or(u32::MAX))
+            .unwrap_or(self.config.max_delay);
+        let delay = exponential.min(self.config.max_delay);
+        self.attempt = self.attempt.saturating_add(1);
+        
+        let jitter = self.config.jitter.clamp(0.0, 1.0);
+        delay + delay.mul_f64(jitter * self.next_unit())
+    }
+    
+    /// Reset the backoff to the base delay
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+    
+    /// Uniform random value in [0, 1) from a xorshift64 generator
+    fn next_unit(&mut self) -> f64 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(jitter: f64) -> BackoffConfig {
+        BackoffConfig {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(8),
+            reset_after: Duration::from_secs(30),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn test_backoff_sequence_without_jitter() {
+        let mut backoff = Backoff::with_seed(config(0.0), 42);
+        
+        let delays: Vec<u64> = (0..6)
+            .map(|_| backoff.next_delay(Duration::ZERO).as_secs())
+            .collect();
+        
+        // Doubles from the base delay and is capped at max_delay
+        assert_eq!(delays, vec![1, 2, 4, 8, 8, 8]);
+    }
+
+    #[test]
+    fn test_backoff_resets_after_stable_connection() {
+        let mut backoff = Backoff::with_seed(config(0.0), 42);
+        
+        backoff.next_delay(Duration::ZERO);
+        backoff.next_delay(Duration::ZERO);
+        assert_eq!(backoff.next_delay(Duration::from_secs(5)), Duration::from_secs(4));
+        
+        // A connection that stayed up past reset_after starts over from the base delay
+        assert_eq!(backoff.next_delay(Duration::from_secs(30)), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(Duration::ZERO), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_backoff_jitter_is_bounded_and_deterministic() {
+        let mut backoff1 = Backoff::with_seed(config(0.5), 7);
+        let mut backoff2 = Backoff::with_seed(config(0.5), 7);
+        
+        for expected_base in [1u64, 2, 4, 8, 8] {
+            let delay1 = backoff1.next_delay(Duration::ZERO);
+            let delay2 = backoff2.next_delay(Duration::ZERO);
+            
+            // Same seed produces the same sequence
+            assert_eq!(delay1, delay2);
+            
+            let base = Duration::from_secs(expected_base);
+            assert!(delay1 >= base);
+            assert!(delay1 < base + base.mul_f64(0.5));
+        }
+    }
+
+    #[test]
+    fn test_backoff_different_seeds_desynchronize() {
+        let mut backoff1 = Backoff::with_seed(config(0.5), 1);
+        let mut backoff2 = Backoff::with_seed(config(0.5), 2);
+        
+        let delays1: Vec<Duration> = (0..4).map(|_| backoff1.next_delay(Duration::ZERO)).collect();
+        let delays2: Vec<Duration> = (0..4).map(|_| backoff2.next_delay(Duration::ZERO)).collect();
+        
+        assert_ne!(delays1, delays2);
+    }
+}
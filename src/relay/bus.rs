@@ -0,0 +1,158 @@
+use crate::transport::NostrTransport;
+use crate::Result;
+use futures_util::future::BoxFuture;
+use nostr::{Event, Filter};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// Default number of events a slow bus connection may fall behind before missing some
+pub const DEFAULT_BUS_CAPACITY: usize = 10_000;
+
+/// In-process stand-in for strfry shared by relays in the same process
+///
+/// Relays configured with the same bus see each other's events as if they were
+/// connected to one strfry, so multi-relay gossip runs without external services.
+/// Clones share the bus.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    events: broadcast::Sender<Arc<Event>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUS_CAPACITY)
+    }
+}
+
+impl EventBus {
+    /// Create a bus buffering up to `capacity` events per connection
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: broadcast::channel(capacity.max(1)).0,
+        }
+    }
+    
+    /// Open a connection that speaks the Nostr relay protocol
+    ///
+    /// Events published on any connection are delivered to every connection's
+    /// matching subscriptions, the publisher's included.
+    pub fn connect(&self) -> BusTransport {
+        BusTransport {
+            events: self.events.clone(),
+            receiver: self.events.subscribe(),
+            subscriptions: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// One relay's connection to an [`EventBus`]
+#[derive(Debug)]
+pub struct BusTransport {
+    events: broadcast::Sender<Arc<Event>>,
+    receiver: broadcast::Receiver<Arc<Event>>,
+    subscriptions: HashMap<String, Vec<Filter>>,
+    /// Messages to hand out before the next bus event
+    pending: VecDeque<String>,
+}
+
+impl BusTransport {
+    /// Act on a client message as a relay would, queueing any reply
+    fn handle_message(&mut self, message: &str) -> Result<()> {
+        let parsed: Value = serde_json::from_str(message)?;
+        let text = |i: usize| parsed.get(i).and_then(Value::as_str);
+        match text(0) {
+            Some("EVENT") => {
+                let event: Event = serde_json::from_value(parsed[1].clone())?;
+                self.pending.push_back(json!(["OK", event.id, true, ""]).to_string());
+                let _ = self.events.send(Arc::new(event));
+            }
+            Some("REQ") => {
+                let Some(subscription_id) = text(1).map(str::to_string) else {
+                    return Ok(());
+                };
+                let filters = parsed.as_array().map_or(&[][..], |arr| &arr[2..]);
+                let filters = filters
+                    .iter()
+                    .map(|filter| serde_json::from_value(filter.clone()))
+                    .collect::<std::result::Result<Vec<Filter>, _>>()?;
+                // Nothing is stored, so there is never a backlog to send first
+                self.pending.push_back(json!(["EOSE", subscription_id]).to_string());
+                self.subscriptions.insert(subscription_id, filters);
+            }
+            Some("CLOSE") => {
+                if let Some(subscription_id) = text(1) {
+                    self.subscriptions.remove(subscription_id);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    
+    /// Queue an `EVENT` message for every subscription the event matches
+    fn deliver(&mut self, event: &Event) {
+        for (subscription_id, filters) in &self.subscriptions {
+            if filters.iter().any(|filter| filter.match_event(event)) {
+                self.pending.push_back(json!(["EVENT", subscription_id, event]).to_string());
+            }
+        }
+    }
+}
+
+impl NostrTransport for BusTransport {
+    fn send_message(&mut self, message: String) -> BoxFuture<'_, Result<()>> {
+        let handled = self.handle_message(&message);
+        Box::pin(async move { handled })
+    }
+    
+    fn recv_message(&mut self) -> BoxFuture<'_, Option<Result<String>>> {
+        Box::pin(async move {
+            loop {
+                if let Some(message) = self.pending.pop_front() {
+                    return Some(Ok(message));
+                }
+                match self.receiver.recv().await {
+                    Ok(event) => self.deliver(&event),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Event bus connection fell behind, {} events missed", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr::{EventBuilder, Keys, Kind};
+
+    #[tokio::test]
+    async fn test_events_delivered_to_matching_subscriptions() {
+        let bus = EventBus::default();
+        let mut publisher = bus.connect();
+        let mut subscriber = bus.connect();
+        
+        subscriber.send_message(json!(["REQ", "notes", {"kinds": [1]}]).to_string()).await.unwrap();
+        assert_eq!(subscriber.recv_message().await.unwrap().unwrap(), r#"["EOSE","notes"]"#);
+        
+        let keys = Keys::generate();
+        let reaction = EventBuilder::new(Kind::Reaction, "+", &[]).to_event(&keys).unwrap();
+        let note = EventBuilder::new_text_note("hello", &[]).to_event(&keys).unwrap();
+        for event in [&reaction, &note] {
+            publisher.send_message(json!(["EVENT", event]).to_string()).await.unwrap();
+            let ok: Value = serde_json::from_str(&publisher.recv_message().await.unwrap().unwrap()).unwrap();
+            assert_eq!(ok, json!(["OK", event.id, true, ""]));
+        }
+        
+        // Only the note matches the subscription
+        let delivered: Value = serde_json::from_str(&subscriber.recv_message().await.unwrap().unwrap()).unwrap();
+        assert_eq!(delivered[1], "notes");
+        assert_eq!(delivered[2]["id"], note.id.to_string());
+    }
+}
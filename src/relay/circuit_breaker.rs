@@ -0,0 +1,375 @@
+use super::config::CircuitBreakerConfig;
+use crate::bitcoin_rpc::{BitcoinRpc, BlockHeaderInfo, EstimateMode, FeeEstimate, MempoolEntry, MempoolSequence, NetworkInfo, PackageResult, RawTxInfo};
+use crate::error::{BitcoinRpcError, RelayError};
+use crate::Result;
+use bitcoin::{Block, BlockHash, OutPoint, Txid};
+use futures_util::future::BoxFuture;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// State of a [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+    /// Calls fail fast until the cool-down ends
+    Open,
+    /// The cool-down ended; one probe call decides whether to close or reopen
+    HalfOpen,
+}
+
+/// Stops calling a node after repeated failures
+///
+/// Opens after `failure_threshold` consecutive failures and fails fast for
+/// `cool_down`. The first call after that is a probe: success closes the
+/// breaker, failure reopens it for another cool-down.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+/// Whether the breaker let a call through
+enum Admission {
+    Rejected,
+    Call,
+    Probe,
+}
+
+/// Releases the probe slot if a probe call is dropped before its outcome is recorded
+struct ProbeGuard<'a> {
+    breaker: Option<&'a CircuitBreaker>,
+}
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(breaker) = self.breaker {
+            breaker.inner.lock().unwrap().probe_in_flight = false;
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Create a closed breaker
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner {
+                consecutive_failures: 0,
+                opened_at: None,
+                probe_in_flight: false,
+            }),
+        }
+    }
+    
+    /// Current state
+    pub fn state(&self) -> BreakerState {
+        let inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.config.cool_down => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+    
+    /// Whether a call would go through now; a half-open breaker admits one probe at a time
+    /// 
+    /// Only checks: the probe slot is taken by [`call`](Self::call), which also frees it.
+    pub fn allow(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => true,
+            Some(opened_at) if opened_at.elapsed() < self.config.cool_down => false,
+            Some(_) => !inner.probe_in_flight,
+        }
+    }
+    
+    fn admit(&self) -> Admission {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => Admission::Call,
+            Some(opened_at) if opened_at.elapsed() < self.config.cool_down => Admission::Rejected,
+            Some(_) if inner.probe_in_flight => Admission::Rejected,
+            Some(_) => {
+                inner.probe_in_flight = true;
+                Admission::Probe
+            }
+        }
+    }
+    
+    /// Run `call` if the breaker allows it, recording its outcome
+    /// 
+    /// Returns `None` without running `call` while the breaker is open. Errors
+    /// for which `is_node_failure` is false count as successes, since a working
+    /// node reported them. A probe dropped before finishing frees the probe slot.
+    pub async fn call<T, E>(&self, call: impl Future<Output = std::result::Result<T, E>>, is_node_failure: impl Fn(&E) -> bool) -> Option<std::result::Result<T, E>> {
+        let mut probe = match self.admit() {
+            Admission::Rejected => return None,
+            Admission::Call => ProbeGuard { breaker: None },
+            Admission::Probe => ProbeGuard { breaker: Some(self) },
+        };
+        let result = call.await;
+        // The outcome settles the probe
+        probe.breaker = None;
+        match &result {
+            Err(e) if is_node_failure(e) => self.record_failure(),
+            _ => self.record_success(),
+        }
+        Some(result)
+    }
+    
+    /// Record a call that reached a working node
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.opened_at.is_some() {
+            info!("Bitcoin node recovered, closing circuit breaker");
+        }
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        inner.probe_in_flight = false;
+    }
+    
+    /// Record a call that failed because the node is unavailable
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        if inner.probe_in_flight || (inner.opened_at.is_none() && inner.consecutive_failures >= self.config.failure_threshold) {
+            warn!(
+                "Bitcoin node failed {} consecutive calls, failing fast for {:?}",
+                inner.consecutive_failures, self.config.cool_down
+            );
+            inner.opened_at = Some(Instant::now());
+            inner.probe_in_flight = false;
+        }
+    }
+}
+
+/// Whether an RPC error means the node itself is unavailable
+///
+/// Errors reported by a working node, such as policy rejections, don't count.
+fn is_node_failure(error: &RelayError) -> bool {
+    match error {
+        RelayError::Http(_) => true,
+        RelayError::BitcoinRpc(e) => matches!(
+            e,
+            BitcoinRpcError::ConnectionFailed { .. }
+                | BitcoinRpcError::RequestFailed { .. }
+                | BitcoinRpcError::InvalidResponse
+                | BitcoinRpcError::AuthenticationFailed
+        ),
+        _ => false,
+    }
+}
+
+/// A `BitcoinRpc` whose calls go through a circuit breaker
+///
+/// While the breaker is open, calls fail with `BitcoinRpcError::ConnectionFailed`
+/// without reaching the node.
+pub struct BreakerRpc {
+    inner: Arc<dyn BitcoinRpc>,
+    breaker: Arc<CircuitBreaker>,
+    url: String,
+}
+
+impl BreakerRpc {
+    /// Wrap `inner`, reporting `url` in fast-fail errors
+    pub fn new(inner: Arc<dyn BitcoinRpc>, breaker: Arc<CircuitBreaker>, url: impl Into<String>) -> Self {
+        Self {
+            inner,
+            breaker,
+            url: url.into(),
+        }
+    }
+    
+    fn guard<'a, T: Send + 'a>(&'a self, call: BoxFuture<'a, Result<T>>) -> BoxFuture<'a, Result<T>> {
+        Box::pin(async move {
+            self.breaker
+                .call(call, is_node_failure)
+                .await
+                .unwrap_or_else(|| Err(BitcoinRpcError::connection_failed(&self.url).into()))
+        })
+    }
+}
+
+impl BitcoinRpc for BreakerRpc {
+    fn get_best_block_hash(&self) -> BoxFuture<'_, Result<BlockHash>> {
+        self.guard(self.inner.get_best_block_hash())
+    }
+    
+    fn get_block<'a>(&'a self, block_hash: &'a BlockHash) -> BoxFuture<'a, Result<Block>> {
+        self.guard(self.inner.get_block(block_hash))
+    }
+    
+    fn get_block_txids<'a>(&'a self, block_hash: &'a BlockHash) -> BoxFuture<'a, Result<(BlockHeaderInfo, Vec<Txid>)>> {
+        self.guard(self.inner.get_block_txids(block_hash))
+    }
+    
+    fn get_chain(&self) -> BoxFuture<'_, Result<String>> {
+        self.guard(self.inner.get_chain())
+    }
+    
+    fn get_network_info(&self) -> BoxFuture<'_, Result<NetworkInfo>> {
+        self.guard(self.inner.get_network_info())
+    }
+    
+    fn send_raw_transaction<'a>(&'a self, tx_hex: &'a str) -> BoxFuture<'a, Result<String>> {
+        self.guard(self.inner.send_raw_transaction(tx_hex))
+    }
+    
+    fn get_raw_mempool(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        self.guard(self.inner.get_raw_mempool())
+    }
+    
+    fn get_raw_mempool_sequence(&self) -> BoxFuture<'_, Result<MempoolSequence>> {
+        self.guard(self.inner.get_raw_mempool_sequence())
+    }
+    
+    fn get_raw_transaction<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<String>> {
+        self.guard(self.inner.get_raw_transaction(txid))
+    }
+    
+    fn get_raw_transaction_verbose<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<RawTxInfo>> {
+        self.guard(self.inner.get_raw_transaction_verbose(txid))
+    }
+    
+    fn get_mempool_entry<'a>(&'a self, txid: &'a str) -> BoxFuture<'a, Result<MempoolEntry>> {
+        self.guard(self.inner.get_mempool_entry(txid))
+    }
+    
+    fn submit_package<'a>(&'a self, tx_hexes: &'a [String]) -> BoxFuture<'a, Result<PackageResult>> {
+        self.guard(self.inner.submit_package(tx_hexes))
+    }
+    
+    fn estimate_smart_fee(&self, conf_target: u16, mode: EstimateMode) -> BoxFuture<'_, Result<FeeEstimate>> {
+        self.guard(self.inner.estimate_smart_fee(conf_target, mode))
+    }
+    
+    fn get_tx_spending_prevout<'a>(&'a self, outpoints: &'a [OutPoint]) -> BoxFuture<'a, Result<Vec<Option<String>>>> {
+        self.guard(self.inner.get_tx_spending_prevout(outpoints))
+    }
+    
+    fn get_block_count(&self) -> BoxFuture<'_, Result<u64>> {
+        self.guard(self.inner.get_block_count())
+    }
+    
+    fn get_block_hash(&self, height: u64) -> BoxFuture<'_, Result<BlockHash>> {
+        self.guard(self.inner.get_block_hash(height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn breaker(cool_down: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            cool_down,
+        })
+    }
+
+    #[test]
+    fn test_breaker_transitions() {
+        let breaker = breaker(Duration::from_millis(50));
+        
+        // Closed: failures below the threshold keep calls flowing
+        for _ in 0..2 {
+            assert!(breaker.allow());
+            breaker.record_failure();
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        
+        // Open: the threshold is reached and calls fail fast
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.allow());
+        
+        // Half-open: after the cool-down a single probe goes through; checking doesn't take it
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.allow());
+        assert!(breaker.allow());
+        assert!(matches!(breaker.admit(), Admission::Probe));
+        assert!(!breaker.allow());
+        assert!(matches!(breaker.admit(), Admission::Rejected));
+        
+        // Closed: the probe succeeded
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn test_failed_probe_reopens() {
+        let breaker = breaker(Duration::from_millis(50));
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+        
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(matches!(breaker.admit(), Admission::Probe));
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_probe_frees_half_open_breaker() {
+        let breaker = Arc::new(breaker(Duration::from_millis(50)));
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+        std::thread::sleep(Duration::from_millis(60));
+        
+        // The probe is abandoned, say by a caller's timeout, before the node answers
+        let mock = crate::MockBitcoinRpc::new();
+        mock.set_send_delay(Some(Duration::from_secs(5)));
+        let rpc = BreakerRpc::new(Arc::new(mock.clone()), Arc::clone(&breaker), "mock");
+        let probe = tokio::time::timeout(Duration::from_millis(20), rpc.send_raw_transaction(&crate::test_utils::sample_tx_hex())).await;
+        assert!(probe.is_err());
+        
+        // Another probe may go out instead of the breaker staying half-open for good
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn test_breaker_rpc_fails_fast_on_unreachable_node() {
+        // Nothing listens on port 1
+        let client = crate::BitcoinRpcClient::new("http://127.0.0.1:1".to_string(), "user".to_string(), "pass".to_string());
+        let breaker = Arc::new(breaker(Duration::from_secs(60)));
+        let rpc = BreakerRpc::new(Arc::new(client), Arc::clone(&breaker), "http://127.0.0.1:1");
+        
+        for _ in 0..3 {
+            assert!(rpc.get_raw_mempool().await.is_err());
+        }
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(matches!(
+            rpc.get_raw_mempool().await,
+            Err(RelayError::BitcoinRpc(BitcoinRpcError::ConnectionFailed { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_node_rejections_do_not_trip_breaker() {
+        let mock = crate::MockBitcoinRpc::new();
+        mock.set_send_error(Some((-26, "min relay fee not met")));
+        let breaker = Arc::new(breaker(Duration::from_secs(60)));
+        let rpc = BreakerRpc::new(Arc::new(mock.clone()), Arc::clone(&breaker), "mock");
+        
+        for _ in 0..5 {
+            assert!(rpc.send_raw_transaction(&crate::test_utils::sample_tx_hex()).await.is_err());
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
@@ -0,0 +1,134 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, BufRead, Read, Write};
+
+/// Frame magic number zstd output starts with
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// zstd level used for persisted state, favouring speed
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compression applied to state the relay writes to disk
+///
+/// Reading detects compressed data on its own, so files written before the
+/// setting changed stay readable. zstd needs the `zstd` feature; without it,
+/// reading a compressed file fails with `io::ErrorKind::Unsupported`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Write plain JSON
+    #[default]
+    None,
+    
+    /// Write zstd-compressed JSON
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Encode bytes for writing
+    pub fn compress(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::encode_all(bytes, ZSTD_LEVEL),
+        }
+    }
+    
+    /// The compression `bytes` were written with
+    pub fn detect(bytes: &[u8]) -> io::Result<Self> {
+        if !bytes.starts_with(&ZSTD_MAGIC) {
+            return Ok(Compression::None);
+        }
+        #[cfg(feature = "zstd")]
+        return Ok(Compression::Zstd);
+        #[cfg(not(feature = "zstd"))]
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "zstd-compressed data needs the `zstd` feature"));
+    }
+    
+    /// Decode bytes read back, whichever compression they were written with
+    ///
+    /// Concatenated zstd frames decode to their concatenated contents.
+    pub fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match Self::detect(bytes)? {
+            Compression::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::decode_all(bytes),
+        }
+    }
+    
+    /// Serialize `value` as JSON into `writer`, compressed as a single stream
+    pub fn write_json<W: Write, T: Serialize>(&self, writer: W, value: &T) -> io::Result<()> {
+        match self {
+            Compression::None => {
+                let mut writer = io::BufWriter::new(writer);
+                serde_json::to_writer(&mut writer, value)?;
+                writer.flush()
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(writer, ZSTD_LEVEL)?;
+                serde_json::to_writer(&mut encoder, value)?;
+                encoder.finish()?.flush()
+            }
+        }
+    }
+    
+    /// Deserialize JSON from `reader`, whichever compression it was written with
+    pub fn read_json<R: Read, T: DeserializeOwned>(reader: R) -> io::Result<T> {
+        let mut reader = io::BufReader::new(reader);
+        match Self::detect(reader.fill_buf()?)? {
+            Compression::None => Ok(serde_json::from_reader(reader)?),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(serde_json::from_reader(zstd::stream::read::Decoder::with_buffer(reader)?)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_round_trip() {
+        let data = br#"{"txids":["aa","bb"]}"#;
+        let plain = Compression::None.compress(data).unwrap();
+        assert_eq!(plain, data);
+        assert_eq!(Compression::detect(&plain).unwrap(), Compression::None);
+        assert_eq!(Compression::decompress(&plain).unwrap(), data);
+        
+        let mut written = Vec::new();
+        Compression::None.write_json(&mut written, &vec!["aa", "bb"]).unwrap();
+        assert_eq!(written, br#"["aa","bb"]"#);
+        assert_eq!(Compression::read_json::<_, Vec<String>>(written.as_slice()).unwrap(), vec!["aa", "bb"]);
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_compressed_data_needs_feature() {
+        let compressed = [0x28, 0xb5, 0x2f, 0xfd, 0x00];
+        assert_eq!(Compression::detect(&compressed).unwrap_err().kind(), io::ErrorKind::Unsupported);
+        assert!(Compression::read_json::<_, Vec<String>>(compressed.as_slice()).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_round_trip_and_detection() {
+        let data = br#"{"txids":["aa","bb","aa","bb","aa","bb"]}"#;
+        
+        let compressed = Compression::Zstd.compress(data).unwrap();
+        assert_eq!(Compression::detect(&compressed).unwrap(), Compression::Zstd);
+        assert_eq!(Compression::decompress(&compressed).unwrap(), data);
+        
+        // Frames appended one after another decode as one stream
+        let mut appended = Compression::Zstd.compress(b"first\n").unwrap();
+        appended.extend(Compression::Zstd.compress(b"second\n").unwrap());
+        assert_eq!(Compression::decompress(&appended).unwrap(), b"first\nsecond\n");
+        
+        let value: Vec<String> = (0..100).map(|i| format!("{:064x}", i)).collect();
+        let mut written = Vec::new();
+        Compression::Zstd.write_json(&mut written, &value).unwrap();
+        assert_eq!(Compression::detect(&written).unwrap(), Compression::Zstd);
+        assert_eq!(Compression::read_json::<_, Vec<String>>(written.as_slice()).unwrap(), value);
+    }
+}
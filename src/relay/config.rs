@@ -1,15 +1,318 @@
 use crate::validation::ValidationConfig;
 use crate::error::ConfigError;
-use std::net::SocketAddr;
+use crate::filter::ScriptFilter;
+use crate::nostr::TagNamespace;
+use super::bus::EventBus;
+use super::compression::Compression;
+use super::encryption::BroadcastCipher;
+use super::discovery::bounded_relay_name;
+use super::metrics::{MetricsSink, NoopMetrics};
+use super::payload::BroadcastEncoding;
+use nostr::prelude::XOnlyPublicKey;
+use nostr::Keys;
+use bitcoin::hashes::Hash;
+use bitcoin::Txid;
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Authentication credentials for Bitcoin RPC
-#[derive(Debug, Clone)]
+/// 
+/// `Debug` output redacts the password so configs can be logged safely.
+#[derive(Clone)]
 pub struct RpcAuth {
     pub username: String,
     pub password: String,
 }
 
+impl RpcAuth {
+    /// Credentials for a node's RPC interface
+    pub fn new(username: String, password: String) -> Self {
+        Self { username, password }
+    }
+    
+    /// The unredacted password, for authenticating to the node
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
+impl std::fmt::Debug for RpcAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcAuth")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// An extra Bitcoin node that accepted transactions are also submitted to
+#[derive(Debug, Clone)]
+pub struct BitcoinNodeConfig {
+    pub url: String,
+    pub auth: RpcAuth,
+}
+
+/// Reconnect backoff settings for the upstream Nostr relay connection
+#[derive(Debug, Clone)]
+pub struct BackoffConfig {
+    /// Delay before the first reconnect attempt
+    pub base_delay: Duration,
+    
+    /// Upper bound for the exponentially growing delay
+    pub max_delay: Duration,
+    
+    /// A connection that stays up at least this long resets the backoff
+    pub reset_after: Duration,
+    
+    /// Random jitter added to each delay, as a fraction of the delay (0.0 - 1.0)
+    pub jitter: f64,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(300),
+            reset_after: Duration::from_secs(60),
+            jitter: 0.25,
+        }
+    }
+}
+
+/// How the relay reacts to another relay announcing the same `relay_id`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RelayIdCheck {
+    /// Do not publish presence events or check for conflicts
+    #[default]
+    Disabled,
+    
+    /// Publish presence events and log a warning on conflict
+    Warn,
+    
+    /// Publish presence events and stop the server on conflict
+    FailFast,
+}
+
+/// What happens to an event sent to strfry while the outbound queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued event to make room
+    #[default]
+    DropOldest,
+    
+    /// Drop the new event
+    Reject,
+}
+
+/// Which of two conflicting transactions a relay keeps when a peer gossips a double spend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Submit the remote transaction and let the node's replacement rules decide, so it
+    /// replaces the local one only if it pays a higher fee and the replacement is allowed
+    #[default]
+    NodeAuthoritative,
+    
+    /// Keep whichever transaction reached the local mempool first; remote transactions
+    /// spending an outpoint a mempool transaction already spends are not submitted
+    ///
+    /// Needs Bitcoin Core 24 or newer for `gettxspendingprevout`; older nodes fall back to
+    /// [`ConflictPolicy::NodeAuthoritative`].
+    FirstSeen,
+}
+
+/// Peer discovery settings
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    /// Publish discovery events and track peers learned from them
+    pub enabled: bool,
+    
+    /// How often this relay announces itself
+    pub announce_interval: Duration,
+    
+    /// Peers not heard from within this window are dropped from the roster
+    pub peer_ttl: Duration,
+    
+    /// Maximum number of peers kept in the roster
+    pub max_peers: usize,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            announce_interval: Duration::from_secs(60),
+            peer_ttl: Duration::from_secs(300),
+            max_peers: 256,
+        }
+    }
+}
+
+/// Connection pool settings for the HTTP client used for Bitcoin RPC
+///
+/// `bitcoind` closes idle RPC connections after `-rpcservertimeout` (30s by default), so
+/// `pool_idle_timeout` should stay below it or pooled requests hit already-closed sockets.
+/// Per-request timeouts (such as `ValidationConfig::validation_timeout_ms`) are independent
+/// of these settings: they bound a single call, while the pool only governs idle connections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpPoolConfig {
+    /// Idle connections kept open to the node
+    pub pool_max_idle_per_host: usize,
+    
+    /// How long an idle connection is kept before it is closed (`None` keeps it indefinitely)
+    pub pool_idle_timeout: Option<Duration>,
+    
+    /// TCP keepalive interval for RPC connections (`None` disables keepalive)
+    pub tcp_keepalive: Option<Duration>,
+    
+    /// SOCKS5 proxy (`host:port`) for RPC connections (`None` connects directly)
+    ///
+    /// Host names are resolved by the proxy, so `.onion` RPC URLs work through Tor.
+    pub socks_proxy: Option<String>,
+}
+
+impl Default for HttpPoolConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: 4,
+            pool_idle_timeout: Some(Duration::from_secs(25)),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            socks_proxy: None,
+        }
+    }
+}
+
+impl HttpPoolConfig {
+    /// Apply these settings to an HTTP client builder
+    ///
+    /// Fails if `socks_proxy` is not a valid proxy address.
+    pub fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::Result<reqwest::ClientBuilder> {
+        let builder = builder
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive);
+        match &self.socks_proxy {
+            // socks5h resolves host names at the proxy rather than locally
+            Some(proxy) => Ok(builder.proxy(reqwest::Proxy::all(format!("socks5h://{}", proxy))?)),
+            None => Ok(builder),
+        }
+    }
+}
+
+/// Global limit on `sendrawtransaction` calls, shared by every submission path
+#[derive(Debug, Clone)]
+pub struct SubmissionRateLimit {
+    /// Sustained submissions per second
+    pub per_second: f64,
+    
+    /// Submissions allowed back-to-back before the rate applies
+    pub burst: u32,
+    
+    /// Submissions that may wait for a token at once; further ones are rejected
+    pub max_queued: usize,
+    
+    /// Longest a queued submission waits before it is rejected
+    pub max_wait: Duration,
+}
+
+impl Default for SubmissionRateLimit {
+    fn default() -> Self {
+        Self {
+            per_second: 10.0,
+            burst: 20,
+            max_queued: 100,
+            max_wait: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Cap on transactions being submitted to the Bitcoin node at the same time
+#[derive(Debug, Clone)]
+pub struct InflightSubmissionLimit {
+    /// Submissions allowed in flight at once
+    pub max_inflight: usize,
+    
+    /// How long a submission waits for a free slot before the client is told the server is busy
+    pub acquire_timeout: Duration,
+}
+
+impl Default for InflightSubmissionLimit {
+    fn default() -> Self {
+        Self {
+            max_inflight: 16,
+            acquire_timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Temporary ban on client IPs whose WebSocket handshakes keep failing, e.g. port scanners
+#[derive(Debug, Clone)]
+pub struct HandshakeBanPolicy {
+    /// Failed handshakes within `window` that trigger a ban
+    pub max_failures: u32,
+    
+    /// Period failures are counted over
+    pub window: Duration,
+    
+    /// How long connections from a banned IP are dropped without a handshake
+    pub ban_duration: Duration,
+}
+
+impl Default for HandshakeBanPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: 5,
+            window: Duration::from_secs(60),
+            ban_duration: Duration::from_secs(600),
+        }
+    }
+}
+
+/// This relay's share of mempool gossip when a fleet of relays divides it by txid
+///
+/// A relay gossips a mempool transaction only when the first byte of its txid, as
+/// displayed, modulo `count` equals `index`. Give each relay in the fleet the same
+/// `count` and a distinct `index` below it. Transactions from peers are handled
+/// by every relay regardless of shard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    /// This relay's shard, below `count`
+    pub index: u8,
+    
+    /// Number of shards in the fleet
+    pub count: u8,
+}
+
+impl Shard {
+    /// Whether `txid` falls in this shard
+    pub fn contains(&self, txid: &Txid) -> bool {
+        // Txids display byte-reversed, so the first displayed byte is the last stored one
+        let first_byte = txid.to_byte_array()[31];
+        first_byte.checked_rem(self.count) == Some(self.index)
+    }
+}
+
+/// When to stop calling a failing Bitcoin node
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed RPC calls that open the breaker
+    pub failure_threshold: u32,
+    
+    /// How long calls fail fast before a single probe call is let through
+    pub cool_down: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cool_down: Duration::from_secs(30),
+        }
+    }
+}
+
 /// Configuration for the Bitcoin-Nostr relay server
 #[derive(Debug, Clone)]
 pub struct RelayConfig {
@@ -19,222 +322,1442 @@ pub struct RelayConfig {
     /// Bitcoin RPC authentication credentials
     pub bitcoin_rpc_auth: RpcAuth,
     
+    /// HTTP connection pool settings for Bitcoin RPC
+    ///
+    /// Validation calls use them too; a validator built by hand needs
+    /// [`TransactionValidator::with_http_pool`](crate::TransactionValidator::with_http_pool).
+    pub rpc_pool: HttpPoolConfig,
+    
+    /// Further nodes every submitted transaction is also sent to
+    pub additional_bitcoin_nodes: Vec<BitcoinNodeConfig>,
+    
     /// Strfry Nostr relay URL (e.g., "ws://127.0.0.1:7777")
     pub strfry_url: String,
     
     /// Relay identifier (unique for each relay instance)
     pub relay_id: String,
     
-    /// WebSocket server listen address
+    /// WebSocket server listen address, IPv4 or IPv6
+    ///
+    /// `[::]` listens on every IPv6 interface; whether it also accepts IPv4
+    /// clients follows the OS default for dual-stack sockets.
     pub websocket_listen_addr: SocketAddr,
     
+    /// Unix domain socket also accepting WebSocket clients, for co-located services (Unix only)
+    pub unix_socket_path: Option<PathBuf>,
+    
+    /// Check the Bitcoin RPC is reachable and the strfry URL is valid before listening
+    pub preflight_checks: bool,
+    
     /// Configuration for transaction validation
     pub validation_config: ValidationConfig,
     
     /// Mempool polling interval
     pub mempool_poll_interval: Duration,
     
+    /// Longest interval an idle mempool backs polling off to (`None` always polls at `mempool_poll_interval`)
+    pub max_mempool_poll_interval: Option<Duration>,
+    
+    /// Skip diffing the mempool while the node's mempool sequence number is unchanged
+    pub mempool_sequence_polling: bool,
+    
+    /// Newly seen mempool transactions fetched and gossiped at once, e.g. when a large mempool is first scanned
+    pub mempool_fetch_concurrency: usize,
+    
     /// Maximum number of concurrent client connections
     pub max_client_connections: usize,
     
     /// WebSocket buffer size for client connections
     pub websocket_buffer_size: usize,
+    
+    /// Send transaction broadcasts to new clients without waiting for a `REQ`
+    /// 
+    /// On by default, as clients have always received every broadcast. A client's
+    /// first `REQ` replaces this implicit subscription with its own filters.
+    pub auto_subscribe_new_clients: bool,
+    
+    /// Most open `REQ` subscriptions per client; a `REQ` over the limit is answered with `CLOSED`
+    /// 
+    /// Each broadcast is sent once per matching subscription, so this bounds how much one
+    /// connection multiplies the relay's outgoing traffic.
+    pub max_subscriptions_per_client: usize,
+    
+    /// How many of one client's messages are handled at once
+    /// 
+    /// With 1, a client's submissions reach the node in the order they were sent, so a
+    /// child can follow its parent. Higher values may reorder submissions and responses.
+    pub client_message_concurrency: usize,
+    
+    /// Close client connections that send nothing for this long (`None` keeps them open)
+    pub client_idle_timeout: Option<Duration>,
+    
+    /// After accepting a client's transaction, wait this long for a peer relay to report its
+    /// node accepted it and tell the client whether propagation was confirmed (`None` sends no follow-up)
+    pub propagation_ack_timeout: Option<Duration>,
+    
+    /// After the node accepts a client's transaction, wait this long, check it is still
+    /// in the mempool and report `resident` in a follow-up response (`None` skips the check)
+    pub residency_check_delay: Option<Duration>,
+    
+    /// Answer a client resubmitting a transaction on the same connection within this window
+    /// with the first submission's response instead of processing it again (`None` processes every submission)
+    pub client_resubmission_window: Option<Duration>,
+    
+    /// Maximum number of pending connections in the listen queue
+    pub listen_backlog: u32,
+    
+    /// Disable Nagle's algorithm on accepted client connections
+    pub tcp_nodelay: bool,
+    
+    /// Maximum WebSocket message (and frame) size in bytes, for clients and strfry
+    pub websocket_max_message_size: usize,
+    
+    /// Backoff policy for reconnecting to the strfry relay
+    pub strfry_reconnect_backoff: BackoffConfig,
+    
+    /// Broadcasts held while strfry is disconnected, oldest dropped first on overflow
+    pub strfry_retry_buffer_size: usize,
+    
+    /// Events waiting to be written to a connected strfry
+    pub strfry_outbound_queue_size: usize,
+    
+    /// What to drop when the outbound queue is full
+    pub strfry_overflow_policy: OverflowPolicy,
+    
+    /// Startup presence handshake used to detect duplicate relay IDs
+    pub relay_id_check: RelayIdCheck,
+    
+    /// Only gossip mempool transactions touching these scripts (empty relays everything)
+    pub script_filter: ScriptFilter,
+    
+    /// Bitcoin network this relay serves, when known
+    pub network: Option<crate::networks::Network>,
+    
+    /// Peer relay discovery via Nostr
+    pub discovery: DiscoveryConfig,
+    
+    /// Human-readable name announced in presence and discovery events (`None` announces only the relay ID)
+    pub relay_name: Option<String>,
+    
+    /// Answer and act on mempool summaries exchanged with peer relays
+    ///
+    /// Only trusted peers are answered: those on `peer_relay_allowlist`, or without
+    /// an allowlist, those in the discovery roster.
+    pub mempool_reconciliation: bool,
+    
+    /// Tell peers when gossiped transactions leave the mempool without confirming, and
+    /// act on their notices
+    pub eviction_notifications: bool,
+    
+    /// Preferred encoding of transaction broadcasts; compact is only used once every known peer supports it
+    pub broadcast_encoding: BroadcastEncoding,
+    
+    /// Tell submitting clients when their transaction is replaced or leaves the mempool unconfirmed
+    pub replacement_notifications: bool,
+    
+    /// Confirmations a block needs before its unseen transactions are gossiped (`None` gossips mempool transactions only)
+    pub broadcast_block_tx_min_depth: Option<u32>,
+    
+    /// Where the server reports metrics; discarded by default
+    pub metrics_sink: Arc<dyn MetricsSink>,
+    
+    /// How remote transactions that conflict with the local mempool are handled
+    pub conflict_policy: ConflictPolicy,
+    
+    /// How many generations of unconfirmed ancestors to gossip along with a new transaction (0 disables)
+    pub max_ancestor_depth: usize,
+    
+    /// File the dedup state is saved to on shutdown and restored from on startup (`None` disables)
+    pub dedup_snapshot_path: Option<PathBuf>,
+    
+    /// How long dedup entries stay valid, both while running and once restored
+    pub dedup_snapshot_ttl: Duration,
+    
+    /// JSON Lines file failed submissions are appended to (`None` disables)
+    pub dead_letter_path: Option<PathBuf>,
+    
+    /// Size at which the dead-letter log is rotated
+    pub dead_letter_max_bytes: u64,
+    
+    /// Compression of the dedup snapshot and dead-letter log
+    pub persistence_compression: Compression,
+    
+    /// Stable key signing presence and discovery events (generated when unset)
+    pub identity_keys: Option<Keys>,
+    
+    /// Key signing transaction broadcasts and responses, may be rotated (generated when unset)
+    pub content_keys: Option<Keys>,
+    
+    /// Pubkeys allowed to submit transactions (`None` accepts submissions from anyone)
+    pub submitter_allowlist: Option<HashSet<XOnlyPublicKey>>,
+    
+    /// Identity pubkeys of peer relays allowed to forward transactions and ask for mempool contents
+    /// (`None` refuses all forwarded transactions and answers peers in the discovery roster)
+    pub peer_relay_allowlist: Option<HashSet<XOnlyPublicKey>>,
+    
+    /// Drop broadcasts and forwarded transactions that already passed through more
+    /// relays than this (`None` accepts any number of hops)
+    pub max_hops: Option<u32>,
+    
+    /// Only accept submissions over connections authenticated with NIP-42
+    pub require_auth: bool,
+    
+    /// Global cap on transactions submitted to the Bitcoin node (`None` is unlimited)
+    pub submission_rate_limit: Option<SubmissionRateLimit>,
+    
+    /// Cap on concurrent submissions to the Bitcoin node (`None` is unlimited)
+    pub inflight_submission_limit: Option<InflightSubmissionLimit>,
+    
+    /// Ban client IPs after repeated failed WebSocket handshakes (`None` never bans)
+    pub handshake_ban: Option<HandshakeBanPolicy>,
+    
+    /// Prefix of the custom tags written to and read from strfry
+    pub tag_namespace: TagNamespace,
+    
+    /// Fail fast while the Bitcoin node keeps erroring (`None` always calls the node)
+    pub rpc_circuit_breaker: Option<CircuitBreakerConfig>,
+    
+    /// Skip gossiping mempool transactions that entered the mempool longer ago than this (`None` gossips all)
+    pub max_broadcast_age: Option<Duration>,
+    
+    /// Only gossip mempool transactions in this shard (`None` gossips all)
+    pub shard: Option<Shard>,
+    
+    /// Drop strfry events whose `created_at` is further than this from local time, and ignore
+    /// `first_seen` times beyond it for latency (`None` accepts any)
+    pub max_clock_skew: Option<Duration>,
+    
+    /// SOCKS5 proxy (`host:port`) for the strfry connection (`None` connects directly)
+    ///
+    /// RPC connections use `rpc_pool.socks_proxy`; client connections to the
+    /// relay are inbound and never proxied.
+    pub socks_proxy: Option<String>,
+    
+    /// In-process bus used in place of strfry (`None` connects to `strfry_url`)
+    pub event_bus: Option<EventBus>,
+    
+    /// Encrypts the content of every event sent to peer relays for relays sharing its secret
+    /// (`None` publishes in the clear)
+    pub broadcast_cipher: Option<BroadcastCipher>,
 }
 
-impl RelayConfig {
-    /// Create a new RelayConfig with the provided URLs and addresses
-    /// 
-    /// This validates URLs and socket addresses at construction time
-    pub fn new(
-        bitcoin_rpc_url: impl Into<String>,
-        strfry_url: impl Into<String>,
-        relay_id: impl Into<String>,
-        websocket_listen_addr: SocketAddr,
-    ) -> Result<Self, ConfigError> {
-        let bitcoin_url = bitcoin_rpc_url.into();
-        let nostr_url = strfry_url.into();
-        let relay_id_str = relay_id.into();
+impl RelayConfig {
+    /// Create a new RelayConfig with the provided URLs and addresses
+    /// 
+    /// This validates URLs and socket addresses at construction time
+    pub fn new(
+        bitcoin_rpc_url: impl Into<String>,
+        strfry_url: impl Into<String>,
+        relay_id: impl Into<String>,
+        websocket_listen_addr: SocketAddr,
+    ) -> Result<Self, ConfigError> {
+        let bitcoin_url = bitcoin_rpc_url.into();
+        let nostr_url = strfry_url.into();
+        let relay_id_str = relay_id.into();
+        
+        // Validate Bitcoin RPC URL
+        url::Url::parse(&bitcoin_url)
+            .map_err(|_| ConfigError::invalid_url(&bitcoin_url))?;
+        
+        // Validate Nostr relay URL  
+        url::Url::parse(&nostr_url)
+            .map_err(|_| ConfigError::invalid_url(&nostr_url))?;
+        
+        // Validate relay ID is not empty
+        if relay_id_str.trim().is_empty() {
+            return Err(ConfigError::InvalidParameter { 
+                param: "relay_id cannot be empty".to_string() 
+            });
+        }
+        
+        Ok(Self {
+            bitcoin_rpc_url: bitcoin_url,
+            bitcoin_rpc_auth: RpcAuth::new("user".to_string(), "password".to_string()),
+            rpc_pool: HttpPoolConfig::default(),
+            additional_bitcoin_nodes: Vec::new(),
+            strfry_url: nostr_url,
+            relay_id: relay_id_str,
+            websocket_listen_addr,
+            unix_socket_path: None,
+            preflight_checks: false,
+            validation_config: ValidationConfig::default(),
+            mempool_poll_interval: Duration::from_secs(2),
+            max_mempool_poll_interval: None,
+            mempool_sequence_polling: false,
+            mempool_fetch_concurrency: 1,
+            max_client_connections: 1000,
+            websocket_buffer_size: 100,
+            listen_backlog: 1024,
+            tcp_nodelay: true,
+            auto_subscribe_new_clients: true,
+            max_subscriptions_per_client: 20,
+            client_message_concurrency: 1,
+            client_idle_timeout: None,
+            propagation_ack_timeout: None,
+            residency_check_delay: None,
+            client_resubmission_window: None,
+            // Largest accepted transaction (400 KB) hex-encoded, plus event envelope
+            websocket_max_message_size: 1024 * 1024,
+            strfry_reconnect_backoff: BackoffConfig::default(),
+            strfry_retry_buffer_size: 1000,
+            strfry_outbound_queue_size: 1000,
+            strfry_overflow_policy: OverflowPolicy::default(),
+            relay_id_check: RelayIdCheck::default(),
+            script_filter: ScriptFilter::default(),
+            network: None,
+            discovery: DiscoveryConfig::default(),
+            relay_name: None,
+            mempool_reconciliation: false,
+            eviction_notifications: false,
+            broadcast_encoding: BroadcastEncoding::Verbose,
+            replacement_notifications: false,
+            broadcast_block_tx_min_depth: None,
+            metrics_sink: Arc::new(NoopMetrics),
+            conflict_policy: ConflictPolicy::default(),
+            // Bitcoin Core's default ancestor limit
+            max_ancestor_depth: 25,
+            dedup_snapshot_path: None,
+            dedup_snapshot_ttl: Duration::from_secs(3600),
+            dead_letter_path: None,
+            dead_letter_max_bytes: 10 * 1024 * 1024,
+            persistence_compression: Compression::default(),
+            identity_keys: None,
+            content_keys: None,
+            submitter_allowlist: None,
+            peer_relay_allowlist: None,
+            max_hops: None,
+            require_auth: false,
+            submission_rate_limit: None,
+            inflight_submission_limit: None,
+            handshake_ban: None,
+            tag_namespace: TagNamespace::default(),
+            rpc_circuit_breaker: None,
+            max_broadcast_age: None,
+            shard: None,
+            max_clock_skew: None,
+            socks_proxy: None,
+            event_bus: None,
+            broadcast_cipher: None,
+        })
+    }
+    
+    /// Check settings the builder methods can't reject on their own
+    /// 
+    /// Run when a [`RelayServer`](super::RelayServer) is created.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(shard) = self.shard {
+            if shard.count == 0 || shard.index >= shard.count {
+                return Err(ConfigError::InvalidParameter {
+                    param: format!("shard index {} must be below a non-zero shard count, got {}", shard.index, shard.count),
+                });
+            }
+        }
+        Ok(())
+    }
+    
+    
+    /// Set custom Bitcoin RPC credentials
+    pub fn with_auth(mut self, username: String, password: String) -> Self {
+        self.bitcoin_rpc_auth = RpcAuth::new(username, password);
+        self
+    }
+    
+    /// Also submit transactions to another Bitcoin node
+    pub fn with_additional_bitcoin_node(mut self, url: impl Into<String>, username: String, password: String) -> Self {
+        self.additional_bitcoin_nodes.push(BitcoinNodeConfig {
+            url: url.into(),
+            auth: RpcAuth::new(username, password),
+        });
+        self
+    }
+    
+    /// Set HTTP connection pool settings for Bitcoin RPC
+    pub fn with_rpc_pool(mut self, pool: HttpPoolConfig) -> Self {
+        self.rpc_pool = pool;
+        self
+    }
+    
+    /// Also accept WebSocket clients on a Unix domain socket at `path`
+    ///
+    /// A stale socket at `path` is replaced on startup; any other file there
+    /// makes startup fail rather than being deleted.
+    pub fn with_unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix_socket_path = Some(path.into());
+        self
+    }
+    
+    /// Fail [`run`](super::RelayServer::run) at startup if the Bitcoin RPC is unreachable or the
+    /// strfry URL is invalid, rather than leaving the background tasks to retry
+    pub fn with_preflight_checks(mut self, enabled: bool) -> Self {
+        self.preflight_checks = enabled;
+        self
+    }
+    
+    /// Set custom validation configuration
+    pub fn with_validation(mut self, config: ValidationConfig) -> Self {
+        self.validation_config = config;
+        self
+    }
+    
+    /// Set custom mempool polling interval
+    pub fn with_mempool_poll_interval(mut self, interval: Duration) -> Self {
+        self.mempool_poll_interval = interval;
+        self
+    }
+    
+    /// Back mempool polling off toward `max_interval` while no new transactions appear
+    ///
+    /// Polling returns to `mempool_poll_interval` as soon as a poll finds new transactions.
+    pub fn with_max_mempool_poll_interval(mut self, max_interval: Duration) -> Self {
+        self.max_mempool_poll_interval = Some(max_interval);
+        self
+    }
+    
+    /// Poll with `getrawmempool false true` and only diff the mempool when its sequence number moved
+    ///
+    /// Needs Bitcoin Core 0.21 or newer; polling falls back to plain `getrawmempool` if the node refuses.
+    pub fn with_mempool_sequence_polling(mut self, enabled: bool) -> Self {
+        self.mempool_sequence_polling = enabled;
+        self
+    }
+    
+    /// Fetch and gossip up to `width` newly seen mempool transactions at once
+    ///
+    /// The default of 1 handles them one at a time, in mempool order.
+    pub fn with_mempool_fetch_concurrency(mut self, width: usize) -> Self {
+        self.mempool_fetch_concurrency = width.max(1);
+        self
+    }
+    
+    /// Backward compatibility: Set mempool polling interval from seconds
+    pub fn with_mempool_poll_interval_secs(mut self, seconds: u64) -> Self {
+        self.mempool_poll_interval = Duration::from_secs(seconds);
+        self
+    }
+    
+    /// Listen on `ip`, keeping the configured port, e.g. `Ipv6Addr::LOCALHOST` for `[::1]`
+    pub fn with_listen_ip(mut self, ip: IpAddr) -> Self {
+        self.websocket_listen_addr.set_ip(ip);
+        self
+    }
+    
+    /// Set the accept backlog of the WebSocket listener
+    pub fn with_listen_backlog(mut self, backlog: u32) -> Self {
+        self.listen_backlog = backlog;
+        self
+    }
+    
+    /// Enable or disable `TCP_NODELAY` on accepted client connections
+    pub fn with_tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.tcp_nodelay = nodelay;
+        self
+    }
+    
+    /// Subscribe new clients to transaction broadcasts as soon as they connect
+    pub fn with_auto_subscribe_new_clients(mut self, enabled: bool) -> Self {
+        self.auto_subscribe_new_clients = enabled;
+        self
+    }
+    
+    /// Allow each client at most `max` open subscriptions
+    pub fn with_max_subscriptions_per_client(mut self, max: usize) -> Self {
+        self.max_subscriptions_per_client = max;
+        self
+    }
+    
+    /// Handle up to `concurrency` of one client's messages at once
+    pub fn with_client_message_concurrency(mut self, concurrency: usize) -> Self {
+        self.client_message_concurrency = concurrency;
+        self
+    }
+    
+    /// Close a client's connection once it has sent no frame for `timeout`
+    ///
+    /// Only inbound frames, pongs included, count as activity; data sent to the
+    /// client doesn't, so connections to vanished clients are reaped.
+    pub fn with_client_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.client_idle_timeout = Some(timeout);
+        self
+    }
+    
+    /// Follow up accepted submissions with whether a peer relay's node accepted them
+    /// 
+    /// The broadcast asks peers for an acceptance notice, which relays of this version send
+    /// once their node takes the transaction.
+    pub fn with_propagation_ack(mut self, timeout: Duration) -> Self {
+        self.propagation_ack_timeout = Some(timeout);
+        self
+    }
+    
+    /// Follow up accepted submissions with whether they were still in the mempool after `delay`
+    /// 
+    /// Catches transactions the node accepts and then evicts straight away, e.g. from a full mempool.
+    /// The accepted response itself is sent without waiting.
+    pub fn with_residency_check(mut self, delay: Duration) -> Self {
+        self.residency_check_delay = Some(delay);
+        self
+    }
+    
+    /// Replay the original response to a transaction the same connection submitted within `window`
+    /// 
+    /// A resubmission arriving while the first is still being processed waits for its response.
+    /// Failures are replayed too, so keep the window short enough for deliberate retries.
+    pub fn with_client_resubmission_window(mut self, window: Duration) -> Self {
+        self.client_resubmission_window = Some(window);
+        self
+    }
+    
+    /// Set the maximum WebSocket message size in bytes
+    pub fn with_websocket_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.websocket_max_message_size = max_message_size;
+        self
+    }
+    
+    /// Set custom strfry reconnect backoff policy
+    pub fn with_strfry_reconnect_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.strfry_reconnect_backoff = backoff;
+        self
+    }
+    
+    /// Set how many broadcasts are held for retry while strfry is disconnected
+    pub fn with_strfry_retry_buffer_size(mut self, size: usize) -> Self {
+        self.strfry_retry_buffer_size = size;
+        self
+    }
+    
+    /// Bound the queue of events waiting to be written to strfry
+    pub fn with_strfry_outbound_queue(mut self, size: usize, policy: OverflowPolicy) -> Self {
+        self.strfry_outbound_queue_size = size;
+        self.strfry_overflow_policy = policy;
+        self
+    }
+    
+    /// Enable the presence handshake that detects other relays using the same relay ID
+    pub fn with_relay_id_check(mut self, check: RelayIdCheck) -> Self {
+        self.relay_id_check = check;
+        self
+    }
+    
+    /// Only gossip mempool transactions that match the given script filter
+    pub fn with_script_filter(mut self, filter: ScriptFilter) -> Self {
+        self.script_filter = filter;
+        self
+    }
+    
+    /// Set the Bitcoin network this relay serves
+    pub fn with_network(mut self, network: crate::networks::Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+    
+    /// Set how many generations of unconfirmed ancestors are gossiped with a new transaction
+    pub fn with_max_ancestor_depth(mut self, depth: usize) -> Self {
+        self.max_ancestor_depth = depth;
+        self
+    }
+    
+    /// Persist the dedup state to `path` across restarts, keeping entries for `ttl`
+    pub fn with_dedup_snapshot(mut self, path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        self.dedup_snapshot_path = Some(path.into());
+        self.dedup_snapshot_ttl = ttl;
+        self
+    }
+    
+    /// Record every transaction the node fails to accept in a dead-letter log at `path`
+    ///
+    /// The log is rotated to `<path>.1` once it reaches `max_bytes`. Transactions the node
+    /// already knows are not recorded.
+    pub fn with_dead_letter_log(mut self, path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        self.dead_letter_path = Some(path.into());
+        self.dead_letter_max_bytes = max_bytes;
+        self
+    }
+    
+    /// Compress the dedup snapshot and dead-letter log on disk
+    ///
+    /// Files written without compression stay readable after it is turned on, and vice versa.
+    pub fn with_persistence_compression(mut self, compression: Compression) -> Self {
+        self.persistence_compression = compression;
+        self
+    }
+    
+    /// Set the stable identity key used for presence and discovery events
+    pub fn with_identity_keys(mut self, keys: Keys) -> Self {
+        self.identity_keys = Some(keys);
+        self
+    }
+    
+    /// Set the content key used to sign transaction broadcasts
+    pub fn with_content_keys(mut self, keys: Keys) -> Self {
+        self.content_keys = Some(keys);
+        self
+    }
+    
+    /// Only accept transaction submissions signed by these pubkeys
+    pub fn with_submitter_allowlist(mut self, pubkeys: impl IntoIterator<Item = XOnlyPublicKey>) -> Self {
+        self.submitter_allowlist = Some(pubkeys.into_iter().collect());
+        self
+    }
+    
+    /// Only accept forwarded transactions signed by these peer relay identity keys
+    pub fn with_peer_relay_allowlist(mut self, pubkeys: impl IntoIterator<Item = XOnlyPublicKey>) -> Self {
+        self.peer_relay_allowlist = Some(pubkeys.into_iter().collect());
+        self
+    }
+    
+    /// Drop transactions that reach this relay after passing through more than `max_hops` relays
+    /// 
+    /// Bounds how far a transaction travels through a forwarding mesh, so a misconfigured
+    /// loop dies out; drops are counted in the loop metrics. Hop counts are reported by the
+    /// sender, so one is never taken as lower than a count already seen for the same transaction.
+    pub fn with_max_hops(mut self, max_hops: u32) -> Self {
+        self.max_hops = Some(max_hops);
+        self
+    }
+    
+    /// Send each client a NIP-42 challenge and only accept submissions once it has authenticated
+    ///
+    /// With an allowlist too, the authenticated pubkey is the one checked against it.
+    pub fn with_require_auth(mut self, required: bool) -> Self {
+        self.require_auth = required;
+        self
+    }
+    
+    /// Cap the rate of transactions submitted to the Bitcoin node
+    pub fn with_submission_rate_limit(mut self, limit: SubmissionRateLimit) -> Self {
+        self.submission_rate_limit = Some(limit);
+        self
+    }
+    
+    /// Cap how many transactions are submitted to the Bitcoin node concurrently
+    pub fn with_inflight_submission_limit(mut self, limit: InflightSubmissionLimit) -> Self {
+        self.inflight_submission_limit = Some(limit);
+        self
+    }
+    
+    /// Temporarily ban client IPs whose WebSocket handshakes keep failing
+    pub fn with_handshake_ban(mut self, policy: HandshakeBanPolicy) -> Self {
+        self.handshake_ban = Some(policy);
+        self
+    }
+    
+    /// Prefix custom tags such as `relay_id` with `prefix`, e.g. `btcrelay:relay_id`
+    ///
+    /// Every relay in a fleet must use the same namespace to recognize each other's events.
+    pub fn with_tag_namespace(mut self, prefix: impl Into<String>) -> Self {
+        self.tag_namespace = TagNamespace::new(prefix);
+        self
+    }
+    
+    /// Guard Bitcoin RPC calls with a circuit breaker
+    pub fn with_rpc_circuit_breaker(mut self, breaker: CircuitBreakerConfig) -> Self {
+        self.rpc_circuit_breaker = Some(breaker);
+        self
+    }
+    
+    /// Only gossip mempool transactions that entered the mempool within `max_age`
+    ///
+    /// Older transactions have most likely propagated already.
+    pub fn with_max_broadcast_age(mut self, max_age: Duration) -> Self {
+        self.max_broadcast_age = Some(max_age);
+        self
+    }
+    
+    /// Only gossip mempool transactions whose txid falls in `shard`, splitting gossip across a fleet
+    pub fn with_shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+    
+    /// Drop events from strfry timestamped more than `tolerance` before or after local time
+    ///
+    /// Broadcasts whose `first_seen` is outside the same window are still relayed but left out
+    /// of the propagation latency histogram, so a peer with a broken or malicious clock can't
+    /// skew it.
+    pub fn with_max_clock_skew(mut self, tolerance: Duration) -> Self {
+        self.max_clock_skew = Some(tolerance);
+        self
+    }
+    
+    /// Route RPC and strfry connections through a SOCKS5 proxy such as Tor
+    pub fn with_socks_proxy(mut self, proxy: impl Into<String>) -> Self {
+        let proxy = proxy.into();
+        self.rpc_pool.socks_proxy = Some(proxy.clone());
+        self.socks_proxy = Some(proxy);
+        self
+    }
+    
+    /// Exchange events with other relays in this process over `bus` instead of strfry
+    ///
+    /// For single-host deployments and multi-relay tests without a strfry instance.
+    pub fn with_event_bus(mut self, bus: EventBus) -> Self {
+        self.event_bus = Some(bus);
+        self
+    }
+    
+    /// Encrypt peer traffic with a secret shared by a private set of relays
+    ///
+    /// Covers the content of every event relays exchange: transaction broadcasts, eviction
+    /// notices, presence, discovery and capability announcements, mempool summaries and
+    /// their requests, and peer transaction requests. Tags stay public. Relays decrypt
+    /// with the same secret and ignore any event they can't, so every participant needs
+    /// the same secret. Clients of this relay still get plaintext.
+    pub fn with_broadcast_encryption(mut self, shared_secret: impl AsRef<[u8]>) -> Self {
+        self.broadcast_cipher = Some(BroadcastCipher::new(shared_secret));
+        self
+    }
+    
+    /// Set custom peer discovery configuration
+    pub fn with_discovery(mut self, discovery: DiscoveryConfig) -> Self {
+        self.discovery = discovery;
+        self
+    }
+    
+    /// Announce a human-readable name alongside the relay ID
+    ///
+    /// Names longer than [`MAX_RELAY_NAME_LEN`](super::discovery::MAX_RELAY_NAME_LEN) characters are truncated.
+    pub fn with_relay_name(mut self, name: impl AsRef<str>) -> Self {
+        self.relay_name = Some(bounded_relay_name(name.as_ref()));
+        self
+    }
+    
+    /// Enable mempool reconciliation with peer relays
+    pub fn with_mempool_reconciliation(mut self, enabled: bool) -> Self {
+        self.mempool_reconciliation = enabled;
+        self
+    }
+    
+    /// Publish an eviction notice when a gossiped transaction leaves the mempool without
+    /// appearing in a recent block
+    ///
+    /// Peers' notices are subscribed to as well: their transactions are forgotten, so a
+    /// later broadcast of one is submitted to the node again, and the notices are passed
+    /// on to local clients.
+    pub fn with_eviction_notifications(mut self, enabled: bool) -> Self {
+        self.eviction_notifications = enabled;
+        self
+    }
+    
+    /// Prefer `encoding` for transaction broadcasts
+    ///
+    /// Compact broadcasts need discovery: they are only sent while every peer in the
+    /// roster advertises support for them, and fall back to verbose otherwise.
+    /// Announce-only broadcasts carry the txid alone; peers request the transaction when they need it.
+    pub fn with_broadcast_encoding(mut self, encoding: BroadcastEncoding) -> Self {
+        self.broadcast_encoding = encoding;
+        self
+    }
+    
+    /// Send a follow-up response to the client that submitted a transaction when it is
+    /// replaced or otherwise leaves the mempool without confirming
+    pub fn with_replacement_notifications(mut self, enabled: bool) -> Self {
+        self.replacement_notifications = enabled;
+        self
+    }
+    
+    /// Gossip transactions first seen in a block once the block is `depth` blocks deep
+    ///
+    /// A depth of 1 gossips the tip's transactions right away; deeper settings skip
+    /// transactions of blocks that get reorged out before reaching that depth.
+    pub fn with_broadcast_block_tx_min_depth(mut self, depth: u32) -> Self {
+        self.broadcast_block_tx_min_depth = Some(depth.max(1));
+        self
+    }
+    
+    /// Report metrics to an embedder-supplied backend
+    pub fn with_metrics_sink(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics_sink = sink;
+        self
+    }
+    
+    /// Choose how remote transactions that conflict with the local mempool are handled
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+    
+    /// Create a configuration for common network patterns (recommended convenience method)
+    /// 
+    /// This provides the same functionality as the standalone `network_config` function
+    /// but follows the pattern used by mature Rust libraries like tokio, reqwest, etc.
+    pub fn for_network(network: crate::networks::Network, relay_id: u16) -> Self {
+        crate::networks::network_config(network, relay_id)
+    }
+    
+    /// Regtest configuration for `relay_id`
+    #[deprecated(note = "use `RelayConfig::for_network(Network::Regtest, relay_id)`")]
+    pub fn regtest(relay_id: u16) -> Self {
+        Self::for_network(crate::networks::Network::Regtest, relay_id)
+    }
+    
+    /// Testnet4 configuration for `relay_id`
+    #[deprecated(note = "use `RelayConfig::for_network(Network::Testnet4, relay_id)`")]
+    pub fn testnet4(relay_id: u16) -> Self {
+        Self::for_network(crate::networks::Network::Testnet4, relay_id)
+    }
+    
+    /// Set custom Bitcoin RPC credentials
+    #[deprecated(note = "use `with_auth`")]
+    pub fn with_bitcoin_auth(self, username: String, password: String) -> Self {
+        self.with_auth(username, password)
+    }
+    
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self::for_network(crate::networks::Network::Regtest, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_config_new() {
+        let config = RelayConfig::new(
+            "http://127.0.0.1:18332".to_string(),
+            "ws://127.0.0.1:7777".to_string(),
+            "test-relay".to_string(),
+            "127.0.0.1:7779".parse().unwrap(),
+        ).unwrap();
+        
+        assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:18332");
+        assert_eq!(config.bitcoin_rpc_auth.username, "user");
+        assert_eq!(config.bitcoin_rpc_auth.password(), "password");
+        assert_eq!(config.strfry_url, "ws://127.0.0.1:7777");
+        assert_eq!(config.relay_id, "test-relay");
+        assert_eq!(config.websocket_listen_addr, "127.0.0.1:7779".parse::<SocketAddr>().unwrap());
+        assert_eq!(config.mempool_poll_interval, Duration::from_secs(2));
+        assert_eq!(config.max_client_connections, 1000);
+        assert_eq!(config.websocket_buffer_size, 100);
+        assert_eq!(config.websocket_max_message_size, 1024 * 1024);
+    }
+
+    #[test]
+    fn test_relay_config_for_network_regtest() {
+        let config1 = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config1.bitcoin_rpc_url, "http://127.0.0.1:18332");
+        assert_eq!(config1.strfry_url, "ws://127.0.0.1:7777");
+        assert_eq!(config1.relay_id, "1");
+        assert_eq!(config1.websocket_listen_addr, "127.0.0.1:7779".parse::<SocketAddr>().unwrap());
+        
+        let config2 = RelayConfig::for_network(crate::networks::Network::Regtest, 2);
+        assert_eq!(config2.bitcoin_rpc_url, "http://127.0.0.1:18444");
+        assert_eq!(config2.strfry_url, "ws://127.0.0.1:7778");
+        assert_eq!(config2.relay_id, "2");
+        assert_eq!(config2.websocket_listen_addr, "127.0.0.1:7780".parse::<SocketAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_relay_config_for_network_testnet4() {
+        let config1 = RelayConfig::for_network(crate::networks::Network::Testnet4, 1);
+        assert_eq!(config1.bitcoin_rpc_url, "http://127.0.0.1:48330");
+        assert_eq!(config1.strfry_url, "ws://127.0.0.1:7777");
+        assert_eq!(config1.relay_id, "1");
+        
+        let config2 = RelayConfig::for_network(crate::networks::Network::Testnet4, 2);
+        assert_eq!(config2.bitcoin_rpc_url, "http://127.0.0.1:48350");
+        assert_eq!(config2.strfry_url, "ws://127.0.0.1:7778");
+        assert_eq!(config2.relay_id, "2");
+    }
+
+    #[test]
+    fn test_relay_config_default() {
+        let config = RelayConfig::default();
+        
+        // Default should be regtest relay 1
+        assert_eq!(config.relay_id, "1");
+        assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:18332");
+        assert_eq!(config.strfry_url, "ws://127.0.0.1:7777");
+    }
+
+    #[test]
+    fn test_relay_config_clone() {
+        let config1 = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        let config2 = config1.clone();
+        
+        assert_eq!(config1.bitcoin_rpc_url, config2.bitcoin_rpc_url);
+        assert_eq!(config1.relay_id, config2.relay_id);
+        assert_eq!(config1.strfry_url, config2.strfry_url);
+    }
+
+    #[test]
+    fn test_with_auth() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
+            .with_auth("custom_user".to_string(), "custom_pass".to_string());
+        
+        assert_eq!(config.bitcoin_rpc_auth.username, "custom_user");
+        assert_eq!(config.bitcoin_rpc_auth.password(), "custom_pass");
+        
+        // Other fields should remain unchanged
+        assert_eq!(config.relay_id, "1");
+        assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:18332");
+    }
+
+    #[test]
+    fn test_with_validation_config() {
+        let mut validation_config = ValidationConfig::default();
+        validation_config.enable_validation = false;
+        validation_config.cache_ttl_seconds = 300;
+        
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
+            .with_validation(validation_config.clone());
+        
+        assert_eq!(config.validation_config.enable_validation, false);
+        assert_eq!(config.validation_config.cache_ttl_seconds, 300);
+    }
+
+    #[test]
+    fn test_with_mempool_poll_interval() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
+            .with_mempool_poll_interval(Duration::from_secs(5));
+        
+        assert_eq!(config.mempool_poll_interval.as_secs(), 5);
+        
+        // Other fields should remain unchanged
+        assert_eq!(config.relay_id, "1");
+        assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:18332");
+    }
+
+    #[test]
+    fn test_with_strfry_reconnect_backoff() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
+            .with_strfry_reconnect_backoff(BackoffConfig {
+                base_delay: Duration::from_millis(500),
+                max_delay: Duration::from_secs(10),
+                reset_after: Duration::from_secs(20),
+                jitter: 0.0,
+            });
+        
+        assert_eq!(config.strfry_reconnect_backoff.base_delay, Duration::from_millis(500));
+        assert_eq!(config.strfry_reconnect_backoff.max_delay, Duration::from_secs(10));
+        
+        // Defaults keep the historical 5 second first delay
+        let default_config = RelayConfig::default();
+        assert_eq!(default_config.strfry_reconnect_backoff.base_delay, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_with_relay_id_check() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.relay_id_check, RelayIdCheck::Disabled);
+        
+        let config = config.with_relay_id_check(RelayIdCheck::FailFast);
+        assert_eq!(config.relay_id_check, RelayIdCheck::FailFast);
+    }
+
+    #[test]
+    fn test_with_script_filter() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.script_filter.is_empty());
+        
+        let script = bitcoin::ScriptBuf::from_bytes(vec![0x51]);
+        let config = config.with_script_filter(ScriptFilter::new().with_script(script.clone()));
+        assert!(config.script_filter.contains(&script));
+    }
+
+    #[test]
+    fn test_with_discovery() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(!config.discovery.enabled);
+        
+        let config = config.with_discovery(DiscoveryConfig {
+            enabled: true,
+            max_peers: 8,
+            ..Default::default()
+        });
+        assert!(config.discovery.enabled);
+        assert_eq!(config.discovery.max_peers, 8);
+    }
+
+    #[test]
+    fn test_with_signing_keys() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.identity_keys.is_none());
+        assert!(config.content_keys.is_none());
+        
+        let identity = Keys::generate();
+        let content = Keys::generate();
+        let config = config
+            .with_identity_keys(identity.clone())
+            .with_content_keys(content.clone());
+        assert_eq!(config.identity_keys.unwrap().public_key(), identity.public_key());
+        assert_eq!(config.content_keys.unwrap().public_key(), content.public_key());
+    }
+
+    #[test]
+    fn test_with_strfry_retry_buffer_size() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.strfry_retry_buffer_size, 1000);
+        
+        let config = config.with_strfry_retry_buffer_size(10);
+        assert_eq!(config.strfry_retry_buffer_size, 10);
+    }
+
+    #[test]
+    fn test_with_mempool_reconciliation() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(!config.mempool_reconciliation);
+        
+        let config = config.with_mempool_reconciliation(true);
+        assert!(config.mempool_reconciliation);
+    }
+
+    #[test]
+    fn test_with_relay_name() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.relay_name.is_none());
+        
+        let config = config.with_relay_name("Alice's Miner Relay");
+        assert_eq!(config.relay_name.as_deref(), Some("Alice's Miner Relay"));
+        
+        let max = crate::relay::discovery::MAX_RELAY_NAME_LEN;
+        let config = config.with_relay_name("x".repeat(max * 2));
+        assert_eq!(config.relay_name.unwrap().len(), max);
+    }
+
+    #[test]
+    fn test_with_eviction_notifications() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(!config.eviction_notifications);
+        
+        let config = config.with_eviction_notifications(true);
+        assert!(config.eviction_notifications);
+    }
+
+    #[test]
+    fn test_with_broadcast_encoding() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.broadcast_encoding, BroadcastEncoding::Verbose);
+        
+        let config = config.with_broadcast_encoding(BroadcastEncoding::Compact);
+        assert_eq!(config.broadcast_encoding, BroadcastEncoding::Compact);
+        
+        let config = config.with_broadcast_encoding(BroadcastEncoding::AnnounceOnly);
+        assert_eq!(config.broadcast_encoding, BroadcastEncoding::AnnounceOnly);
+    }
+
+    #[test]
+    fn test_with_replacement_notifications() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(!config.replacement_notifications);
+        
+        let config = config.with_replacement_notifications(true);
+        assert!(config.replacement_notifications);
+    }
+
+    #[test]
+    fn test_with_broadcast_block_tx_min_depth() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.broadcast_block_tx_min_depth.is_none());
+        
+        assert_eq!(config.clone().with_broadcast_block_tx_min_depth(6).broadcast_block_tx_min_depth, Some(6));
+        assert_eq!(config.with_broadcast_block_tx_min_depth(0).broadcast_block_tx_min_depth, Some(1));
+    }
+
+    #[test]
+    fn test_with_metrics_sink() {
+        #[derive(Debug)]
+        struct Named;
+        impl MetricsSink for Named {}
+        
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(format!("{:?}", config.metrics_sink), "NoopMetrics");
+        
+        let config = config.with_metrics_sink(Arc::new(Named));
+        assert_eq!(format!("{:?}", config.metrics_sink), "Named");
+    }
+
+    #[test]
+    fn test_with_conflict_policy() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.conflict_policy, ConflictPolicy::NodeAuthoritative);
+        
+        let config = config.with_conflict_policy(ConflictPolicy::FirstSeen);
+        assert_eq!(config.conflict_policy, ConflictPolicy::FirstSeen);
+    }
+
+    #[test]
+    fn test_with_strfry_outbound_queue() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.strfry_outbound_queue_size, 1000);
+        assert_eq!(config.strfry_overflow_policy, OverflowPolicy::DropOldest);
+        
+        let config = config.with_strfry_outbound_queue(50, OverflowPolicy::Reject);
+        assert_eq!(config.strfry_outbound_queue_size, 50);
+        assert_eq!(config.strfry_overflow_policy, OverflowPolicy::Reject);
+    }
+
+    #[test]
+    fn test_with_submitter_allowlist() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.submitter_allowlist.is_none());
+        
+        let trusted = Keys::generate().public_key();
+        let config = config.with_submitter_allowlist([trusted]);
+        assert!(config.submitter_allowlist.unwrap().contains(&trusted));
+    }
+
+    #[test]
+    fn test_with_peer_relay_allowlist() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.peer_relay_allowlist.is_none());
+        
+        let peer = Keys::generate().public_key();
+        let config = config.with_peer_relay_allowlist([peer]);
+        assert!(config.peer_relay_allowlist.unwrap().contains(&peer));
+    }
+
+    #[test]
+    fn test_with_max_hops() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.max_hops.is_none());
+        assert_eq!(config.with_max_hops(3).max_hops, Some(3));
+    }
+
+    #[test]
+    fn test_with_require_auth() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(!config.require_auth);
+        assert!(config.with_require_auth(true).require_auth);
+    }
+
+    #[test]
+    fn test_with_inflight_submission_limit() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.inflight_submission_limit.is_none());
+        
+        let config = config.with_inflight_submission_limit(InflightSubmissionLimit {
+            max_inflight: 4,
+            ..Default::default()
+        });
+        let limit = config.inflight_submission_limit.unwrap();
+        assert_eq!(limit.max_inflight, 4);
+        assert_eq!(limit.acquire_timeout, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_with_handshake_ban() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.handshake_ban.is_none());
         
-        // Validate Bitcoin RPC URL
-        url::Url::parse(&bitcoin_url)
-            .map_err(|_| ConfigError::invalid_url(&bitcoin_url))?;
+        let config = config.with_handshake_ban(HandshakeBanPolicy {
+            max_failures: 3,
+            ..Default::default()
+        });
+        let policy = config.handshake_ban.unwrap();
+        assert_eq!(policy.max_failures, 3);
+        assert_eq!(policy.ban_duration, Duration::from_secs(600));
+    }
+
+    #[test]
+    fn test_with_tag_namespace() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.tag_namespace.tag_name("relay_id"), "relay_id");
         
-        // Validate Nostr relay URL  
-        url::Url::parse(&nostr_url)
-            .map_err(|_| ConfigError::invalid_url(&nostr_url))?;
+        let config = config.with_tag_namespace("btcrelay");
+        assert_eq!(config.tag_namespace.tag_name("relay_id"), "btcrelay:relay_id");
+    }
+
+    #[test]
+    fn test_with_rpc_circuit_breaker() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.rpc_circuit_breaker.is_none());
         
-        // Validate relay ID is not empty
-        if relay_id_str.trim().is_empty() {
-            return Err(ConfigError::InvalidParameter { 
-                param: "relay_id cannot be empty".to_string() 
-            });
-        }
+        let config = config.with_rpc_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: 3,
+            ..Default::default()
+        });
+        let breaker = config.rpc_circuit_breaker.unwrap();
+        assert_eq!(breaker.failure_threshold, 3);
+        assert_eq!(breaker.cool_down, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_with_submission_rate_limit() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.submission_rate_limit.is_none());
         
-        Ok(Self {
-            bitcoin_rpc_url: bitcoin_url,
-            bitcoin_rpc_auth: RpcAuth {
-                username: "user".to_string(),
-                password: "password".to_string(),
-            },
-            strfry_url: nostr_url,
-            relay_id: relay_id_str,
-            websocket_listen_addr,
-            validation_config: ValidationConfig::default(),
-            mempool_poll_interval: Duration::from_secs(2),
-            max_client_connections: 1000,
-            websocket_buffer_size: 100,
-        })
+        let config = config.with_submission_rate_limit(SubmissionRateLimit {
+            per_second: 2.0,
+            ..Default::default()
+        });
+        let limit = config.submission_rate_limit.unwrap();
+        assert_eq!(limit.per_second, 2.0);
+        assert_eq!(limit.burst, 20);
     }
-    
-    
-    /// Set custom Bitcoin RPC credentials
-    pub fn with_auth(mut self, username: String, password: String) -> Self {
-        self.bitcoin_rpc_auth = RpcAuth { username, password };
-        self
+
+    #[test]
+    fn test_with_listen_ip() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
+            .with_listen_ip(std::net::Ipv6Addr::LOCALHOST.into());
+        assert_eq!(config.websocket_listen_addr, "[::1]:7779".parse::<SocketAddr>().unwrap());
+        
+        let config = config.with_listen_ip(std::net::Ipv6Addr::UNSPECIFIED.into());
+        assert_eq!(config.websocket_listen_addr.to_string(), "[::]:7779");
     }
-    
-    /// Set custom validation configuration
-    pub fn with_validation(mut self, config: ValidationConfig) -> Self {
-        self.validation_config = config;
-        self
+
+    #[test]
+    fn test_with_tcp_options() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.listen_backlog, 1024);
+        assert!(config.tcp_nodelay);
+        
+        let config = config.with_listen_backlog(4096).with_tcp_nodelay(false);
+        assert_eq!(config.listen_backlog, 4096);
+        assert!(!config.tcp_nodelay);
     }
-    
-    /// Set custom mempool polling interval
-    pub fn with_mempool_poll_interval(mut self, interval: Duration) -> Self {
-        self.mempool_poll_interval = interval;
-        self
+
+    #[test]
+    fn test_with_propagation_ack() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.propagation_ack_timeout.is_none());
+        
+        let config = config.with_propagation_ack(Duration::from_secs(10));
+        assert_eq!(config.propagation_ack_timeout, Some(Duration::from_secs(10)));
     }
-    
-    /// Backward compatibility: Set mempool polling interval from seconds
-    pub fn with_mempool_poll_interval_secs(mut self, seconds: u64) -> Self {
-        self.mempool_poll_interval = Duration::from_secs(seconds);
-        self
+
+    #[test]
+    fn test_with_residency_check() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.residency_check_delay.is_none());
+        
+        let config = config.with_residency_check(Duration::from_millis(500));
+        assert_eq!(config.residency_check_delay, Some(Duration::from_millis(500)));
     }
-    
-    /// Create a configuration for common network patterns (recommended convenience method)
-    /// 
-    /// This provides the same functionality as the standalone `network_config` function
-    /// but follows the pattern used by mature Rust libraries like tokio, reqwest, etc.
-    pub fn for_network(network: crate::networks::Network, relay_id: u16) -> Self {
-        crate::networks::network_config(network, relay_id)
+
+    #[test]
+    fn test_with_client_resubmission_window() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.client_resubmission_window.is_none());
+        
+        let config = config.with_client_resubmission_window(Duration::from_secs(2));
+        assert_eq!(config.client_resubmission_window, Some(Duration::from_secs(2)));
     }
-    
-}
 
-impl Default for RelayConfig {
-    fn default() -> Self {
-        Self::for_network(crate::networks::Network::Regtest, 1)
+    #[test]
+    fn test_with_auto_subscribe_new_clients() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.auto_subscribe_new_clients);
+        
+        let config = config.with_auto_subscribe_new_clients(false);
+        assert!(!config.auto_subscribe_new_clients);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_with_max_subscriptions_per_client() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.max_subscriptions_per_client, 20);
+        
+        let config = config.with_max_subscriptions_per_client(2);
+        assert_eq!(config.max_subscriptions_per_client, 2);
+    }
 
     #[test]
-    fn test_relay_config_new() {
-        let config = RelayConfig::new(
-            "http://127.0.0.1:18332".to_string(),
-            "ws://127.0.0.1:7777".to_string(),
-            "test-relay".to_string(),
-            "127.0.0.1:7779".parse().unwrap(),
-        ).unwrap();
+    fn test_with_client_message_concurrency() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.client_message_concurrency, 1);
+        
+        let config = config.with_client_message_concurrency(8);
+        assert_eq!(config.client_message_concurrency, 8);
+    }
+
+    #[test]
+    fn test_with_client_idle_timeout() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.client_idle_timeout.is_none());
         
+        let config = config.with_client_idle_timeout(Duration::from_secs(300));
+        assert_eq!(config.client_idle_timeout, Some(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn test_with_additional_bitcoin_node() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.additional_bitcoin_nodes.is_empty());
+        
+        let config = config
+            .with_additional_bitcoin_node("http://10.0.0.2:8332", "miner".to_string(), "secret".to_string())
+            .with_additional_bitcoin_node("http://10.0.0.3:8332", "public".to_string(), "secret".to_string());
+        assert_eq!(config.additional_bitcoin_nodes.len(), 2);
+        assert_eq!(config.additional_bitcoin_nodes[0].url, "http://10.0.0.2:8332");
+        assert_eq!(config.additional_bitcoin_nodes[1].auth.username, "public");
+        // The primary node is unchanged
         assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:18332");
-        assert_eq!(config.bitcoin_rpc_auth.username, "user");
-        assert_eq!(config.bitcoin_rpc_auth.password, "password");
-        assert_eq!(config.strfry_url, "ws://127.0.0.1:7777");
-        assert_eq!(config.relay_id, "test-relay");
-        assert_eq!(config.websocket_listen_addr, "127.0.0.1:7779".parse::<SocketAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_with_rpc_pool() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.rpc_pool, HttpPoolConfig::default());
+        
+        let pool = HttpPoolConfig {
+            pool_max_idle_per_host: 16,
+            tcp_keepalive: None,
+            ..Default::default()
+        };
+        let config = config.with_rpc_pool(pool.clone());
+        assert_eq!(config.rpc_pool, pool);
+    }
+
+    #[test]
+    fn test_with_max_mempool_poll_interval() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.max_mempool_poll_interval.is_none());
+        
+        let config = config.with_max_mempool_poll_interval(Duration::from_secs(30));
+        assert_eq!(config.max_mempool_poll_interval, Some(Duration::from_secs(30)));
         assert_eq!(config.mempool_poll_interval, Duration::from_secs(2));
-        assert_eq!(config.max_client_connections, 1000);
-        assert_eq!(config.websocket_buffer_size, 100);
     }
 
     #[test]
-    fn test_relay_config_for_network_regtest() {
-        let config1 = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
-        assert_eq!(config1.bitcoin_rpc_url, "http://127.0.0.1:18332");
-        assert_eq!(config1.strfry_url, "ws://127.0.0.1:7777");
-        assert_eq!(config1.relay_id, "1");
-        assert_eq!(config1.websocket_listen_addr, "127.0.0.1:7779".parse::<SocketAddr>().unwrap());
+    fn test_with_mempool_sequence_polling() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(!config.mempool_sequence_polling);
+        assert!(config.with_mempool_sequence_polling(true).mempool_sequence_polling);
+    }
+
+    #[test]
+    fn test_with_mempool_fetch_concurrency() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.mempool_fetch_concurrency, 1);
+        assert_eq!(config.clone().with_mempool_fetch_concurrency(16).mempool_fetch_concurrency, 16);
+        assert_eq!(config.with_mempool_fetch_concurrency(0).mempool_fetch_concurrency, 1);
+    }
+
+    #[test]
+    fn test_with_max_broadcast_age() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.max_broadcast_age.is_none());
         
-        let config2 = RelayConfig::for_network(crate::networks::Network::Regtest, 2);
-        assert_eq!(config2.bitcoin_rpc_url, "http://127.0.0.1:18444");
-        assert_eq!(config2.strfry_url, "ws://127.0.0.1:7778");
-        assert_eq!(config2.relay_id, "2");
-        assert_eq!(config2.websocket_listen_addr, "127.0.0.1:7780".parse::<SocketAddr>().unwrap());
+        let config = config.with_max_broadcast_age(Duration::from_secs(600));
+        assert_eq!(config.max_broadcast_age, Some(Duration::from_secs(600)));
     }
 
     #[test]
-    fn test_relay_config_for_network_testnet4() {
-        let config1 = RelayConfig::for_network(crate::networks::Network::Testnet4, 1);
-        assert_eq!(config1.bitcoin_rpc_url, "http://127.0.0.1:48330");
-        assert_eq!(config1.strfry_url, "ws://127.0.0.1:7777");
-        assert_eq!(config1.relay_id, "1");
+    fn test_with_shard() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.shard.is_none());
         
-        let config2 = RelayConfig::for_network(crate::networks::Network::Testnet4, 2);
-        assert_eq!(config2.bitcoin_rpc_url, "http://127.0.0.1:48350");
-        assert_eq!(config2.strfry_url, "ws://127.0.0.1:7778");
-        assert_eq!(config2.relay_id, "2");
+        let config = config.with_shard(Shard { index: 1, count: 4 });
+        assert_eq!(config.shard, Some(Shard { index: 1, count: 4 }));
+        assert!(config.validate().is_ok());
+        
+        // An index outside the fleet would never gossip anything
+        for shard in [Shard { index: 4, count: 4 }, Shard { index: 0, count: 0 }] {
+            let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1).with_shard(shard);
+            assert!(matches!(config.validate(), Err(ConfigError::InvalidParameter { .. })));
+        }
     }
 
     #[test]
-    fn test_relay_config_default() {
-        let config = RelayConfig::default();
+    fn test_shard_by_first_txid_byte() {
+        let txid = |prefix: &str| format!("{}{}", prefix, "ff".repeat(31)).parse::<Txid>().unwrap();
+        let txids: Vec<Txid> = ["00", "01", "02", "03", "04", "05", "fe", "ff"].into_iter().map(txid).collect();
+        let in_shard = |shard: Shard| txids.iter().filter(|txid| shard.contains(txid)).count();
         
-        // Default should be regtest relay 1
-        assert_eq!(config.relay_id, "1");
-        assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:18332");
-        assert_eq!(config.strfry_url, "ws://127.0.0.1:7777");
+        let shard = Shard { index: 1, count: 3 };
+        let matching: Vec<&Txid> = txids.iter().filter(|txid| shard.contains(txid)).collect();
+        assert_eq!(matching, vec![&txids[1], &txids[4]]);
+        
+        // Every txid belongs to exactly one shard of a fleet
+        assert_eq!((0..3).map(|index| in_shard(Shard { index, count: 3 })).sum::<usize>(), txids.len());
+        assert_eq!(in_shard(Shard { index: 0, count: 1 }), txids.len());
     }
 
     #[test]
-    fn test_relay_config_clone() {
-        let config1 = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
-        let config2 = config1.clone();
+    fn test_with_max_clock_skew() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.max_clock_skew.is_none());
         
-        assert_eq!(config1.bitcoin_rpc_url, config2.bitcoin_rpc_url);
-        assert_eq!(config1.relay_id, config2.relay_id);
-        assert_eq!(config1.strfry_url, config2.strfry_url);
+        let config = config.with_max_clock_skew(Duration::from_secs(300));
+        assert_eq!(config.max_clock_skew, Some(Duration::from_secs(300)));
     }
 
     #[test]
-    fn test_with_auth() {
-        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
-            .with_auth("custom_user".to_string(), "custom_pass".to_string());
+    fn test_with_socks_proxy() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.socks_proxy.is_none());
+        assert!(config.rpc_pool.socks_proxy.is_none());
         
-        assert_eq!(config.bitcoin_rpc_auth.username, "custom_user");
-        assert_eq!(config.bitcoin_rpc_auth.password, "custom_pass");
+        let config = config.with_socks_proxy("127.0.0.1:9050");
+        assert_eq!(config.socks_proxy.as_deref(), Some("127.0.0.1:9050"));
+        assert_eq!(config.rpc_pool.socks_proxy.as_deref(), Some("127.0.0.1:9050"));
+    }
+
+    #[test]
+    fn test_with_event_bus() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.event_bus.is_none());
+        assert!(config.with_event_bus(EventBus::default()).event_bus.is_some());
+    }
+
+    #[test]
+    fn test_with_broadcast_encryption() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.broadcast_cipher.is_none());
         
-        // Other fields should remain unchanged
-        assert_eq!(config.relay_id, "1");
-        assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:18332");
+        let config = config.with_broadcast_encryption("miners only");
+        let cipher = config.broadcast_cipher.unwrap();
+        let encrypted = BroadcastCipher::new("miners only").encrypt("content").unwrap();
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "content");
     }
 
     #[test]
-    fn test_with_validation_config() {
-        let mut validation_config = ValidationConfig::default();
-        validation_config.enable_validation = false;
-        validation_config.cache_ttl_seconds = 300;
+    fn test_with_max_ancestor_depth() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.max_ancestor_depth, 25);
         
-        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
-            .with_validation(validation_config.clone());
+        let config = config.with_max_ancestor_depth(0);
+        assert_eq!(config.max_ancestor_depth, 0);
+    }
+
+    #[test]
+    fn test_with_unix_socket() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.unix_socket_path.is_none());
         
-        assert_eq!(config.validation_config.enable_validation, false);
-        assert_eq!(config.validation_config.cache_ttl_seconds, 300);
+        let config = config.with_unix_socket("/run/relay.sock");
+        assert_eq!(config.unix_socket_path, Some(PathBuf::from("/run/relay.sock")));
     }
 
     #[test]
-    fn test_with_mempool_poll_interval() {
-        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
-            .with_mempool_poll_interval(Duration::from_secs(5));
+    fn test_with_preflight_checks() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(!config.preflight_checks);
+        assert!(config.with_preflight_checks(true).preflight_checks);
+    }
+
+    #[test]
+    fn test_with_dead_letter_log() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.dead_letter_path.is_none());
         
-        assert_eq!(config.mempool_poll_interval.as_secs(), 5);
+        let config = config.with_dead_letter_log("/var/lib/relay/dead-letters.jsonl", 1024);
+        assert_eq!(config.dead_letter_path, Some(PathBuf::from("/var/lib/relay/dead-letters.jsonl")));
+        assert_eq!(config.dead_letter_max_bytes, 1024);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_with_persistence_compression() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert_eq!(config.persistence_compression, Compression::None);
         
-        // Other fields should remain unchanged
-        assert_eq!(config.relay_id, "1");
-        assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:18332");
+        let config = config.with_persistence_compression(Compression::Zstd);
+        assert_eq!(config.persistence_compression, Compression::Zstd);
+    }
+
+    #[test]
+    fn test_with_dedup_snapshot() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1);
+        assert!(config.dedup_snapshot_path.is_none());
+        
+        let config = config.with_dedup_snapshot("/var/lib/relay/dedup.json", Duration::from_secs(600));
+        assert_eq!(config.dedup_snapshot_path, Some(PathBuf::from("/var/lib/relay/dedup.json")));
+        assert_eq!(config.dedup_snapshot_ttl, Duration::from_secs(600));
     }
 
     #[test]
@@ -247,7 +1770,7 @@ mod tests {
         assert_eq!(config.relay_id, "2");
         assert_eq!(config.bitcoin_rpc_url, "http://127.0.0.1:48350");
         assert_eq!(config.bitcoin_rpc_auth.username, "testuser");
-        assert_eq!(config.bitcoin_rpc_auth.password, "testpass");
+        assert_eq!(config.bitcoin_rpc_auth.password(), "testpass");
         assert_eq!(config.mempool_poll_interval.as_secs(), 10);
         assert_eq!(config.strfry_url, "ws://127.0.0.1:7778");
     }
@@ -275,6 +1798,20 @@ mod tests {
         assert!(debug_str.contains("7777"));
     }
 
+    #[test]
+    fn test_debug_redacts_rpc_password() {
+        let config = RelayConfig::for_network(crate::networks::Network::Regtest, 1)
+            .with_auth("rpcuser".to_string(), "hunter2-secret".to_string())
+            .with_additional_bitcoin_node("http://10.0.0.2:8332", "miner".to_string(), "other-secret".to_string());
+        let debug_str = format!("{:?}", config);
+        
+        assert!(!debug_str.contains("hunter2-secret"));
+        assert!(!debug_str.contains("other-secret"));
+        assert!(debug_str.contains("rpcuser"));
+        assert!(debug_str.contains("password: \"***\""));
+        assert_eq!(config.bitcoin_rpc_auth.password(), "hunter2-secret");
+    }
+
     #[test]
     fn test_relay_config_validation() {
         // Test invalid URL validation
@@ -307,7 +1844,7 @@ mod tests {
         assert_eq!(config1.strfry_url, "ws://127.0.0.1:7777");
         assert_eq!(config1.relay_id, "1");
         assert_eq!(config1.bitcoin_rpc_auth.username, "user");
-        assert_eq!(config1.bitcoin_rpc_auth.password, "pass");
+        assert_eq!(config1.bitcoin_rpc_auth.password(), "pass");
         assert_eq!(config1.mempool_poll_interval.as_secs(), 5);
         
         // Test testnet4
@@ -316,4 +1853,19 @@ mod tests {
         assert_eq!(config2.strfry_url, "ws://127.0.0.1:7778");
         assert_eq!(config2.relay_id, "2");
     }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_constructors_match_current_api() {
+        use crate::networks::Network;
+        
+        let old = RelayConfig::regtest(2).with_bitcoin_auth("user".to_string(), "pass".to_string());
+        let new = RelayConfig::for_network(Network::Regtest, 2).with_auth("user".to_string(), "pass".to_string());
+        assert_eq!(format!("{:?}", old), format!("{:?}", new));
+        assert_eq!(old.bitcoin_rpc_auth.password(), "pass");
+        
+        let old = RelayConfig::testnet4(1);
+        let new = RelayConfig::for_network(Network::Testnet4, 1);
+        assert_eq!(format!("{:?}", old), format!("{:?}", new));
+    }
 }
\ No newline at end of file
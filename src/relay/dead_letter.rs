@@ -0,0 +1,239 @@
+use super::compression::Compression;
+use super::subscription::TxSource;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// A transaction the Bitcoin node did not accept, as recorded in the dead-letter log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Empty when the hex could not be decoded
+    pub txid: String,
+    pub hex: String,
+    pub source: TxSource,
+    pub error: String,
+    /// When the submission failed, in ms since the epoch
+    pub timestamp: u64,
+}
+
+/// Append-only JSON Lines log of failed submissions
+///
+/// Once the log would grow past `max_bytes` it is moved to [`rotated_path`](Self::rotated_path),
+/// replacing the previous rotation, so at most about twice `max_bytes` is kept on disk.
+///
+/// With compression each record is appended as its own zstd frame, since the log is only
+/// ever appended to; [`read`](Self::read) decodes the frames as one stream.
+#[derive(Debug)]
+pub struct DeadLetterLog {
+    path: PathBuf,
+    max_bytes: u64,
+    compression: Compression,
+    write_lock: Mutex<()>,
+}
+
+impl DeadLetterLog {
+    /// Log to `path`, rotating it once it reaches `max_bytes`
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes,
+            compression: Compression::None,
+            write_lock: Mutex::new(()),
+        }
+    }
+    
+    /// Compress records as they are appended
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+    
+    /// Path of the current log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Path the full log is moved to on rotation: the log path with `.1` appended
+    pub fn rotated_path(&self) -> PathBuf {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        rotated.into()
+    }
+    
+    /// Append a record, rotating first if it would take the log past the size limit
+    ///
+    /// A log written with a different compression setting is rotated too, so a file never mixes formats.
+    pub fn append(&self, letter: &DeadLetter) -> Result<()> {
+        let mut line = serde_json::to_vec(letter)?;
+        line.push(b'\n');
+        let record = self.compression.compress(&line)?;
+        
+        let _guard = self.write_lock.lock().unwrap();
+        let len = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if len > 0 && (len + record.len() as u64 > self.max_bytes || self.written_compression()? != self.compression) {
+            std::fs::rename(&self.path, self.rotated_path())?;
+        }
+        
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&record)?;
+        Ok(())
+    }
+    
+    /// Compression of the records already in the log
+    fn written_compression(&self) -> Result<Compression> {
+        let mut start = Vec::with_capacity(4);
+        std::fs::File::open(&self.path)?.take(4).read_to_end(&mut start)?;
+        Ok(Compression::detect(&start)?)
+    }
+    
+    /// Read every record of a log file, e.g. to inspect or replay it
+    ///
+    /// Compressed logs are detected and decompressed. A missing file yields no records.
+    pub fn read(path: &Path) -> Result<Vec<DeadLetter>> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let contents = String::from_utf8(Compression::decompress(&bytes)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+}
+
+/// Feeds a [`DeadLetterLog`] from a dedicated writer thread
+///
+/// Letters are queued on a bounded channel so file writes and compression stay off the
+/// async workers. When the writer falls behind, further letters are dropped rather than
+/// queued without bound. The thread exits once every clone of the writer is dropped.
+#[derive(Debug, Clone)]
+pub struct DeadLetterWriter {
+    sender: std::sync::mpsc::SyncSender<DeadLetter>,
+}
+
+impl DeadLetterWriter {
+    /// Start the writer thread for `log`, queueing up to `capacity` letters
+    pub fn spawn(log: DeadLetterLog, capacity: usize) -> Self {
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<DeadLetter>(capacity);
+        std::thread::spawn(move || {
+            for letter in receiver {
+                if let Err(e) = log.append(&letter) {
+                    warn!("Failed to write dead letter for {}: {}", letter.txid, e);
+                }
+            }
+        });
+        Self { sender }
+    }
+    
+    /// Queue a letter for writing, returning false if the queue was full and it was dropped
+    pub fn write(&self, letter: DeadLetter) -> bool {
+        self.sender.try_send(letter).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("dead-letter-{}-{}.jsonl", name, std::process::id()))
+    }
+
+    fn letter(txid: &str) -> DeadLetter {
+        DeadLetter {
+            txid: txid.to_string(),
+            hex: "0200".to_string(),
+            source: TxSource::Peer,
+            error: "min relay fee not met".to_string(),
+            timestamp: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let log = DeadLetterLog::new(log_path("append"), 1024 * 1024);
+        log.append(&letter("aa")).unwrap();
+        log.append(&letter("bb")).unwrap();
+        
+        let records = DeadLetterLog::read(log.path()).unwrap();
+        std::fs::remove_file(log.path()).unwrap();
+        assert_eq!(records, vec![letter("aa"), letter("bb")]);
+        assert!(DeadLetterLog::read(log.path()).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compressed_log() {
+        let log = DeadLetterLog::new(log_path("compressed"), 1024 * 1024).with_compression(Compression::Zstd);
+        log.append(&letter("aa")).unwrap();
+        log.append(&letter("bb")).unwrap();
+        
+        let on_disk = std::fs::read(log.path()).unwrap();
+        let records = DeadLetterLog::read(log.path()).unwrap();
+        std::fs::remove_file(log.path()).unwrap();
+        assert_eq!(Compression::detect(&on_disk).unwrap(), Compression::Zstd);
+        assert_eq!(records, vec![letter("aa"), letter("bb")]);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_changing_compression_rotates() {
+        let path = log_path("switch");
+        DeadLetterLog::new(&path, 1024 * 1024).append(&letter("aa")).unwrap();
+        let log = DeadLetterLog::new(&path, 1024 * 1024).with_compression(Compression::Zstd);
+        log.append(&letter("bb")).unwrap();
+        
+        let rotated = DeadLetterLog::read(&log.rotated_path()).unwrap();
+        let current = DeadLetterLog::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(log.rotated_path()).unwrap();
+        assert_eq!(rotated, vec![letter("aa")]);
+        assert_eq!(current, vec![letter("bb")]);
+    }
+
+    #[test]
+    fn test_rotates_by_size() {
+        let record_len = serde_json::to_vec(&letter("aa")).unwrap().len() as u64 + 1;
+        let log = DeadLetterLog::new(log_path("rotate"), record_len * 2);
+        for txid in ["aa", "bb", "cc"] {
+            log.append(&letter(txid)).unwrap();
+        }
+        
+        let current = DeadLetterLog::read(log.path()).unwrap();
+        let rotated = DeadLetterLog::read(&log.rotated_path()).unwrap();
+        std::fs::remove_file(log.path()).unwrap();
+        std::fs::remove_file(log.rotated_path()).unwrap();
+        assert_eq!(rotated, vec![letter("aa"), letter("bb")]);
+        assert_eq!(current, vec![letter("cc")]);
+    }
+
+    #[test]
+    fn test_writer_appends_in_background() {
+        let log = DeadLetterLog::new(log_path("writer"), 1024 * 1024);
+        let path = log.path().to_path_buf();
+        let writer = DeadLetterWriter::spawn(log, 4);
+        assert!(writer.write(letter("aa")));
+        assert!(writer.write(letter("bb")));
+        
+        let started = std::time::Instant::now();
+        while DeadLetterLog::read(&path).unwrap().len() < 2 {
+            assert!(started.elapsed() < std::time::Duration::from_secs(2), "letters written");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let records = DeadLetterLog::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(records, vec![letter("aa"), letter("bb")]);
+    }
+}
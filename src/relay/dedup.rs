@@ -0,0 +1,135 @@
+use super::compression::Compression;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Dedup state persisted across restarts
+///
+/// Each txid maps to when it was recorded, in ms since the epoch, so entries
+/// can be expired on load.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DedupSnapshot {
+    /// Transactions received from remote relays
+    #[serde(default)]
+    pub remote_transactions: HashMap<String, u64>,
+    /// Mempool transactions this relay has already gossiped
+    #[serde(default)]
+    pub broadcast_transactions: HashMap<String, u64>,
+}
+
+impl DedupSnapshot {
+    /// Write the snapshot to `path`, replacing any previous snapshot
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.save_compressed(path, Compression::None)
+    }
+    
+    /// Write the snapshot to `path` with `compression`, replacing any previous snapshot
+    ///
+    /// The whole snapshot is compressed as one stream.
+    pub fn save_compressed(&self, path: &Path, compression: Compression) -> Result<()> {
+        // Write then rename so a crash mid-write never leaves a truncated snapshot
+        let tmp_path = path.with_extension("tmp");
+        compression.write_json(std::fs::File::create(&tmp_path)?, self)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+    
+    /// Read a snapshot from `path`, dropping entries older than `ttl`
+    ///
+    /// Compressed snapshots are detected and decompressed. A missing file yields an empty snapshot.
+    pub fn load(path: &Path, ttl: Duration, now_ms: u64) -> Result<Self> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut snapshot: Self = Compression::read_json(file)?;
+        snapshot.expire(ttl, now_ms);
+        Ok(snapshot)
+    }
+    
+    /// Drop entries recorded more than `ttl` before `now_ms`
+    pub fn expire(&mut self, ttl: Duration, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX));
+        self.remote_transactions.retain(|_, recorded| *recorded >= cutoff);
+        self.broadcast_transactions.retain(|_, recorded| *recorded >= cutoff);
+    }
+    
+    /// Total number of entries
+    pub fn len(&self) -> usize {
+        self.remote_transactions.len() + self.broadcast_transactions.len()
+    }
+    
+    /// Whether the snapshot holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("dedup-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let path = snapshot_path("round-trip");
+        let snapshot = DedupSnapshot {
+            remote_transactions: HashMap::from([("aa".to_string(), 1_000)]),
+            broadcast_transactions: HashMap::from([("bb".to_string(), 2_000)]),
+        };
+        
+        snapshot.save(&path).unwrap();
+        let loaded = DedupSnapshot::load(&path, Duration::from_secs(60), 2_000).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(loaded, snapshot);
+        assert_eq!(loaded.len(), 2);
+    }
+
+    #[test]
+    fn test_expired_entries_dropped_on_load() {
+        let path = snapshot_path("expired");
+        let snapshot = DedupSnapshot {
+            remote_transactions: HashMap::from([("old".to_string(), 1_000), ("new".to_string(), 100_000)]),
+            broadcast_transactions: HashMap::from([("stale".to_string(), 5_000)]),
+        };
+        
+        snapshot.save(&path).unwrap();
+        let loaded = DedupSnapshot::load(&path, Duration::from_secs(60), 110_000).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(loaded.remote_transactions.keys().collect::<Vec<_>>(), vec!["new"]);
+        assert!(loaded.broadcast_transactions.is_empty());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compressed_snapshot_round_trip() {
+        let path = snapshot_path("compressed");
+        let snapshot = DedupSnapshot {
+            remote_transactions: (0..500).map(|i| (format!("{:064x}", i), 1_000)).collect(),
+            broadcast_transactions: HashMap::from([("bb".to_string(), 2_000)]),
+        };
+        
+        snapshot.save_compressed(&path, Compression::Zstd).unwrap();
+        let on_disk = std::fs::read(&path).unwrap();
+        let loaded = DedupSnapshot::load(&path, Duration::from_secs(60), 2_000).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(Compression::detect(&on_disk).unwrap(), Compression::Zstd);
+        assert!(on_disk.len() < serde_json::to_vec(&snapshot).unwrap().len());
+        assert_eq!(loaded, snapshot);
+    }
+
+    #[test]
+    fn test_missing_snapshot_is_empty() {
+        let loaded = DedupSnapshot::load(&snapshot_path("missing"), Duration::from_secs(60), 0).unwrap();
+        assert!(loaded.is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Longest relay display name, in characters, that is announced or recorded
+pub const MAX_RELAY_NAME_LEN: usize = 64;
+
+/// Cut a relay display name down to [`MAX_RELAY_NAME_LEN`] characters
+pub fn bounded_relay_name(name: &str) -> String {
+    name.trim().chars().take(MAX_RELAY_NAME_LEN).collect()
+}
+
+/// Content of a relay discovery event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscoveryAnnouncement {
+    pub relay_id: String,
+    /// Human-readable name chosen by the operator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_name: Option<String>,
+    /// WebSocket address clients can connect to
+    pub listen_addr: String,
+    /// Networks served by the relay (e.g. "regtest")
+    pub networks: Vec<String>,
+}
+
+/// An optional feature a relay advertises in its capabilities event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Accepts transaction packages from clients
+    Packages,
+    /// Answers fee estimate requests from clients
+    FeeEstimates,
+    /// Reads compact transaction broadcasts
+    CompactEncoding,
+    /// Answers mempool summary requests from peers
+    MempoolReconciliation,
+    /// A capability added by a newer relay
+    #[serde(other)]
+    Unknown,
+}
+
+/// Content of a relay capabilities event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityAnnouncement {
+    pub relay_id: String,
+    pub capabilities: Vec<Capability>,
+}
+
+/// A peer relay learned from a discovery event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    pub relay_id: String,
+    /// Display name, at most [`MAX_RELAY_NAME_LEN`] characters
+    pub relay_name: Option<String>,
+    /// Hex-encoded Nostr public key the announcement was signed with
+    pub pubkey: String,
+    pub listen_addr: String,
+    pub networks: Vec<String>,
+    /// Features from the peer's latest capabilities event, empty if it sent none
+    pub capabilities: Vec<Capability>,
+    pub last_seen: Instant,
+}
+
+/// Capabilities a peer advertised and when
+#[derive(Debug)]
+struct AdvertisedCapabilities {
+    capabilities: Vec<Capability>,
+    last_seen: Instant,
+}
+
+/// Bounded roster of peer relays with expiry of stale entries
+#[derive(Debug)]
+pub struct PeerRoster {
+    self_relay_id: String,
+    max_peers: usize,
+    ttl: Duration,
+    peers: HashMap<String, PeerInfo>,
+    /// By hex-encoded signer pubkey, so a relay can only speak for itself; kept apart
+    /// from `peers` as capabilities may arrive before discovery
+    capabilities: HashMap<String, AdvertisedCapabilities>,
+}
+
+impl PeerRoster {
+    pub fn new(self_relay_id: impl Into<String>, max_peers: usize, ttl: Duration) -> Self {
+        Self {
+            self_relay_id: self_relay_id.into(),
+            max_peers,
+            ttl,
+            peers: HashMap::new(),
+            capabilities: HashMap::new(),
+        }
+    }
+    
+    /// Record an announcement seen at `now`; returns false if it was ignored
+    /// 
+    /// Our own announcements are ignored, as are announcements for a relay ID a live
+    /// peer already holds under a different `pubkey`, so one relay can't take over
+    /// another's entry. When the roster is full, the least recently seen peer is
+    /// evicted to make room.
+    pub fn record(&mut self, announcement: DiscoveryAnnouncement, pubkey: impl Into<String>, now: Instant) -> bool {
+        if announcement.relay_id == self.self_relay_id || self.max_peers == 0 {
+            return false;
+        }
+        
+        self.expire(now);
+        
+        let pubkey = pubkey.into();
+        if self.peers.get(&announcement.relay_id).is_some_and(|peer| peer.pubkey != pubkey) {
+            return false;
+        }
+        
+        if !self.peers.contains_key(&announcement.relay_id) && self.peers.len() >= self.max_peers {
+            if let Some(oldest) = self
+                .peers
+                .values()
+                .min_by_key(|peer| peer.last_seen)
+                .map(|peer| peer.relay_id.clone())
+            {
+                self.peers.remove(&oldest);
+            }
+        }
+        
+        self.peers.insert(announcement.relay_id.clone(), PeerInfo {
+            relay_id: announcement.relay_id,
+            relay_name: announcement.relay_name.as_deref().map(bounded_relay_name),
+            pubkey,
+            listen_addr: announcement.listen_addr,
+            networks: announcement.networks,
+            capabilities: Vec::new(),
+            last_seen: now,
+        });
+        true
+    }
+    
+    /// Record a capabilities event signed by the hex-encoded `pubkey` at `now`; returns false if it was ignored
+    /// 
+    /// Capabilities apply to the peer that signed them, whatever relay ID they name.
+    /// Bounded like discovery announcements, evicting the least recently seen advertiser.
+    pub fn record_capabilities(&mut self, announcement: CapabilityAnnouncement, pubkey: impl Into<String>, now: Instant) -> bool {
+        if announcement.relay_id == self.self_relay_id || self.max_peers == 0 {
+            return false;
+        }
+        
+        self.expire(now);
+        
+        let pubkey = pubkey.into();
+        if !self.capabilities.contains_key(&pubkey) && self.capabilities.len() >= self.max_peers {
+            if let Some(oldest) = self
+                .capabilities
+                .iter()
+                .min_by_key(|(_, advertised)| advertised.last_seen)
+                .map(|(pubkey, _)| pubkey.clone())
+            {
+                self.capabilities.remove(&oldest);
+            }
+        }
+        
+        self.capabilities.insert(pubkey, AdvertisedCapabilities {
+            capabilities: announcement.capabilities,
+            last_seen: now,
+        });
+        true
+    }
+    
+    /// Whether every live peer, from either discovery or capabilities events, advertises `capability`
+    /// 
+    /// False when no peers are known, since a relay not yet heard from may lack it.
+    pub fn all_support(&self, capability: Capability, now: Instant) -> bool {
+        let live = |last_seen: Instant| now.saturating_duration_since(last_seen) < self.ttl;
+        let supports = |pubkey: &String| {
+            self.capabilities
+                .get(pubkey)
+                .is_some_and(|advertised| live(advertised.last_seen) && advertised.capabilities.contains(&capability))
+        };
+        
+        let mut known = self
+            .peers
+            .values()
+            .filter(|peer| live(peer.last_seen))
+            .map(|peer| &peer.pubkey)
+            .chain(self.capabilities.iter().filter(|(_, advertised)| live(advertised.last_seen)).map(|(pubkey, _)| pubkey))
+            .peekable();
+        known.peek().is_some() && known.all(supports)
+    }
+    
+    /// Drop peers not seen within the TTL
+    pub fn expire(&mut self, now: Instant) {
+        let ttl = self.ttl;
+        self.peers.retain(|_, peer| now.saturating_duration_since(peer.last_seen) < ttl);
+        self.capabilities.retain(|_, advertised| now.saturating_duration_since(advertised.last_seen) < ttl);
+    }
+    
+    /// Live peers as of `now`, sorted by relay ID
+    pub fn peers(&self, now: Instant) -> Vec<PeerInfo> {
+        let mut peers: Vec<PeerInfo> = self
+            .peers
+            .values()
+            .filter(|peer| now.saturating_duration_since(peer.last_seen) < self.ttl)
+            .cloned()
+            .map(|mut peer| {
+                if let Some(advertised) = self.capabilities.get(&peer.pubkey) {
+                    peer.capabilities = advertised.capabilities.clone();
+                }
+                peer
+            })
+            .collect();
+        peers.sort_by(|a, b| a.relay_id.cmp(&b.relay_id));
+        peers
+    }
+    
+    /// Whether a live peer announced itself with the hex-encoded `pubkey` as of `now`
+    pub fn has_pubkey(&self, pubkey: &str, now: Instant) -> bool {
+        self.peers
+            .values()
+            .any(|peer| peer.pubkey == pubkey && now.saturating_duration_since(peer.last_seen) < self.ttl)
+    }
+    
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announcement(relay_id: &str) -> DiscoveryAnnouncement {
+        DiscoveryAnnouncement {
+            relay_id: relay_id.to_string(),
+            relay_name: None,
+            listen_addr: "127.0.0.1:7779".to_string(),
+            networks: vec!["regtest".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_roster_insertion_and_refresh() {
+        let mut roster = PeerRoster::new("1", 10, Duration::from_secs(60));
+        let start = Instant::now();
+        
+        assert!(roster.record(announcement("2"), "pk2", start));
+        assert!(roster.record(announcement("3"), "pk3", start));
+        
+        // Re-announcing refreshes instead of duplicating
+        assert!(roster.record(announcement("2"), "pk2", start + Duration::from_secs(10)));
+        
+        let peers = roster.peers(start + Duration::from_secs(10));
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].relay_id, "2");
+        assert_eq!(peers[0].last_seen, start + Duration::from_secs(10));
+        assert_eq!(peers[1].networks, vec!["regtest".to_string()]);
+    }
+
+    #[test]
+    fn test_roster_expires_stale_peers() {
+        let mut roster = PeerRoster::new("1", 10, Duration::from_secs(60));
+        let start = Instant::now();
+        
+        roster.record(announcement("2"), "pk2", start);
+        roster.record(announcement("3"), "pk3", start + Duration::from_secs(30));
+        
+        let later = start + Duration::from_secs(70);
+        let peers = roster.peers(later);
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].relay_id, "3");
+        
+        roster.expire(later);
+        assert_eq!(roster.len(), 1);
+    }
+
+    #[test]
+    fn test_relay_id_stays_with_its_pubkey() {
+        let mut roster = PeerRoster::new("1", 10, Duration::from_secs(60));
+        let start = Instant::now();
+        
+        assert!(roster.record(announcement("2"), "pk2", start));
+        assert!(!roster.record(announcement("2"), "impostor", start + Duration::from_secs(10)));
+        let peers = roster.peers(start + Duration::from_secs(10));
+        assert_eq!(peers[0].pubkey, "pk2");
+        assert_eq!(peers[0].last_seen, start);
+        
+        // Once the peer expires, its relay ID is free again
+        assert!(roster.record(announcement("2"), "pk2b", start + Duration::from_secs(70)));
+        assert_eq!(roster.peers(start + Duration::from_secs(70))[0].pubkey, "pk2b");
+    }
+
+    #[test]
+    fn test_roster_excludes_self() {
+        let mut roster = PeerRoster::new("1", 10, Duration::from_secs(60));
+        
+        assert!(!roster.record(announcement("1"), "pk1", Instant::now()));
+        assert!(roster.is_empty());
+    }
+
+    #[test]
+    fn test_peer_name_is_bounded() {
+        let mut roster = PeerRoster::new("1", 10, Duration::from_secs(60));
+        let now = Instant::now();
+        let mut named = announcement("2");
+        named.relay_name = Some("ü".repeat(MAX_RELAY_NAME_LEN + 10));
+        
+        roster.record(named, "pk2", now);
+        roster.record(announcement("3"), "pk3", now);
+        
+        let peers = roster.peers(now);
+        assert_eq!(peers[0].relay_name.as_ref().unwrap().chars().count(), MAX_RELAY_NAME_LEN);
+        assert_eq!(peers[1].relay_name, None);
+    }
+
+    #[test]
+    fn test_roster_is_bounded() {
+        let mut roster = PeerRoster::new("1", 2, Duration::from_secs(60));
+        let start = Instant::now();
+        
+        roster.record(announcement("2"), "pk2", start);
+        roster.record(announcement("3"), "pk3", start + Duration::from_secs(1));
+        roster.record(announcement("4"), "pk4", start + Duration::from_secs(2));
+        
+        // The least recently seen peer was evicted
+        let ids: Vec<String> = roster.peers(start + Duration::from_secs(2)).into_iter().map(|p| p.relay_id).collect();
+        assert_eq!(ids, vec!["3".to_string(), "4".to_string()]);
+    }
+
+    fn capabilities(relay_id: &str, capabilities: &[Capability]) -> CapabilityAnnouncement {
+        CapabilityAnnouncement {
+            relay_id: relay_id.to_string(),
+            capabilities: capabilities.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_capabilities_parse() {
+        let content = r#"{"relay_id":"2","capabilities":["packages","compact_encoding","teleportation"]}"#;
+        let parsed: CapabilityAnnouncement = serde_json::from_str(content).unwrap();
+        
+        assert_eq!(parsed.relay_id, "2");
+        assert_eq!(parsed.capabilities, vec![Capability::Packages, Capability::CompactEncoding, Capability::Unknown]);
+        assert_eq!(
+            serde_json::to_string(&capabilities("2", &[Capability::FeeEstimates, Capability::MempoolReconciliation])).unwrap(),
+            r#"{"relay_id":"2","capabilities":["fee_estimates","mempool_reconciliation"]}"#
+        );
+    }
+
+    #[test]
+    fn test_capabilities_in_roster() {
+        let mut roster = PeerRoster::new("1", 10, Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(!roster.all_support(Capability::CompactEncoding, start));
+        
+        assert!(!roster.record_capabilities(capabilities("1", &[Capability::CompactEncoding]), "pk1", start));
+        assert!(roster.record_capabilities(capabilities("2", &[Capability::CompactEncoding]), "pk2", start));
+        roster.record(announcement("2"), "pk2", start);
+        assert!(roster.all_support(Capability::CompactEncoding, start));
+        assert!(!roster.all_support(Capability::Packages, start));
+        assert_eq!(roster.peers(start)[0].capabilities, vec![Capability::CompactEncoding]);
+        
+        // A peer that never advertised is assumed to lack every capability
+        roster.record(announcement("3"), "pk3", start);
+        assert!(!roster.all_support(Capability::CompactEncoding, start));
+        assert!(roster.peers(start)[1].capabilities.is_empty());
+        
+        // Until it expires
+        let later = start + Duration::from_secs(30);
+        roster.record(announcement("2"), "pk2", later);
+        roster.record_capabilities(capabilities("2", &[Capability::CompactEncoding]), "pk2", later);
+        assert!(roster.all_support(Capability::CompactEncoding, start + Duration::from_secs(70)));
+    }
+
+    #[test]
+    fn test_capabilities_belong_to_signer() {
+        let mut roster = PeerRoster::new("1", 10, Duration::from_secs(60));
+        let now = Instant::now();
+        roster.record(announcement("2"), "pk2", now);
+        
+        // Another key claiming relay 2's ID doesn't speak for it
+        roster.record_capabilities(capabilities("2", &[Capability::CompactEncoding]), "impostor", now);
+        assert!(roster.peers(now)[0].capabilities.is_empty());
+        assert!(!roster.all_support(Capability::CompactEncoding, now));
+        
+        roster.record_capabilities(capabilities("2", &[Capability::CompactEncoding]), "pk2", now);
+        assert_eq!(roster.peers(now)[0].capabilities, vec![Capability::CompactEncoding]);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::NostrError;
+use bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+use nostr::nips::nip04;
+use nostr::prelude::{SecretKey, XOnlyPublicKey};
+use nostr::Keys;
+
+/// Separates the NIP-04 ciphertext from its MAC in encrypted content
+const MAC_SEPARATOR: &str = "&mac=";
+
+/// Symmetric encryption of peer event content for a closed set of relays
+///
+/// Every participating relay is configured with the same secret. Content is
+/// NIP-04 encrypted to a key pair derived from the secret and authenticated
+/// with HMAC-SHA256, so a relay with another secret rejects it rather than
+/// misreading it. Tags, and so the sender's relay id and network, stay public.
+#[derive(Clone)]
+pub struct BroadcastCipher {
+    secret_key: SecretKey,
+    public_key: XOnlyPublicKey,
+    mac_key: [u8; 32],
+}
+
+impl std::fmt::Debug for BroadcastCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BroadcastCipher").finish_non_exhaustive()
+    }
+}
+
+impl BroadcastCipher {
+    /// Derive the encryption and MAC keys from a secret shared by the participating relays
+    pub fn new(shared_secret: impl AsRef<[u8]>) -> Self {
+        let derive = |purpose: &[u8]| sha256::Hash::hash(&[purpose, shared_secret.as_ref()].concat()).to_byte_array();
+        // A SHA-256 output is a valid secret key but for a negligible fraction of values
+        let secret_key = SecretKey::from_slice(&derive(b"bitcoin-nostr-relay/broadcast-encryption"))
+            .expect("derived key is a valid secret key");
+        let keys = Keys::new(secret_key);
+        Self {
+            secret_key,
+            public_key: keys.public_key(),
+            mac_key: derive(b"bitcoin-nostr-relay/broadcast-mac"),
+        }
+    }
+    
+    /// Encrypt event content
+    pub fn encrypt(&self, content: &str) -> Result<String, NostrError> {
+        let ciphertext = nip04::encrypt(&self.secret_key, &self.public_key, content)
+            .map_err(|e| NostrError::encryption(e.to_string()))?;
+        let mac = self.mac(&ciphertext);
+        Ok(format!("{}{}{}", ciphertext, MAC_SEPARATOR, hex::encode(mac)))
+    }
+    
+    /// Decrypt content produced by [`encrypt`](Self::encrypt) with the same secret
+    ///
+    /// Content encrypted with another secret, tampered with or not encrypted at all is rejected.
+    pub fn decrypt(&self, content: &str) -> Result<String, NostrError> {
+        let (ciphertext, mac) = content
+            .rsplit_once(MAC_SEPARATOR)
+            .ok_or_else(|| NostrError::encryption("content is not encrypted"))?;
+        let mac = hex::decode(mac).map_err(|_| NostrError::encryption("malformed MAC"))?;
+        let expected = self.mac(ciphertext);
+        // Compare in constant time
+        if mac.len() != expected.len() || mac.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) != 0 {
+            return Err(NostrError::encryption("authentication failed, the relays' secrets may differ"));
+        }
+        nip04::decrypt(&self.secret_key, &self.public_key, ciphertext).map_err(|e| NostrError::encryption(e.to_string()))
+    }
+    
+    fn mac(&self, ciphertext: &str) -> [u8; 32] {
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(&self.mac_key);
+        engine.input(ciphertext.as_bytes());
+        hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay::payload::TxBroadcastPayload;
+    use bitcoin::{consensus::deserialize, Transaction};
+
+    #[test]
+    fn test_round_trip_with_shared_secret() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let content = serde_json::to_string(&TxBroadcastPayload::from_transaction(&tx)).unwrap();
+        
+        let encrypted = BroadcastCipher::new("miners only").encrypt(&content).unwrap();
+        assert!(!encrypted.contains(&tx.txid().to_string()));
+        
+        // Another relay configured with the same secret reads it
+        let decrypted = BroadcastCipher::new("miners only").decrypt(&encrypted).unwrap();
+        let payload: TxBroadcastPayload = serde_json::from_str(&decrypted).unwrap();
+        assert_eq!(payload.txid, tx.txid().to_string());
+        assert_eq!(payload.hex, crate::test_utils::sample_tx_hex());
+    }
+
+    #[test]
+    fn test_wrong_secret_and_plaintext_rejected() {
+        let encrypted = BroadcastCipher::new("miners only").encrypt(r#"{"txid":"aa"}"#).unwrap();
+        let other = BroadcastCipher::new("someone else");
+        assert!(matches!(other.decrypt(&encrypted), Err(NostrError::Encryption { .. })));
+        assert!(matches!(other.decrypt(r#"{"txid":"aa"}"#), Err(NostrError::Encryption { .. })));
+        
+        // Flipping a ciphertext byte fails authentication
+        let mut tampered = encrypted.into_bytes();
+        tampered[0] = if tampered[0] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(BroadcastCipher::new("miners only").decrypt(&tampered).is_err());
+    }
+}
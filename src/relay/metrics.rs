@@ -0,0 +1,276 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+#[cfg(feature = "prometheus")]
+use std::collections::BTreeMap;
+#[cfg(feature = "prometheus")]
+use std::sync::Mutex;
+
+/// Upper bounds (inclusive, in milliseconds) of the latency histogram buckets
+pub const LATENCY_BUCKETS_MS: [u64; 9] = [10, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// Fixed-bucket histogram of relay-to-relay propagation latency
+///
+/// The last bucket counts everything slower than the largest bound.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    count: u64,
+    sum_ms: u64,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Record one observation
+    pub fn record(&mut self, latency: Duration) {
+        let ms = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum_ms = self.sum_ms.saturating_add(ms);
+    }
+    
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+    
+    /// Mean latency, if anything was recorded
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_millis(self.sum_ms / self.count))
+    }
+    
+    /// Observation count per bucket, paired with the bucket's upper bound (`None` for the overflow bucket)
+    pub fn buckets(&self) -> Vec<(Option<u64>, u64)> {
+        LATENCY_BUCKETS_MS
+            .iter()
+            .map(|bound| Some(*bound))
+            .chain(std::iter::once(None))
+            .zip(self.buckets.iter().copied())
+            .collect()
+    }
+}
+
+/// Counts of transaction broadcasts dropped to break propagation loops
+///
+/// Non-exhaustive so counters can be added; read it from [`RelayServer::loop_metrics`](crate::RelayServer::loop_metrics).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LoopMetrics {
+    /// Our own broadcasts echoed back to us by strfry or a peer
+    pub self_echoes_dropped: u64,
+    /// Broadcasts of transactions already received from another relay
+    pub duplicates_dropped: u64,
+    /// Broadcasts and forwarded transactions that passed through more relays than allowed
+    pub hop_limit_dropped: u64,
+}
+
+/// An instrumentation point reported to a [`MetricsSink`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Metric {
+    /// A transaction was gossiped to peers (counter)
+    TxBroadcast,
+    /// A transaction broadcast arrived from a peer relay (counter)
+    TxReceived,
+    /// One of our own broadcasts came back to us (counter)
+    SelfEchoDropped,
+    /// A peer broadcast a transaction another peer already delivered (counter)
+    DuplicateDropped,
+    /// A broadcast or forwarded transaction exceeded the hop limit (counter)
+    HopLimitDropped,
+    /// Seconds between a peer first seeing a transaction and us receiving it (observation)
+    PropagationLatency,
+    /// The Bitcoin node accepted a submitted transaction (counter)
+    NodeSubmissionAccepted,
+    /// The Bitcoin node rejected a submitted transaction or could not be reached (counter)
+    NodeSubmissionFailed,
+    /// An event was dropped because the strfry queues were full (counter)
+    StrfryEventDropped,
+    /// An inbound event was dropped for a timestamp too far from local time (counter)
+    ClockSkewDropped,
+    /// A submitted or gossiped transaction failed validation (counter)
+    ValidationFailed,
+}
+
+impl Metric {
+    /// Name in Prometheus conventions, e.g. `relay_tx_broadcasts_total`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::TxBroadcast => "relay_tx_broadcasts_total",
+            Metric::TxReceived => "relay_tx_received_total",
+            Metric::SelfEchoDropped => "relay_self_echoes_dropped_total",
+            Metric::DuplicateDropped => "relay_duplicates_dropped_total",
+            Metric::HopLimitDropped => "relay_hop_limit_dropped_total",
+            Metric::PropagationLatency => "relay_propagation_latency_seconds",
+            Metric::NodeSubmissionAccepted => "relay_node_submissions_accepted_total",
+            Metric::NodeSubmissionFailed => "relay_node_submissions_failed_total",
+            Metric::StrfryEventDropped => "relay_strfry_events_dropped_total",
+            Metric::ClockSkewDropped => "relay_clock_skew_dropped_total",
+            Metric::ValidationFailed => "relay_validation_failures_total",
+        }
+    }
+}
+
+/// Transaction counts since the server was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    /// Transactions gossiped to peers
+    pub broadcast: u64,
+    /// Transaction broadcasts received from peer relays, duplicates included
+    pub received: u64,
+    /// Transactions that failed validation or that the Bitcoin node refused
+    pub rejected: u64,
+    /// Transactions the Bitcoin node accepted
+    pub submitted: u64,
+}
+
+/// Running totals behind [`RelayStats`]
+#[derive(Debug, Default)]
+pub(crate) struct StatsCounters {
+    broadcast: AtomicU64,
+    received: AtomicU64,
+    rejected: AtomicU64,
+    submitted: AtomicU64,
+}
+
+impl StatsCounters {
+    /// Add an occurrence of `metric` to the total it feeds, if any
+    pub(crate) fn record(&self, metric: Metric) {
+        let counter = match metric {
+            Metric::TxBroadcast => &self.broadcast,
+            Metric::TxReceived => &self.received,
+            Metric::ValidationFailed | Metric::NodeSubmissionFailed => &self.rejected,
+            Metric::NodeSubmissionAccepted => &self.submitted,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    pub(crate) fn snapshot(&self) -> RelayStats {
+        RelayStats {
+            broadcast: self.broadcast.load(Ordering::Relaxed),
+            received: self.received.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            submitted: self.submitted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Backend the server reports metrics to, e.g. a StatsD or OpenTelemetry exporter
+///
+/// Both methods are called inline on hot paths and should not block.
+pub trait MetricsSink: Send + Sync + std::fmt::Debug {
+    /// Count one occurrence of `metric`
+    fn incr(&self, _metric: Metric) {}
+    
+    /// Record a measured value of `metric`
+    fn observe(&self, _metric: Metric, _value: f64) {}
+}
+
+/// Sink that discards every metric, used unless another is configured
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl MetricsSink for NoopMetrics {}
+
+/// Sink keeping counters and observation totals for rendering in the Prometheus text format
+#[cfg(feature = "prometheus")]
+#[derive(Debug, Default)]
+pub struct PrometheusSink {
+    counters: Mutex<BTreeMap<&'static str, u64>>,
+    /// Sum and count per observed metric
+    summaries: Mutex<BTreeMap<&'static str, (f64, u64)>>,
+}
+
+#[cfg(feature = "prometheus")]
+impl PrometheusSink {
+    /// Create a sink with nothing recorded
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Everything recorded so far, in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, value) in self.counters.lock().unwrap().iter() {
+            out.push_str(&format!("# TYPE {name} counter\n{name} {value}\n"));
+        }
+        for (name, (sum, count)) in self.summaries.lock().unwrap().iter() {
+            out.push_str(&format!("# TYPE {name} summary\n{name}_sum {sum}\n{name}_count {count}\n"));
+        }
+        out
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl MetricsSink for PrometheusSink {
+    fn incr(&self, metric: Metric) {
+        *self.counters.lock().unwrap().entry(metric.name()).or_default() += 1;
+    }
+    
+    fn observe(&self, metric: Metric, value: f64) {
+        let mut summaries = self.summaries.lock().unwrap();
+        let (sum, count) = summaries.entry(metric.name()).or_default();
+        *sum += value;
+        *count += 1;
+    }
+}
+
+/// Latency between a peer's first-seen timestamp and local receipt, both in ms since the epoch
+///
+/// Clock skew can put the peer's timestamp in our future; such deltas are clamped to zero.
+pub fn propagation_latency(first_seen_ms: u64, received_ms: u64) -> Duration {
+    Duration::from_millis(received_ms.saturating_sub(first_seen_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_millis(5));
+        histogram.record(Duration::from_millis(10));
+        histogram.record(Duration::from_millis(700));
+        histogram.record(Duration::from_secs(60));
+        
+        assert_eq!(histogram.count(), 4);
+        let buckets = histogram.buckets();
+        assert_eq!(buckets[0], (Some(10), 2));
+        assert_eq!(buckets[5], (Some(1_000), 1));
+        assert_eq!(buckets[9], (None, 1));
+        assert_eq!(histogram.mean(), Some(Duration::from_millis((5 + 10 + 700 + 60_000) / 4)));
+    }
+
+    #[test]
+    fn test_negative_latency_clamped() {
+        assert_eq!(propagation_latency(1_000, 1_250), Duration::from_millis(250));
+        assert_eq!(propagation_latency(2_000, 1_000), Duration::ZERO);
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn test_prometheus_rendering() {
+        let sink = PrometheusSink::new();
+        sink.incr(Metric::TxBroadcast);
+        sink.incr(Metric::TxBroadcast);
+        sink.observe(Metric::PropagationLatency, 0.5);
+        sink.observe(Metric::PropagationLatency, 1.25);
+        
+        assert_eq!(
+            sink.render(),
+            "# TYPE relay_tx_broadcasts_total counter\n\
+             relay_tx_broadcasts_total 2\n\
+             # TYPE relay_propagation_latency_seconds summary\n\
+             relay_propagation_latency_seconds_sum 1.75\n\
+             relay_propagation_latency_seconds_count 2\n"
+        );
+    }
+}
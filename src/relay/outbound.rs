@@ -0,0 +1,149 @@
+use super::config::OverflowPolicy;
+use nostr::Event;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Events waiting to be written to strfry
+type SharedEvent = Arc<Event>;
+
+/// Outcome of pushing onto an [`OutboundQueue`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Enqueued {
+    /// The event was queued
+    Queued,
+    /// The event was queued after dropping this, the oldest queued event
+    DroppedOldest(SharedEvent),
+    /// The queue was full and the event was rejected
+    Rejected(SharedEvent),
+}
+
+/// Bounded FIFO of events for the strfry connection task
+///
+/// Clones share the queue. Only one task should call [`OutboundQueue::recv`].
+#[derive(Debug, Clone)]
+pub struct OutboundQueue {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    events: Mutex<VecDeque<SharedEvent>>,
+    ready: Notify,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+impl OutboundQueue {
+    /// Create an empty queue holding at most `capacity` events
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                events: Mutex::new(VecDeque::new()),
+                ready: Notify::new(),
+                capacity: capacity.max(1),
+                policy,
+            }),
+        }
+    }
+    
+    /// Queue an event, applying the overflow policy if the queue is full
+    pub fn push(&self, event: SharedEvent) -> Enqueued {
+        let mut events = self.shared.events.lock().unwrap();
+        let outcome = if events.len() < self.shared.capacity {
+            Enqueued::Queued
+        } else {
+            match self.shared.policy {
+                OverflowPolicy::Reject => return Enqueued::Rejected(event),
+                OverflowPolicy::DropOldest => match events.pop_front() {
+                    Some(dropped) => Enqueued::DroppedOldest(dropped),
+                    None => Enqueued::Queued,
+                },
+            }
+        };
+        events.push_back(event);
+        drop(events);
+        self.shared.ready.notify_one();
+        outcome
+    }
+    
+    /// Wait for the next event
+    ///
+    /// Cancel-safe: an event is only removed when it is returned.
+    pub async fn recv(&self) -> SharedEvent {
+        loop {
+            let ready = self.shared.ready.notified();
+            if let Some(event) = self.shared.events.lock().unwrap().pop_front() {
+                return event;
+            }
+            ready.await;
+        }
+    }
+    
+    /// Number of queued events
+    pub fn len(&self) -> usize {
+        self.shared.events.lock().unwrap().len()
+    }
+    
+    /// Whether no events are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr::{EventBuilder, Keys};
+    use std::time::Duration;
+
+    fn notes(count: usize) -> Vec<SharedEvent> {
+        let keys = Keys::generate();
+        (0..count)
+            .map(|i| Arc::new(EventBuilder::new_text_note(i.to_string(), &[]).to_event(&keys).unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_when_full() {
+        let queue = OutboundQueue::new(2, OverflowPolicy::DropOldest);
+        let events = notes(3);
+        
+        assert_eq!(queue.push(Arc::clone(&events[0])), Enqueued::Queued);
+        assert_eq!(queue.push(Arc::clone(&events[1])), Enqueued::Queued);
+        assert_eq!(queue.push(Arc::clone(&events[2])), Enqueued::DroppedOldest(Arc::clone(&events[0])));
+        
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.recv().await.id, events[1].id);
+        assert_eq!(queue.recv().await.id, events[2].id);
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reject_when_full() {
+        let queue = OutboundQueue::new(2, OverflowPolicy::Reject);
+        let events = notes(3);
+        
+        queue.push(Arc::clone(&events[0]));
+        queue.push(Arc::clone(&events[1]));
+        assert_eq!(queue.push(Arc::clone(&events[2])), Enqueued::Rejected(Arc::clone(&events[2])));
+        
+        assert_eq!(queue.recv().await.id, events[0].id);
+        assert_eq!(queue.recv().await.id, events[1].id);
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recv_waits_for_push() {
+        let queue = OutboundQueue::new(4, OverflowPolicy::DropOldest);
+        let receiver = queue.clone();
+        let waiting = tokio::spawn(async move { receiver.recv().await });
+        
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let event = notes(1).remove(0);
+        queue.push(Arc::clone(&event));
+        
+        let received = tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+        assert_eq!(received.id, event.id);
+    }
+}
@@ -0,0 +1,295 @@
+use bitcoin::Transaction;
+use serde::{Deserialize, Serialize};
+
+/// Schema version written by this relay
+pub const TX_BROADCAST_SCHEMA_VERSION: u32 = 2;
+
+/// Payload fields a reader can work out from `hex`, left out of compact broadcasts
+const DERIVED_FIELDS: [&str; 7] = ["size", "vsize", "weight", "version", "locktime", "inputs", "outputs"];
+
+/// How transaction broadcasts are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BroadcastEncoding {
+    /// Every payload field
+    #[default]
+    Verbose,
+    
+    /// Only the txid, hex, fee and first-seen time; readers derive the rest from the hex
+    ///
+    /// Relays that predate compact broadcasts read them with the derived fields zeroed.
+    Compact,
+    
+    /// Only the txid, fee and first-seen time, plus the key to request the hex from
+    ///
+    /// Peers fetch the transaction with a transaction request, trading latency for
+    /// bandwidth. Relays that predate announcements ignore them. Announcer and requester
+    /// must trust each other, as for mempool reconciliation.
+    AnnounceOnly,
+}
+
+/// Content of a transaction broadcast event (kind 20012)
+///
+/// `txid` and `hex` are present in every schema version; newer versions may
+/// only add fields, so relays can still act on payloads from newer peers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxBroadcastPayload {
+    /// Payload schema version (0 for payloads that predate versioning)
+    #[serde(default)]
+    pub schema_version: u32,
+    pub txid: String,
+    /// Empty in announce-only broadcasts
+    #[serde(default)]
+    pub hex: String,
+    #[serde(default)]
+    pub size: usize,
+    #[serde(default)]
+    pub vsize: usize,
+    #[serde(default)]
+    pub weight: u64,
+    #[serde(default)]
+    pub version: i32,
+    #[serde(default)]
+    pub locktime: u32,
+    #[serde(default)]
+    pub inputs: usize,
+    #[serde(default)]
+    pub outputs: usize,
+    /// Fee paid, when known from the local mempool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_sat: Option<u64>,
+    /// When the broadcasting relay first saw the transaction, in ms since the epoch (schema 2+)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<u64>,
+    /// Hex identity key of the relay to request the transaction from (announce-only broadcasts)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announcer: Option<String>,
+    /// Relays the transaction passed through before the broadcasting relay, 0 at its origin
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub hops: u32,
+    /// Whether the broadcasting relay wants an acceptance notice from peers whose node takes the transaction
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub ack_requested: bool,
+}
+
+fn is_zero(hops: &u32) -> bool {
+    *hops == 0
+}
+
+fn is_false(flag: &bool) -> bool {
+    !*flag
+}
+
+impl TxBroadcastPayload {
+    /// Describe a transaction using the current schema
+    pub fn from_transaction(tx: &Transaction) -> Self {
+        let raw = bitcoin::consensus::serialize(tx);
+        Self {
+            schema_version: TX_BROADCAST_SCHEMA_VERSION,
+            txid: tx.txid().to_string(),
+            size: raw.len(),
+            vsize: tx.vsize(),
+            weight: tx.weight().to_wu(),
+            version: tx.version,
+            locktime: tx.lock_time.to_consensus_u32(),
+            inputs: tx.input.len(),
+            outputs: tx.output.len(),
+            fee_sat: None,
+            first_seen: None,
+            announcer: None,
+            hops: 0,
+            ack_requested: false,
+            hex: hex::encode(raw),
+        }
+    }
+    
+    /// Set the fee paid by the transaction
+    pub fn with_fee_sat(mut self, fee_sat: u64) -> Self {
+        self.fee_sat = Some(fee_sat);
+        self
+    }
+    
+    /// Set when the transaction was first seen, in ms since the epoch
+    pub fn with_first_seen(mut self, first_seen_ms: u64) -> Self {
+        self.first_seen = Some(first_seen_ms);
+        self
+    }
+    
+    /// Set how many relays the transaction passed through before this one
+    pub fn with_hops(mut self, hops: u32) -> Self {
+        self.hops = hops;
+        self
+    }
+    
+    /// Set the identity key peers should request the transaction from
+    pub fn with_announcer(mut self, identity: impl Into<String>) -> Self {
+        self.announcer = Some(identity.into());
+        self
+    }
+    
+    /// Ask peers to publish an acceptance notice once their node takes the transaction
+    pub fn with_ack_requested(mut self) -> Self {
+        self.ack_requested = true;
+        self
+    }
+    
+    /// Whether this is an announce-only broadcast, carrying no transaction
+    pub fn is_announcement(&self) -> bool {
+        self.hex.is_empty()
+    }
+    
+    /// Whether the payload was written with a schema newer than this relay understands
+    pub fn is_newer_schema(&self) -> bool {
+        self.schema_version > TX_BROADCAST_SCHEMA_VERSION
+    }
+    
+    /// Serialize as event content in the given encoding
+    pub fn to_content(&self, encoding: BroadcastEncoding) -> serde_json::Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(fields) = value.as_object_mut() {
+            if encoding != BroadcastEncoding::Verbose {
+                for field in DERIVED_FIELDS {
+                    fields.remove(field);
+                }
+            }
+            if encoding == BroadcastEncoding::AnnounceOnly {
+                fields.remove("hex");
+            } else {
+                fields.remove("announcer");
+            }
+        }
+        serde_json::to_string(&value)
+    }
+    
+    /// Fill in the fields a compact payload leaves out, if `hex` decodes to a transaction
+    pub fn fill_derived_fields(&mut self) {
+        if self.size != 0 {
+            return;
+        }
+        let Some(tx) = hex::decode(&self.hex).ok().and_then(|raw| bitcoin::consensus::deserialize::<Transaction>(&raw).ok()) else {
+            return;
+        };
+        let derived = Self::from_transaction(&tx);
+        self.size = derived.size;
+        self.vsize = derived.vsize;
+        self.weight = derived.weight;
+        self.version = derived.version;
+        self.locktime = derived.locktime;
+        self.inputs = derived.inputs;
+        self.outputs = derived.outputs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::consensus::deserialize;
+    use serde_json::json;
+
+    fn sample_tx() -> Transaction {
+        deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let payload = TxBroadcastPayload::from_transaction(&sample_tx())
+            .with_fee_sat(1_000)
+            .with_first_seen(1_700_000_000_000);
+        let json = serde_json::to_string(&payload).unwrap();
+        let parsed: TxBroadcastPayload = serde_json::from_str(&json).unwrap();
+        
+        assert_eq!(parsed, payload);
+        assert_eq!(parsed.schema_version, TX_BROADCAST_SCHEMA_VERSION);
+        assert_eq!(parsed.version, 2);
+        assert_eq!(parsed.locktime, 0);
+        assert_eq!(parsed.size, 61);
+        assert_eq!(parsed.vsize, 61);
+        assert_eq!(parsed.weight, 244);
+        assert_eq!(parsed.fee_sat, Some(1_000));
+        assert_eq!(parsed.first_seen, Some(1_700_000_000_000));
+    }
+
+    #[test]
+    fn test_payload_fields_are_plain_json() {
+        let payload = TxBroadcastPayload::from_transaction(&sample_tx());
+        let value = serde_json::to_value(&payload).unwrap();
+        
+        assert_eq!(value["version"], json!(2));
+        assert_eq!(value["locktime"], json!(0));
+        // Unknown fees are omitted rather than sent as null
+        assert!(value.get("fee_sat").is_none());
+    }
+
+    #[test]
+    fn test_unversioned_payload_parses() {
+        // Content written by relays before the schema was versioned
+        let legacy = json!({
+            "txid": "abcd",
+            "size": 61,
+            "version": 2,
+            "inputs": 1,
+            "outputs": 1,
+            "hex": "00"
+        });
+        let parsed: TxBroadcastPayload = serde_json::from_value(legacy).unwrap();
+        
+        assert_eq!(parsed.schema_version, 0);
+        assert_eq!(parsed.first_seen, None);
+        assert_eq!(parsed.txid, "abcd");
+        assert!(!parsed.is_newer_schema());
+    }
+
+    #[test]
+    fn test_newer_schema_version_parses() {
+        let newer = json!({
+            "schema_version": TX_BROADCAST_SCHEMA_VERSION + 1,
+            "txid": "abcd",
+            "hex": "00",
+            "some_future_field": [1, 2, 3]
+        });
+        let parsed: TxBroadcastPayload = serde_json::from_value(newer).unwrap();
+        
+        assert!(parsed.is_newer_schema());
+        assert_eq!(parsed.hex, "00");
+    }
+
+    #[test]
+    fn test_compact_encoding() {
+        let payload = TxBroadcastPayload::from_transaction(&sample_tx()).with_fee_sat(1_000);
+        let verbose: serde_json::Value = serde_json::from_str(&payload.to_content(BroadcastEncoding::Verbose).unwrap()).unwrap();
+        let compact: serde_json::Value = serde_json::from_str(&payload.to_content(BroadcastEncoding::Compact).unwrap()).unwrap();
+        
+        assert_eq!(verbose, serde_json::to_value(&payload).unwrap());
+        assert_eq!(compact, json!({
+            "schema_version": TX_BROADCAST_SCHEMA_VERSION,
+            "txid": payload.txid,
+            "hex": payload.hex,
+            "fee_sat": 1_000
+        }));
+        
+        let mut parsed: TxBroadcastPayload = serde_json::from_value(compact).unwrap();
+        assert_eq!(parsed.vsize, 0);
+        parsed.fill_derived_fields();
+        assert_eq!(parsed, payload);
+    }
+
+    #[test]
+    fn test_announce_only_encoding() {
+        let payload = TxBroadcastPayload::from_transaction(&sample_tx()).with_announcer("ab".repeat(32));
+        let announcement: serde_json::Value = serde_json::from_str(&payload.to_content(BroadcastEncoding::AnnounceOnly).unwrap()).unwrap();
+        assert_eq!(announcement, json!({
+            "schema_version": TX_BROADCAST_SCHEMA_VERSION,
+            "txid": payload.txid,
+            "announcer": "ab".repeat(32)
+        }));
+        
+        let mut parsed: TxBroadcastPayload = serde_json::from_value(announcement).unwrap();
+        parsed.fill_derived_fields();
+        assert!(parsed.is_announcement());
+        assert_eq!(parsed.announcer, payload.announcer);
+        
+        // Full broadcasts don't name an announcer
+        let verbose: serde_json::Value = serde_json::from_str(&payload.to_content(BroadcastEncoding::Verbose).unwrap()).unwrap();
+        assert!(verbose.get("announcer").is_none());
+        assert!(!TxBroadcastPayload::from_transaction(&sample_tx()).is_announcement());
+    }
+}
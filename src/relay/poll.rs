@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+/// Consecutive idle polls before the interval starts growing
+pub const IDLE_POLLS_BEFORE_BACKOFF: u32 = 3;
+
+/// Mempool poll interval that backs off while the mempool is idle
+///
+/// After [`IDLE_POLLS_BEFORE_BACKOFF`] polls in a row find nothing new, each
+/// further idle poll doubles the interval up to `max`. A poll that finds new
+/// transactions snaps it back to `min`.
+#[derive(Debug, Clone)]
+pub struct AdaptivePollInterval {
+    min: Duration,
+    max: Duration,
+    idle_polls: u32,
+    current: Duration,
+}
+
+impl AdaptivePollInterval {
+    /// Create an interval starting at `min`; a `max` below `min` keeps it fixed
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max: max.max(min),
+            idle_polls: 0,
+            current: min,
+        }
+    }
+    
+    /// Delay before the next poll
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+    
+    /// Record whether a poll found new transactions, returning the delay before the next poll
+    pub fn record_poll(&mut self, found_new: bool) -> Duration {
+        if found_new {
+            self.idle_polls = 0;
+            self.current = self.min;
+        } else {
+            self.idle_polls = self.idle_polls.saturating_add(1);
+            if self.idle_polls > IDLE_POLLS_BEFORE_BACKOFF {
+                self.current = self.current.saturating_mul(2).min(self.max);
+            }
+        }
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_through_idle_and_busy_phases() {
+        let mut interval = AdaptivePollInterval::new(Duration::from_secs(2), Duration::from_secs(30));
+        
+        // Idle: a few polls at the minimum, then doubling up to the maximum
+        let idle: Vec<u64> = (0..8).map(|_| interval.record_poll(false).as_secs()).collect();
+        assert_eq!(idle, vec![2, 2, 2, 4, 8, 16, 30, 30]);
+        
+        // Busy: new transactions snap straight back to the minimum
+        assert_eq!(interval.record_poll(true), Duration::from_secs(2));
+        assert_eq!(interval.record_poll(false), Duration::from_secs(2));
+        assert_eq!(interval.record_poll(true), Duration::from_secs(2));
+        assert_eq!(interval.current(), Duration::from_secs(2));
+        
+        // Over five idle minutes on a mock clock, polls drop from 150 to 15
+        let mut clock = Duration::ZERO;
+        let mut polls = 0;
+        while clock < Duration::from_secs(300) {
+            clock += interval.record_poll(false);
+            polls += 1;
+        }
+        assert_eq!(polls, 15);
+    }
+
+    #[test]
+    fn test_max_below_min_keeps_interval_fixed() {
+        let mut interval = AdaptivePollInterval::new(Duration::from_secs(2), Duration::from_secs(1));
+        for _ in 0..10 {
+            assert_eq!(interval.record_poll(false), Duration::from_secs(2));
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use super::config::{HandshakeBanPolicy, SubmissionRateLimit};
+use crate::error::BitcoinRpcError;
+use lru::LruCache;
+use nostr::prelude::XOnlyPublicKey;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// Global token bucket guarding `sendrawtransaction`
+///
+/// Tokens refill continuously at `per_second` up to `burst`. A submission that
+/// finds the bucket empty waits for a token in a bounded queue, and is rejected
+/// when the queue is full or no token frees up within `max_wait`.
+#[derive(Debug)]
+pub struct SubmissionLimiter {
+    config: SubmissionRateLimit,
+    bucket: Mutex<Bucket>,
+    queue: Semaphore,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl SubmissionLimiter {
+    /// Create a limiter starting with a full bucket
+    pub fn new(config: SubmissionRateLimit) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: config.burst as f64,
+                last_refill: Instant::now(),
+            }),
+            queue: Semaphore::new(config.max_queued),
+            config,
+        }
+    }
+    
+    /// Take a token, waiting in the bounded queue if none is available
+    pub async fn acquire(&self) -> Result<(), BitcoinRpcError> {
+        let mut wait = match self.try_take() {
+            Ok(()) => return Ok(()),
+            Err(wait) => wait,
+        };
+        
+        let Ok(_slot) = self.queue.try_acquire() else {
+            return Err(BitcoinRpcError::RateLimited);
+        };
+        
+        let deadline = Instant::now() + self.config.max_wait;
+        loop {
+            if Instant::now().checked_add(wait).is_none_or(|ready| ready > deadline) {
+                return Err(BitcoinRpcError::RateLimited);
+            }
+            tokio::time::sleep(wait).await;
+            match self.try_take() {
+                Ok(()) => return Ok(()),
+                Err(next_wait) => wait = next_wait,
+            }
+        }
+    }
+    
+    /// Take a token if one is available, otherwise return the time until the next one
+    fn try_take(&self) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.last_refill).as_secs_f64() * self.config.per_second;
+        bucket.tokens = (bucket.tokens + refill).min(self.config.burst as f64);
+        bucket.last_refill = now;
+        
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if self.config.per_second > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.config.per_second))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+}
+
+/// Most client IPs whose handshake failures are tracked at once
+const MAX_TRACKED_PEERS: usize = 10_000;
+
+/// Handshake failures of one client IP
+#[derive(Debug)]
+struct PeerFailures {
+    count: u32,
+    window_start: Instant,
+    banned_until: Option<Instant>,
+}
+
+/// Per-IP count of failed WebSocket handshakes, banning IPs that fail too often
+///
+/// Only the most recently failing [`MAX_TRACKED_PEERS`] IPs are remembered.
+#[derive(Debug)]
+pub struct HandshakeBans {
+    policy: HandshakeBanPolicy,
+    peers: Mutex<LruCache<IpAddr, PeerFailures>>,
+}
+
+impl HandshakeBans {
+    /// Track failures under `policy`
+    pub fn new(policy: HandshakeBanPolicy) -> Self {
+        Self {
+            policy,
+            peers: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_TRACKED_PEERS).unwrap())),
+        }
+    }
+    
+    /// Whether connections from `ip` should be dropped at `now`
+    pub fn is_banned(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+        match peers.peek(&ip).and_then(|peer| peer.banned_until) {
+            Some(until) if now < until => true,
+            Some(_) => {
+                peers.pop(&ip);
+                false
+            }
+            None => false,
+        }
+    }
+    
+    /// Record a failed handshake from `ip`, returning whether it got `ip` banned
+    pub fn record_failure(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+        let peer = peers.get_or_insert_mut(ip, || PeerFailures {
+            count: 0,
+            window_start: now,
+            banned_until: None,
+        });
+        if now.duration_since(peer.window_start) > self.policy.window {
+            peer.count = 0;
+            peer.window_start = now;
+        }
+        peer.count += 1;
+        if peer.banned_until.is_none() && peer.count >= self.policy.max_failures {
+            peer.banned_until = Some(now + self.policy.ban_duration);
+            return true;
+        }
+        false
+    }
+    
+    /// Forget the failures of `ip` after it completes a handshake
+    pub fn record_success(&self, ip: IpAddr) {
+        self.peers.lock().unwrap().pop(&ip);
+    }
+}
+
+/// Most peer and request kind pairs whose requests are counted at once
+const MAX_LIMITED_PEERS: usize = 1_000;
+
+/// Per-peer count of requests of each kind in a fixed window, for requests peers make of this relay
+///
+/// Only the most recently active [`MAX_LIMITED_PEERS`] peer and kind pairs are remembered.
+#[derive(Debug)]
+pub(crate) struct PeerRequestLimiter {
+    max_requests: u32,
+    window: Duration,
+    /// Start of the current window and requests counted in it
+    peers: Mutex<LruCache<(XOnlyPublicKey, u16), (Instant, u32)>>,
+}
+
+impl PeerRequestLimiter {
+    /// Allow each peer `max_requests` requests of each kind per `window`
+    pub(crate) fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+            peers: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_LIMITED_PEERS).unwrap())),
+        }
+    }
+    
+    /// Count a request of `kind` from `peer` at `now`, returning whether it is within the limit
+    pub(crate) fn allow(&self, peer: XOnlyPublicKey, kind: u16, now: Instant) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+        let (window_start, count) = peers.get_or_insert_mut((peer, kind), || (now, 0));
+        if now.duration_since(*window_start) >= self.window {
+            *window_start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= self.max_requests
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(per_second: f64, burst: u32, max_queued: usize, max_wait: Duration) -> SubmissionRateLimit {
+        SubmissionRateLimit {
+            per_second,
+            burst,
+            max_queued,
+            max_wait,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_burst_then_reject() {
+        let limiter = SubmissionLimiter::new(config(1.0, 3, 0, Duration::ZERO));
+        
+        for _ in 0..3 {
+            assert!(limiter.acquire().await.is_ok());
+        }
+        assert!(matches!(limiter.acquire().await, Err(BitcoinRpcError::RateLimited)));
+    }
+
+    #[tokio::test]
+    async fn test_queued_submission_waits_for_refill() {
+        let limiter = SubmissionLimiter::new(config(20.0, 1, 1, Duration::from_secs(1)));
+        assert!(limiter.acquire().await.is_ok());
+        
+        let started = Instant::now();
+        assert!(limiter.acquire().await.is_ok());
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_queue_gives_up_after_max_wait() {
+        // One token every 10s never arrives within a 50ms wait
+        let limiter = SubmissionLimiter::new(config(0.1, 1, 4, Duration::from_millis(50)));
+        assert!(limiter.acquire().await.is_ok());
+        assert!(matches!(limiter.acquire().await, Err(BitcoinRpcError::RateLimited)));
+    }
+
+    #[test]
+    fn test_handshake_bans_expire() {
+        let bans = HandshakeBans::new(HandshakeBanPolicy {
+            max_failures: 3,
+            window: Duration::from_secs(10),
+            ban_duration: Duration::from_secs(60),
+        });
+        let (scanner, client): (IpAddr, IpAddr) = ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+        let start = Instant::now();
+        
+        // Failures spread wider than the window never add up to a ban
+        for i in 0..3 {
+            assert!(!bans.record_failure(client, start + Duration::from_secs(11 * i)));
+        }
+        assert!(!bans.is_banned(client, start + Duration::from_secs(22)));
+        
+        assert!(!bans.record_failure(scanner, start));
+        assert!(!bans.record_failure(scanner, start));
+        assert!(bans.record_failure(scanner, start));
+        assert!(!bans.record_failure(scanner, start), "already banned");
+        assert!(bans.is_banned(scanner, start + Duration::from_secs(59)));
+        assert!(!bans.is_banned(scanner, start + Duration::from_secs(60)));
+        
+        // A successful handshake clears past failures
+        let later = start + Duration::from_secs(22);
+        assert!(!bans.record_failure(client, later));
+        bans.record_success(client);
+        assert!(!bans.record_failure(client, later));
+        assert!(!bans.record_failure(client, later));
+    }
+
+    #[test]
+    fn test_peer_requests_limited_per_kind() {
+        let limiter = PeerRequestLimiter::new(2, Duration::from_secs(60));
+        let peer = nostr::Keys::generate().public_key();
+        let start = Instant::now();
+        
+        assert!(limiter.allow(peer, 1, start));
+        assert!(limiter.allow(peer, 1, start));
+        assert!(!limiter.allow(peer, 1, start));
+        // Other kinds and other peers have their own budget
+        assert!(limiter.allow(peer, 2, start));
+        assert!(limiter.allow(nostr::Keys::generate().public_key(), 1, start));
+        // The budget refills with the next window
+        assert!(limiter.allow(peer, 1, start + Duration::from_secs(60)));
+    }
+}
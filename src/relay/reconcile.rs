@@ -0,0 +1,119 @@
+use crate::error::ValidationError;
+use crate::validation::parse_txid;
+use bitcoin::Txid;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Most txids sent in one mempool summary, keeping the event well under the WebSocket message limit
+pub const MAX_SUMMARY_TXIDS: usize = 10_000;
+
+/// Content of a mempool summary event: the sender's mempool txids
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolSummary {
+    pub relay_id: String,
+    /// Sorted txids, at most [`MAX_SUMMARY_TXIDS`]
+    pub txids: Vec<String>,
+    /// Whether txids were left out to respect the size limit
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl MempoolSummary {
+    /// Summarize a mempool, keeping the first [`MAX_SUMMARY_TXIDS`] txids in sorted order
+    pub fn new(relay_id: impl Into<String>, txids: impl IntoIterator<Item = Txid>) -> Self {
+        let mut txids: Vec<String> = txids.into_iter().map(|txid| txid.to_string()).collect::<BTreeSet<_>>().into_iter().collect();
+        let truncated = txids.len() > MAX_SUMMARY_TXIDS;
+        txids.truncate(MAX_SUMMARY_TXIDS);
+        Self {
+            relay_id: relay_id.into(),
+            txids,
+            truncated,
+        }
+    }
+    
+    /// Parse the summary's txids, failing on the first that isn't a valid txid
+    pub fn parse_txids(&self) -> Result<Vec<Txid>, ValidationError> {
+        self.txids.iter().map(|txid| parse_txid(txid)).collect()
+    }
+}
+
+/// Txids present in one of two mempools but not the other, each list sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MempoolDiff {
+    /// In the remote mempool only; these can be requested from the peer
+    pub missing_locally: Vec<Txid>,
+    /// In the local mempool only
+    pub missing_remotely: Vec<Txid>,
+}
+
+impl MempoolDiff {
+    /// Compare a local mempool with a peer's
+    pub fn between<'a>(
+        local: impl IntoIterator<Item = &'a Txid>,
+        remote: impl IntoIterator<Item = &'a Txid>,
+    ) -> Self {
+        let local: BTreeSet<&Txid> = local.into_iter().collect();
+        let remote: BTreeSet<&Txid> = remote.into_iter().collect();
+        Self {
+            missing_locally: remote.difference(&local).map(|txid| **txid).collect(),
+            missing_remotely: local.difference(&remote).map(|txid| **txid).collect(),
+        }
+    }
+    
+    /// Whether both mempools hold the same transactions
+    pub fn is_empty(&self) -> bool {
+        self.missing_locally.is_empty() && self.missing_remotely.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Txids made of one repeated byte, so byte and hex order agree
+    fn txids(bytes: &[u8]) -> Vec<Txid> {
+        bytes.iter().map(|byte| format!("{:02x}", byte).repeat(32).parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_diff_of_drifted_mempools() {
+        let local = txids(&[0xcc, 0xaa, 0xbb]);
+        let remote = txids(&[0xdd, 0xbb, 0xee, 0xaa]);
+        
+        let diff = MempoolDiff::between(&local, &remote);
+        assert_eq!(diff.missing_locally, txids(&[0xdd, 0xee]));
+        assert_eq!(diff.missing_remotely, txids(&[0xcc]));
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_of_identical_and_empty_mempools() {
+        let mempool = txids(&[0xaa, 0xbb]);
+        assert!(MempoolDiff::between(&mempool, &txids(&[0xbb, 0xaa])).is_empty());
+        
+        let diff = MempoolDiff::between(&Vec::new(), &mempool);
+        assert_eq!(diff.missing_locally, mempool);
+        assert!(diff.missing_remotely.is_empty());
+    }
+
+    #[test]
+    fn test_summary_sorted_and_bounded() {
+        let summary = MempoolSummary::new("1", txids(&[0xbb, 0xaa, 0xbb]));
+        assert_eq!(summary.parse_txids().unwrap(), txids(&[0xaa, 0xbb]));
+        assert!(!summary.truncated);
+        
+        let large = MempoolSummary::new("1", (0..MAX_SUMMARY_TXIDS + 5).map(|i| format!("{:064x}", i).parse().unwrap()));
+        assert_eq!(large.txids.len(), MAX_SUMMARY_TXIDS);
+        assert!(large.truncated);
+        
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(serde_json::from_str::<MempoolSummary>(&json).unwrap(), summary);
+    }
+
+    #[test]
+    fn test_summary_with_invalid_txid_rejected() {
+        let mut summary = MempoolSummary::new("1", txids(&[0xaa]));
+        summary.txids.push("not-a-txid".to_string());
+        assert!(matches!(summary.parse_txids(), Err(ValidationError::InvalidTxid { .. })));
+    }
+}
@@ -1,37 +1,270 @@
-use crate::{BitcoinRpcClient, NostrClient, TransactionValidator, ValidationError};
-use super::config::RelayConfig;
+use crate::bitcoin_rpc::{additional_node_clients, send_raw_transaction_to_all};
+use crate::nostr::{build_tx_acceptance_with_namespace, build_tx_broadcast_encoded_with_namespace, HOPS_TAG, build_tx_eviction_with_namespace, parse_tx_acceptance, parse_tx_broadcast, parse_tx_eviction, tx_broadcast_topics, ResponseCode, KIND_FORWARD_TX, KIND_REQUEST_TX, KIND_SUBMIT_TX, KIND_TX_ACCEPTED, KIND_TX_BROADCAST, KIND_TX_EVICTED, KIND_TX_RESPONSE};
+use crate::transport::{connect_websocket, NostrTransport};
+use crate::{AdditionalNode, BitcoinRpc, BitcoinRpcClient, EstimateMode, NetworkError, NostrClient, NostrError, SubmitOutcome, TransactionValidator, ValidationError};
+use crate::validation::parse_txid;
+use super::auth::{new_challenge, parse_auth_message, verify_auth};
+use super::backoff::Backoff;
+use super::circuit_breaker::{BreakerRpc, BreakerState, CircuitBreaker};
+use super::config::{ConflictPolicy, RelayConfig, RelayIdCheck};
+use super::dead_letter::{DeadLetter, DeadLetterLog, DeadLetterWriter};
+use super::dedup::DedupSnapshot;
+use super::discovery::{Capability, CapabilityAnnouncement, DiscoveryAnnouncement, PeerInfo, PeerRoster};
+use super::outbound::{Enqueued, OutboundQueue};
+use super::metrics::{propagation_latency, LatencyHistogram, LoopMetrics, Metric, RelayStats, StatsCounters};
+use super::payload::{BroadcastEncoding, TxBroadcastPayload};
+use super::poll::AdaptivePollInterval;
+use super::rate_limit::{HandshakeBans, PeerRequestLimiter, SubmissionLimiter};
+use super::reconcile::{MempoolDiff, MempoolSummary};
+use super::state::{BitcoinState, ConnectionState, StrfryState};
+use super::subscription::{RelayedTx, TxFilter, TxSource, TxSubscription};
 use crate::Result;
-use bitcoin::{consensus::deserialize, Transaction};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{consensus::deserialize, BlockHash, OutPoint, Transaction, Txid, Wtxid};
 use futures_util::{SinkExt, StreamExt};
-use nostr::{Event, EventBuilder, Keys, Kind, Tag};
+use lru::LruCache;
+use nostr::{Event, EventBuilder, EventId, Filter, Keys, Kind, Tag};
+use nostr::prelude::XOnlyPublicKey;
+use serde::Deserialize;
 use serde_json::{json, Value};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc, RwLock};
-use tokio_tungstenite::{accept_async, connect_async, tungstenite::protocol::Message};
-use tracing::{error, info, warn};
-use url::Url;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::{broadcast, mpsc, watch, Notify, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio_tungstenite::tungstenite::protocol::{Message, WebSocketConfig};
+use tokio_tungstenite::{accept_async_with_config, WebSocketStream};
+use tracing::{debug, error, info, warn};
 
 // Transaction relay event kinds
-const KIND_SUBMIT_TX: u16 = 20010;
-const KIND_TX_RESPONSE: u16 = 20011;  
-const KIND_TX_BROADCAST: u16 = 20012;
-const KIND_REQUEST_TX: u16 = 20013;
+const KIND_RELAY_PRESENCE: u16 = 20014;
+const KIND_RELAY_DISCOVERY: u16 = 20015;
+const KIND_SUBMIT_PACKAGE: u16 = 20016;
+const KIND_ESTIMATE_FEE: u16 = 20017;
+const KIND_MEMPOOL_SUMMARY_REQUEST: u16 = 20018;
+const KIND_MEMPOOL_SUMMARY: u16 = 20019;
+const KIND_RELAY_CAPABILITIES: u16 = 20021;
+const KIND_PEER_TX_REQUEST: u16 = 20023;
 
-type ClientMap = Arc<RwLock<HashMap<String, broadcast::Sender<Event>>>>;
+/// Most txids asked for in one reconciliation request
+const MAX_REQUESTED_TXIDS: usize = 1000;
+
+/// Most requests of each kind a peer relay may make of us per minute
+const MAX_PEER_REQUESTS_PER_MINUTE: u32 = 60;
+
+/// Most blocks searched for a departed mempool transaction; with more mined since the
+/// last search, departures go unreported rather than risk calling a confirmed one evicted
+const EVICTION_BLOCK_LOOKBACK: usize = 24;
+
+/// Most blocks whose transactions are gossiped in one block monitor poll
+const MAX_BLOCKS_PER_POLL: usize = 10;
+
+/// Most client submissions remembered for replacement notifications
+const MAX_TRACKED_SUBMISSIONS: usize = 10_000;
+
+/// Most segwit transactions remembered for lookups by wtxid
+const MAX_INDEXED_WTXIDS: usize = 100_000;
+
+/// Most announced transactions remembered as already requested
+const MAX_REQUESTED_ANNOUNCEMENTS: usize = 10_000;
+
+/// Most peer transactions remembered as rejected
+const MAX_REJECTED_REMOTE: usize = 10_000;
+
+/// How long a peer transaction the node or validator rejected is ignored from other peers
+const REJECTED_REMOTE_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Most transaction broadcast event ids remembered, so a re-delivered event is handled once
+const MAX_SEEN_BROADCAST_EVENTS: usize = 10_000;
+
+/// Most transactions whose hop count from a peer is remembered for gossiping them onward
+const MAX_TRACKED_HOPS: usize = 10_000;
+
+/// Most txids remembered as gossiped after they leave the mempool, so blocks don't gossip them again
+const MAX_GOSSIPED_TXIDS: usize = 100_000;
+
+/// Most recent client submissions remembered for replaying their response to a resubmission
+const MAX_CLIENT_SUBMISSIONS: usize = 10_000;
+
+/// Subscription id broadcasts carry for clients subscribed on connect
+const IMPLICIT_SUBSCRIPTION_ID: &str = "sub_id";
+
+/// How long to wait for an announced transaction before requesting it again
+const ANNOUNCEMENT_REQUEST_RETRY: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long announced txids are collected before they are requested from their announcer in one batch
+const ANNOUNCEMENT_BATCH_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Bitcoin Core's limit on the number of transactions in a package
+const MAX_PACKAGE_COUNT: usize = 25;
+
+/// Confirmation target used when a fee estimate request doesn't give one
+const DEFAULT_FEE_CONF_TARGET: u16 = 6;
+
+/// Content of a fee estimate request; empty content asks for the defaults
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct FeeEstimateRequest {
+    conf_target: u16,
+    mode: EstimateMode,
+}
+
+impl Default for FeeEstimateRequest {
+    fn default() -> Self {
+        Self {
+            conf_target: DEFAULT_FEE_CONF_TARGET,
+            mode: EstimateMode::default(),
+        }
+    }
+}
+
+/// Content of a request for transactions by txid, from a client or a peer relay
+#[derive(Debug, Deserialize)]
+struct TxRequest {
+    /// Txids, or wtxids of transactions the relay has seen
+    txids: Vec<String>,
+}
+
+/// Messages read from a client and waiting to be handled
+const CLIENT_MESSAGE_QUEUE: usize = 64;
+
+/// Protocol frames waiting to be written to a client; a client that lets more pile up is disconnected
+const CLIENT_FRAME_QUEUE: usize = 64;
+
+/// Protocol frames for one client that aren't events, such as NIP-42 AUTH and OK
+#[derive(Clone)]
+struct ClientFrames {
+    sender: mpsc::Sender<String>,
+    /// Notified once the queue overflows, so the connection can be closed
+    overflowed: Arc<Notify>,
+}
+
+impl ClientFrames {
+    fn new(capacity: usize) -> (Self, mpsc::Receiver<String>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (Self { sender, overflowed: Arc::new(Notify::new()) }, receiver)
+    }
+    
+    /// Queue a frame without waiting, flagging the client for disconnection if its queue is full
+    fn send(&self, frame: String) {
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.sender.try_send(frame) {
+            self.overflowed.notify_one();
+        }
+    }
+}
+
+/// Dead letters waiting to be written; more are dropped while the log falls behind
+const DEAD_LETTER_QUEUE: usize = 1024;
+
+/// Events are shared behind an `Arc` so fan-out to many clients doesn't deep-clone payloads
+type SharedEvent = Arc<Event>;
+type ClientMap = Arc<RwLock<HashMap<String, broadcast::Sender<SharedEvent>>>>;
+/// Client ids mapped to their subscriptions' filters by subscription id
+type SubscriptionMap = Arc<RwLock<HashMap<String, HashMap<String, Vec<Filter>>>>>;
+/// Txids mapped to when they were recorded, in ms since the epoch
+type SeenTxids = Arc<RwLock<HashMap<Txid, u64>>>;
+
+/// An accepted client submission, kept to tell the client if it leaves the mempool unconfirmed
+#[derive(Debug)]
+struct TrackedSubmission {
+    client_id: String,
+    request_id: EventId,
+    outpoints: Vec<OutPoint>,
+}
+
+/// A transaction a client submitted, kept to answer its resubmission on the same connection
+#[derive(Debug, Clone)]
+struct ClientSubmission {
+    submitted: std::time::Instant,
+    /// Content of the response, `None` until it is sent
+    response: watch::Receiver<Option<Value>>,
+}
 
 /// Core Bitcoin-Nostr relay server implementation
 #[derive(Clone)]
 pub struct RelayServer {
-    bitcoin_client: BitcoinRpcClient,
+    bitcoin_client: Arc<dyn BitcoinRpc>,
+    /// Nodes submitted transactions are also sent to
+    additional_nodes: Vec<AdditionalNode>,
     clients: ClientMap,
-    keys: Keys,
-    tx_broadcaster: broadcast::Sender<Event>,
-    strfry_sender: mpsc::UnboundedSender<Event>,
-    strfry_receiver: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<Event>>>,
-    remote_transactions: Arc<RwLock<HashSet<String>>>,
+    /// Numbers Unix socket clients, which have no peer address to identify them
+    next_local_client: Arc<AtomicU64>,
+    /// Each client's subscriptions to transaction broadcasts by subscription id, from a `REQ` or on connect
+    subscriptions: SubscriptionMap,
+    /// Pubkeys clients authenticated as with NIP-42
+    authenticated_clients: Arc<RwLock<HashMap<String, XOnlyPublicKey>>>,
+    /// Signs presence and discovery events
+    identity_keys: Keys,
+    /// Signs transaction broadcasts and responses
+    content_keys: Keys,
+    tx_broadcaster: broadcast::Sender<SharedEvent>,
+    /// (txid, relay_id) of every acceptance notice received from a peer relay
+    peer_acceptances: broadcast::Sender<(Txid, String)>,
+    /// Every transaction the relay handles, for in-process subscribers
+    tx_stream: broadcast::Sender<Arc<RelayedTx>>,
+    /// Events waiting to be written to strfry
+    strfry_outbound: OutboundQueue,
+    /// Set once a task owns the strfry connection
+    strfry_task_started: Arc<AtomicBool>,
+    /// Outbound events dropped by a full outbound queue or retry buffer
+    strfry_events_dropped: Arc<AtomicU64>,
+    strfry_connected: Arc<AtomicBool>,
+    /// Whether strfry sent EOSE for the transaction subscription of the current session
+    strfry_backfilled: Arc<AtomicBool>,
+    /// Whether the latest mempool poll reached the node
+    mempool_reachable: Arc<AtomicBool>,
+    /// Strfry and Bitcoin node state for embedders to watch
+    connection_state: Arc<watch::Sender<ConnectionState>>,
+    /// Broadcasts that couldn't reach strfry, resent after the next reconnect
+    strfry_retry_buffer: Arc<tokio::sync::Mutex<VecDeque<SharedEvent>>>,
+    remote_transactions: SeenTxids,
+    /// Mempool transactions already gossiped, including ancestors sent ahead of their children
+    broadcast_txids: SeenTxids,
+    /// Txids pruned from `broadcast_txids` and `remote_transactions`, checked before gossiping a block's transactions
+    gossiped_txids: Arc<std::sync::Mutex<LruCache<Txid, ()>>>,
+    shutdown_notify: Arc<Notify>,
+    relay_id_conflicts: Arc<RwLock<HashSet<XOnlyPublicKey>>>,
+    relay_id_conflict_notify: Arc<Notify>,
+    peer_roster: Arc<RwLock<PeerRoster>>,
+    submission_limiter: Option<Arc<SubmissionLimiter>>,
+    handshake_bans: Option<Arc<HandshakeBans>>,
+    rpc_breaker: Option<Arc<CircuitBreaker>>,
+    /// Slots for submissions in flight to the node
+    submission_slots: Option<Arc<Semaphore>>,
+    propagation_latency: Arc<std::sync::Mutex<LatencyHistogram>>,
+    /// Failed submissions, when a dead-letter log is configured
+    dead_letters: Option<DeadLetterWriter>,
+    /// Accepted client submissions by txid, when replacement notifications are on
+    submitters: Arc<std::sync::Mutex<LruCache<Txid, TrackedSubmission>>>,
+    /// Client submissions waiting for a peer's acceptance notice, whose broadcasts ask for one
+    awaiting_acceptance: Arc<std::sync::Mutex<HashSet<Txid>>>,
+    /// Txids of segwit transactions the relay has handled, by wtxid
+    wtxid_index: Arc<std::sync::Mutex<LruCache<Wtxid, Txid>>>,
+    /// Requests peer relays made of us, counted per peer and kind
+    peer_requests: Arc<PeerRequestLimiter>,
+    /// When announced transactions were last requested from their announcer
+    requested_announcements: Arc<std::sync::Mutex<LruCache<Txid, std::time::Instant>>>,
+    /// When peer transactions were last rejected for reasons another delivery won't change
+    rejected_remote: Arc<std::sync::Mutex<LruCache<Txid, std::time::Instant>>>,
+    /// Announced txids waiting to be requested, by announcer
+    announcement_batches: Arc<std::sync::Mutex<HashMap<XOnlyPublicKey, Vec<Txid>>>>,
+    /// Requests we made of each announcer, counted like the requests peers make of us
+    announcement_requests: Arc<PeerRequestLimiter>,
+    /// Ids of transaction broadcast events already handled
+    seen_broadcast_events: Arc<std::sync::Mutex<LruCache<EventId, ()>>>,
+    /// Recent submissions by client and SHA-256 of the submitted content, when resubmissions are replayed
+    client_submissions: Arc<std::sync::Mutex<LruCache<(String, sha256::Hash), ClientSubmission>>>,
+    /// Where to record the response to a submission other submissions are waiting on, by request id
+    pending_replays: Arc<std::sync::Mutex<HashMap<EventId, watch::Sender<Option<Value>>>>>,
+    /// Relays each transaction from a peer passed through before reaching us
+    tx_hops: Arc<std::sync::Mutex<LruCache<Txid, u32>>>,
+    self_echoes_dropped: Arc<AtomicU64>,
+    duplicates_dropped: Arc<AtomicU64>,
+    hop_limit_dropped: Arc<AtomicU64>,
+    stats: Arc<StatsCounters>,
     validator: TransactionValidator,
     config: RelayConfig,
 }
@@ -40,32 +273,149 @@ impl RelayServer {
     /// Create a new RelayServer with the given components
     pub fn new(
         bitcoin_client: BitcoinRpcClient,
+        nostr_client: Option<NostrClient>,
+        validator: TransactionValidator,
+        config: RelayConfig,
+    ) -> Result<Self> {
+        Self::with_rpc(Arc::new(bitcoin_client), nostr_client, validator, config)
+    }
+    
+    /// Create a new RelayServer backed by any `BitcoinRpc` implementation
+    pub fn with_rpc(
+        bitcoin_client: Arc<dyn BitcoinRpc>,
         _nostr_client: Option<NostrClient>,
         validator: TransactionValidator,
         config: RelayConfig,
     ) -> Result<Self> {
+        config.validate()?;
         let (tx_broadcaster, _) = broadcast::channel(1000);
-        let (strfry_sender, strfry_receiver) = mpsc::unbounded_channel();
+        let (peer_acceptances, _) = broadcast::channel(1000);
+        let (tx_stream, _) = broadcast::channel(1000);
+        let peer_roster = PeerRoster::new(
+            config.relay_id.clone(),
+            config.discovery.max_peers,
+            config.discovery.peer_ttl,
+        );
+        
+        // Every call to the primary node, from monitoring and submissions alike, goes through the breaker
+        let rpc_breaker = config.rpc_circuit_breaker.clone().map(|breaker| Arc::new(CircuitBreaker::new(breaker)));
+        let bitcoin_client: Arc<dyn BitcoinRpc> = match &rpc_breaker {
+            Some(breaker) => Arc::new(BreakerRpc::new(bitcoin_client, Arc::clone(breaker), config.bitcoin_rpc_url.clone())),
+            None => bitcoin_client,
+        };
+        let validator = match &rpc_breaker {
+            Some(breaker) => validator.with_circuit_breaker(Arc::clone(breaker)),
+            None => validator,
+        };
+        let additional_nodes = additional_node_clients(&config.additional_bitcoin_nodes, &config.rpc_pool, &config.relay_id)?;
         
         Ok(Self {
             bitcoin_client,
+            additional_nodes,
             clients: Arc::new(RwLock::new(HashMap::new())),
-            keys: Keys::generate(),
+            next_local_client: Arc::new(AtomicU64::new(0)),
+            subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            authenticated_clients: Arc::new(RwLock::new(HashMap::new())),
+            identity_keys: config.identity_keys.clone().unwrap_or_else(Keys::generate),
+            content_keys: config.content_keys.clone().unwrap_or_else(Keys::generate),
             tx_broadcaster,
-            strfry_sender,
-            strfry_receiver: Arc::new(tokio::sync::Mutex::new(strfry_receiver)),
-            remote_transactions: Arc::new(RwLock::new(HashSet::new())),
+            peer_acceptances,
+            tx_stream,
+            strfry_outbound: OutboundQueue::new(config.strfry_outbound_queue_size, config.strfry_overflow_policy),
+            strfry_task_started: Arc::new(AtomicBool::new(false)),
+            strfry_events_dropped: Arc::new(AtomicU64::new(0)),
+            strfry_connected: Arc::new(AtomicBool::new(false)),
+            strfry_backfilled: Arc::new(AtomicBool::new(false)),
+            mempool_reachable: Arc::new(AtomicBool::new(false)),
+            connection_state: Arc::new(watch::Sender::new(ConnectionState::new())),
+            strfry_retry_buffer: Arc::new(tokio::sync::Mutex::new(VecDeque::new())),
+            remote_transactions: Arc::new(RwLock::new(HashMap::new())),
+            broadcast_txids: Arc::new(RwLock::new(HashMap::new())),
+            gossiped_txids: Arc::new(std::sync::Mutex::new(LruCache::new(NonZeroUsize::new(MAX_GOSSIPED_TXIDS).unwrap()))),
+            shutdown_notify: Arc::new(Notify::new()),
+            relay_id_conflicts: Arc::new(RwLock::new(HashSet::new())),
+            relay_id_conflict_notify: Arc::new(Notify::new()),
+            peer_roster: Arc::new(RwLock::new(peer_roster)),
+            submission_limiter: config.submission_rate_limit.clone().map(|limit| Arc::new(SubmissionLimiter::new(limit))),
+            handshake_bans: config.handshake_ban.clone().map(|policy| Arc::new(HandshakeBans::new(policy))),
+            rpc_breaker,
+            submission_slots: config.inflight_submission_limit.as_ref().map(|limit| Arc::new(Semaphore::new(limit.max_inflight))),
+            propagation_latency: Arc::new(std::sync::Mutex::new(LatencyHistogram::new())),
+            dead_letters: config
+                .dead_letter_path
+                .as_ref()
+                .map(|path| DeadLetterWriter::spawn(
+                    DeadLetterLog::new(path, config.dead_letter_max_bytes).with_compression(config.persistence_compression),
+                    DEAD_LETTER_QUEUE,
+                )),
+            submitters: Arc::new(std::sync::Mutex::new(LruCache::new(NonZeroUsize::new(MAX_TRACKED_SUBMISSIONS).unwrap()))),
+            awaiting_acceptance: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            wtxid_index: Arc::new(std::sync::Mutex::new(LruCache::new(NonZeroUsize::new(MAX_INDEXED_WTXIDS).unwrap()))),
+            peer_requests: Arc::new(PeerRequestLimiter::new(MAX_PEER_REQUESTS_PER_MINUTE, std::time::Duration::from_secs(60))),
+            requested_announcements: Arc::new(std::sync::Mutex::new(LruCache::new(NonZeroUsize::new(MAX_REQUESTED_ANNOUNCEMENTS).unwrap()))),
+            rejected_remote: Arc::new(std::sync::Mutex::new(LruCache::new(NonZeroUsize::new(MAX_REJECTED_REMOTE).unwrap()))),
+            announcement_batches: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            announcement_requests: Arc::new(PeerRequestLimiter::new(MAX_PEER_REQUESTS_PER_MINUTE, std::time::Duration::from_secs(60))),
+            seen_broadcast_events: Arc::new(std::sync::Mutex::new(LruCache::new(NonZeroUsize::new(MAX_SEEN_BROADCAST_EVENTS).unwrap()))),
+            client_submissions: Arc::new(std::sync::Mutex::new(LruCache::new(NonZeroUsize::new(MAX_CLIENT_SUBMISSIONS).unwrap()))),
+            pending_replays: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            tx_hops: Arc::new(std::sync::Mutex::new(LruCache::new(NonZeroUsize::new(MAX_TRACKED_HOPS).unwrap()))),
+            self_echoes_dropped: Arc::new(AtomicU64::new(0)),
+            duplicates_dropped: Arc::new(AtomicU64::new(0)),
+            hop_limit_dropped: Arc::new(AtomicU64::new(0)),
+            stats: Arc::new(StatsCounters::default()),
             validator,
             config,
         })
     }
     
+    /// Also submit transactions to another node
+    pub fn with_additional_node(mut self, label: impl Into<String>, node: Arc<dyn BitcoinRpc>) -> Self {
+        self.additional_nodes.push((label.into(), node));
+        self
+    }
+    
+    /// Share a submission limiter with other components submitting to the same node
+    pub fn with_submission_limiter(mut self, limiter: Arc<SubmissionLimiter>) -> Self {
+        self.submission_limiter = Some(limiter);
+        self
+    }
+    
     /// Start the relay server on the given address
+    /// 
+    /// Returns after `shutdown`, saving the dedup snapshot if one is configured. Signals are
+    /// left to the embedder, e.g. call `shutdown` on Ctrl-C.
     pub async fn run(self) -> Result<()> {
+        if self.config.preflight_checks {
+            self.preflight().await?;
+        }
+        self.check_node_network().await?;
+        self.check_node_connections().await;
+        
+        match self.load_dedup_snapshot().await {
+            Ok(0) => {}
+            Ok(restored) => info!("Relay-{}: Restored {} dedup entries from snapshot", self.config.relay_id, restored),
+            Err(e) => warn!("Relay-{}: Could not load dedup snapshot: {}", self.config.relay_id, e),
+        }
+        
         let addr = self.config.websocket_listen_addr;
-        let listener = TcpListener::bind(addr).await?;
+        let listener = self.bind_listener()?;
         info!("Relay-{} Bitcoin Transaction Relay Server listening on {}", self.config.relay_id, addr);
         
+        if let Some(path) = &self.config.unix_socket_path {
+            #[cfg(unix)]
+            {
+                let unix_listener = bind_unix_listener(path)?;
+                info!("Relay-{}: Also listening on Unix socket {}", self.config.relay_id, path.display());
+                let server_clone = self.clone();
+                tokio::spawn(async move {
+                    server_clone.accept_unix_clients(unix_listener).await;
+                });
+            }
+            #[cfg(not(unix))]
+            warn!("Relay-{}: Unix socket {} ignored, Unix sockets are not supported on this platform", self.config.relay_id, path.display());
+        }
+        
         // Start mempool monitoring task
         let server_clone = self.clone();
         tokio::spawn(async move {
@@ -74,6 +424,14 @@ impl RelayServer {
             }
         });
         
+        // Start block monitoring task
+        if let Some(min_depth) = self.config.broadcast_block_tx_min_depth {
+            let server_clone = self.clone();
+            tokio::spawn(async move {
+                server_clone.monitor_blocks(min_depth).await;
+            });
+        }
+        
         // Start strfry client connection task
         let server_clone = self.clone();
         tokio::spawn(async move {
@@ -82,70 +440,364 @@ impl RelayServer {
             }
         });
         
-        while let Ok((stream, peer_addr)) = listener.accept().await {
-            info!("New client connection from {}", peer_addr);
-            let server = self.clone();
+        // Start peer discovery announcements
+        if self.config.discovery.enabled {
+            let server_clone = self.clone();
             tokio::spawn(async move {
-                if let Err(e) = server.handle_connection(stream, peer_addr).await {
-                    error!("Error handling connection from {}: {}", peer_addr, e);
-                }
+                server_clone.announce_discovery().await;
             });
         }
         
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let Ok((stream, peer_addr)) = accepted else { break };
+                    if self.is_handshake_banned(peer_addr) {
+                        debug!("Dropping connection from banned client {}", peer_addr);
+                        continue;
+                    }
+                    info!("New client connection from {}", peer_addr);
+                    let server = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = server.handle_connection(stream, peer_addr).await {
+                            error!("Error handling connection from {}: {}", peer_addr, e);
+                        }
+                    });
+                }
+                _ = self.relay_id_conflict_notify.notified() => {
+                    error!("Relay-{}: Shutting down, relay_id is already in use on the network", self.config.relay_id);
+                    self.save_dedup_snapshot_logged().await;
+                    return Err(crate::ConfigError::duplicate_relay_id(&self.config.relay_id).into());
+                }
+                _ = self.shutdown_notify.notified() => {
+                    info!("Relay-{}: Shutting down", self.config.relay_id);
+                    break;
+                }
+            }
+        }
+        
+        self.save_dedup_snapshot_logged().await;
+        Ok(())
+    }
+    
+    /// Ask a running server to stop accepting connections and return from `run`
+    pub fn shutdown(&self) {
+        self.shutdown_notify.notify_one();
+    }
+    
+    /// Current dedup state
+    pub async fn dedup_snapshot(&self) -> DedupSnapshot {
+        let to_snapshot = |seen: &HashMap<Txid, u64>| seen.iter().map(|(txid, recorded)| (txid.to_string(), *recorded)).collect();
+        DedupSnapshot {
+            remote_transactions: to_snapshot(&*self.remote_transactions.read().await),
+            broadcast_transactions: to_snapshot(&*self.broadcast_txids.read().await),
+        }
+    }
+    
+    /// Merge a snapshot into the dedup state, returning the number of entries restored
+    ///
+    /// Entries whose key isn't a valid txid are dropped.
+    pub async fn restore_dedup_snapshot(&self, snapshot: DedupSnapshot) -> usize {
+        let remote = parse_seen_txids(snapshot.remote_transactions);
+        let broadcast = parse_seen_txids(snapshot.broadcast_transactions);
+        let restored = remote.len() + broadcast.len();
+        self.remote_transactions.write().await.extend(remote);
+        self.broadcast_txids.write().await.extend(broadcast);
+        restored
+    }
+    
+    /// Save the dedup state to the configured snapshot path, if any
+    pub async fn save_dedup_snapshot(&self) -> Result<()> {
+        let Some(path) = &self.config.dedup_snapshot_path else {
+            return Ok(());
+        };
+        let mut snapshot = self.dedup_snapshot().await;
+        snapshot.expire(self.config.dedup_snapshot_ttl, unix_time_ms());
+        snapshot.save_compressed(path, self.config.persistence_compression)
+    }
+    
+    /// Restore unexpired dedup state from the configured snapshot path, if any
+    pub async fn load_dedup_snapshot(&self) -> Result<usize> {
+        let Some(path) = &self.config.dedup_snapshot_path else {
+            return Ok(0);
+        };
+        let snapshot = DedupSnapshot::load(path, self.config.dedup_snapshot_ttl, unix_time_ms())?;
+        Ok(self.restore_dedup_snapshot(snapshot).await)
+    }
+    
+    async fn save_dedup_snapshot_logged(&self) {
+        if let Err(e) = self.save_dedup_snapshot().await {
+            error!("Relay-{}: Failed to save dedup snapshot: {}", self.config.relay_id, e);
+        }
+    }
+    
+    /// Refuse to run against a Bitcoin node on a different chain than configured
+    /// 
+    /// An unreachable node is only logged, since the relay tolerates the node
+    /// coming up later.
+    async fn check_node_network(&self) -> Result<()> {
+        let Some(network) = self.config.network else {
+            return Ok(());
+        };
+        
+        match self.bitcoin_client.get_chain().await {
+            Ok(chain) if network.matches_chain(&chain) => Ok(()),
+            Ok(chain) => {
+                error!("Relay-{}: Bitcoin node is on chain \"{}\" but relay is configured for {:?}", self.config.relay_id, chain, network);
+                Err(crate::ConfigError::network_mismatch(network, chain).into())
+            }
+            Err(e) => {
+                warn!("Relay-{}: Could not verify Bitcoin node network: {}", self.config.relay_id, e);
+                Ok(())
+            }
+        }
+    }
+    
+    /// Warn if the node has no p2p peers, since transactions it accepts then go no further
+    async fn check_node_connections(&self) {
+        match self.bitcoin_client.get_network_info().await {
+            Ok(info) if !info.network_active => {
+                warn!("Relay-{}: Bitcoin node has p2p networking disabled and will not relay transactions to peers", self.config.relay_id);
+            }
+            Ok(info) if info.connections == 0 => {
+                warn!("Relay-{}: Bitcoin node has no p2p connections and will not relay transactions to peers", self.config.relay_id);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Relay-{}: Could not check Bitcoin node p2p connections: {}", self.config.relay_id, e),
+        }
+    }
+    
+    /// Check the strfry URL is a WebSocket URL and the Bitcoin RPC answers
+    async fn preflight(&self) -> Result<()> {
+        let strfry_url = url::Url::parse(&self.config.strfry_url).ok().filter(|url| matches!(url.scheme(), "ws" | "wss"));
+        if strfry_url.is_none() && self.config.event_bus.is_none() {
+            error!("Relay-{}: Strfry URL {} is not a ws:// or wss:// URL", self.config.relay_id, self.config.strfry_url);
+            return Err(crate::ConfigError::invalid_url(&self.config.strfry_url).into());
+        }
+        if let Err(e) = self.bitcoin_client.get_chain().await {
+            error!("Relay-{}: Bitcoin RPC at {} is unreachable: {}", self.config.relay_id, self.config.bitcoin_rpc_url, e);
+            return Err(crate::ConfigError::rpc_unreachable(&self.config.bitcoin_rpc_url, e).into());
+        }
         Ok(())
     }
     
+    /// WebSocket protocol limits so oversized frames are rejected before buffering
+    /// 
+    /// tungstenite has no permessage-deflate, so connections are never compressed.
+    fn websocket_config(&self) -> WebSocketConfig {
+        WebSocketConfig {
+            max_message_size: Some(self.config.websocket_max_message_size),
+            max_frame_size: Some(self.config.websocket_max_message_size),
+            ..Default::default()
+        }
+    }
+    
+    /// Bind the WebSocket listener with the configured accept backlog
+    /// 
+    /// Fails with `NetworkError::BindFailed`, e.g. when another process holds the port;
+    /// the cause is logged with a hint for the usual operator mistakes.
+    fn bind_listener(&self) -> Result<TcpListener> {
+        let addr = self.config.websocket_listen_addr;
+        let bind = || {
+            let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+            // Match `TcpListener::bind`, which allows quick restarts on the same port
+            #[cfg(unix)]
+            socket.set_reuseaddr(true)?;
+            socket.bind(addr)?;
+            socket.listen(self.config.listen_backlog)
+        };
+        bind().map_err(|e| {
+            error!("Relay-{}: Failed to listen on {}: {}", self.config.relay_id, addr, bind_failure_reason(&e));
+            NetworkError::bind_failed(addr).into()
+        })
+    }
+    
+    /// Accept WebSocket clients on the Unix socket until the listener fails
+    #[cfg(unix)]
+    async fn accept_unix_clients(&self, listener: tokio::net::UnixListener) {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    error!("Relay-{}: Unix socket accept failed: {}", self.config.relay_id, e);
+                    return;
+                }
+            };
+            let client_id = format!("unix:{}", self.next_local_client.fetch_add(1, Ordering::SeqCst));
+            info!("New client connection from {}", client_id);
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.serve_client(stream, client_id.clone()).await {
+                    error!("Error handling connection from {}: {}", client_id, e);
+                }
+            });
+        }
+    }
+    
     /// Handle a new WebSocket client connection
+    /// 
+    /// Failed handshakes are common scanner noise: they are logged at debug level and
+    /// count towards a temporary ban of the client's IP when one is configured.
     async fn handle_connection(&self, stream: TcpStream, peer_addr: SocketAddr) -> Result<()> {
-        let ws_stream = accept_async(stream).await?;
-        let client_id = peer_addr.to_string();
-        
+        // Nostr messages are small and latency-sensitive
+        stream.set_nodelay(self.config.tcp_nodelay)?;
+        let ws_stream = match accept_async_with_config(stream, Some(self.websocket_config())).await {
+            Ok(ws_stream) => ws_stream,
+            Err(e) => {
+                debug!("WebSocket handshake with {} failed: {}", peer_addr, e);
+                if let Some(bans) = &self.handshake_bans {
+                    if bans.record_failure(peer_addr.ip(), std::time::Instant::now()) {
+                        warn!("Banning {} after repeated failed WebSocket handshakes", peer_addr.ip());
+                    }
+                }
+                return Ok(());
+            }
+        };
+        if let Some(bans) = &self.handshake_bans {
+            bans.record_success(peer_addr.ip());
+        }
+        self.serve_websocket(ws_stream, peer_addr.to_string()).await
+    }
+    
+    /// Whether connections from this client are currently dropped for failed handshakes
+    fn is_handshake_banned(&self, peer_addr: SocketAddr) -> bool {
+        self.handshake_bans.as_ref().is_some_and(|bans| bans.is_banned(peer_addr.ip(), std::time::Instant::now()))
+    }
+    
+    /// Speak the WebSocket/Nostr protocol with a client over any byte stream
+    async fn serve_client<S>(&self, stream: S, client_id: String) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let ws_stream = accept_async_with_config(stream, Some(self.websocket_config())).await?;
+        self.serve_websocket(ws_stream, client_id).await
+    }
+    
+    /// Speak the Nostr protocol with a client whose WebSocket handshake is done
+    async fn serve_websocket<S>(&self, ws_stream: WebSocketStream<S>, client_id: String) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
         let (tx_sender, mut tx_receiver) = broadcast::channel(self.config.websocket_buffer_size);
         let mut global_receiver = self.tx_broadcaster.subscribe();
         self.clients.write().await.insert(client_id.clone(), tx_sender);
+        if self.config.auto_subscribe_new_clients {
+            let firehose = HashMap::from([(IMPLICIT_SUBSCRIPTION_ID.to_string(), vec![Filter::new()])]);
+            self.subscriptions.write().await.insert(client_id.clone(), firehose);
+        }
+        
+        let (frames, mut frame_receiver) = ClientFrames::new(CLIENT_FRAME_QUEUE);
+        let challenge = self.config.require_auth.then(new_challenge);
+        if let Some(challenge) = &challenge {
+            frames.send(json!(["AUTH", challenge]).to_string());
+        }
         
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
-        let server = self.clone();
+        let task_server = self.clone();
+        let task_client_id = client_id.clone();
         
         // Handle outgoing messages to client
         let broadcast_task = tokio::spawn(async move {
             loop {
+                // Biased so a response is never overtaken by a broadcast queued after it
                 tokio::select! {
+                    biased;
+                    Some(frame) = frame_receiver.recv() => {
+                        if let Err(e) = ws_sender.send(Message::Text(frame)).await {
+                            error!("Failed to send message to client: {}", e);
+                            break;
+                        }
+                    }
                     event = tx_receiver.recv() => {
                         match event {
                             Ok(event) => {
-                                let message = json!(["EVENT", "sub_id", event]).to_string();
+                                let message = json!(["EVENT", "sub_id", event.as_ref()]).to_string();
                                 if let Err(e) = ws_sender.send(Message::Text(message)).await {
                                     error!("Failed to send message to client: {}", e);
                                     break;
                                 }
                             }
-                            Err(_) => break,
+                            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                warn!("Client {} fell behind, {} responses dropped", task_client_id, skipped);
+                            }
+                            Err(broadcast::error::RecvError::Closed) => break,
                         }
                     }
                     event = global_receiver.recv() => {
                         match event {
                             Ok(event) => {
-                                let message = json!(["EVENT", "sub_id", event]).to_string();
-                                if let Err(e) = ws_sender.send(Message::Text(message)).await {
-                                    error!("Failed to send message to client: {}", e);
+                                // Broadcasts go out once per subscription whose filters match
+                                let matching: Vec<String> = task_server.subscriptions.read().await
+                                    .get(&task_client_id)
+                                    .map(|subscriptions| subscriptions.iter()
+                                        .filter(|(_, filters)| filters.iter().any(|filter| filter.match_event(&event)))
+                                        .map(|(subscription_id, _)| subscription_id.clone())
+                                        .collect())
+                                    .unwrap_or_default();
+                                let mut failed = false;
+                                for subscription_id in matching {
+                                    let message = json!(["EVENT", subscription_id, event.as_ref()]).to_string();
+                                    if let Err(e) = ws_sender.send(Message::Text(message)).await {
+                                        error!("Failed to send message to client: {}", e);
+                                        failed = true;
+                                        break;
+                                    }
+                                }
+                                if failed {
                                     break;
                                 }
                             }
-                            Err(_) => break,
+                            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                warn!("Client {} fell behind, {} broadcasts dropped", task_client_id, skipped);
+                            }
+                            Err(broadcast::error::RecvError::Closed) => break,
                         }
                     }
                 }
             }
         });
         
-        // Handle incoming messages from client
-        while let Some(msg) = ws_receiver.next().await {
+        // Messages are handled off the read loop so a slow submission can't hold up control frames like Close
+        let (message_sender, message_receiver) = mpsc::channel(CLIENT_MESSAGE_QUEUE);
+        tokio::spawn(self.clone().process_client_messages(message_receiver, client_id.clone(), frames.clone()));
+        
+        // Handle incoming messages from client; only these count as activity for the idle timeout
+        loop {
+            let read = async {
+                match self.config.client_idle_timeout {
+                    Some(idle_timeout) => tokio::time::timeout(idle_timeout, ws_receiver.next()).await.map_err(|_| idle_timeout),
+                    None => Ok(ws_receiver.next().await),
+                }
+            };
+            let next = tokio::select! {
+                next = read => match next {
+                    Ok(next) => next,
+                    Err(idle_timeout) => {
+                        info!("Client {} idle for {:?}, closing connection", client_id, idle_timeout);
+                        break;
+                    }
+                },
+                _ = frames.overflowed.notified() => {
+                    warn!("Client {} is not reading its replies, closing connection", client_id);
+                    break;
+                }
+            };
+            let Some(msg) = next else {
+                break;
+            };
             match msg? {
                 Message::Text(text) => {
-                    if let Err(e) = server.handle_nostr_message(&text, &client_id).await {
-                        error!("Error handling nostr message: {}", e);
+                    // Authenticate before queueing, so events sent right after AUTH see the result
+                    if let Some(challenge) = &challenge {
+                        if let Some(auth) = parse_auth_message(&text) {
+                            frames.send(self.authenticate_client(auth, challenge, &client_id).await);
+                            continue;
+                        }
                     }
+                    // Only fails if the message worker panicked
+                    let Ok(()) = message_sender.send(text).await else {
+                        break;
+                    };
                 }
                 Message::Close(_) => {
                     info!("Client {} disconnected", client_id);
@@ -156,12 +808,70 @@ impl RelayServer {
         }
         
         broadcast_task.abort();
-        self.clients.write().await.remove(&client_id);
+        self.forget_client(&client_id).await;
         Ok(())
     }
     
-    /// Handle incoming Nostr messages from clients
-    async fn handle_nostr_message(&self, message: &str, client_id: &str) -> Result<()> {
+    /// Stop sending responses and broadcasts to a client
+    /// 
+    /// Its remembered submissions are dropped too, since another client may reuse its id.
+    async fn forget_client(&self, client_id: &str) {
+        self.clients.write().await.remove(client_id);
+        self.subscriptions.write().await.remove(client_id);
+        self.authenticated_clients.write().await.remove(client_id);
+        
+        let mut submissions = self.client_submissions.lock().unwrap();
+        let forgotten: Vec<_> = submissions.iter().filter(|((id, _), _)| id == client_id).map(|(key, _)| key.clone()).collect();
+        for key in forgotten {
+            submissions.pop(&key);
+        }
+    }
+    
+    /// Check a client's NIP-42 AUTH response, returning the `OK` frame to answer it with
+    async fn authenticate_client(&self, auth: std::result::Result<Event, NostrError>, challenge: &str, client_id: &str) -> String {
+        let event = match auth {
+            Ok(event) => event,
+            Err(e) => return json!(["NOTICE", format!("invalid: {}", e)]).to_string(),
+        };
+        match verify_auth(&event, challenge, nostr::Timestamp::now()) {
+            Ok(pubkey) => {
+                info!("Client {} authenticated as {}", client_id, pubkey);
+                self.authenticated_clients.write().await.insert(client_id.to_string(), pubkey);
+                json!(["OK", event.id, true, ""]).to_string()
+            }
+            Err(e) => {
+                warn!("Client {} failed to authenticate: {}", client_id, e);
+                json!(["OK", event.id, false, format!("invalid: {}", e)]).to_string()
+            }
+        }
+    }
+    
+    /// Handle a client's messages in arrival order, `client_message_concurrency` at a time
+    /// 
+    /// Messages still queued when the client disconnects are handled before this returns.
+    /// Protocol replies, such as `CLOSED`, go out through `frames`.
+    async fn process_client_messages(self, mut messages: mpsc::Receiver<String>, client_id: String, frames: ClientFrames) {
+        let slots = Arc::new(Semaphore::new(self.config.client_message_concurrency.max(1)));
+        while let Some(text) = messages.recv().await {
+            let Ok(slot) = Arc::clone(&slots).acquire_owned().await else {
+                break;
+            };
+            let server = self.clone();
+            let client_id = client_id.clone();
+            let frames = frames.clone();
+            tokio::spawn(async move {
+                match server.handle_nostr_message(&text, &client_id).await {
+                    Ok(Some(reply)) => frames.send(reply),
+                    Ok(None) => {}
+                    Err(e) => error!("Error handling nostr message: {}", e),
+                }
+                drop(slot);
+            });
+        }
+    }
+    
+    /// Handle incoming Nostr messages from clients, returning a protocol frame to reply with, if any
+    async fn handle_nostr_message(&self, message: &str, client_id: &str) -> Result<Option<String>> {
         let parsed: Value = serde_json::from_str(message)?;
         
         if let Some(arr) = parsed.as_array() {
@@ -170,20 +880,56 @@ impl RelayServer {
                 
                 match msg_type {
                     "EVENT" => {
-                        if arr.len() >= 2 {
-                            let event: Event = serde_json::from_value(arr[1].clone())?;
-                            self.handle_event(event, client_id).await?;
-                        }
+                        let event: Event = serde_json::from_value(arr[1].clone())?;
+                        self.handle_event(event, client_id).await?;
                     }
                     "REQ" => {
-                        info!("Client {} subscribed", client_id);
+                        let Some(subscription_id) = arr[1].as_str().map(str::to_string) else {
+                            return Ok(Some(json!(["NOTICE", "invalid: subscription id must be a string"]).to_string()));
+                        };
+                        let mut filters = match arr[2..]
+                            .iter()
+                            .map(|filter| serde_json::from_value::<Filter>(filter.clone()))
+                            .collect::<std::result::Result<Vec<_>, _>>()
+                        {
+                            Ok(filters) => filters,
+                            Err(e) => {
+                                return Ok(Some(json!(["CLOSED", subscription_id, format!("invalid: bad filter: {}", e)]).to_string()));
+                            }
+                        };
+                        // A REQ without filters has always meant every broadcast
+                        if filters.is_empty() {
+                            filters.push(Filter::new());
+                        }
+                        let mut subscriptions = self.subscriptions.write().await;
+                        let client_subscriptions = subscriptions.entry(client_id.to_string()).or_default();
+                        // An explicit subscription replaces the one made on connect
+                        client_subscriptions.remove(IMPLICIT_SUBSCRIPTION_ID);
+                        let is_new = !client_subscriptions.contains_key(&subscription_id);
+                        if is_new && client_subscriptions.len() >= self.config.max_subscriptions_per_client {
+                            warn!("Client {} is over the subscription limit, closing {}", client_id, subscription_id);
+                            return Ok(Some(json!(["CLOSED", subscription_id, "error: too many subscriptions"]).to_string()));
+                        }
+                        info!("Client {} subscribed as {}", client_id, subscription_id);
+                        client_subscriptions.insert(subscription_id.clone(), filters);
+                        // Broadcasts are ephemeral, so there are never stored events to send first
+                        return Ok(Some(json!(["EOSE", subscription_id]).to_string()));
+                    }
+                    "CLOSE" => {
+                        let Some(subscription_id) = arr[1].as_str() else {
+                            return Ok(Some(json!(["NOTICE", "invalid: subscription id must be a string"]).to_string()));
+                        };
+                        info!("Client {} closed subscription {}", client_id, subscription_id);
+                        if let Some(client_subscriptions) = self.subscriptions.write().await.get_mut(client_id) {
+                            client_subscriptions.remove(subscription_id);
+                        }
                     }
                     _ => {}
                 }
             }
         }
         
-        Ok(())
+        Ok(None)
     }
     
     /// Handle specific Nostr events
@@ -191,7 +937,10 @@ impl RelayServer {
         let kind = event.kind.as_u32();
         match kind {
             k if k == KIND_SUBMIT_TX as u32 => self.handle_submit_tx(event, client_id).await,
+            k if k == KIND_FORWARD_TX as u32 => self.handle_forward_tx(event, client_id).await,
             k if k == KIND_REQUEST_TX as u32 => self.handle_request_tx(event, client_id).await,
+            k if k == KIND_SUBMIT_PACKAGE as u32 => self.handle_submit_package(event, client_id).await,
+            k if k == KIND_ESTIMATE_FEE as u32 => self.handle_estimate_fee(event, client_id).await,
             _ => {
                 warn!("Unhandled event kind: {}", event.kind.as_u32());
                 Ok(())
@@ -199,10 +948,159 @@ impl RelayServer {
         }
     }
     
+    /// Whether the event's author may submit transactions to this relay
+    fn is_authorized_submitter(&self, event: &Event) -> bool {
+        match &self.config.submitter_allowlist {
+            None => true,
+            // The pubkey is only meaningful if the event is actually signed by it
+            Some(allowlist) => allowlist.contains(&event.pubkey) && event.verify().is_ok(),
+        }
+    }
+    
+    /// Why a client's submission is refused, if it is
+    /// 
+    /// When authentication is required, the pubkey the connection authenticated as is
+    /// checked against the allowlist in place of the event's author.
+    async fn submission_refusal(&self, event: &Event, client_id: &str) -> Option<&'static str> {
+        const UNAUTHORIZED: &str = "Unauthorized submitter: pubkey is not allowed to submit transactions";
+        if !self.config.require_auth {
+            return (!self.is_authorized_submitter(event)).then_some(UNAUTHORIZED);
+        }
+        match self.authenticated_clients.read().await.get(client_id) {
+            None => Some("auth-required: authenticate with NIP-42 before submitting"),
+            Some(pubkey) if self.config.submitter_allowlist.as_ref().is_some_and(|allowlist| !allowlist.contains(pubkey)) => Some(UNAUTHORIZED),
+            Some(_) => None,
+        }
+    }
+    
     /// Handle transaction submission from clients
     async fn handle_submit_tx(&self, event: Event, client_id: &str) -> Result<()> {
         info!("🌐 Relay-{}: Received transaction via WEBSOCKET from {}", self.config.relay_id, client_id);
         
+        if let Some(refusal) = self.submission_refusal(&event, client_id).await {
+            warn!("Relay-{}: Rejected transaction from unauthorized submitter {}", self.config.relay_id, event.pubkey);
+            self.send_tx_response(client_id, event.id, ResponseCode::Unauthorized, refusal, "").await?;
+            return Ok(());
+        }
+        
+        if let Some(window) = self.config.client_resubmission_window {
+            if let Some(original) = self.record_client_submission(&event, client_id, window) {
+                return self.replay_response(original, client_id, event.id).await;
+            }
+        }
+        
+        let request_id = event.id;
+        let result = self.submit_tx(event, client_id, TxSource::Client).await;
+        // Resubmissions waiting on a submission that sent no response give up
+        self.pending_replays.lock().unwrap().remove(&request_id);
+        result
+    }
+    
+    /// Remember a client's submission, or return the earlier one if the client submitted
+    /// the same transaction within `window`
+    /// 
+    /// This is separate from the validator's cache of recently processed transactions,
+    /// which answers every client, and after the first, with `already_known`.
+    fn record_client_submission(&self, event: &Event, client_id: &str, window: std::time::Duration) -> Option<ClientSubmission> {
+        let key = (client_id.to_string(), sha256::Hash::hash(event.content.trim().as_bytes()));
+        let mut submissions = self.client_submissions.lock().unwrap();
+        if let Some(original) = submissions.get(&key) {
+            if original.submitted.elapsed() < window {
+                return Some(original.clone());
+            }
+        }
+        
+        let (sender, response) = watch::channel(None);
+        self.pending_replays.lock().unwrap().insert(event.id, sender);
+        submissions.put(key, ClientSubmission {
+            submitted: std::time::Instant::now(),
+            response,
+        });
+        None
+    }
+    
+    /// Answer a resubmission with the response to the original submission, waiting for it if needed
+    async fn replay_response(&self, mut original: ClientSubmission, client_id: &str, request_id: EventId) -> Result<()> {
+        info!("Relay-{}: Client {} resubmitted a transaction, replaying the original response", self.config.relay_id, client_id);
+        let content = match original.response.wait_for(Option::is_some).await {
+            Ok(content) => content.clone(),
+            Err(_) => None,
+        };
+        match content {
+            Some(content) => self.send_response(client_id, request_id, content).await,
+            None => self.send_tx_response(client_id, request_id, ResponseCode::AlreadyKnown, "Transaction recently processed", "").await,
+        }
+    }
+    
+    /// Handle a transaction forwarded by a peer relay
+    /// 
+    /// Only relays on `peer_relay_allowlist` may forward; without an allowlist all forwards
+    /// are refused. Client authentication and the submitter allowlist don't apply.
+    async fn handle_forward_tx(&self, event: Event, client_id: &str) -> Result<()> {
+        info!("🌐 Relay-{}: Received forwarded transaction via WEBSOCKET from {}", self.config.relay_id, client_id);
+        
+        if !self.is_trusted_peer_relay(&event) {
+            warn!("Relay-{}: Rejected transaction forwarded by unknown relay {}", self.config.relay_id, event.pubkey);
+            let message = "Unknown peer relay: pubkey is not allowed to forward transactions";
+            self.send_tx_response(client_id, event.id, ResponseCode::Unauthorized, message, "").await?;
+            return Ok(());
+        }
+        
+        let tx = hex::decode(event.content.trim()).ok().and_then(|bytes| deserialize::<Transaction>(&bytes).ok());
+        let reported = self.config.tag_namespace.value(&event, HOPS_TAG).and_then(|hops| hops.parse().ok()).unwrap_or(0);
+        let hops = match &tx {
+            Some(tx) => self.sender_hops(tx.txid(), reported),
+            None => reported,
+        };
+        if self.exceeds_hop_limit(hops) {
+            debug!("Relay-{}: Dropping transaction forwarded after {} hops", self.config.relay_id, hops);
+            self.send_tx_response(client_id, event.id, ResponseCode::InvalidRequest, "Hop limit exceeded", "").await?;
+            return Ok(());
+        }
+        if let Some(tx) = tx {
+            self.tx_hops.lock().unwrap().put(tx.txid(), hops.saturating_add(1));
+        }
+        
+        self.submit_tx(event, client_id, TxSource::Peer).await
+    }
+    
+    /// Hop count a sender reported for `txid`, raised to what we already know of it
+    /// 
+    /// Hop counts are sender-supplied and can't be verified. A sender can't lower the count
+    /// of a transaction that already reached us by a longer path, but the hop limit only
+    /// reliably stops loops among relays that report honestly.
+    fn sender_hops(&self, txid: Txid, reported: u32) -> u32 {
+        let known = self.tx_hops.lock().unwrap().peek(&txid).copied().unwrap_or(0);
+        reported.max(known.saturating_sub(1))
+    }
+    
+    /// Whether a transaction that passed through `hops` relays before its sender is over
+    /// the hop limit, counting the drop if so
+    fn exceeds_hop_limit(&self, hops: u32) -> bool {
+        let exceeded = self.config.max_hops.is_some_and(|max_hops| hops.saturating_add(1) > max_hops);
+        if exceeded {
+            self.hop_limit_dropped.fetch_add(1, Ordering::Relaxed);
+            self.count(Metric::HopLimitDropped);
+        }
+        exceeded
+    }
+    
+    /// Whether a forwarded event is signed by the identity key of an allowlisted relay
+    /// 
+    /// Discovery announcements are unauthenticated, so being in the roster is not enough:
+    /// without an allowlist no relay may forward.
+    fn is_trusted_peer_relay(&self, event: &Event) -> bool {
+        if event.verify().is_err() {
+            return false;
+        }
+        self.config
+            .peer_relay_allowlist
+            .as_ref()
+            .is_some_and(|allowlist| allowlist.contains(&event.pubkey))
+    }
+    
+    /// Validate, submit, publish and gossip a submitted transaction, answering the sender
+    async fn submit_tx(&self, event: Event, client_id: &str, source: TxSource) -> Result<()> {
         let tx_hex = event.content.trim();
         
         // Validate transaction
@@ -211,11 +1109,12 @@ impl RelayServer {
                 // Validation passed, continue to submission
             }
             Err(ValidationError::RecentlyProcessed { txid: _ }) => {
-                self.send_tx_response(client_id, false, "Transaction recently processed", "").await?;
+                self.send_tx_response(client_id, event.id, ResponseCode::AlreadyKnown, "Transaction recently processed", "").await?;
                 return Ok(());
             }
             Err(e) => {
-                self.send_tx_response(client_id, false, &e.to_string(), "").await?;
+                self.count(Metric::ValidationFailed);
+                self.send_tx_response(client_id, event.id, (&e).into(), &e.to_string(), "").await?;
                 return Ok(());
             }
         }
@@ -225,71 +1124,521 @@ impl RelayServer {
             Ok(tx_bytes) => {
                 match deserialize::<Transaction>(&tx_bytes) {
                     Ok(tx) => {
-                        let txid = tx.txid().to_string();
+                        let txid = tx.txid();
                         info!("Decoded transaction: {}", txid);
+                        // Subscribe before submitting so a fast peer acceptance isn't missed
+                        let acceptances = self.peer_acceptances.subscribe();
+                        // Claimed before submitting so the mempool monitor doesn't also gossip it
+                        let claimed = self.broadcast_txids.write().await.insert(txid, unix_time_ms()).is_none();
                         
-                        match self.submit_to_bitcoin_node(tx_hex).await {
+                        match self.submit_to_bitcoin_node(tx_hex, source).await {
                             Ok(_) => {
-                                self.send_tx_response(client_id, true, "Transaction accepted", &txid).await?;
+                                if claimed {
+                                    self.broadcast_txids.write().await.insert(txid, unix_time_ms());
+                                    self.publish_transaction(&tx, source);
+                                }
+                                self.send_tx_response(client_id, event.id, ResponseCode::Accepted, "Transaction accepted", &txid.to_string()).await?;
+                                // Gossiped after answering so the submitter sees its response first
+                                if claimed {
+                                    if self.config.propagation_ack_timeout.is_some() {
+                                        self.awaiting_acceptance.lock().unwrap().insert(txid);
+                                    }
+                                    if let Err(e) = self.broadcast_transaction(&tx).await {
+                                        error!("Relay-{}: Failed to broadcast transaction {}: {}", self.config.relay_id, txid, e);
+                                    }
+                                }
+                                if let Some(delay) = self.config.residency_check_delay {
+                                    let server = self.clone();
+                                    let client_id = client_id.to_string();
+                                    let request_id = event.id;
+                                    tokio::spawn(async move {
+                                        if let Err(e) = server.confirm_residency(&client_id, request_id, txid, delay).await {
+                                            warn!("Relay-{}: Failed to send residency response for {}: {}", server.config.relay_id, txid, e);
+                                        }
+                                    });
+                                }
+                                if self.config.replacement_notifications {
+                                    self.submitters.lock().unwrap().put(txid, TrackedSubmission {
+                                        client_id: client_id.to_string(),
+                                        request_id: event.id,
+                                        outpoints: tx.input.iter().map(|input| input.previous_output).collect(),
+                                    });
+                                }
+                                if let Some(timeout) = self.config.propagation_ack_timeout {
+                                    let server = self.clone();
+                                    let client_id = client_id.to_string();
+                                    let request_id = event.id;
+                                    tokio::spawn(async move {
+                                        if let Err(e) = server.confirm_propagation(acceptances, &client_id, request_id, txid, timeout).await {
+                                            warn!("Relay-{}: Failed to send propagation response for {}: {}", server.config.relay_id, txid, e);
+                                        }
+                                    });
+                                }
                             }
                             Err(e) => {
-                                error!("Failed to submit transaction to Bitcoin node: {}", e);
-                                self.send_tx_response(client_id, false, &e.to_string(), &txid).await?;
+                                if claimed {
+                                    self.broadcast_txids.write().await.remove(&txid);
+                                }
+                                if let Some(rejection) = crate::validation::replacement_rejection(&e) {
+                                    info!("Relay-{}: Replacement {} rejected: {}", self.config.relay_id, txid, rejection);
+                                    self.send_tx_response(client_id, event.id, (&rejection).into(), &rejection.to_string(), &txid.to_string()).await?;
+                                } else {
+                                    error!("Failed to submit transaction to Bitcoin node: {}", e);
+                                    self.send_tx_response(client_id, event.id, (&e).into(), &e.to_string(), &txid.to_string()).await?;
+                                }
                             }
                         }
                     }
                     Err(e) => {
+                        self.count(Metric::ValidationFailed);
                         error!("Failed to deserialize transaction: {}", e);
-                        self.send_tx_response(client_id, false, "Invalid transaction format", "").await?;
+                        self.send_tx_response(client_id, event.id, ResponseCode::InvalidTransaction, "Invalid transaction format", "").await?;
                     }
                 }
             }
             Err(e) => {
+                self.count(Metric::ValidationFailed);
                 error!("Failed to decode transaction hex: {}", e);
-                self.send_tx_response(client_id, false, "Invalid hex encoding", "").await?;
+                self.send_tx_response(client_id, event.id, ResponseCode::InvalidHex, "Invalid hex encoding", "").await?;
             }
         }
         
         Ok(())
     }
     
-    /// Submit a transaction to the Bitcoin node
-    async fn submit_to_bitcoin_node(&self, tx_hex: &str) -> Result<String> {
-        self.bitcoin_client.send_raw_transaction(tx_hex).await
-    }
-    
-    /// Send a transaction response back to the client
-    async fn send_tx_response(&self, client_id: &str, success: bool, message: &str, txid: &str) -> Result<()> {
-        let content = json!({
-            "success": success,
-            "message": message,
-            "txid": txid
-        });
-        
-        let event = EventBuilder::new(
-            Kind::Ephemeral(KIND_TX_RESPONSE),
-            content.to_string(),
-            &[]
-        ).to_event(&self.keys)?;
+    /// Handle a package of related transactions (JSON array of hexes, parents first) from clients
+    /// 
+    /// The whole package is validated before any of it reaches the node; if a transaction
+    /// fails, every failure is reported and nothing is submitted.
+    async fn handle_submit_package(&self, event: Event, client_id: &str) -> Result<()> {
+        info!("🌐 Relay-{}: Received transaction package via WEBSOCKET from {}", self.config.relay_id, client_id);
         
-        if let Some(sender) = self.clients.read().await.get(client_id) {
-            let _ = sender.send(event);
+        if let Some(refusal) = self.submission_refusal(&event, client_id).await {
+            warn!("Relay-{}: Rejected package from unauthorized submitter {}", self.config.relay_id, event.pubkey);
+            self.send_tx_response(client_id, event.id, ResponseCode::Unauthorized, refusal, "").await?;
+            return Ok(());
         }
         
-        Ok(())
-    }
+        let tx_hexes: Vec<String> = match serde_json::from_str::<Vec<String>>(&event.content) {
+            Ok(tx_hexes) => tx_hexes.iter().map(|tx_hex| tx_hex.trim().to_string()).collect(),
+            Err(_) => {
+                self.send_tx_response(client_id, event.id, ResponseCode::InvalidRequest, "Package must be a JSON array of transaction hexes", "").await?;
+                return Ok(());
+            }
+        };
+        if tx_hexes.is_empty() || tx_hexes.len() > MAX_PACKAGE_COUNT {
+            let message = format!("Package must contain between 1 and {} transactions", MAX_PACKAGE_COUNT);
+            self.send_tx_response(client_id, event.id, ResponseCode::InvalidRequest, &message, "").await?;
+            return Ok(());
+        }
+        
+        let mut txids = Vec::with_capacity(tx_hexes.len());
+        let mut txs = Vec::with_capacity(tx_hexes.len());
+        for tx_hex in &tx_hexes {
+            match hex::decode(tx_hex).ok().and_then(|bytes| deserialize::<Transaction>(&bytes).ok()) {
+                Some(tx) => {
+                    txids.push(tx.txid());
+                    txs.push(tx);
+                }
+                None => {
+                    self.send_tx_response(client_id, event.id, ResponseCode::InvalidTransaction, "Invalid transaction format", "").await?;
+                    return Ok(());
+                }
+            }
+        }
+        // The child is the package's identifying transaction
+        let package_txid = txids.last().map(Txid::to_string).unwrap_or_default();
+        
+        // The node reports transactions it already has, so recently processed ones still go to it
+        let tx_hex_refs: Vec<&str> = tx_hexes.iter().map(String::as_str).collect();
+        let failures: Vec<(Txid, ValidationError)> = self.validator.validate_batch(&tx_hex_refs).await
+            .into_iter()
+            .zip(&txids)
+            .filter_map(|(result, txid)| match result {
+                Ok(()) | Err(ValidationError::RecentlyProcessed { .. }) => None,
+                Err(e) => Some((*txid, e)),
+            })
+            .collect();
+        if let Some((_, first)) = failures.first() {
+            for _ in &failures {
+                self.count(Metric::ValidationFailed);
+            }
+            let failed: Vec<String> = failures.iter().map(|(txid, e)| format!("{}: {}", txid, e)).collect();
+            let message = format!("Package failed validation: {}", failed.join("; "));
+            self.send_tx_response(client_id, event.id, first.into(), &message, &package_txid).await?;
+            return Ok(());
+        }
+        
+        if let Some(limiter) = &self.submission_limiter {
+            if let Err(e) = limiter.acquire().await {
+                self.send_tx_response(client_id, event.id, (&e).into(), &e.to_string(), &package_txid).await?;
+                return Ok(());
+            }
+        }
+        let _slot = match self.acquire_submission_slot().await {
+            Ok(slot) => slot,
+            Err(e) => {
+                self.send_tx_response(client_id, event.id, (&e).into(), &e.to_string(), &package_txid).await?;
+                return Ok(());
+            }
+        };
+        
+        match self.bitcoin_client.submit_package(&tx_hexes).await {
+            Ok(result) if result.is_success() => {
+                for tx in &txs {
+                    self.count(Metric::NodeSubmissionAccepted);
+                    self.publish_transaction(tx, TxSource::Client);
+                }
+                self.send_tx_response(client_id, event.id, ResponseCode::Accepted, "Package accepted", &package_txid).await?;
+            }
+            Ok(result) => {
+                for (txid, error) in result.rejected() {
+                    self.count(Metric::NodeSubmissionFailed);
+                    if let Some(position) = txids.iter().position(|known| known.to_string() == txid) {
+                        self.record_dead_letter(&tx_hexes[position], TxSource::Client, error);
+                    }
+                }
+                let rejected: Vec<String> = result
+                    .rejected()
+                    .into_iter()
+                    .map(|(txid, error)| format!("{}: {}", txid, error))
+                    .collect();
+                let code = result.rejected().first().map_or(ResponseCode::NodeRejected, |(_, error)| ResponseCode::for_core_reason(error));
+                let message = format!("Package rejected ({}): {}", result.package_msg, rejected.join("; "));
+                self.send_tx_response(client_id, event.id, code, &message, &package_txid).await?;
+            }
+            Err(e) => {
+                error!("Failed to submit package to Bitcoin node: {}", e);
+                for tx_hex in &tx_hexes {
+                    self.count(Metric::NodeSubmissionFailed);
+                    if !is_backpressure(&e) {
+                        self.record_dead_letter(tx_hex, TxSource::Client, &e.to_string());
+                    }
+                }
+                self.send_tx_response(client_id, event.id, (&e).into(), &e.to_string(), &package_txid).await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Validate a transaction, submit it to the Bitcoin node and gossip it, reporting what happened
+    /// 
+    /// For embedders driving a running server. The transaction goes to strfry and subscribed
+    /// clients unless it was gossiped before, and the mempool monitor won't gossip it a second
+    /// time. A transaction the node already has is reported with `submitted` false rather than
+    /// as an error, and a failed broadcast with `broadcast` false.
+    pub async fn submit_transaction(&self, tx_hex: &str) -> Result<SubmitOutcome> {
+        let tx_hex = tx_hex.trim();
+        if let Err(e) = self.validator.validate(tx_hex).await {
+            if !matches!(e, ValidationError::RecentlyProcessed { .. }) {
+                self.count(Metric::ValidationFailed);
+            }
+            return Err(e.into());
+        }
+        let tx: Transaction = deserialize(&hex::decode(tx_hex)?)?;
+        let txid = tx.txid();
+        
+        // Claimed before submitting so a mempool monitor tick in between neither gossips nor forgets it
+        let claimed = self.broadcast_txids.write().await.insert(txid, unix_time_ms()).is_none();
+        let submitted = match self.submit_to_bitcoin_node(tx_hex, TxSource::Client).await {
+            Ok(_) => true,
+            Err(e) if ResponseCode::from(&e) == ResponseCode::AlreadyKnown => false,
+            Err(e) => {
+                if claimed {
+                    self.broadcast_txids.write().await.remove(&txid);
+                }
+                return Err(crate::validation::replacement_rejection(&e).map_or(e, Into::into));
+            }
+        };
+        
+        // Only the submission that claimed the txid gossips it, as for client submissions
+        let broadcast = claimed && {
+            // Refreshed in case a poll from before the node had it dropped the claim
+            self.broadcast_txids.write().await.insert(txid, unix_time_ms());
+            self.publish_transaction(&tx, TxSource::Client);
+            match self.broadcast_transaction(&tx).await {
+                Ok(()) => true,
+                Err(e) => {
+                    error!("Relay-{}: Failed to broadcast transaction {}: {}", self.config.relay_id, txid, e);
+                    false
+                }
+            }
+        };
+        Ok(SubmitOutcome {
+            txid: txid.to_string(),
+            validated: self.validator.config().enable_validation,
+            submitted,
+            broadcast,
+        })
+    }
+    
+    /// Gossip a transaction without submitting it to the node, returning its txid
+    /// 
+    /// For embedders driving a running server. The mempool monitor won't gossip it a second time.
+    pub async fn gossip_transaction(&self, tx_hex: &str) -> Result<String> {
+        let tx: Transaction = deserialize(&hex::decode(tx_hex.trim())?)?;
+        let txid = tx.txid();
+        self.broadcast_txids.write().await.insert(txid, unix_time_ms());
+        self.broadcast_transaction(&tx).await?;
+        Ok(txid.to_string())
+    }
+    
+    /// Submit a transaction to the Bitcoin node
+    /// 
+    /// Failures are recorded in the dead-letter log, if one is configured.
+    async fn submit_to_bitcoin_node(&self, tx_hex: &str, source: TxSource) -> Result<String> {
+        let result = async {
+            if let Some(limiter) = &self.submission_limiter {
+                limiter.acquire().await?;
+            }
+            let _slot = self.acquire_submission_slot().await?;
+            send_raw_transaction_to_all(self.bitcoin_client.as_ref(), &self.additional_nodes, tx_hex).await
+        }.await;
+        match &result {
+            Ok(_) => self.count(Metric::NodeSubmissionAccepted),
+            Err(e) => {
+                self.count(Metric::NodeSubmissionFailed);
+                // Turned away by our own limits rather than refused by the node
+                if !is_backpressure(e) {
+                    self.record_dead_letter(tx_hex, source, &e.to_string());
+                }
+            }
+        }
+        result
+    }
+    
+    /// Append a transaction the node did not accept to the dead-letter log, unless the node already had it
+    fn record_dead_letter(&self, tx_hex: &str, source: TxSource, error: &str) {
+        let Some(log) = &self.dead_letters else {
+            return;
+        };
+        if ResponseCode::for_core_reason(error) == ResponseCode::AlreadyKnown {
+            return;
+        }
+        let letter = DeadLetter {
+            txid: hex::decode(tx_hex)
+                .ok()
+                .and_then(|bytes| deserialize::<Transaction>(&bytes).ok())
+                .map(|tx| tx.txid().to_string())
+                .unwrap_or_default(),
+            hex: tx_hex.to_string(),
+            source,
+            error: error.to_string(),
+            timestamp: unix_time_ms(),
+        };
+        let txid = letter.txid.clone();
+        if !log.write(letter) {
+            warn!("Relay-{}: Dead-letter queue full, dropping {}", self.config.relay_id, txid);
+        }
+    }
+    
+    /// Wait briefly for a free submission slot, failing with `ServerBusy` when none frees up
+    async fn acquire_submission_slot(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        let (Some(slots), Some(limit)) = (&self.submission_slots, &self.config.inflight_submission_limit) else {
+            return Ok(None);
+        };
+        match tokio::time::timeout(limit.acquire_timeout, Arc::clone(slots).acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            _ => {
+                warn!("Relay-{}: All {} submission slots busy, turning submission away", self.config.relay_id, limit.max_inflight);
+                Err(crate::BitcoinRpcError::ServerBusy.into())
+            }
+        }
+    }
+    
+    /// Whether `txid` is still in the node's mempool after `delay`, or `None` if the node couldn't be asked
+    async fn is_resident_after(&self, txid: Txid, delay: std::time::Duration) -> Option<bool> {
+        tokio::time::sleep(delay).await;
+        match self.bitcoin_client.get_mempool_entry(&txid.to_string()).await {
+            Ok(_) => Some(true),
+            Err(crate::RelayError::BitcoinRpc(crate::BitcoinRpcError::BitcoinCore { code: -5, .. })) => {
+                warn!("Relay-{}: Accepted transaction {} is not in the mempool", self.config.relay_id, txid);
+                Some(false)
+            }
+            Err(e) => {
+                warn!("Relay-{}: Could not check whether {} is in the mempool: {}", self.config.relay_id, txid, e);
+                None
+            }
+        }
+    }
+    
+    /// Check `txid` is still in the mempool after `delay` and tell the client whether it is
+    ///
+    /// No follow-up is sent when the node can't be asked.
+    async fn confirm_residency(&self, client_id: &str, request_id: EventId, txid: Txid, delay: std::time::Duration) -> Result<()> {
+        let Some(resident) = self.is_resident_after(txid, delay).await else {
+            return Ok(());
+        };
+        let (code, message) = if resident {
+            (ResponseCode::Accepted, "Transaction still in the mempool")
+        } else {
+            (ResponseCode::Evicted, "Transaction no longer in the mempool")
+        };
+        let mut content = tx_response_content(code, message, &txid.to_string());
+        content["resident"] = json!(resident);
+        self.send_response(client_id, request_id, content).await
+    }
+    
+    /// Wait for a peer relay to report its node accepted `txid` and tell the client whether one did in time
+    async fn confirm_propagation(
+        &self,
+        mut acceptances: broadcast::Receiver<(Txid, String)>,
+        client_id: &str,
+        request_id: EventId,
+        txid: Txid,
+        timeout: std::time::Duration,
+    ) -> Result<()> {
+        let peer = tokio::time::timeout(timeout, async {
+            loop {
+                match acceptances.recv().await {
+                    Ok((accepted_txid, relay_id)) if accepted_txid == txid && relay_id != self.config.relay_id => return Some(relay_id),
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }).await.ok().flatten();
+        self.awaiting_acceptance.lock().unwrap().remove(&txid);
+        
+        let content = match peer {
+            Some(relay_id) => {
+                let message = format!("Propagation confirmed by relay {}", relay_id);
+                let mut content = tx_response_content(ResponseCode::PropagationConfirmed, &message, &txid.to_string());
+                content["propagation"] = json!("confirmed");
+                content["peer_relay_id"] = json!(relay_id);
+                content
+            }
+            None => {
+                let message = format!("No peer relay accepted the transaction within {:?}", timeout);
+                let mut content = tx_response_content(ResponseCode::PropagationTimedOut, &message, &txid.to_string());
+                content["propagation"] = json!("timed_out");
+                content
+            }
+        };
+        self.send_response(client_id, request_id, content).await
+    }
+    
+    /// Answer a fee estimate request with the node's `estimatesmartfee` result
+    /// 
+    /// When the node lacks fee data the response succeeds with a null `fee_rate`
+    /// and the node's reason in `errors`.
+    async fn handle_estimate_fee(&self, event: Event, client_id: &str) -> Result<()> {
+        let content = event.content.trim();
+        let request = if content.is_empty() {
+            Ok(FeeEstimateRequest::default())
+        } else {
+            serde_json::from_str::<FeeEstimateRequest>(content)
+        };
+        let request = match request {
+            Ok(request) => request,
+            Err(e) => {
+                let content = response_content(ResponseCode::InvalidRequest, &format!("Invalid fee estimate request: {}", e));
+                return self.send_response(client_id, event.id, content).await;
+            }
+        };
+        
+        let content = match self.bitcoin_client.estimate_smart_fee(request.conf_target, request.mode).await {
+            Ok(estimate) => json!({
+                "success": true,
+                "code": ResponseCode::Ok,
+                "conf_target": request.conf_target,
+                "mode": request.mode,
+                "fee_rate": estimate.fee_rate,
+                "sat_per_vbyte": estimate.sat_per_vbyte(),
+                "blocks": estimate.blocks,
+                "errors": estimate.errors
+            }),
+            Err(e) => response_content(ResponseCode::for_lookup_error(&e), &e.to_string()),
+        };
+        self.send_response(client_id, event.id, content).await
+    }
+    
+    /// Send a transaction response back to the client; only success codes report success
+    async fn send_tx_response(&self, client_id: &str, request_id: EventId, code: ResponseCode, message: &str, txid: &str) -> Result<()> {
+        self.send_response(client_id, request_id, tx_response_content(code, message, txid)).await
+    }
+    
+    /// Send a response event with the given content to the client
+    /// 
+    /// The response references the request event with an `e` tag so clients can
+    /// match it to their request. Fails with `NostrError::ClientGone` if the client disconnected or its
+    /// outgoing task stopped; such a client is forgotten.
+    async fn send_response(&self, client_id: &str, request_id: EventId, content: Value) -> Result<()> {
+        let event = EventBuilder::new(
+            Kind::Ephemeral(KIND_TX_RESPONSE),
+            content.to_string(),
+            &[Tag::Event(request_id, None, None)]
+        ).to_event(&self.content_keys)?;
+        if let Some(replays) = self.pending_replays.lock().unwrap().remove(&request_id) {
+            replays.send_replace(Some(content));
+        }
+        
+        let delivered = match self.clients.read().await.get(client_id) {
+            Some(sender) => sender.send(Arc::new(event)).is_ok(),
+            None => false,
+        };
+        if !delivered {
+            warn!("Relay-{}: Client {} is gone, dropping response", self.config.relay_id, client_id);
+            self.forget_client(client_id).await;
+            return Err(NostrError::client_gone(client_id).into());
+        }
+        
+        Ok(())
+    }
     
-    /// Handle transaction lookup requests
-    async fn handle_request_tx(&self, _event: Event, client_id: &str) -> Result<()> {
+    /// Answer a client's request for transactions with one response per txid
+    /// 
+    /// Found transactions are returned as raw hex in the response's `hex` field.
+    /// A wtxid of a transaction the relay has handled is answered with that
+    /// transaction, its `txid` and the requested `wtxid`; other ids are looked up as txids.
+    async fn handle_request_tx(&self, event: Event, client_id: &str) -> Result<()> {
         info!("Transaction request from client {}", client_id);
-        // TODO: Implement transaction lookup
+        let request: TxRequest = match serde_json::from_str(&event.content) {
+            Ok(request) => request,
+            Err(e) => {
+                let content = response_content(ResponseCode::InvalidRequest, &format!("Invalid transaction request: {}", e));
+                return self.send_response(client_id, event.id, content).await;
+            }
+        };
+        
+        for txid in request.txids.iter().take(MAX_REQUESTED_TXIDS) {
+            let (resolved, raw_tx) = match parse_txid(txid) {
+                Ok(id) => {
+                    let resolved = self.txid_for_wtxid(&Wtxid::from_raw_hash(id.to_raw_hash()));
+                    (resolved, self.get_raw_transaction(&resolved.unwrap_or(id)).await)
+                }
+                Err(e) => (None, Err(e.into())),
+            };
+            let content = match (raw_tx, resolved) {
+                (Ok(hex), Some(resolved)) => {
+                    let mut content = tx_response_content(ResponseCode::Ok, "Transaction found", &resolved.to_string());
+                    content["wtxid"] = json!(txid);
+                    content["hex"] = json!(hex);
+                    content
+                }
+                (Ok(hex), None) => {
+                    let mut content = tx_response_content(ResponseCode::Ok, "Transaction found", txid);
+                    content["hex"] = json!(hex);
+                    content
+                }
+                (Err(e), _) => tx_response_content(ResponseCode::for_lookup_error(&e), &e.to_string(), txid),
+            };
+            self.send_response(client_id, event.id, content).await?;
+        }
+        
         Ok(())
     }
     
+    /// Txid of a segwit transaction the relay has handled, by its wtxid
+    fn txid_for_wtxid(&self, wtxid: &Wtxid) -> Option<Txid> {
+        self.wtxid_index.lock().unwrap().get(wtxid).copied()
+    }
+    
     /// Monitor the Bitcoin mempool for new transactions
     async fn monitor_mempool(&self) -> Result<()> {
         let mut known_txids = match self.get_mempool_txids().await {
             Ok(txids) => {
+                self.set_mempool_reachable(true);
                 info!("Relay-{}: Initialized with {} existing transactions in mempool", self.config.relay_id, txids.len());
                 txids.into_iter().collect()
             }
@@ -300,243 +1649,5357 @@ impl RelayServer {
         };
         
         info!("Relay-{}: Starting mempool monitoring", self.config.relay_id);
+        let min_interval = self.config.mempool_poll_interval;
+        let mut interval = AdaptivePollInterval::new(min_interval, self.config.max_mempool_poll_interval.unwrap_or(min_interval));
+        let mut last_best_block = if self.config.eviction_notifications || self.config.replacement_notifications {
+            self.bitcoin_client.get_best_block_hash().await.ok()
+        } else {
+            None
+        };
+        let mut use_sequence = self.config.mempool_sequence_polling;
+        let mut last_sequence = None;
         
         loop {
-            match self.get_mempool_txids().await {
-                Ok(current_txids) => {
-                    for txid in &current_txids {
-                        if !known_txids.contains(txid) {
-                            let is_remote = {
-                                let remote_txs = self.remote_transactions.read().await;
-                                remote_txs.contains(txid)
-                            };
-                            
-                            if !is_remote {
-                                if let Ok(raw_tx) = self.get_raw_transaction(txid).await {
-                                    if let Ok(tx) = bitcoin::consensus::deserialize::<bitcoin::Transaction>(
-                                        &hex::decode(&raw_tx)?
-                                    ) {
-                                        if let Err(e) = self.broadcast_transaction(&tx, txid).await {
-                                            error!("Relay-{}: Failed to broadcast transaction {}: {}", self.config.relay_id, txid, e);
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            known_txids.insert(txid.clone());
+            let polled_at = unix_time_ms();
+            let polled = self.poll_mempool(&mut use_sequence).await;
+            self.set_mempool_reachable(polled.is_ok());
+            match polled {
+                Ok((_, Some(sequence))) if last_sequence == Some(sequence) => {
+                    // Nothing entered or left the mempool since the last diff
+                    let previous = interval.current();
+                    if interval.record_poll(false) != previous {
+                        debug!("Relay-{}: Polling mempool every {:?}", self.config.relay_id, interval.current());
+                    }
+                }
+                Ok((current_txids, sequence)) => {
+                    last_sequence = sequence;
+                    let current: HashSet<Txid> = current_txids.iter().copied().collect();
+                    let new_txids: Vec<Txid> = current_txids.iter().filter(|txid| !known_txids.contains(*txid)).copied().collect();
+                    let found_new = !new_txids.is_empty();
+                    self.relay_new_mempool_transactions(&new_txids).await?;
+                    known_txids.extend(new_txids);
+                    
+                    if self.config.eviction_notifications || self.config.replacement_notifications {
+                        let departed: Vec<Txid> = {
+                            let gossiped = self.broadcast_txids.read().await;
+                            let submitters = self.submitters.lock().unwrap();
+                            known_txids
+                                .iter()
+                                .filter(|txid| !current.contains(*txid))
+                                .filter(|txid| (self.config.eviction_notifications && gossiped.contains_key(*txid)) || submitters.contains(*txid))
+                                .copied()
+                                .collect()
+                        };
+                        if !departed.is_empty() {
+                            self.handle_departures(departed, &mut last_best_block).await;
                         }
                     }
                     
-                    known_txids.retain(|txid| current_txids.contains(txid));
+                    known_txids.retain(|txid| current.contains(txid));
+                    // Claims made since the poll are for submissions the snapshot may predate
+                    {
+                        let mut broadcast = self.broadcast_txids.write().await;
+                        let mut gossiped = self.gossiped_txids.lock().unwrap();
+                        broadcast.retain(|txid, recorded| {
+                            let keep = *recorded >= polled_at || current.contains(txid);
+                            if !keep {
+                                gossiped.put(*txid, ());
+                            }
+                            keep
+                        });
+                    }
+                    
+                    let previous = interval.current();
+                    if interval.record_poll(found_new) != previous {
+                        debug!("Relay-{}: Polling mempool every {:?}", self.config.relay_id, interval.current());
+                    }
                 }
                 Err(e) => {
                     error!("Relay-{}: Failed to get mempool: {}", self.config.relay_id, e);
                 }
             }
             
-            tokio::time::sleep(self.config.mempool_poll_interval).await;
+            let cutoff = unix_time_ms().saturating_sub(u64::try_from(self.config.dedup_snapshot_ttl.as_millis()).unwrap_or(u64::MAX));
+            {
+                let mut remote = self.remote_transactions.write().await;
+                let mut gossiped = self.gossiped_txids.lock().unwrap();
+                remote.retain(|txid, recorded| {
+                    let keep = *recorded >= cutoff;
+                    if !keep {
+                        gossiped.put(*txid, ());
+                    }
+                    keep
+                });
+            }
+            
+            tokio::time::sleep(interval.current()).await;
         }
     }
     
-    /// Get the list of transaction IDs from the mempool
-    async fn get_mempool_txids(&self) -> Result<Vec<String>> {
-        self.bitcoin_client.get_raw_mempool().await
+    /// Report transactions that left the mempool: to peers if they were gossiped, to their submitter if tracked
+    ///
+    /// Nothing is reported when recent blocks can't all be searched, so a confirmed
+    /// transaction is never reported as evicted.
+    async fn handle_departures(&self, departed: Vec<Txid>, last_best_block: &mut Option<BlockHash>) {
+        let confirmed = match self.recently_confirmed(last_best_block).await {
+            Ok(Some(confirmed)) => confirmed,
+            Ok(None) => {
+                warn!("Relay-{}: Too many blocks since the last check to tell confirmed from evicted transactions", self.config.relay_id);
+                return;
+            }
+            Err(e) => {
+                warn!("Relay-{}: Can't tell confirmed from evicted transactions: {}", self.config.relay_id, e);
+                return;
+            }
+        };
+        let evicted: Vec<Txid> = departed.into_iter().filter(|txid| !confirmed.contains(txid)).collect();
+        
+        if self.config.eviction_notifications {
+            let gossiped: Vec<Txid> = {
+                let broadcast = self.broadcast_txids.read().await;
+                evicted.iter().filter(|txid| broadcast.contains_key(*txid)).copied().collect()
+            };
+            if !gossiped.is_empty() {
+                self.notify_evictions(&gossiped).await;
+            }
+        }
+        
+        if self.config.replacement_notifications {
+            {
+                let mut submitters = self.submitters.lock().unwrap();
+                for txid in &confirmed {
+                    submitters.pop(txid);
+                }
+            }
+            for txid in evicted {
+                let Some(submission) = self.submitters.lock().unwrap().pop(&txid) else {
+                    continue;
+                };
+                self.notify_submitter(txid, submission).await;
+            }
+        }
     }
     
-    /// Get the raw transaction hex for a given transaction ID
-    async fn get_raw_transaction(&self, txid: &str) -> Result<String> {
-        self.bitcoin_client.get_raw_transaction(txid).await
+    /// Tell a client its transaction left the mempool unconfirmed, naming the replacement if the node has one
+    async fn notify_submitter(&self, txid: Txid, submission: TrackedSubmission) {
+        let replaced_by = self.mempool_spender(&txid.to_string(), &submission.outpoints).await;
+        let content = match replaced_by {
+            Some(replacement) => {
+                let message = format!("Transaction replaced by {}", replacement);
+                let mut content = tx_response_content(ResponseCode::Replaced, &message, &txid.to_string());
+                content["mempool"] = json!("replaced");
+                content["replaced_by"] = json!(replacement);
+                content
+            }
+            None => {
+                let mut content = tx_response_content(ResponseCode::Evicted, "Transaction left the mempool unconfirmed", &txid.to_string());
+                content["mempool"] = json!("evicted");
+                content
+            }
+        };
+        if let Err(e) = self.send_response(&submission.client_id, submission.request_id, content).await {
+            debug!("Relay-{}: Could not notify submitter of {}: {}", self.config.relay_id, txid, e);
+        }
     }
     
-    /// Broadcast a transaction to the Nostr network
-    async fn broadcast_transaction(&self, tx: &Transaction, txid: &str) -> Result<()> {
-        let content = json!({
-            "txid": txid,
-            "size": bitcoin::consensus::serialize(tx).len(),
-            "version": tx.version,
-            "inputs": tx.input.len(),
-            "outputs": tx.output.len(),
-            "hex": hex::encode(bitcoin::consensus::serialize(tx))
-        });
-        
-        let event = EventBuilder::new(
-            Kind::Ephemeral(KIND_TX_BROADCAST), 
-            content.to_string(),
-            &[
-                Tag::Hashtag("bitcoin".to_string()),
-                Tag::Hashtag("transaction".to_string()),
-                Tag::Generic(
-                    nostr::TagKind::Custom("relay_id".to_string()),
-                    vec![self.config.relay_id.clone()],
-                ),
-            ]
-        ).to_event(&self.keys)?;
-        
-        match self.send_to_strfry(&event).await {
-            Ok(_) => info!("📡 Relay-{}: Broadcasting transaction {} via Nostr", self.config.relay_id, txid),
-            Err(e) => error!("Relay-{}: Failed to broadcast transaction {} to strfry: {}", self.config.relay_id, txid, e),
+    /// Publish an eviction notice for gossiped transactions that left the mempool without confirming
+    async fn notify_evictions(&self, evicted: &[Txid]) {
+        info!("Relay-{}: {} gossiped transactions left the mempool unconfirmed", self.config.relay_id, evicted.len());
+        let event = match build_tx_eviction_with_namespace(evicted, &self.config.relay_id, &self.config.tag_namespace, self.config.network, &self.content_keys) {
+            Ok(event) => Arc::new(event),
+            Err(e) => {
+                error!("Relay-{}: Failed to build eviction notice: {}", self.config.relay_id, e);
+                return;
+            }
+        };
+        let gossip = match self.seal(&event, &self.content_keys) {
+            Ok(gossip) => gossip,
+            Err(e) => {
+                error!("Relay-{}: Failed to encrypt eviction notice: {}", self.config.relay_id, e);
+                return;
+            }
+        };
+        if let Err(e) = self.send_to_strfry(gossip).await {
+            warn!("Relay-{}: Failed to publish eviction notice: {}", self.config.relay_id, e);
         }
-        
-        let _ = self.tx_broadcaster.send(event.clone());
-        
-        Ok(())
+        let _ = self.tx_broadcaster.send(event);
     }
     
-    /// Send an event to the Strfry relay
-    async fn send_to_strfry(&self, event: &Event) -> Result<()> {
-        if let Err(_) = self.strfry_sender.send(event.clone()) {
-            return Err(crate::NostrError::SendFailed.into());
+    /// Tell peers our node accepted `txid`, answering a broadcast that asked for it
+    async fn notify_acceptance(&self, txid: Txid) {
+        let event = match build_tx_acceptance_with_namespace(&[txid], &self.config.relay_id, &self.config.tag_namespace, self.config.network, &self.content_keys) {
+            Ok(event) => Arc::new(event),
+            Err(e) => {
+                error!("Relay-{}: Failed to build acceptance notice: {}", self.config.relay_id, e);
+                return;
+            }
+        };
+        let gossip = match self.seal(&event, &self.content_keys) {
+            Ok(gossip) => gossip,
+            Err(e) => {
+                error!("Relay-{}: Failed to encrypt acceptance notice: {}", self.config.relay_id, e);
+                return;
+            }
+        };
+        if let Err(e) = self.send_to_strfry(gossip).await {
+            warn!("Relay-{}: Failed to publish acceptance notice for {}: {}", self.config.relay_id, txid, e);
         }
-        Ok(())
     }
     
-    /// Connect to the Strfry Nostr relay
-    async fn connect_to_strfry(&self) -> Result<()> {
-        info!("Relay-{}: Connecting to strfry relay at {}", self.config.relay_id, self.config.strfry_url);
-        
-        loop {
-            match self.try_connect_to_strfry().await {
-                Ok(_) => {
-                    info!("Relay-{}: Strfry connection closed, reconnecting in 5 seconds", self.config.relay_id);
-                }
-                Err(e) => {
-                    error!("Relay-{}: Failed to connect to strfry: {}, retrying in 5 seconds", self.config.relay_id, e);
-                }
+    /// Pass a peer's notice that its node accepted transactions on to submissions waiting for one
+    fn handle_peer_acceptance(&self, event: Event) {
+        let Some(relay_id) = self.event_relay_id(&event).filter(|relay_id| *relay_id != self.config.relay_id) else {
+            return;
+        };
+        let accepted = match parse_tx_acceptance(&event) {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Relay-{}: Ignoring malformed acceptance notice {}: {}", self.config.relay_id, event.id, e);
+                return;
             }
-            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        };
+        for txid in accepted.into_iter().take(MAX_REQUESTED_TXIDS) {
+            let _ = self.peer_acceptances.send((txid, relay_id.clone()));
         }
     }
     
-    /// Attempt to connect to Strfry (with retry logic)
-    async fn try_connect_to_strfry(&self) -> Result<()> {
-        let url = Url::parse(&self.config.strfry_url)?;
-        let (ws_stream, _) = connect_async(url).await?;
-        info!("Relay-{}: Connected to strfry relay", self.config.relay_id);
-        
-        let (mut ws_sender, mut ws_receiver) = ws_stream.split();
-        
-        // Subscribe to transaction broadcasts
-        let current_timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-            
-        let subscription = json!([
-            "REQ",
-            format!("tx_relay_{}", self.config.relay_id),
-            {
-                "kinds": [KIND_TX_BROADCAST as u64],
-                "#t": ["bitcoin", "transaction"],
-                "since": current_timestamp
+    /// Act on a peer's notice that transactions left its mempool without confirming
+    /// 
+    /// The transactions are forgotten as delivered by peers, so one broadcast again,
+    /// e.g. once fees drop, reaches the node again; the notice is passed on to local clients.
+    async fn handle_peer_eviction(&self, event: Event) {
+        if self.event_relay_id(&event).as_deref() == Some(self.config.relay_id.as_str()) {
+            return;
+        }
+        let evicted = match parse_tx_eviction(&event) {
+            Ok(evicted) => evicted,
+            Err(e) => {
+                warn!("Relay-{}: Ignoring malformed eviction notice {}: {}", self.config.relay_id, event.id, e);
+                return;
             }
-        ]);
-        
-        ws_sender.send(Message::Text(subscription.to_string())).await?;
-        info!("Relay-{}: Subscribed to transaction broadcasts", self.config.relay_id);
-        
-        let strfry_receiver = Arc::clone(&self.strfry_receiver);
-        let mut strfry_receiver = strfry_receiver.lock().await;
-        
+        };
+        debug!("Relay-{}: Peer reports {} transactions evicted", self.config.relay_id, evicted.len());
+        {
+            let mut remote = self.remote_transactions.write().await;
+            for txid in evicted.iter().take(MAX_REQUESTED_TXIDS) {
+                remote.remove(txid);
+            }
+        }
+        let _ = self.tx_broadcaster.send(Arc::new(event));
+    }
+    
+    /// Txids confirmed in blocks mined since `last_best_block`, which is advanced to the current tip
+    ///
+    /// `None` if those blocks can't all be searched: `last_best_block` is unknown, was
+    /// reorganized away, or is more than [`EVICTION_BLOCK_LOOKBACK`] blocks back.
+    async fn recently_confirmed(&self, last_best_block: &mut Option<BlockHash>) -> Result<Option<HashSet<Txid>>> {
+        let best = self.bitcoin_client.get_best_block_hash().await?;
+        let mut confirmed = HashSet::new();
+        let mut hash = Some(best);
+        let mut searched = 0;
+        let complete = loop {
+            let Some(block_hash) = hash else {
+                // Searched back to genesis
+                break true;
+            };
+            if Some(block_hash) == *last_best_block {
+                break true;
+            }
+            if searched == EVICTION_BLOCK_LOOKBACK {
+                break false;
+            }
+            let (header, txids) = self.bitcoin_client.get_block_txids(&block_hash).await?;
+            confirmed.extend(txids);
+            searched += 1;
+            hash = header.previous_block_hash;
+        };
+        *last_best_block = Some(best);
+        Ok(complete.then_some(confirmed))
+    }
+    
+    /// Gossip the unseen transactions of each block once it is `min_depth` blocks deep
+    ///
+    /// Blocks already that deep at startup are skipped. After a long outage only the
+    /// [`MAX_BLOCKS_PER_POLL`] blocks nearest the target depth are gossiped. Each poll
+    /// only asks for the best block hash until the tip changes.
+    async fn monitor_blocks(&self, min_depth: u32) {
+        let mut last_tip = self.bitcoin_client.get_best_block_hash().await.ok();
+        let mut last_gossiped = self.block_at_depth(min_depth).await.ok().flatten();
         loop {
-            tokio::select! {
-                msg = ws_receiver.next() => {
-                    match msg {
-                        Some(Ok(Message::Text(text))) => {
-                            if let Err(e) = self.handle_strfry_message(&text).await {
-                                error!("Relay-{}: Error handling strfry message: {}", self.config.relay_id, e);
-                            }
-                        }
-                        Some(Ok(Message::Close(_))) => {
-                            info!("Relay-{}: Strfry connection closed", self.config.relay_id);
-                            break;
-                        }
-                        Some(Err(e)) => {
-                            error!("Relay-{}: WebSocket error: {}", self.config.relay_id, e);
-                            break;
-                        }
-                        None => break,
-                        _ => {}
+            tokio::time::sleep(self.config.mempool_poll_interval).await;
+            let tip = match self.bitcoin_client.get_best_block_hash().await {
+                Ok(tip) if Some(tip) != last_tip => tip,
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Relay-{}: Failed to follow blocks: {}", self.config.relay_id, e);
+                    continue;
+                }
+            };
+            let target = match self.block_at_depth(min_depth).await {
+                Ok(target) => {
+                    last_tip = Some(tip);
+                    match target {
+                        Some(target) if Some(target) != last_gossiped => target,
+                        _ => continue,
                     }
                 }
-                event = strfry_receiver.recv() => {
-                    if let Some(event) = event {
-                        let message = json!(["EVENT", event]);
-                        if let Err(e) = ws_sender.send(Message::Text(message.to_string())).await {
-                            error!("Relay-{}: Failed to send event to strfry: {}", self.config.relay_id, e);
-                            break;
-                        }
-                    } else {
+                Err(e) => {
+                    warn!("Relay-{}: Failed to follow blocks: {}", self.config.relay_id, e);
+                    continue;
+                }
+            };
+            
+            let mut blocks = Vec::new();
+            let mut hash = Some(target);
+            while let Some(block_hash) = hash {
+                if Some(block_hash) == last_gossiped || blocks.len() == MAX_BLOCKS_PER_POLL {
+                    break;
+                }
+                match self.bitcoin_client.get_block(&block_hash).await {
+                    Ok(block) => {
+                        hash = Some(block.header.prev_blockhash).filter(|prev| *prev != BlockHash::all_zeros());
+                        blocks.push(block);
+                    }
+                    Err(e) => {
+                        warn!("Relay-{}: Failed to fetch block {}: {}", self.config.relay_id, block_hash, e);
                         break;
                     }
                 }
             }
+            for block in blocks.iter().rev() {
+                self.gossip_block_transactions(block).await;
+            }
+            last_gossiped = Some(target);
         }
-        
-        Ok(())
     }
     
-    /// Handle messages received from the Strfry relay
-    async fn handle_strfry_message(&self, message: &str) -> Result<()> {
-        let parsed: Value = serde_json::from_str(message)?;
-        
-        if let Some(arr) = parsed.as_array() {
-            if arr.len() >= 3 && arr[0].as_str() == Some("EVENT") {
-                let event: Event = serde_json::from_value(arr[2].clone())?;
-                
-                if event.kind.as_u32() == KIND_TX_BROADCAST as u32 {
-                    self.handle_remote_transaction(event).await?;
-                }
-            }
+    /// Hash of the block with `depth` confirmations, or `None` if the chain is shorter
+    async fn block_at_depth(&self, depth: u32) -> Result<Option<BlockHash>> {
+        let height = self.bitcoin_client.get_block_count().await?;
+        match (height + 1).checked_sub(u64::from(depth)) {
+            Some(target) => Ok(Some(self.bitcoin_client.get_block_hash(target).await?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Gossip the transactions of a block that neither we nor a peer have broadcast
+    ///
+    /// A transaction that fails to gossip is logged and skipped.
+    async fn gossip_block_transactions(&self, block: &bitcoin::Block) {
+        let mut sent = 0;
+        for tx in block.txdata.iter().filter(|tx| !tx.is_coin_base()) {
+            let txid = tx.txid();
+            if self.is_broadcast_transaction(&txid).await
+                || self.is_remote_transaction(&txid).await
+                || self.gossiped_txids.lock().unwrap().contains(&txid)
+            {
+                continue;
+            }
+            if let Err(e) = self.broadcast_transaction(tx).await {
+                warn!("Relay-{}: Failed to gossip transaction {} of block {}: {}", self.config.relay_id, txid, block.block_hash(), e);
+                continue;
+            }
+            self.broadcast_txids.write().await.insert(txid, unix_time_ms());
+            self.publish_transaction(tx, TxSource::Block);
+            sent += 1;
+        }
+        if sent > 0 {
+            info!("Relay-{}: Gossiped {} transactions first seen in block {}", self.config.relay_id, sent, block.block_hash());
+        }
+    }
+    
+    /// Whether a transaction was received from a remote relay
+    async fn is_remote_transaction(&self, txid: &Txid) -> bool {
+        self.remote_transactions.read().await.contains_key(txid)
+    }
+    
+    /// Whether a mempool transaction was already gossiped
+    async fn is_broadcast_transaction(&self, txid: &Txid) -> bool {
+        self.broadcast_txids.read().await.contains_key(txid)
+    }
+    
+    /// Gossip newly seen mempool transactions not received from a peer
+    ///
+    /// Up to `mempool_fetch_concurrency` transactions are fetched and gossiped at once.
+    async fn relay_new_mempool_transactions(&self, txids: &[Txid]) -> Result<()> {
+        let mut relayed = futures_util::stream::iter(txids.iter().copied())
+            .map(|txid| async move {
+                if self.is_remote_transaction(&txid).await {
+                    return Ok(());
+                }
+                self.relay_mempool_transaction(&txid).await
+            })
+            .buffer_unordered(self.config.mempool_fetch_concurrency.max(1));
+        while let Some(result) = relayed.next().await {
+            result?;
         }
-        
         Ok(())
     }
     
-    /// Handle transactions received from remote relays
-    async fn handle_remote_transaction(&self, event: Event) -> Result<()> {
-        // Check if this event came from our own relay
-        for tag in &event.tags {
-            if let nostr::Tag::Generic(kind, values) = tag {
-                if *kind == nostr::TagKind::Custom("relay_id".to_string()) && !values.is_empty() {
-                    if values[0] == self.config.relay_id {
-                        return Ok(());
+    /// Gossip a newly seen mempool transaction, preceded by any unbroadcast ancestors
+    /// 
+    /// With a [`Shard`](super::config::Shard) configured, transactions outside it are left to other relays.
+    async fn relay_mempool_transaction(&self, txid: &Txid) -> Result<()> {
+        if self.config.shard.is_some_and(|shard| !shard.contains(txid)) {
+            return Ok(());
+        }
+        if self.is_broadcast_transaction(txid).await || self.is_stale(txid).await {
+            return Ok(());
+        }
+        
+        let Ok(raw_tx) = self.get_raw_transaction(txid).await else {
+            return Ok(());
+        };
+        let Some(tx) = hex::decode(&raw_tx).ok().and_then(|bytes| deserialize::<Transaction>(&bytes).ok()) else {
+            return Ok(());
+        };
+        if !self.matches_script_filter(&tx).await {
+            return Ok(());
+        }
+        
+        // Parents go first so CPFP packages arrive at peers in a usable order; those in
+        // another shard are left to the relay that owns it
+        for ancestor_txid in self.unbroadcast_ancestors(txid).await {
+            if self.config.shard.is_some_and(|shard| !shard.contains(&ancestor_txid)) || self.is_stale(&ancestor_txid).await {
+                continue;
+            }
+            let ancestor = self.get_raw_transaction(&ancestor_txid).await.ok()
+                .and_then(|raw| hex::decode(raw).ok())
+                .and_then(|bytes| deserialize::<Transaction>(&bytes).ok());
+            if let Some(ancestor) = ancestor {
+                // Concurrent fetches can reach the same parent through several children
+                if self.broadcast_txids.write().await.insert(ancestor_txid, unix_time_ms()).is_some() {
+                    continue;
+                }
+                info!("Relay-{}: Gossiping unconfirmed ancestor {} of {}", self.config.relay_id, ancestor_txid, txid);
+                if let Err(e) = self.broadcast_transaction(&ancestor).await {
+                    error!("Relay-{}: Failed to broadcast transaction {}: {}", self.config.relay_id, ancestor_txid, e);
+                }
+                self.publish_transaction(&ancestor, TxSource::Mempool);
+            }
+        }
+        
+        if self.broadcast_txids.write().await.insert(*txid, unix_time_ms()).is_some() {
+            return Ok(());
+        }
+        if let Err(e) = self.broadcast_transaction(&tx).await {
+            error!("Relay-{}: Failed to broadcast transaction {}: {}", self.config.relay_id, txid, e);
+        }
+        self.publish_transaction(&tx, TxSource::Mempool);
+        
+        Ok(())
+    }
+    
+    /// Whether a mempool transaction is older than `max_broadcast_age`
+    ///
+    /// Transactions whose mempool entry can't be fetched are treated as fresh.
+    async fn is_stale(&self, txid: &Txid) -> bool {
+        let Some(max_age) = self.config.max_broadcast_age else {
+            return false;
+        };
+        let Ok(entry) = self.bitcoin_client.get_mempool_entry(&txid.to_string()).await else {
+            return false;
+        };
+        let age_secs = (unix_time_ms() / 1000).saturating_sub(entry.time);
+        if age_secs > max_age.as_secs() {
+            debug!("Relay-{}: Not gossiping {}, in mempool for {}s", self.config.relay_id, txid, age_secs);
+            return true;
+        }
+        false
+    }
+    
+    /// Unconfirmed ancestors of a mempool transaction that haven't been gossiped yet, parents first
+    /// 
+    /// The walk stops at ancestors that were already broadcast or came from a remote
+    /// relay, and at `max_ancestor_depth` generations.
+    async fn unbroadcast_ancestors(&self, txid: &Txid) -> Vec<Txid> {
+        let mut ancestors: Vec<(u64, Txid)> = Vec::new();
+        let mut visited: HashSet<Txid> = HashSet::new();
+        let mut frontier = vec![*txid];
+        
+        for _ in 0..self.config.max_ancestor_depth {
+            let mut next = Vec::new();
+            for current in &frontier {
+                let Ok(entry) = self.bitcoin_client.get_mempool_entry(&current.to_string()).await else {
+                    continue;
+                };
+                for parent in entry.depends.iter().filter_map(|parent| parse_txid(parent).ok()) {
+                    if self.is_broadcast_transaction(&parent).await
+                        || self.is_remote_transaction(&parent).await
+                        || !visited.insert(parent)
+                    {
+                        continue;
                     }
+                    next.push(parent);
                 }
             }
+            if next.is_empty() {
+                break;
+            }
+            for parent in &next {
+                // A parent always has fewer ancestors than its children, giving a topological order
+                let ancestor_count = match self.bitcoin_client.get_mempool_entry(&parent.to_string()).await {
+                    Ok(entry) => entry.ancestorcount,
+                    Err(_) => 0,
+                };
+                ancestors.push((ancestor_count, *parent));
+            }
+            frontier = next;
         }
         
-        let tx_data: Value = serde_json::from_str(&event.content)?;
+        ancestors.sort_by_key(|(ancestor_count, _)| *ancestor_count);
+        ancestors.into_iter().map(|(_, txid)| txid).collect()
+    }
+    
+    /// Whether a mempool transaction passes the configured script filter
+    async fn matches_script_filter(&self, tx: &Transaction) -> bool {
+        let filter = &self.config.script_filter;
+        if filter.is_empty() || filter.matches_outputs(tx) {
+            return true;
+        }
+        if !filter.match_inputs {
+            return false;
+        }
         
-        if let Some(tx_hex) = tx_data.get("hex").and_then(|h| h.as_str()) {
-            if let Some(txid) = tx_data.get("txid").and_then(|t| t.as_str()) {
-                let mut remote_txs = self.remote_transactions.write().await;
-                remote_txs.insert(txid.to_string());
-                
-                match self.validator.validate(tx_hex).await {
-                    Ok(()) => {}
-                    Err(ValidationError::RecentlyProcessed { txid: _ }) => {
-                        return Ok(());
+        // Look up the scripts being spent; inputs whose parent can't be fetched are skipped
+        let mut prevout_scripts = Vec::new();
+        for input in &tx.input {
+            let outpoint = input.previous_output;
+            let parent = self.get_raw_transaction(&outpoint.txid).await.ok()
+                .and_then(|raw_parent| hex::decode(raw_parent).ok())
+                .and_then(|bytes| deserialize::<Transaction>(&bytes).ok());
+            if let Some(output) = parent.as_ref().and_then(|p| p.output.get(outpoint.vout as usize)) {
+                prevout_scripts.push(output.script_pubkey.clone());
+            }
+        }
+        filter.matches_prevouts(&prevout_scripts)
+    }
+    
+    /// Get the mempool txids, with the mempool sequence number when `use_sequence` is set
+    ///
+    /// Clears `use_sequence` for good if the node doesn't support sequence numbers.
+    async fn poll_mempool(&self, use_sequence: &mut bool) -> Result<(Vec<Txid>, Option<u64>)> {
+        if *use_sequence {
+            match self.bitcoin_client.get_raw_mempool_sequence().await {
+                Ok(mempool) => return Ok((self.parse_mempool_txids(&mempool.txids), Some(mempool.mempool_sequence))),
+                Err(crate::RelayError::BitcoinRpc(e @ (crate::BitcoinRpcError::BitcoinCore { .. } | crate::BitcoinRpcError::UnsupportedMethod { .. }))) => {
+                    warn!("Relay-{}: Node doesn't report the mempool sequence ({}), diffing every poll", self.config.relay_id, e);
+                    *use_sequence = false;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((self.get_mempool_txids().await?, None))
+    }
+    
+    /// Get the list of transaction IDs from the mempool
+    async fn get_mempool_txids(&self) -> Result<Vec<Txid>> {
+        let txids = self.bitcoin_client.get_raw_mempool().await?;
+        Ok(self.parse_mempool_txids(&txids))
+    }
+    
+    /// Parse the txids the node reports, skipping entries that aren't valid txids
+    fn parse_mempool_txids(&self, txids: &[String]) -> Vec<Txid> {
+        txids
+            .iter()
+            .filter_map(|txid| match parse_txid(txid) {
+                Ok(txid) => Some(txid),
+                Err(e) => {
+                    warn!("Relay-{}: Skipping mempool entry: {}", self.config.relay_id, e);
+                    None
+                }
+            })
+            .collect()
+    }
+    
+    /// Get the raw transaction hex for a given transaction ID
+    async fn get_raw_transaction(&self, txid: &Txid) -> Result<String> {
+        self.bitcoin_client.get_raw_transaction(&txid.to_string()).await
+    }
+    
+    /// Broadcast a transaction to the Nostr network
+    async fn broadcast_transaction(&self, tx: &Transaction) -> Result<()> {
+        self.broadcast_transaction_encoded(tx, self.broadcast_encoding().await).await
+    }
+    
+    /// Broadcast a transaction to the Nostr network in a given encoding
+    async fn broadcast_transaction_encoded(&self, tx: &Transaction, encoding: BroadcastEncoding) -> Result<()> {
+        let txid = tx.txid();
+        let hops = self.tx_hops.lock().unwrap().get(&txid).copied().unwrap_or(0);
+        let mut payload = TxBroadcastPayload::from_transaction(tx).with_first_seen(unix_time_ms()).with_hops(hops);
+        if self.awaiting_acceptance.lock().unwrap().contains(&txid) {
+            payload = payload.with_ack_requested();
+        }
+        if let Ok(entry) = self.bitcoin_client.get_mempool_entry(&txid.to_string()).await {
+            payload = payload.with_fee_sat((entry.fees.base * 100_000_000.0).round() as u64);
+        }
+        if encoding == BroadcastEncoding::AnnounceOnly {
+            payload = payload.with_announcer(self.identity_keys.public_key().to_string());
+        }
+        
+        let event = Arc::new(build_tx_broadcast_encoded_with_namespace(&payload, encoding, &self.config.relay_id, &self.config.tag_namespace, self.config.network, &self.content_keys)?);
+        self.count(Metric::TxBroadcast);
+        
+        // Only the copy leaving for strfry is encrypted; local clients get plaintext
+        let gossip = self.seal(&event, &self.content_keys)?;
+        match self.send_to_strfry(Arc::clone(&gossip)).await {
+            Ok(_) => info!("📡 Relay-{}: Broadcasting transaction {} via Nostr", self.config.relay_id, txid),
+            Err(crate::RelayError::Nostr(NostrError::OutboundQueueFull)) => {}
+            Err(e) => {
+                warn!("Relay-{}: Strfry unavailable ({}), queueing transaction {} for retry", self.config.relay_id, e, txid);
+                self.queue_strfry_retry(gossip).await;
+            }
+        }
+        
+        // Compact encoding is only negotiated with peer relays; local clients may not read it
+        let local = match encoding {
+            BroadcastEncoding::Compact => Arc::new(build_tx_broadcast_encoded_with_namespace(&payload, BroadcastEncoding::Verbose, &self.config.relay_id, &self.config.tag_namespace, self.config.network, &self.content_keys)?),
+            _ => event,
+        };
+        let _ = self.tx_broadcaster.send(local);
+        
+        Ok(())
+    }
+    
+    /// Subscribe to transactions handled by this relay that match `filter`
+    pub fn subscribe_transactions(&self, filter: TxFilter) -> TxSubscription {
+        TxSubscription::new(self.tx_stream.subscribe(), filter)
+    }
+    
+    /// Hand a transaction to in-process subscribers, indexing it for lookups by wtxid
+    fn publish_transaction(&self, tx: &Transaction, source: TxSource) {
+        let (txid, wtxid) = (tx.txid(), tx.wtxid());
+        if wtxid.to_raw_hash() != txid.to_raw_hash() {
+            self.wtxid_index.lock().unwrap().put(wtxid, txid);
+        }
+        if self.tx_stream.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.tx_stream.send(Arc::new(RelayedTx {
+            txid,
+            tx: tx.clone(),
+            source,
+        }));
+    }
+    
+    /// Encrypt the content of an event for peer relays, if broadcast encryption is configured
+    fn seal_content(&self, content: String) -> Result<String> {
+        match &self.config.broadcast_cipher {
+            Some(cipher) => Ok(cipher.encrypt(&content)?),
+            None => Ok(content),
+        }
+    }
+    
+    /// The copy of a locally published event that goes to peer relays, re-signed with
+    /// encrypted content if broadcast encryption is configured
+    fn seal(&self, event: &SharedEvent, keys: &Keys) -> Result<SharedEvent> {
+        match &self.config.broadcast_cipher {
+            Some(cipher) => Ok(Arc::new(EventBuilder::new(event.kind, cipher.encrypt(&event.content)?, &event.tags).to_event(keys)?)),
+            None => Ok(Arc::clone(event)),
+        }
+    }
+    
+    /// Send an event to the Strfry relay
+    /// 
+    /// A full outbound queue drops the oldest queued event or rejects this one,
+    /// as configured by `strfry_overflow_policy`.
+    async fn send_to_strfry(&self, event: SharedEvent) -> Result<()> {
+        if !self.strfry_connected.load(Ordering::SeqCst) {
+            return Err(NostrError::SendFailed.into());
+        }
+        match self.strfry_outbound.push(event) {
+            Enqueued::Queued => Ok(()),
+            Enqueued::DroppedOldest(dropped) => {
+                self.strfry_events_dropped.fetch_add(1, Ordering::Relaxed);
+                self.count(Metric::StrfryEventDropped);
+                warn!("Relay-{}: Strfry outbound queue full, dropping event {}", self.config.relay_id, dropped.id);
+                Ok(())
+            }
+            Enqueued::Rejected(rejected) => {
+                self.strfry_events_dropped.fetch_add(1, Ordering::Relaxed);
+                self.count(Metric::StrfryEventDropped);
+                warn!("Relay-{}: Strfry outbound queue full, rejecting event {}", self.config.relay_id, rejected.id);
+                Err(NostrError::OutboundQueueFull.into())
+            }
+        }
+    }
+    
+    /// Hold an event until the strfry connection is re-established
+    async fn queue_strfry_retry(&self, event: SharedEvent) {
+        let mut buffer = self.strfry_retry_buffer.lock().await;
+        buffer.push_back(event);
+        
+        while buffer.len() > self.config.strfry_retry_buffer_size {
+            if let Some(dropped) = buffer.pop_front() {
+                self.strfry_events_dropped.fetch_add(1, Ordering::Relaxed);
+                self.count(Metric::StrfryEventDropped);
+                warn!("Relay-{}: Strfry retry buffer full, dropping event {}", self.config.relay_id, dropped.id);
+            }
+        }
+    }
+    
+    /// Connect to the Strfry Nostr relay
+    async fn connect_to_strfry(&self) -> Result<()> {
+        if self.config.event_bus.is_some() {
+            info!("Relay-{}: Connecting to the in-process event bus", self.config.relay_id);
+        } else {
+            info!("Relay-{}: Connecting to strfry relay at {}", self.config.relay_id, self.config.strfry_url);
+        }
+        
+        if self.strfry_task_started.swap(true, Ordering::SeqCst) {
+            return Err(crate::RelayError::Other("strfry connection task is already running".to_string()));
+        }
+        let mut backoff = Backoff::new(self.config.strfry_reconnect_backoff.clone());
+        let mut first_attempt = true;
+        
+        loop {
+            if !std::mem::take(&mut first_attempt) {
+                self.set_strfry_state(StrfryState::Reconnecting);
+            }
+            let connected_at = std::time::Instant::now();
+            let delay = match self.try_connect_to_strfry().await {
+                Ok(_) => {
+                    let delay = backoff.next_delay(connected_at.elapsed());
+                    info!("Relay-{}: Strfry connection closed, reconnecting in {:?}", self.config.relay_id, delay);
+                    delay
+                }
+                Err(e) => {
+                    self.set_strfry_state(StrfryState::Disconnected);
+                    let delay = backoff.next_delay(std::time::Duration::ZERO);
+                    error!("Relay-{}: Failed to connect to strfry: {}, retrying in {:?}", self.config.relay_id, e, delay);
+                    delay
+                }
+            };
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    /// `REQ` for transaction broadcasts on our network created since `since` (seconds since the epoch)
+    fn tx_broadcast_subscription(&self, since: u64) -> Value {
+        let mut kinds = vec![KIND_TX_BROADCAST as u64];
+        if self.config.eviction_notifications {
+            kinds.push(KIND_TX_EVICTED as u64);
+        }
+        if self.config.propagation_ack_timeout.is_some() {
+            kinds.push(KIND_TX_ACCEPTED as u64);
+        }
+        json!([
+            "REQ",
+            self.tx_subscription_id(),
+            {
+                "kinds": kinds,
+                "#t": tx_broadcast_topics(self.config.network),
+                "since": since
+            }
+        ])
+    }
+    
+    /// Run a single strfry connection, forwarding queued outbound events until it closes
+    async fn try_connect_to_strfry(&self) -> Result<()> {
+        self.strfry_connected.store(false, Ordering::SeqCst);
+        
+        if let Some(bus) = &self.config.event_bus {
+            return self.run_strfry_session(bus.connect()).await;
+        }
+        let ws_stream = connect_websocket(
+            &self.config.strfry_url,
+            self.config.socks_proxy.as_deref(),
+            Some(self.websocket_config()),
+        ).await?;
+        info!("Relay-{}: Connected to strfry relay", self.config.relay_id);
+        
+        self.run_strfry_session(ws_stream).await
+    }
+    
+    /// Every `REQ` this relay keeps open on strfry, transaction broadcasts first
+    fn strfry_subscriptions(&self, since: u64) -> Vec<Value> {
+        let mut subscriptions = vec![self.tx_broadcast_subscription(since)];
+        
+        if self.config.relay_id_check != RelayIdCheck::Disabled {
+            subscriptions.push(json!([
+                "REQ",
+                format!("relay_presence_{}", self.config.relay_id),
+                {
+                    "kinds": [KIND_RELAY_PRESENCE as u64],
+                    "since": since
+                }
+            ]));
+        }
+        
+        if self.config.discovery.enabled {
+            subscriptions.push(json!([
+                "REQ",
+                format!("relay_discovery_{}", self.config.relay_id),
+                {
+                    "kinds": [KIND_RELAY_DISCOVERY as u64, KIND_RELAY_CAPABILITIES as u64],
+                    "since": since
+                }
+            ]));
+        }
+        
+        let mut addressed_kinds = Vec::new();
+        if self.config.mempool_reconciliation {
+            addressed_kinds.extend([KIND_MEMPOOL_SUMMARY_REQUEST as u64, KIND_MEMPOOL_SUMMARY as u64]);
+        }
+        if self.answers_tx_requests() {
+            addressed_kinds.push(KIND_PEER_TX_REQUEST as u64);
+        }
+        if !addressed_kinds.is_empty() {
+            subscriptions.push(json!([
+                "REQ",
+                format!("relay_reconcile_{}", self.config.relay_id),
+                {
+                    "kinds": addressed_kinds,
+                    "#p": [self.identity_keys.public_key().to_string()],
+                    "since": since
+                }
+            ]));
+        }
+        
+        subscriptions
+    }
+    
+    /// Gossip over an established strfry connection until it closes
+    async fn run_strfry_session<T: NostrTransport>(&self, mut transport: T) -> Result<()> {
+        self.strfry_backfilled.store(false, Ordering::SeqCst);
+        
+        for subscription in self.strfry_subscriptions(unix_time_ms() / 1000) {
+            transport.send_message(subscription.to_string()).await?;
+        }
+        info!("Relay-{}: Subscribed to transaction broadcasts", self.config.relay_id);
+        
+        if self.config.relay_id_check != RelayIdCheck::Disabled {
+            let presence = json!(["EVENT", self.relay_presence_event()?]);
+            transport.send_message(presence.to_string()).await?;
+            info!("Relay-{}: Announced relay presence", self.config.relay_id);
+        }
+        
+        if self.config.discovery.enabled {
+            let capabilities = json!(["EVENT", self.relay_capabilities_event()?]);
+            transport.send_message(capabilities.to_string()).await?;
+        }
+        
+        // Flush broadcasts that were held while disconnected
+        let pending: Vec<SharedEvent> = self.strfry_retry_buffer.lock().await.drain(..).collect();
+        if !pending.is_empty() {
+            info!("Relay-{}: Resending {} queued events to strfry", self.config.relay_id, pending.len());
+        }
+        for (i, event) in pending.iter().enumerate() {
+            let message = json!(["EVENT", event.as_ref()]);
+            if let Err(e) = transport.send_message(message.to_string()).await {
+                let mut buffer = self.strfry_retry_buffer.lock().await;
+                for event in pending[i..].iter().rev() {
+                    buffer.push_front(Arc::clone(event));
+                }
+                return Err(e);
+            }
+        }
+        
+        self.strfry_connected.store(true, Ordering::SeqCst);
+        self.set_strfry_state(StrfryState::Connected);
+        
+        // Subscriptions strfry closed, each with its backoff and when it was last renewed
+        let mut resubscribe_backoff: HashMap<String, (Backoff, tokio::time::Instant)> = HashMap::new();
+        let mut pending_resubscribes: Vec<(tokio::time::Instant, String)> = Vec::new();
+        
+        loop {
+            let next_resubscribe = pending_resubscribes.iter().map(|(at, _)| *at).min();
+            tokio::select! {
+                _ = tokio::time::sleep_until(next_resubscribe.unwrap_or_else(tokio::time::Instant::now)), if next_resubscribe.is_some() => {
+                    let now = tokio::time::Instant::now();
+                    let (due, waiting): (Vec<_>, Vec<_>) = pending_resubscribes.drain(..).partition(|(at, _)| *at <= now);
+                    pending_resubscribes = waiting;
+                    // Renew from now rather than replaying everything since the session began
+                    let subscriptions = self.strfry_subscriptions(unix_time_ms() / 1000);
+                    let mut failed = false;
+                    for (_, closed) in due {
+                        let Some(renewed) = subscriptions.iter().find(|req| req[1] == closed.as_str()) else { continue };
+                        info!("Relay-{}: Resubscribing to {}", self.config.relay_id, closed);
+                        if let Some((_, renewed_at)) = resubscribe_backoff.get_mut(&closed) {
+                            *renewed_at = now;
+                        }
+                        if let Err(e) = transport.send_message(renewed.to_string()).await {
+                            error!("Relay-{}: Failed to resubscribe to {}: {}", self.config.relay_id, closed, e);
+                            failed = true;
+                            break;
+                        }
                     }
-                    Err(e) => {
-                        warn!("Relay-{}: Transaction {} failed validation: {}", self.config.relay_id, txid, e);
-                        return Ok(());
+                    if failed {
+                        break;
                     }
                 }
-                
-                match self.submit_to_bitcoin_node(tx_hex).await {
-                    Ok(_) => {
-                        info!("🌐 Relay-{}: Received transaction {} via Nostr", self.config.relay_id, txid);
+                msg = transport.recv_message() => {
+                    match msg {
+                        Some(Ok(text)) => {
+                            match self.handle_strfry_message(&text).await {
+                                Ok(Some(closed)) => {
+                                    if pending_resubscribes.iter().all(|(_, id)| *id != closed) {
+                                        let now = tokio::time::Instant::now();
+                                        let (backoff, renewed_at) = resubscribe_backoff
+                                            .entry(closed.clone())
+                                            .or_insert_with(|| (Backoff::new(self.config.strfry_reconnect_backoff.clone()), now));
+                                        let delay = backoff.next_delay(renewed_at.elapsed());
+                                        debug!("Relay-{}: Resubscribing to {} in {:?}", self.config.relay_id, closed, delay);
+                                        pending_resubscribes.push((now + delay, closed));
+                                    }
+                                }
+                                Ok(None) => {}
+                                Err(e) => error!("Relay-{}: Error handling strfry message: {}", self.config.relay_id, e),
+                            }
+                        }
+                        Some(Err(e)) => {
+                            error!("Relay-{}: WebSocket error: {}", self.config.relay_id, e);
+                            break;
+                        }
+                        None => {
+                            info!("Relay-{}: Strfry connection closed", self.config.relay_id);
+                            break;
+                        }
                     }
-                    Err(e) => {
-                        let error_msg = e.to_string();
-                        if !error_msg.contains("already in mempool") && !error_msg.contains("already exists") {
-                            warn!("Relay-{}: Failed to submit remote transaction {} to local Bitcoin node: {}", self.config.relay_id, txid, error_msg);
+                }
+                event = self.strfry_outbound.recv() => {
+                    let message = json!(["EVENT", event.as_ref()]);
+                    if let Err(e) = transport.send_message(message.to_string()).await {
+                        error!("Relay-{}: Failed to send event to strfry: {}", self.config.relay_id, e);
+                        self.queue_strfry_retry(event).await;
+                        break;
+                    }
+                }
+            }
+        }
+        
+        self.strfry_connected.store(false, Ordering::SeqCst);
+        self.set_strfry_state(StrfryState::Disconnected);
+        
+        Ok(())
+    }
+    
+    /// Handle messages received from the Strfry relay
+    /// 
+    /// Returns the ID of one of our subscriptions when strfry closed it, so the
+    /// session can subscribe again. Closes with a `restricted:` or `auth-required:`
+    /// reason are final and not returned. Frames too short for their type are ignored.
+    async fn handle_strfry_message(&self, message: &str) -> Result<Option<String>> {
+        let parsed: Value = serde_json::from_str(message)?;
+        let Some(arr) = parsed.as_array() else {
+            debug!("Relay-{}: Ignoring non-array strfry message", self.config.relay_id);
+            return Ok(None);
+        };
+        let text = |i: usize| arr.get(i).and_then(Value::as_str);
+        
+        match text(0) {
+            Some("EVENT") if arr.len() >= 3 => {
+                let mut event: Event = serde_json::from_value(arr[2].clone())?;
+                if !self.within_clock_skew(&event) {
+                    self.count(Metric::ClockSkewDropped);
+                    debug!("Relay-{}: Dropping event {} created at {}, too far from local time", self.config.relay_id, event.id, event.created_at.as_u64());
+                    return Ok(None);
+                }
+                // Every kind peers exchange is encrypted, so anything that doesn't decrypt is from outside the group
+                if let Some(cipher) = &self.config.broadcast_cipher {
+                    match cipher.decrypt(&event.content) {
+                        Ok(content) => event.content = content,
+                        Err(e) => {
+                            debug!("Relay-{}: Ignoring event {} of kind {}: {}", self.config.relay_id, event.id, event.kind.as_u32(), e);
+                            return Ok(None);
                         }
                     }
                 }
+                
+                if event.kind.as_u32() == KIND_TX_BROADCAST as u32 {
+                    self.handle_remote_transaction(event).await?;
+                } else if event.kind.as_u32() == KIND_TX_EVICTED as u32 && self.config.eviction_notifications {
+                    self.handle_peer_eviction(event).await;
+                } else if event.kind.as_u32() == KIND_TX_ACCEPTED as u32 && self.config.propagation_ack_timeout.is_some() {
+                    self.handle_peer_acceptance(event);
+                } else if event.kind.as_u32() == KIND_RELAY_PRESENCE as u32 {
+                    self.handle_relay_presence(event).await?;
+                } else if event.kind.as_u32() == KIND_RELAY_DISCOVERY as u32 {
+                    self.handle_relay_discovery(event).await?;
+                } else if event.kind.as_u32() == KIND_RELAY_CAPABILITIES as u32 {
+                    self.handle_relay_capabilities(event).await?;
+                } else if self.is_addressed_to_us(&event) && self.accepts_peer_request(&event).await {
+                    let reconciling = self.config.mempool_reconciliation;
+                    match event.kind.as_u32() {
+                        k if k == KIND_MEMPOOL_SUMMARY_REQUEST as u32 && reconciling => self.handle_mempool_summary_request(event).await?,
+                        k if k == KIND_MEMPOOL_SUMMARY as u32 && reconciling => self.handle_mempool_summary(event).await?,
+                        k if k == KIND_PEER_TX_REQUEST as u32 && self.answers_tx_requests() => self.handle_peer_tx_request(event).await?,
+                        _ => {}
+                    }
+                }
+            }
+            Some("OK") if arr.len() >= 3 => {
+                let event_id = text(1).unwrap_or_default();
+                let reason = text(3).unwrap_or_default();
+                if arr[2].as_bool() == Some(true) {
+                    debug!("Relay-{}: Strfry accepted event {}", self.config.relay_id, event_id);
+                } else {
+                    warn!("Relay-{}: Strfry rejected event {}: {}", self.config.relay_id, event_id, reason);
+                }
+            }
+            Some("NOTICE") => {
+                warn!("Relay-{}: Strfry notice: {}", self.config.relay_id, text(1).unwrap_or_default());
+            }
+            Some("EOSE") => {
+                if let Some(subscription_id) = text(1) {
+                    debug!("Relay-{}: Stored events for {} received", self.config.relay_id, subscription_id);
+                    if subscription_id == self.tx_subscription_id() {
+                        self.strfry_backfilled.store(true, Ordering::SeqCst);
+                    }
+                }
+            }
+            Some("CLOSED") => {
+                if let Some(subscription_id) = text(1) {
+                    let reason = text(2).unwrap_or_default();
+                    // Asking again won't change a policy refusal
+                    if reason.starts_with("restricted:") || reason.starts_with("auth-required:") {
+                        error!("Relay-{}: Strfry refused subscription {}, not resubscribing: {}", self.config.relay_id, subscription_id, reason);
+                        return Ok(None);
+                    }
+                    warn!("Relay-{}: Strfry closed subscription {}: {}", self.config.relay_id, subscription_id, reason);
+                    if self.strfry_subscriptions(0).iter().any(|req| req[1] == subscription_id) {
+                        return Ok(Some(subscription_id.to_string()));
+                    }
+                }
+            }
+            _ => {
+                debug!("Relay-{}: Ignoring strfry message {}", self.config.relay_id, message);
             }
         }
         
+        Ok(None)
+    }
+    
+    /// Whether an event's `created_at` is within the configured clock skew of local time
+    fn within_clock_skew(&self, event: &Event) -> bool {
+        match self.config.max_clock_skew {
+            Some(tolerance) => event.created_at.as_u64().abs_diff(unix_time_ms() / 1000) <= tolerance.as_secs(),
+            None => true,
+        }
+    }
+    
+    /// Whether a millisecond timestamp is within the configured clock skew of local time
+    fn within_clock_skew_ms(&self, timestamp_ms: u64) -> bool {
+        match self.config.max_clock_skew {
+            Some(tolerance) => u128::from(timestamp_ms.abs_diff(unix_time_ms())) <= tolerance.as_millis(),
+            None => true,
+        }
+    }
+    
+    /// Value of the event's `relay_id` tag in our tag namespace, if present
+    fn event_relay_id(&self, event: &Event) -> Option<String> {
+        self.config.tag_namespace.value(event, "relay_id")
+    }
+    
+    /// Subscription ID of this relay's transaction broadcast `REQ`
+    fn tx_subscription_id(&self) -> String {
+        format!("tx_relay_{}", self.config.relay_id)
+    }
+    
+    /// Build the presence event announcing this relay's ID and display name
+    fn relay_presence_event(&self) -> Result<Event> {
+        let mut content = json!({ "relay_id": self.config.relay_id });
+        if let Some(name) = &self.config.relay_name {
+            content["relay_name"] = json!(name);
+        }
+        
+        let event = EventBuilder::new(
+            Kind::Ephemeral(KIND_RELAY_PRESENCE),
+            self.seal_content(content.to_string())?,
+            &[self.config.tag_namespace.tag("relay_id", self.config.relay_id.clone())]
+        ).to_event(&self.identity_keys)?;
+        
+        Ok(event)
+    }
+    
+    /// Whether a presence event was published by a different relay claiming our relay ID
+    fn is_relay_id_conflict(&self, event: &Event) -> bool {
+        event.kind.as_u32() == KIND_RELAY_PRESENCE as u32
+            && event.pubkey != self.identity_keys.public_key()
+            && self.event_relay_id(event).as_deref() == Some(self.config.relay_id.as_str())
+    }
+    
+    /// Handle presence announcements from other relays
+    async fn handle_relay_presence(&self, event: Event) -> Result<()> {
+        if !self.is_relay_id_conflict(&event) {
+            return Ok(());
+        }
+        
+        // Only react once per conflicting relay so the two sides don't ping-pong
+        if !self.relay_id_conflicts.write().await.insert(event.pubkey) {
+            return Ok(());
+        }
+        
+        error!(
+            "Relay-{}: ⚠️ relay_id CONFLICT: relay {} also claims relay_id {}; gossip between the two relays is broken",
+            self.config.relay_id, event.pubkey, self.config.relay_id
+        );
+        
+        // Re-announce so the other relay detects the conflict as well
+        if let Err(e) = self.send_to_strfry(Arc::new(self.relay_presence_event()?)).await {
+            warn!("Relay-{}: Failed to re-announce presence: {}", self.config.relay_id, e);
+        }
+        
+        if self.config.relay_id_check == RelayIdCheck::FailFast {
+            self.relay_id_conflict_notify.notify_one();
+        }
+        
         Ok(())
     }
-}
\ No newline at end of file
+    
+    /// Watch the strfry connection and Bitcoin node health
+    /// 
+    /// The receiver sees the current state immediately and is notified on each change.
+    pub fn connection_state(&self) -> watch::Receiver<ConnectionState> {
+        self.connection_state.subscribe()
+    }
+    
+    fn set_strfry_state(&self, strfry: StrfryState) {
+        self.connection_state.send_if_modified(|state| state.set_strfry(strfry));
+    }
+    
+    fn set_mempool_reachable(&self, reachable: bool) {
+        self.mempool_reachable.store(reachable, Ordering::SeqCst);
+        let bitcoin = if reachable { BitcoinState::Healthy } else { BitcoinState::Unhealthy };
+        self.connection_state.send_if_modified(|state| state.set_bitcoin(bitcoin));
+    }
+    
+    /// Circuit breaker state for the Bitcoin node, when a breaker is configured
+    pub fn bitcoin_node_state(&self) -> Option<BreakerState> {
+        self.rpc_breaker.as_ref().map(|breaker| breaker.state())
+    }
+    
+    /// Snapshot of the relay-to-relay propagation latency observed for remote transactions
+    pub fn propagation_latency(&self) -> LatencyHistogram {
+        self.propagation_latency.lock().unwrap().clone()
+    }
+    
+    /// Whether strfry finished sending stored transaction broadcasts on the current connection
+    pub fn is_strfry_backfilled(&self) -> bool {
+        self.strfry_backfilled.load(Ordering::SeqCst)
+    }
+    
+    /// Whether the relay can serve submissions: the latest mempool poll reached the
+    /// Bitcoin node and strfry is connected
+    /// 
+    /// False until the first successful poll, for use as a readiness probe.
+    pub fn is_ready(&self) -> bool {
+        self.mempool_reachable.load(Ordering::SeqCst) && self.strfry_connected.load(Ordering::SeqCst)
+    }
+    
+    /// Snapshot of how many transactions this relay has broadcast, received, rejected and submitted
+    pub fn stats(&self) -> RelayStats {
+        self.stats.snapshot()
+    }
+    
+    /// Count an occurrence of `metric` in [`stats`](Self::stats) and report it to the metrics sink
+    fn count(&self, metric: Metric) {
+        self.stats.record(metric);
+        self.config.metrics_sink.incr(metric);
+    }
+    
+    /// Counts of remote broadcasts dropped as self-echoes or duplicates
+    pub fn loop_metrics(&self) -> LoopMetrics {
+        LoopMetrics {
+            self_echoes_dropped: self.self_echoes_dropped.load(Ordering::Relaxed),
+            duplicates_dropped: self.duplicates_dropped.load(Ordering::Relaxed),
+            hop_limit_dropped: self.hop_limit_dropped.load(Ordering::Relaxed),
+        }
+    }
+    
+    /// Outbound events dropped because the strfry outbound queue or retry buffer was full
+    pub fn strfry_events_dropped(&self) -> u64 {
+        self.strfry_events_dropped.load(Ordering::Relaxed)
+    }
+    
+    /// Peer relays currently known from discovery events
+    pub async fn known_peers(&self) -> Vec<PeerInfo> {
+        let mut roster = self.peer_roster.write().await;
+        let now = std::time::Instant::now();
+        roster.expire(now);
+        roster.peers(now)
+    }
+    
+    /// Periodically publish this relay's discovery and capabilities events
+    async fn announce_discovery(&self) {
+        let mut interval = tokio::time::interval(self.config.discovery.announce_interval);
+        loop {
+            interval.tick().await;
+            for event in [self.relay_discovery_event(), self.relay_capabilities_event()] {
+                match event {
+                    Ok(event) => {
+                        if let Err(e) = self.send_to_strfry(Arc::new(event)).await {
+                            warn!("Relay-{}: Failed to publish discovery event: {}", self.config.relay_id, e);
+                        }
+                    }
+                    Err(e) => error!("Relay-{}: Failed to build discovery event: {}", self.config.relay_id, e),
+                }
+            }
+        }
+    }
+    
+    /// Build the event listing the optional features this relay supports
+    fn relay_capabilities_event(&self) -> Result<Event> {
+        let mut capabilities = vec![Capability::Packages, Capability::FeeEstimates, Capability::CompactEncoding];
+        if self.config.mempool_reconciliation {
+            capabilities.push(Capability::MempoolReconciliation);
+        }
+        let announcement = CapabilityAnnouncement {
+            relay_id: self.config.relay_id.clone(),
+            capabilities,
+        };
+        
+        let event = EventBuilder::new(
+            Kind::Ephemeral(KIND_RELAY_CAPABILITIES),
+            self.seal_content(serde_json::to_string(&announcement)?)?,
+            &[self.config.tag_namespace.tag("relay_id", self.config.relay_id.clone())]
+        ).to_event(&self.identity_keys)?;
+        
+        Ok(event)
+    }
+    
+    /// Record the features a peer advertises
+    async fn handle_relay_capabilities(&self, event: Event) -> Result<()> {
+        if event.pubkey == self.identity_keys.public_key() {
+            return Ok(());
+        }
+        
+        let announcement: CapabilityAnnouncement = serde_json::from_str(&event.content)?;
+        debug!("Relay-{}: Peer relay {} supports {:?}", self.config.relay_id, announcement.relay_id, announcement.capabilities);
+        self.peer_roster.write().await.record_capabilities(announcement, event.pubkey.to_string(), std::time::Instant::now());
+        
+        Ok(())
+    }
+    
+    /// Encoding for the next broadcast: compact only if preferred and every known peer reads it
+    async fn broadcast_encoding(&self) -> BroadcastEncoding {
+        match self.config.broadcast_encoding {
+            BroadcastEncoding::Compact if self.peer_roster.read().await.all_support(Capability::CompactEncoding, std::time::Instant::now()) => {
+                BroadcastEncoding::Compact
+            }
+            BroadcastEncoding::AnnounceOnly => BroadcastEncoding::AnnounceOnly,
+            _ => BroadcastEncoding::Verbose,
+        }
+    }
+    
+    /// Encoding for broadcasts that must carry the transaction, such as answers to requests
+    async fn full_broadcast_encoding(&self) -> BroadcastEncoding {
+        match self.broadcast_encoding().await {
+            BroadcastEncoding::AnnounceOnly => BroadcastEncoding::Verbose,
+            encoding => encoding,
+        }
+    }
+    
+    /// Whether peers may ask this relay for transactions
+    fn answers_tx_requests(&self) -> bool {
+        self.config.mempool_reconciliation || self.config.broadcast_encoding == BroadcastEncoding::AnnounceOnly
+    }
+    
+    /// Build the discovery event advertising this relay's listen address and networks
+    fn relay_discovery_event(&self) -> Result<Event> {
+        let announcement = DiscoveryAnnouncement {
+            relay_id: self.config.relay_id.clone(),
+            relay_name: self.config.relay_name.clone(),
+            listen_addr: self.config.websocket_listen_addr.to_string(),
+            networks: self.config.network.iter().map(|n| n.as_str().to_string()).collect(),
+        };
+        
+        let event = EventBuilder::new(
+            Kind::Ephemeral(KIND_RELAY_DISCOVERY),
+            self.seal_content(serde_json::to_string(&announcement)?)?,
+            &[self.config.tag_namespace.tag("relay_id", self.config.relay_id.clone())]
+        ).to_event(&self.identity_keys)?;
+        
+        Ok(event)
+    }
+    
+    /// Record a peer from its discovery event
+    async fn handle_relay_discovery(&self, event: Event) -> Result<()> {
+        if event.pubkey == self.identity_keys.public_key() {
+            return Ok(());
+        }
+        
+        let announcement: DiscoveryAnnouncement = serde_json::from_str(&event.content)?;
+        let relay_id = announcement.relay_id.clone();
+        if self.peer_roster.write().await.record(announcement, event.pubkey.to_string(), std::time::Instant::now()) {
+            info!("Relay-{}: Discovered peer relay {}", self.config.relay_id, relay_id);
+        } else {
+            debug!("Relay-{}: Ignoring discovery event for relay {} from {}", self.config.relay_id, relay_id, event.pubkey);
+        }
+        
+        Ok(())
+    }
+    
+    /// Ask a peer relay, identified by its identity key, for a summary of its mempool
+    /// 
+    /// The summary is diffed against the local mempool and the transactions we lack
+    /// are requested from the peer, which gossips them as regular broadcasts.
+    /// Both relays need `mempool_reconciliation` enabled and must trust each other,
+    /// see [`RelayConfig::mempool_reconciliation`].
+    pub async fn request_mempool_summary(&self, peer: XOnlyPublicKey) -> Result<()> {
+        let content = json!({ "relay_id": self.config.relay_id });
+        let event = self.reconciliation_event(KIND_MEMPOOL_SUMMARY_REQUEST, content.to_string(), peer)?;
+        self.send_to_strfry(Arc::new(event)).await
+    }
+    
+    /// Build a reconciliation event addressed to `peer`
+    fn reconciliation_event(&self, kind: u16, content: String, peer: XOnlyPublicKey) -> Result<Event> {
+        let event = EventBuilder::new(Kind::Ephemeral(kind), self.seal_content(content)?, &[Tag::PubKey(peer, None)])
+            .to_event(&self.identity_keys)?;
+        Ok(event)
+    }
+    
+    /// Whether an event is a message from a peer addressed to this relay
+    fn is_addressed_to_us(&self, event: &Event) -> bool {
+        let identity = self.identity_keys.public_key();
+        event.pubkey != identity
+            && event.tags.iter().any(|tag| matches!(tag, Tag::PubKey(pubkey, _) if *pubkey == identity))
+    }
+    
+    /// Whether `pubkey` is a peer relay we answer: on `peer_relay_allowlist`, or in the roster without one
+    async fn is_trusted_peer(&self, pubkey: &XOnlyPublicKey) -> bool {
+        match &self.config.peer_relay_allowlist {
+            Some(allowlist) => allowlist.contains(pubkey),
+            None => self.peer_roster.read().await.has_pubkey(&pubkey.to_string(), std::time::Instant::now()),
+        }
+    }
+    
+    /// Whether to act on a peer's reconciliation message: it must come from a trusted peer within its rate limit
+    async fn accepts_peer_request(&self, event: &Event) -> bool {
+        if !self.is_trusted_peer(&event.pubkey).await {
+            debug!("Relay-{}: Ignoring kind {} event from untrusted peer {}", self.config.relay_id, event.kind.as_u32(), event.pubkey);
+            return false;
+        }
+        let kind = event.kind.as_u32() as u16;
+        if !self.peer_requests.allow(event.pubkey, kind, std::time::Instant::now()) {
+            warn!("Relay-{}: Peer {} exceeded its rate limit for kind {} events", self.config.relay_id, event.pubkey, kind);
+            return false;
+        }
+        true
+    }
+    
+    /// Answer a peer's summary request with our mempool txids
+    async fn handle_mempool_summary_request(&self, event: Event) -> Result<()> {
+        let summary = MempoolSummary::new(self.config.relay_id.clone(), self.get_mempool_txids().await?);
+        info!("Relay-{}: Sending summary of {} mempool transactions to {}", self.config.relay_id, summary.txids.len(), event.pubkey);
+        let reply = self.reconciliation_event(KIND_MEMPOOL_SUMMARY, serde_json::to_string(&summary)?, event.pubkey)?;
+        self.send_to_strfry(Arc::new(reply)).await
+    }
+    
+    /// Diff a peer's mempool summary against ours and request the transactions we lack
+    async fn handle_mempool_summary(&self, event: Event) -> Result<()> {
+        let summary: MempoolSummary = serde_json::from_str(&event.content)?;
+        let local = self.get_mempool_txids().await?;
+        let diff = MempoolDiff::between(&local, &summary.parse_txids()?);
+        info!(
+            "Relay-{}: Peer {} has {} transactions missing from our mempool, lacks {}",
+            self.config.relay_id, summary.relay_id, diff.missing_locally.len(), diff.missing_remotely.len()
+        );
+        
+        // Transactions already relayed to us were rejected by our node; asking again won't help
+        let mut missing = Vec::new();
+        for txid in diff.missing_locally {
+            if !self.is_remote_transaction(&txid).await {
+                missing.push(txid);
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+        missing.truncate(MAX_REQUESTED_TXIDS);
+        let missing: Vec<String> = missing.iter().map(Txid::to_string).collect();
+        let request = self.reconciliation_event(KIND_PEER_TX_REQUEST, json!({ "txids": missing }).to_string(), event.pubkey)?;
+        self.send_to_strfry(Arc::new(request)).await
+    }
+    
+    /// Gossip the requested transactions that are in our mempool
+    /// 
+    /// Answers always carry the transaction, even from an announce-only relay.
+    /// Invalid txids and transactions that can't be fetched or gossiped are skipped.
+    async fn handle_peer_tx_request(&self, event: Event) -> Result<()> {
+        let request: TxRequest = serde_json::from_str(&event.content)?;
+        let mempool: HashSet<Txid> = self.get_mempool_txids().await?.into_iter().collect();
+        let requested: Vec<Txid> = request.txids.iter().take(MAX_REQUESTED_TXIDS).filter_map(|txid| parse_txid(txid).ok()).collect();
+        
+        let encoding = self.full_broadcast_encoding().await;
+        let mut sent = 0;
+        for txid in requested.iter().filter(|txid| mempool.contains(*txid)) {
+            let Ok(raw_tx) = self.get_raw_transaction(txid).await else {
+                continue;
+            };
+            let Some(tx) = hex::decode(&raw_tx).ok().and_then(|bytes| deserialize::<Transaction>(&bytes).ok()) else {
+                continue;
+            };
+            if let Err(e) = self.broadcast_transaction_encoded(&tx, encoding).await {
+                warn!("Relay-{}: Failed to gossip requested transaction {}: {}", self.config.relay_id, txid, e);
+                continue;
+            }
+            sent += 1;
+        }
+        info!("Relay-{}: Re-broadcast {} transactions requested by {}", self.config.relay_id, sent, event.pubkey);
+        
+        Ok(())
+    }
+    
+    /// Queue an announced transaction to be asked of its announcer, unless it is already known or was just requested
+    /// 
+    /// Only announcements from trusted peers are followed up. Txids announced by the same
+    /// peer within [`ANNOUNCEMENT_BATCH_DELAY`] are requested together.
+    async fn request_announced_transaction(&self, txid: Txid, announcer: Option<&str>) -> Result<()> {
+        let Some(announcer) = announcer.and_then(|announcer| announcer.parse::<XOnlyPublicKey>().ok()) else {
+            warn!("Relay-{}: Ignoring announcement of {} without a valid announcer", self.config.relay_id, txid);
+            return Ok(());
+        };
+        if !self.is_trusted_peer(&announcer).await {
+            debug!("Relay-{}: Ignoring announcement of {} from untrusted peer {}", self.config.relay_id, txid, announcer);
+            return Ok(());
+        }
+        if self.remote_transactions.read().await.contains_key(&txid) || self.broadcast_txids.read().await.contains_key(&txid) {
+            debug!("Relay-{}: Already have announced transaction {}", self.config.relay_id, txid);
+            return Ok(());
+        }
+        {
+            let now = std::time::Instant::now();
+            let mut requested = self.requested_announcements.lock().unwrap();
+            if requested.get(&txid).is_some_and(|at| now.duration_since(*at) < ANNOUNCEMENT_REQUEST_RETRY) {
+                return Ok(());
+            }
+            requested.put(txid, now);
+        }
+        
+        let first = {
+            let mut batches = self.announcement_batches.lock().unwrap();
+            let batch = batches.entry(announcer).or_default();
+            if batch.len() < MAX_REQUESTED_TXIDS {
+                batch.push(txid);
+            }
+            batch.len() == 1
+        };
+        if first {
+            let server = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(ANNOUNCEMENT_BATCH_DELAY).await;
+                if let Err(e) = server.request_announced_batch(announcer).await {
+                    warn!("Relay-{}: Failed to request announced transactions from {}: {}", server.config.relay_id, announcer, e);
+                }
+            });
+        }
+        Ok(())
+    }
+    
+    /// Ask an announcer in one request for the txids it announced that are still missing from our mempool
+    async fn request_announced_batch(&self, announcer: XOnlyPublicKey) -> Result<()> {
+        let Some(batch) = self.announcement_batches.lock().unwrap().remove(&announcer) else {
+            return Ok(());
+        };
+        let mempool: HashSet<Txid> = self.get_mempool_txids().await?.into_iter().collect();
+        let missing: Vec<String> = batch.iter().filter(|txid| !mempool.contains(*txid)).map(Txid::to_string).collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        if !self.announcement_requests.allow(announcer, KIND_PEER_TX_REQUEST, std::time::Instant::now()) {
+            warn!("Relay-{}: Not requesting {} announced transactions, {} was asked too often", self.config.relay_id, missing.len(), announcer);
+            return Ok(());
+        }
+        
+        debug!("Relay-{}: Requesting {} announced transactions from {}", self.config.relay_id, missing.len(), announcer);
+        let request = self.reconciliation_event(KIND_PEER_TX_REQUEST, json!({ "txids": missing }).to_string(), announcer)?;
+        self.send_to_strfry(Arc::new(request)).await
+    }
+    
+    /// Handle transactions received from remote relays
+    /// 
+    /// An event delivered again, e.g. by strfry replaying stored events after a
+    /// reconnect, is ignored. A different event for an already seen txid is still
+    /// counted as a duplicate. Broadcasts whose txid isn't that of their hex are ignored.
+    /// With broadcast encryption, `event` has already been decrypted by `handle_strfry_message`.
+    async fn handle_remote_transaction(&self, event: Event) -> Result<()> {
+        if self.seen_broadcast_events.lock().unwrap().put(event.id, ()).is_some() {
+            debug!("Relay-{}: Ignoring re-delivered broadcast {}", self.config.relay_id, event.id);
+            return Ok(());
+        }
+        
+        // Check if this event came from our own relay
+        if self.event_relay_id(&event).as_deref() == Some(self.config.relay_id.as_str()) {
+            self.self_echoes_dropped.fetch_add(1, Ordering::Relaxed);
+            self.count(Metric::SelfEchoDropped);
+            return Ok(());
+        }
+        self.count(Metric::TxReceived);
+        
+        let payload = match parse_tx_broadcast(&event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Relay-{}: Ignoring malformed transaction broadcast {}: {}", self.config.relay_id, event.id, e);
+                return Ok(());
+            }
+        };
+        if payload.is_newer_schema() {
+            warn!(
+                "Relay-{}: Transaction broadcast {} uses newer schema version {}, relaying txid and hex only",
+                self.config.relay_id, event.id, payload.schema_version
+            );
+        }
+        let txid = match parse_txid(&payload.txid) {
+            Ok(txid) => txid,
+            Err(e) => {
+                warn!("Relay-{}: Ignoring transaction broadcast {}: {}", self.config.relay_id, event.id, e);
+                return Ok(());
+            }
+        };
+        let tx_hex = payload.hex.as_str();
+        
+        let hops = self.sender_hops(txid, payload.hops);
+        if self.exceeds_hop_limit(hops) {
+            debug!("Relay-{}: Dropping broadcast of {} after {} hops", self.config.relay_id, txid, hops);
+            return Ok(());
+        }
+        
+        // A first-seen time the clock skew check would reject says nothing about latency
+        if let Some(first_seen) = payload.first_seen.filter(|first_seen| self.within_clock_skew_ms(*first_seen)) {
+            let latency = propagation_latency(first_seen, unix_time_ms());
+            self.propagation_latency.lock().unwrap().record(latency);
+            self.config.metrics_sink.observe(Metric::PropagationLatency, latency.as_secs_f64());
+            debug!("Relay-{}: Transaction {} propagated in {:?}", self.config.relay_id, txid, latency);
+        }
+        
+        if payload.is_announcement() {
+            return self.request_announced_transaction(txid, payload.announcer.as_deref()).await;
+        }
+        
+        // The txid is the dedup key, so it must be the one the hex actually hashes to
+        let tx = match hex::decode(tx_hex).ok().and_then(|bytes| deserialize::<Transaction>(&bytes).ok()) {
+            Some(tx) if tx.txid() == txid => tx,
+            Some(tx) => {
+                warn!("Relay-{}: Ignoring transaction broadcast {}: txid {} doesn't match hex of {}", self.config.relay_id, event.id, txid, tx.txid());
+                return Ok(());
+            }
+            None => {
+                warn!("Relay-{}: Ignoring transaction broadcast {}: hex doesn't decode", self.config.relay_id, event.id);
+                return Ok(());
+            }
+        };
+        
+        let recently_rejected = self.rejected_remote.lock().unwrap()
+            .get(&txid)
+            .is_some_and(|at| at.elapsed() < REJECTED_REMOTE_TTL);
+        if recently_rejected {
+            debug!("Relay-{}: Ignoring recently rejected transaction {}", self.config.relay_id, txid);
+            return Ok(());
+        }
+        
+        // Another peer already delivered this transaction. Claimed before validating so a
+        // concurrent delivery is dropped, and released again if this one doesn't get in.
+        if self.remote_transactions.write().await.insert(txid, unix_time_ms()).is_some() {
+            self.duplicates_dropped.fetch_add(1, Ordering::Relaxed);
+            self.count(Metric::DuplicateDropped);
+            debug!("Relay-{}: Dropping duplicate broadcast of {}", self.config.relay_id, txid);
+            return Ok(());
+        }
+        self.tx_hops.lock().unwrap().put(txid, hops.saturating_add(1));
+        if !self.accept_remote_transaction(&tx, tx_hex).await {
+            self.remote_transactions.write().await.remove(&txid);
+        } else if payload.ack_requested {
+            self.notify_acceptance(txid).await;
+        }
+        
+        Ok(())
+    }
+    
+    /// Validate a peer's transaction and submit it to the node, returning whether the node now has it
+    /// 
+    /// A transaction rejected on its own merits, e.g. for its fee or a conflict, is ignored
+    /// from other peers for [`REJECTED_REMOTE_TTL`]. One that fails for any other reason,
+    /// e.g. a node hiccup or a parent not yet seen, is accepted from the next peer that broadcasts it.
+    async fn accept_remote_transaction(&self, tx: &Transaction, tx_hex: &str) -> bool {
+        let txid = tx.txid();
+        match self.validator.validate(tx_hex).await {
+            // Validated on an earlier delivery that may not have reached the node; submitting
+            // again is harmless, the node reports it if it already has the transaction
+            Ok(()) | Err(ValidationError::RecentlyProcessed { txid: _ }) => {}
+            Err(e) => {
+                self.count(Metric::ValidationFailed);
+                warn!("Relay-{}: Transaction {} failed validation: {}", self.config.relay_id, txid, e);
+                if is_final_rejection((&e).into()) {
+                    self.remember_rejection(txid);
+                }
+                return false;
+            }
+        }
+        
+        if self.config.conflict_policy == ConflictPolicy::FirstSeen {
+            if let Some(local) = self.conflicting_mempool_tx(tx).await {
+                info!("Relay-{}: Keeping first-seen {} over conflicting remote transaction {}", self.config.relay_id, local, txid);
+                self.remember_rejection(txid);
+                return false;
+            }
+        }
+        
+        match self.submit_to_bitcoin_node(tx_hex, TxSource::Peer).await {
+            Ok(_) => {
+                // Only transactions the node took are published, not e.g. replacement losers
+                self.publish_transaction(tx, TxSource::Peer);
+                info!("🌐 Relay-{}: Received transaction {} via Nostr", self.config.relay_id, txid);
+                true
+            }
+            Err(e) => {
+                if let Some(rejection) = crate::validation::replacement_rejection(&e) {
+                    info!("Relay-{}: Local node kept its transaction over remote {}: {}", self.config.relay_id, txid, rejection);
+                    self.remember_rejection(txid);
+                    return false;
+                }
+                let error_msg = e.to_string();
+                if error_msg.contains("already in mempool") || error_msg.contains("already exists") {
+                    return true;
+                }
+                warn!("Relay-{}: Failed to submit remote transaction {} to local Bitcoin node: {}", self.config.relay_id, txid, error_msg);
+                if is_final_rejection((&e).into()) {
+                    self.remember_rejection(txid);
+                }
+                false
+            }
+        }
+    }
+    
+    /// Ignore further deliveries of a peer transaction for [`REJECTED_REMOTE_TTL`]
+    fn remember_rejection(&self, txid: Txid) {
+        self.rejected_remote.lock().unwrap().put(txid, std::time::Instant::now());
+    }
+    
+    /// A local mempool transaction spending one of `tx`'s inputs, if any
+    /// 
+    /// A node that can't answer is treated as having no conflict, leaving the decision to it.
+    async fn conflicting_mempool_tx(&self, tx: &Transaction) -> Option<String> {
+        let outpoints: Vec<OutPoint> = tx.input.iter().map(|input| input.previous_output).collect();
+        self.mempool_spender(&tx.txid().to_string(), &outpoints).await
+    }
+    
+    /// A mempool transaction other than `txid` spending one of `outpoints`, if the node can say
+    async fn mempool_spender(&self, txid: &str, outpoints: &[OutPoint]) -> Option<String> {
+        match self.bitcoin_client.get_tx_spending_prevout(outpoints).await {
+            Ok(spenders) => spenders.into_iter().flatten().find(|spender| spender != txid),
+            Err(e) => {
+                warn!("Relay-{}: Could not check mempool spenders of {}'s inputs: {}", self.config.relay_id, txid, e);
+                None
+            }
+        }
+    }
+}
+
+/// Bind the Unix socket listener, replacing a socket file left behind by a previous run
+///
+/// Anything else already at the path is left alone and reported as an error.
+#[cfg(unix)]
+fn bind_unix_listener(path: &std::path::Path) -> std::io::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+    
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    tokio::net::UnixListener::bind(path)
+}
+
+/// Current wall-clock time in milliseconds since the epoch
+fn unix_time_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Whether a submission was turned away by the relay's rate limit or in-flight cap rather than by the node
+fn is_backpressure(error: &crate::RelayError) -> bool {
+    matches!(ResponseCode::from(error), ResponseCode::RateLimited | ResponseCode::NodeBusy)
+}
+
+/// Why a listener couldn't bind, with a hint for the usual operator mistakes
+fn bind_failure_reason(error: &std::io::Error) -> String {
+    match error.kind() {
+        std::io::ErrorKind::AddrInUse => "address already in use, is another relay listening on this port?".to_string(),
+        std::io::ErrorKind::AddrNotAvailable => "address not available on this host".to_string(),
+        std::io::ErrorKind::PermissionDenied => "permission denied, ports below 1024 need elevated privileges".to_string(),
+        _ => error.to_string(),
+    }
+}
+
+/// Whether a rejection is down to the transaction itself, so another delivery of it would fare no better
+fn is_final_rejection(code: ResponseCode) -> bool {
+    matches!(
+        code,
+        ResponseCode::InvalidTransaction
+            | ResponseCode::FeeTooLow
+            | ResponseCode::ReplacementUnderpriced
+            | ResponseCode::MempoolConflict
+            | ResponseCode::PolicyRejected
+    )
+}
+
+/// Content of a response to a client request; only success codes report success
+fn response_content(code: ResponseCode, message: &str) -> Value {
+    json!({
+        "success": code.is_success(),
+        "code": code,
+        "message": message
+    })
+}
+
+/// Content of a response about a transaction
+fn tx_response_content(code: ResponseCode, message: &str, txid: &str) -> Value {
+    let mut content = response_content(code, message);
+    content["txid"] = json!(txid);
+    content
+}
+
+/// Parse the txids of a persisted dedup set, dropping entries that aren't valid txids
+fn parse_seen_txids(seen: HashMap<String, u64>) -> HashMap<Txid, u64> {
+    seen.into_iter()
+        .filter_map(|(txid, recorded)| Some((parse_txid(&txid).ok()?, recorded)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nostr::{build_tx_broadcast_encoded, build_tx_broadcast_payload, build_tx_eviction};
+    use crate::{Network, ValidationConfig};
+    use futures_util::FutureExt;
+
+    fn test_server(config: RelayConfig) -> RelayServer {
+        let bitcoin_client = BitcoinRpcClient::new(
+            config.bitcoin_rpc_url.clone(),
+            config.bitcoin_rpc_auth.username.clone(),
+            config.bitcoin_rpc_auth.password().to_string(),
+        );
+        let validator = TransactionValidator::new(ValidationConfig::default(), 18332);
+        RelayServer::new(bitcoin_client, None, validator, config).unwrap()
+    }
+
+    fn mock_server(config: RelayConfig, mock: &crate::MockBitcoinRpc) -> RelayServer {
+        let validator = TransactionValidator::new(config.validation_config.clone(), 18332);
+        RelayServer::with_rpc(Arc::new(mock.clone()), None, validator, config).unwrap()
+    }
+
+    fn fast_poll_config() -> RelayConfig {
+        RelayConfig::for_network(Network::Regtest, 1)
+            .with_mempool_poll_interval(std::time::Duration::from_millis(10))
+    }
+
+    #[tokio::test]
+    async fn test_monitor_mempool_broadcasts_new_transactions() {
+        let mock = crate::MockBitcoinRpc::new();
+        let preexisting = mock.add_mempool_tx(&crate::test_utils::sample_tx_hex());
+        
+        let server = mock_server(fast_poll_config(), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        
+        // Wait for the initial snapshot before adding a new transaction
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        let mut tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        tx.lock_time = bitcoin::absolute::LockTime::from_consensus(42);
+        let new_txid = mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&tx)));
+        
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), client.recv())
+            .await
+            .expect("broadcast within timeout")
+            .unwrap();
+        let content: Value = serde_json::from_str(&event.content).unwrap();
+        assert_eq!(content["txid"], new_txid);
+        assert_eq!(event.kind.as_u32(), KIND_TX_BROADCAST as u32);
+        
+        // Transactions already in the mempool at startup are not re-gossiped
+        assert!(client.try_recv().is_err());
+        assert_ne!(preexisting, new_txid);
+        
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_large_mempool_fetched_with_bounded_concurrency() {
+        const MEMPOOL_SIZE: u32 = 200;
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(fast_poll_config().with_mempool_fetch_concurrency(16), &mock);
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        // One at a time this would take 10s
+        mock.set_fetch_delay(Some(std::time::Duration::from_millis(50)));
+        let mut client = server.tx_broadcaster.subscribe();
+        let mut tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        for lock_time in 0..MEMPOOL_SIZE {
+            tx.lock_time = bitcoin::absolute::LockTime::from_consensus(lock_time);
+            mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&tx)));
+        }
+        // Fetched just ahead of its parent, the child's ancestor walk races the parent's own task
+        tx.lock_time = bitcoin::absolute::LockTime::from_consensus(MEMPOOL_SIZE);
+        mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&child_of(&tx))));
+        let parent_txid = mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&tx)));
+        
+        tokio::time::timeout(std::time::Duration::from_secs(8), async {
+            while server.broadcast_txids.read().await.len() < MEMPOOL_SIZE as usize + 2 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        }).await.expect("whole mempool gossiped");
+        assert!(mock.max_concurrent_fetches() > 1);
+        assert!(mock.max_concurrent_fetches() <= 16);
+        
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let mut parent_gossips = 0;
+        while let Ok(event) = client.try_recv() {
+            let content: Value = serde_json::from_str(&event.content).unwrap();
+            if content["txid"] == parent_txid.as_str() {
+                parent_gossips += 1;
+            }
+        }
+        assert_eq!(parent_gossips, 1);
+        
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_mempool_sequence_skips_diff() {
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(fast_poll_config().with_mempool_sequence_polling(true), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        while mock.call_count("getrawmempool_sequence") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        // While the node reports the same sequence number, a new transaction goes unnoticed
+        mock.pin_mempool_sequence(Some(0));
+        let txid = mock.add_mempool_tx(&crate::test_utils::sample_tx_hex());
+        let polls = mock.call_count("getrawmempool_sequence");
+        while mock.call_count("getrawmempool_sequence") < polls + 3 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert!(client.try_recv().is_err());
+        assert_eq!(mock.call_count("getrawtransaction"), 0);
+        assert_eq!(mock.call_count("getrawmempool"), 1, "only the startup snapshot uses plain getrawmempool");
+        
+        // Once the sequence moves, the diff runs and gossips it
+        mock.pin_mempool_sequence(None);
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), client.recv())
+            .await
+            .expect("broadcast within timeout")
+            .unwrap();
+        assert_eq!(parse_tx_broadcast(&event).unwrap().txid, txid);
+        
+        handle.abort();
+    }
+
+    /// A block extending the mock's chain from `prev`, holding `txdata`
+    fn block_after(prev: bitcoin::BlockHash, txdata: Vec<Transaction>) -> bitcoin::Block {
+        let mut block = bitcoin::blockdata::constants::genesis_block(bitcoin::Network::Regtest);
+        block.header.prev_blockhash = prev;
+        block.txdata = txdata;
+        block
+    }
+
+    #[tokio::test]
+    async fn test_block_transactions_gossiped_at_depth_after_reorg() {
+        let mock = crate::MockBitcoinRpc::new();
+        let base = block_after(bitcoin::BlockHash::all_zeros(), Vec::new());
+        mock.add_block(base.clone());
+        let server = mock_server(fast_poll_config().with_broadcast_block_tx_min_depth(2), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_blocks(2).await });
+        async fn wait_for_polls(mock: &crate::MockBitcoinRpc, polls: usize) {
+            let start = mock.call_count("getbestblockhash");
+            while mock.call_count("getbestblockhash") < start + polls {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let mut orphaned_tx = child_of(&tx);
+        orphaned_tx.lock_time = bitcoin::absolute::LockTime::from_consensus(1);
+        let mut kept_tx = child_of(&tx);
+        kept_tx.lock_time = bitcoin::absolute::LockTime::from_consensus(2);
+        
+        // A tip block is one confirmation short of the configured depth
+        let orphaned = block_after(base.block_hash(), vec![orphaned_tx]);
+        mock.add_block(orphaned);
+        wait_for_polls(&mock, 3).await;
+        assert!(client.try_recv().is_err());
+        
+        // A competing branch overtakes it, so the orphaned block never reaches depth 2
+        let mut replacement = block_after(base.block_hash(), vec![kept_tx.clone()]);
+        replacement.header.nonce = 1;
+        mock.add_block(replacement.clone());
+        mock.add_block(block_after(replacement.block_hash(), Vec::new()));
+        
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), client.recv())
+            .await
+            .expect("broadcast within timeout")
+            .unwrap();
+        assert_eq!(parse_tx_broadcast(&event).unwrap().txid, kept_tx.txid().to_string());
+        wait_for_polls(&mock, 3).await;
+        assert!(client.try_recv().is_err());
+        
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_mempool_transactions_not_gossiped_again_at_depth() {
+        let mock = crate::MockBitcoinRpc::new();
+        let base = block_after(bitcoin::BlockHash::all_zeros(), Vec::new());
+        mock.add_block(base.clone());
+        let server = mock_server(fast_poll_config().with_broadcast_block_tx_min_depth(2), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        let mempool_monitor = server.clone();
+        let mempool_handle = tokio::spawn(async move { mempool_monitor.monitor_mempool().await });
+        let block_monitor = server.clone();
+        let block_handle = tokio::spawn(async move { block_monitor.monitor_blocks(2).await });
+        async fn wait_for_calls(mock: &crate::MockBitcoinRpc, method: &str, calls: usize) {
+            let start = mock.call_count(method);
+            while mock.call_count(method) < start + calls {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }
+        wait_for_calls(&mock, "getrawmempool", 2).await;
+        
+        let coinbase: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let tx = child_of(&coinbase);
+        let txid = mock.add_mempool_tx(&bitcoin::consensus::encode::serialize_hex(&tx));
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), client.recv())
+            .await
+            .expect("broadcast within timeout")
+            .unwrap();
+        assert_eq!(parse_tx_broadcast(&event).unwrap().txid, txid);
+        
+        // Mined, pruned from the mempool's gossip record, then buried to the gossip depth
+        mock.remove_mempool_tx(&txid);
+        let mined = block_after(base.block_hash(), vec![tx]);
+        mock.add_block(mined.clone());
+        wait_for_calls(&mock, "getrawmempool", 3).await;
+        mock.add_block(block_after(mined.block_hash(), Vec::new()));
+        wait_for_calls(&mock, "getblock", 1).await;
+        wait_for_calls(&mock, "getbestblockhash", 3).await;
+        assert!(client.try_recv().is_err());
+        
+        // With the tip unchanged, polls don't fetch blocks
+        let fetched = mock.call_count("getblock");
+        wait_for_calls(&mock, "getbestblockhash", 3).await;
+        assert_eq!(mock.call_count("getblock"), fetched);
+        
+        mempool_handle.abort();
+        block_handle.abort();
+    }
+
+    /// Start the mempool monitor with eviction notices on a chain of one block, and let it gossip `tx`
+    async fn monitor_gossiping(mock: &crate::MockBitcoinRpc, tx: &Transaction) -> (broadcast::Receiver<SharedEvent>, tokio::task::JoinHandle<Result<()>>) {
+        mock.add_block(block_after(bitcoin::BlockHash::all_zeros(), Vec::new()));
+        let server = mock_server(fast_poll_config().with_eviction_notifications(true), mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        let handle = tokio::spawn(async move { server.monitor_mempool().await });
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        mock.add_mempool_tx(&bitcoin::consensus::encode::serialize_hex(tx));
+        let broadcast = tokio::time::timeout(std::time::Duration::from_secs(2), client.recv()).await.unwrap().unwrap();
+        assert_eq!(parse_tx_broadcast(&broadcast).unwrap().txid, tx.txid().to_string());
+        (client, handle)
+    }
+
+    #[tokio::test]
+    async fn test_shard_gossips_only_matching_txids() {
+        let mock = crate::MockBitcoinRpc::new();
+        let sample: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let txs: Vec<Transaction> = (0..16)
+            .map(|lock_time| {
+                let mut tx = sample.clone();
+                tx.lock_time = bitcoin::absolute::LockTime::from_consensus(lock_time);
+                mock.add_mempool_tx(&bitcoin::consensus::encode::serialize_hex(&tx));
+                tx
+            })
+            .collect();
+        
+        let mut gossiped_by_fleet = HashSet::new();
+        for index in 0..3 {
+            let shard = crate::Shard { index, count: 3 };
+            let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_shard(shard), &mock);
+            let mut client = server.tx_broadcaster.subscribe();
+            for tx in &txs {
+                server.relay_mempool_transaction(&tx.txid()).await.unwrap();
+            }
+            
+            let mut gossiped = HashSet::new();
+            while let Ok(event) = client.try_recv() {
+                gossiped.insert(parse_tx_broadcast(&event).unwrap().txid);
+            }
+            let expected: HashSet<String> = txs.iter().map(|tx| tx.txid()).filter(|txid| shard.contains(txid)).map(|txid| txid.to_string()).collect();
+            assert_eq!(gossiped, expected);
+            gossiped_by_fleet.extend(gossiped);
+        }
+        
+        // Together the fleet gossips every transaction once
+        assert_eq!(gossiped_by_fleet.len(), txs.len());
+        
+        // An unbroadcast parent in another shard is left to the relay that owns it
+        let shard = crate::Shard { index: 0, count: 2 };
+        let parent = txs.iter().find(|tx| !shard.contains(&tx.txid())).unwrap();
+        let child = (0..)
+            .map(|lock_time| {
+                let mut child = child_of(parent);
+                child.lock_time = bitcoin::absolute::LockTime::from_consensus(lock_time);
+                child
+            })
+            .find(|child| shard.contains(&child.txid()))
+            .unwrap();
+        mock.add_mempool_tx(&bitcoin::consensus::encode::serialize_hex(&child));
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_shard(shard), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        server.relay_mempool_transaction(&child.txid()).await.unwrap();
+        assert_eq!(parse_tx_broadcast(&client.try_recv().unwrap()).unwrap().txid, child.txid().to_string());
+        assert!(client.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_departure_publishes_eviction() {
+        let mock = crate::MockBitcoinRpc::new();
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let (mut client, handle) = monitor_gossiping(&mock, &tx).await;
+        
+        // The transaction leaves the mempool while no new block holds it
+        mock.remove_mempool_tx(&tx.txid().to_string());
+        let notice = tokio::time::timeout(std::time::Duration::from_secs(2), client.recv()).await.unwrap().unwrap();
+        assert_eq!(notice.kind, Kind::Ephemeral(crate::nostr::KIND_TX_EVICTED));
+        assert_eq!(crate::nostr::parse_tx_eviction(&notice).unwrap(), vec![tx.txid()]);
+        
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_peer_eviction_forgets_remote_transaction() {
+        let relay_1 = test_server(RelayConfig::for_network(Network::Regtest, 1).with_eviction_notifications(true));
+        let relay_2 = test_server(RelayConfig::for_network(Network::Regtest, 2));
+        let subscription = relay_1.tx_broadcast_subscription(0);
+        assert_eq!(subscription[2]["kinds"], json!([KIND_TX_BROADCAST, KIND_TX_EVICTED]));
+        
+        let txid = Txid::all_zeros();
+        relay_1.remote_transactions.write().await.insert(txid, unix_time_ms());
+        let mut client = relay_1.tx_broadcaster.subscribe();
+        let notice = build_tx_eviction(&[txid], &relay_2.config.relay_id, relay_2.config.network, &relay_2.content_keys).unwrap();
+        relay_1.handle_strfry_message(&json!(["EVENT", "tx_relay_1", notice]).to_string()).await.unwrap();
+        
+        assert!(!relay_1.is_remote_transaction(&txid).await);
+        assert_eq!(client.try_recv().unwrap().id, notice.id);
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_departure_is_silent() {
+        let mock = crate::MockBitcoinRpc::new();
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let (mut client, handle) = monitor_gossiping(&mock, &tx).await;
+        
+        // The transaction is mined into the next block
+        let tip = mock.get_best_block_hash().await.unwrap();
+        mock.add_block(block_after(tip, vec![tx.clone()]));
+        mock.remove_mempool_tx(&tx.txid().to_string());
+        while mock.call_count("getblock") == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(client.try_recv().is_err());
+        
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_departure_confirmed_beyond_lookback_is_silent() {
+        let mock = crate::MockBitcoinRpc::new();
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let (mut client, handle) = monitor_gossiping(&mock, &tx).await;
+        
+        // Mined, then buried deeper than the search reaches before the monitor notices
+        let mut tip = mock.get_best_block_hash().await.unwrap();
+        for txdata in std::iter::once(vec![tx.clone()]).chain(std::iter::repeat_n(Vec::new(), EVICTION_BLOCK_LOOKBACK)) {
+            let block = block_after(tip, txdata);
+            tip = block.block_hash();
+            mock.add_block(block);
+        }
+        mock.remove_mempool_tx(&tx.txid().to_string());
+        while mock.call_count("getblock") < EVICTION_BLOCK_LOOKBACK {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(client.try_recv().is_err());
+        
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_submitter_told_of_replacement() {
+        let mock = crate::MockBitcoinRpc::new();
+        mock.add_block(block_after(bitcoin::BlockHash::all_zeros(), Vec::new()));
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = fast_poll_config()
+            .with_validation(validation_config)
+            .with_replacement_notifications(true);
+        let server = mock_server(config, &mock);
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        let original: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let mut responses = submit_from_client(&server, &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(next_response(&mut responses).await["code"], "ACCEPTED");
+        let polls = mock.call_count("getrawmempool");
+        while mock.call_count("getrawmempool") < polls + 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        // A replacement spending the same inputs takes its place
+        let mut replacement = original.clone();
+        replacement.lock_time = bitcoin::absolute::LockTime::from_consensus(1);
+        mock.remove_mempool_tx(&original.txid().to_string());
+        mock.add_mempool_tx(&bitcoin::consensus::encode::serialize_hex(&replacement));
+        
+        let notice = next_response(&mut responses).await;
+        assert_eq!(notice["success"], false);
+        assert_eq!(notice["code"], "REPLACED");
+        assert_eq!(notice["txid"], original.txid().to_string());
+        assert_eq!(notice["mempool"], "replaced");
+        assert_eq!(notice["replaced_by"], replacement.txid().to_string());
+        assert!(server.submitters.lock().unwrap().is_empty());
+        
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_stale_mempool_transactions_not_broadcast() {
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(fast_poll_config().with_max_broadcast_age(std::time::Duration::from_secs(600)), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        // Both appear in the same poll; the stale one is listed first
+        let mut stale: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        stale.lock_time = bitcoin::absolute::LockTime::from_consensus(1);
+        let mut fresh = stale.clone();
+        fresh.lock_time = bitcoin::absolute::LockTime::from_consensus(2);
+        let stale_txid = stale.txid().to_string();
+        mock.set_mempool_time(&stale_txid, unix_time_ms() / 1000 - 3600);
+        mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&stale)));
+        let fresh_txid = mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&fresh)));
+        
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), client.recv())
+            .await
+            .expect("broadcast within timeout")
+            .unwrap();
+        let content: Value = serde_json::from_str(&event.content).unwrap();
+        assert_eq!(content["txid"], fresh_txid);
+        assert!(client.try_recv().is_err());
+        assert!(!server.is_broadcast_transaction(&stale.txid()).await);
+        
+        handle.abort();
+    }
+
+    /// A transaction spending output 0 of `parent`
+    fn child_of(parent: &Transaction) -> Transaction {
+        let mut child = parent.clone();
+        child.input[0].previous_output = bitcoin::OutPoint::new(parent.txid(), 0);
+        child
+    }
+
+    #[tokio::test]
+    async fn test_broadcasting_child_also_broadcasts_parent() {
+        let mock = crate::MockBitcoinRpc::new();
+        let parent: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let child = child_of(&parent);
+        let grandchild = child_of(&child);
+        let parent_txid = mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&parent)));
+        let child_txid = mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&child)));
+        let grandchild_txid = mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&grandchild)));
+        
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        server.relay_mempool_transaction(&grandchild.txid()).await.unwrap();
+        
+        let mut order = Vec::new();
+        while let Ok(event) = client.try_recv() {
+            let content: Value = serde_json::from_str(&event.content).unwrap();
+            order.push(content["txid"].as_str().unwrap().to_string());
+        }
+        assert_eq!(order, vec![parent_txid.clone(), child_txid.clone(), grandchild_txid]);
+        
+        // Ancestors are not gossiped twice when they show up in the mempool scan
+        server.relay_mempool_transaction(&parent.txid()).await.unwrap();
+        assert!(client.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dedup_snapshot_survives_restart() {
+        let mock = crate::MockBitcoinRpc::new();
+        let txid: Txid = mock.add_mempool_tx(&crate::test_utils::sample_tx_hex()).parse().unwrap();
+        let remote_txid = Txid::all_zeros();
+        let path = std::env::temp_dir().join(format!("relay-dedup-{}.json", std::process::id()));
+        let config = RelayConfig::for_network(Network::Regtest, 1).with_dedup_snapshot(&path, std::time::Duration::from_secs(60));
+        
+        let server = mock_server(config.clone(), &mock);
+        server.relay_mempool_transaction(&txid).await.unwrap();
+        server.remote_transactions.write().await.insert(remote_txid, unix_time_ms());
+        server.save_dedup_snapshot().await.unwrap();
+        
+        // A restarted relay doesn't gossip the same transaction again
+        let restarted = mock_server(config, &mock);
+        assert_eq!(restarted.load_dedup_snapshot().await.unwrap(), 2);
+        std::fs::remove_file(&path).unwrap();
+        
+        let mut client = restarted.tx_broadcaster.subscribe();
+        restarted.relay_mempool_transaction(&txid).await.unwrap();
+        assert!(client.try_recv().is_err());
+        assert!(restarted.is_remote_transaction(&remote_txid).await);
+    }
+
+    #[tokio::test]
+    async fn test_expired_remote_transactions_pruned() {
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(fast_poll_config(), &mock);
+        let mut fresh = Txid::all_zeros().to_byte_array();
+        fresh[0] = 1;
+        let fresh = Txid::from_byte_array(fresh);
+        server.remote_transactions.write().await.insert(Txid::all_zeros(), unix_time_ms() - 2 * 3600 * 1000);
+        server.remote_transactions.write().await.insert(fresh, unix_time_ms());
+        
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        handle.abort();
+        
+        assert_eq!(server.remote_transactions.read().await.keys().collect::<Vec<_>>(), vec![&fresh]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_txids_dropped_from_restored_snapshot() {
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1), &crate::MockBitcoinRpc::new());
+        let snapshot = DedupSnapshot {
+            remote_transactions: HashMap::from([("remote".to_string(), 1_000), (Txid::all_zeros().to_string(), 1_000)]),
+            broadcast_transactions: HashMap::from([("ab".repeat(31), 1_000)]),
+        };
+        
+        assert_eq!(server.restore_dedup_snapshot(snapshot).await, 1);
+        assert_eq!(server.remote_transactions.read().await.keys().collect::<Vec<_>>(), vec![&Txid::all_zeros()]);
+        assert!(server.broadcast_txids.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bitcoin_node_state_reports_breaker() {
+        let mock = crate::MockBitcoinRpc::new();
+        assert_eq!(mock_server(RelayConfig::for_network(Network::Regtest, 1), &mock).bitcoin_node_state(), None);
+        
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_rpc_circuit_breaker(crate::CircuitBreakerConfig::default());
+        let server = mock_server(config, &mock);
+        assert!(server.get_mempool_txids().await.is_ok());
+        assert_eq!(server.bitcoin_node_state(), Some(BreakerState::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_submission_fans_out_to_additional_nodes() {
+        let primary = crate::MockBitcoinRpc::new();
+        let failing = crate::MockBitcoinRpc::new();
+        failing.set_send_error(Some((-26, "min relay fee not met")));
+        let accepting = crate::MockBitcoinRpc::new();
+        
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1), &primary)
+            .with_additional_node("failing", Arc::new(failing.clone()))
+            .with_additional_node("accepting", Arc::new(accepting.clone()));
+        
+        let hex = crate::test_utils::sample_tx_hex();
+        assert!(server.submit_to_bitcoin_node(&hex, TxSource::Client).await.is_ok());
+        assert_eq!(primary.sent_transactions(), vec![hex.clone()]);
+        assert_eq!(accepting.sent_transactions(), vec![hex.clone()]);
+        assert_eq!(failing.call_count("sendrawtransaction"), 1);
+        
+        // Any single node accepting is enough
+        primary.set_send_error(Some((-26, "min relay fee not met")));
+        assert!(server.submit_to_bitcoin_node(&hex, TxSource::Client).await.is_ok());
+        
+        accepting.set_send_error(Some((-26, "min relay fee not met")));
+        assert!(server.submit_to_bitcoin_node(&hex, TxSource::Client).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ancestor_depth_is_bounded() {
+        let mock = crate::MockBitcoinRpc::new();
+        let parent: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let child = child_of(&parent);
+        let grandchild = child_of(&child);
+        mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&parent)));
+        mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&child)));
+        mock.add_mempool_tx(&hex::encode(bitcoin::consensus::serialize(&grandchild)));
+        
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_max_ancestor_depth(1), &mock);
+        let ancestors = server.unbroadcast_ancestors(&grandchild.txid()).await;
+        assert_eq!(ancestors, vec![child.txid()]);
+        
+        let disabled = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_max_ancestor_depth(0), &mock);
+        assert!(disabled.unbroadcast_ancestors(&grandchild.txid()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remote_transaction_submitted_to_node() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let validator = TransactionValidator::new(validation_config, 18332);
+        let server = RelayServer::with_rpc(
+            Arc::new(mock.clone()),
+            None,
+            validator,
+            RelayConfig::for_network(Network::Regtest, 1),
+        ).unwrap();
+        
+        // Relay 2 broadcasts a transaction; relay 1 submits it to its node
+        let remote = test_server(RelayConfig::for_network(Network::Regtest, 2));
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let mut remote_client = remote.tx_broadcaster.subscribe();
+        remote.broadcast_transaction(&tx).await.unwrap();
+        let event = remote_client.recv().await.unwrap();
+        
+        let message = json!(["EVENT", "tx_relay_1", event.as_ref()]);
+        server.handle_strfry_message(&message.to_string()).await.unwrap();
+        
+        assert_eq!(mock.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        assert!(server.is_remote_transaction(&tx.txid()).await);
+    }
+
+    #[tokio::test]
+    async fn test_remote_broadcast_with_invalid_txid_ignored() {
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1), &mock);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let mut payload = TxBroadcastPayload::from_transaction(&tx);
+        payload.txid = "not-a-txid".to_string();
+        let event = build_tx_broadcast_payload(&payload, "2", Some(Network::Regtest), &Keys::generate()).unwrap();
+        
+        server.handle_strfry_message(&json!(["EVENT", "tx_relay_1", event]).to_string()).await.unwrap();
+        
+        assert!(server.remote_transactions.read().await.is_empty());
+        assert!(mock.sent_transactions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remote_broadcast_with_mismatched_txid_ignored() {
+        let mock = crate::MockBitcoinRpc::new();
+        let config = RelayConfig::for_network(Network::Regtest, 1).with_validation(ValidationConfig { enable_validation: false, ..Default::default() });
+        let server = mock_server(config, &mock);
+        let in_mempool: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        mock.add_mempool_tx(&crate::test_utils::sample_tx_hex());
+        let mut other = in_mempool.clone();
+        other.lock_time = bitcoin::absolute::LockTime::from_consensus(1);
+        
+        // Claims the other transaction's txid with the hex of one the node already has
+        let mut payload = TxBroadcastPayload::from_transaction(&in_mempool);
+        payload.txid = other.txid().to_string();
+        let event = build_tx_broadcast_payload(&payload, "2", Some(Network::Regtest), &Keys::generate()).unwrap();
+        server.handle_strfry_message(&json!(["EVENT", "tx_relay_1", event]).to_string()).await.unwrap();
+        
+        assert!(server.remote_transactions.read().await.is_empty());
+        assert!(mock.sent_transactions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_for_invalid_txid_answered_with_error() {
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1), &crate::MockBitcoinRpc::new());
+        let (tx, mut rx) = broadcast::channel(4);
+        server.clients.write().await.insert("client".to_string(), tx);
+        let request = EventBuilder::new(Kind::Ephemeral(KIND_REQUEST_TX), json!({ "txids": ["zz"] }).to_string(), &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        
+        server.handle_request_tx(request, "client").await.unwrap();
+        
+        let response: Value = serde_json::from_str(&rx.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(response["message"], "Validation error: Invalid txid: zz");
+    }
+
+    #[tokio::test]
+    async fn test_request_by_wtxid_resolves_to_seen_transaction() {
+        let mock = crate::MockBitcoinRpc::new();
+        let config = RelayConfig::for_network(Network::Regtest, 1).with_validation(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        });
+        let server = mock_server(config, &mock);
+        let (tx, mut rx) = broadcast::channel(4);
+        server.clients.write().await.insert("client".to_string(), tx);
+        let mut segwit: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        segwit.input[0].witness = bitcoin::Witness::from_slice(&[vec![0x01; 72]]);
+        let mut unseen = child_of(&segwit);
+        unseen.input[0].witness = bitcoin::Witness::from_slice(&[vec![0x02; 72]]);
+        mock.add_transaction(&bitcoin::consensus::encode::serialize_hex(&unseen));
+        receive_from_peer(&server, &segwit).await;
+        
+        let ids = [segwit.wtxid().to_string(), segwit.txid().to_string(), unseen.wtxid().to_string()];
+        let request = EventBuilder::new(Kind::Ephemeral(KIND_REQUEST_TX), json!({ "txids": ids }).to_string(), &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        server.handle_request_tx(request, "client").await.unwrap();
+        
+        let by_wtxid: Value = serde_json::from_str(&rx.recv().await.unwrap().content).unwrap();
+        assert_eq!(by_wtxid["success"], true);
+        assert_eq!(by_wtxid["txid"], segwit.txid().to_string());
+        assert_eq!(by_wtxid["wtxid"], segwit.wtxid().to_string());
+        assert_eq!(by_wtxid["hex"], bitcoin::consensus::encode::serialize_hex(&segwit));
+        
+        let by_txid: Value = serde_json::from_str(&rx.recv().await.unwrap().content).unwrap();
+        assert_eq!(by_txid["hex"], by_wtxid["hex"]);
+        assert!(by_txid.get("wtxid").is_none());
+        
+        // The relay never handled this one, so its wtxid maps to nothing
+        let unknown: Value = serde_json::from_str(&rx.recv().await.unwrap().content).unwrap();
+        assert_eq!(unknown["success"], false);
+        assert_eq!(unknown["txid"], unseen.wtxid().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_filtered_transaction_subscription() {
+        let config = RelayConfig::for_network(Network::Regtest, 1).with_validation(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        });
+        let server = mock_server(config, &crate::MockBitcoinRpc::new());
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        
+        let mut from_peers = server.subscribe_transactions(
+            TxFilter::new().with_source(TxSource::Peer).with_script(tx.output[0].script_pubkey.clone()),
+        );
+        let mut other_script = server.subscribe_transactions(
+            TxFilter::new().with_script(bitcoin::ScriptBuf::from_bytes(vec![0x00, 0x14])),
+        );
+        
+        // A local mempool broadcast is skipped by the peer-only subscription
+        server.broadcast_transaction(&tx).await.unwrap();
+        
+        let remote = test_server(RelayConfig::for_network(Network::Regtest, 2));
+        let mut remote_client = remote.tx_broadcaster.subscribe();
+        remote.broadcast_transaction(&tx).await.unwrap();
+        let event = remote_client.recv().await.unwrap();
+        server.handle_strfry_message(&json!(["EVENT", "tx_relay_1", event.as_ref()]).to_string()).await.unwrap();
+        
+        let relayed = from_peers.recv().await.unwrap();
+        assert_eq!(relayed.source, TxSource::Peer);
+        assert_eq!(relayed.txid, tx.txid());
+        assert!(tokio::time::timeout(std::time::Duration::from_millis(50), other_script.recv()).await.is_err());
+        
+        // Dropping the handles releases their receivers
+        drop(from_peers);
+        drop(other_script);
+        assert_eq!(server.tx_stream.receiver_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_client_message_rejected() {
+        use tokio_tungstenite::tungstenite::error::{CapacityError, Error as WsError};
+        
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1)
+            .with_websocket_max_message_size(1024));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        
+        let handle = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            server.handle_connection(stream, peer_addr).await
+        });
+        
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        client.send(Message::Text("x".repeat(4096))).await.unwrap();
+        
+        let result = tokio::time::timeout(std::time::Duration::from_secs(2), handle)
+            .await
+            .expect("connection handler finishes")
+            .unwrap();
+        match result {
+            Err(crate::RelayError::WebSocket(e)) => {
+                assert!(matches!(e, WsError::Capacity(CapacityError::MessageTooLong { .. })));
+            }
+            other => panic!("Expected capacity error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compression_offer_declined() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1).with_auto_subscribe_new_clients(true));
+        let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+        let handler = server.clone();
+        tokio::spawn(async move { handler.serve_client(server_stream, "browser".to_string()).await });
+        
+        // Browsers always offer permessage-deflate; the connection goes ahead uncompressed
+        let mut request = "ws://localhost/".into_client_request().unwrap();
+        request.headers_mut().insert("Sec-WebSocket-Extensions", "permessage-deflate; client_max_window_bits".parse().unwrap());
+        let (mut client, response) = tokio_tungstenite::client_async(request, client_stream).await.unwrap();
+        assert!(response.headers().get("Sec-WebSocket-Extensions").is_none());
+        
+        while server.clients.read().await.is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        let note = EventBuilder::new_text_note("uncompressed", &[]).to_event(&Keys::generate()).unwrap();
+        let _ = server.tx_broadcaster.send(Arc::new(note));
+        let frame: Value = serde_json::from_str(&client.next().await.unwrap().unwrap().into_text().unwrap()).unwrap();
+        assert_eq!(frame[2]["content"], "uncompressed");
+    }
+
+    /// Serve one WebSocket client on an ephemeral port and wait until it is registered
+    async fn connect_client(server: &RelayServer) -> tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = server.clone();
+        tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            handler.handle_connection(stream, peer_addr).await
+        });
+        
+        let (client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        while server.clients.read().await.is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        client
+    }
+
+    async fn next_event_kind(client: &mut tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>) -> Option<u64> {
+        let message = tokio::time::timeout(std::time::Duration::from_millis(500), client.next()).await.ok()??.ok()?;
+        let value: Value = serde_json::from_str(message.to_text().ok()?).ok()?;
+        value[2]["kind"].as_u64()
+    }
+
+    async fn next_frame(client: &mut tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>) -> Value {
+        let message = tokio::time::timeout(std::time::Duration::from_secs(2), client.next()).await.unwrap().unwrap().unwrap();
+        serde_json::from_str(message.to_text().unwrap()).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_submit_over_unix_socket() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(format!("relay-{}.sock", std::process::id()));
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_unix_socket(&path);
+        let server = mock_server(config, &mock);
+        
+        let listener = bind_unix_listener(&path).unwrap();
+        let acceptor = server.clone();
+        tokio::spawn(async move { acceptor.accept_unix_clients(listener).await });
+        
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (mut client, _) = tokio_tungstenite::client_async("ws://localhost/", stream).await.unwrap();
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), crate::test_utils::sample_tx_hex(), &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        client.send(Message::Text(json!(["EVENT", event]).to_string())).await.unwrap();
+        
+        let message = tokio::time::timeout(std::time::Duration::from_secs(2), client.next())
+            .await
+            .expect("response over the Unix socket")
+            .unwrap()
+            .unwrap();
+        let value: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(value[2]["kind"], KIND_TX_RESPONSE);
+        let response: Value = serde_json::from_str(value[2]["content"].as_str().unwrap()).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(mock.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_listener_replaces_only_sockets() {
+        let path = std::env::temp_dir().join(format!("relay-stale-{}.sock", std::process::id()));
+        
+        // A socket left behind by a previous run is replaced
+        drop(bind_unix_listener(&path).unwrap());
+        drop(bind_unix_listener(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        
+        // A regular file at the path is kept
+        std::fs::write(&path, "not a socket").unwrap();
+        let error = bind_unix_listener(&path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_auto_subscribed_client_receives_broadcasts() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1).with_auto_subscribe_new_clients(true));
+        let mut client = connect_client(&server).await;
+        
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        server.broadcast_transaction(&tx).await.unwrap();
+        
+        assert_eq!(next_event_kind(&mut client).await, Some(KIND_TX_BROADCAST as u64));
+    }
+
+    #[tokio::test]
+    async fn test_client_needs_req_for_broadcasts() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1).with_auto_subscribe_new_clients(false));
+        let mut client = connect_client(&server).await;
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        
+        server.broadcast_transaction(&tx).await.unwrap();
+        assert_eq!(next_event_kind(&mut client).await, None);
+        
+        client.send(Message::Text(json!(["REQ", "txs", {"kinds": [KIND_TX_BROADCAST]}]).to_string())).await.unwrap();
+        assert_eq!(next_frame(&mut client).await, json!(["EOSE", "txs"]));
+        server.broadcast_transaction(&tx).await.unwrap();
+        assert_eq!(next_event_kind(&mut client).await, Some(KIND_TX_BROADCAST as u64));
+    }
+
+    #[tokio::test]
+    async fn test_req_filters_and_close_honoured() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        let mut client = connect_client(&server).await;
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let subscribed = |server: &RelayServer, subscription_id: &'static str| {
+            let server = server.clone();
+            async move {
+                server.subscriptions.read().await.values().any(|subscriptions| subscriptions.contains_key(subscription_id))
+            }
+        };
+        
+        // A filter for another kind replaces the firehose, so broadcasts stop arriving
+        client.send(Message::Text(json!(["REQ", "notes", {"kinds": [1]}]).to_string())).await.unwrap();
+        assert_eq!(next_frame(&mut client).await, json!(["EOSE", "notes"]));
+        server.broadcast_transaction(&tx).await.unwrap();
+        assert_eq!(next_event_kind(&mut client).await, None);
+        
+        // A matching subscription receives them under its own id
+        client.send(Message::Text(json!(["REQ", "txs", {"kinds": [KIND_TX_BROADCAST]}]).to_string())).await.unwrap();
+        assert_eq!(next_frame(&mut client).await, json!(["EOSE", "txs"]));
+        server.broadcast_transaction(&tx).await.unwrap();
+        let frame = next_frame(&mut client).await;
+        assert_eq!(frame[0], "EVENT");
+        assert_eq!(frame[1], "txs");
+        
+        // Closing it stops them again
+        client.send(Message::Text(json!(["CLOSE", "txs"]).to_string())).await.unwrap();
+        while subscribed(&server, "txs").await {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        server.broadcast_transaction(&tx).await.unwrap();
+        assert_eq!(next_event_kind(&mut client).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_per_client_capped() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1).with_max_subscriptions_per_client(2));
+        let mut client = connect_client(&server).await;
+        let subscription_count = |server: &RelayServer| {
+            let server = server.clone();
+            async move { server.subscriptions.read().await.values().map(HashMap::len).sum::<usize>() }
+        };
+        
+        for subscription_id in ["a", "b"] {
+            client.send(Message::Text(json!(["REQ", subscription_id, {"kinds": [KIND_TX_BROADCAST]}]).to_string())).await.unwrap();
+            assert_eq!(next_frame(&mut client).await, json!(["EOSE", subscription_id]));
+        }
+        
+        // A third subscription is refused, while replacing an open one is not
+        client.send(Message::Text(json!(["REQ", "c", {"kinds": [KIND_TX_BROADCAST]}]).to_string())).await.unwrap();
+        assert_eq!(next_frame(&mut client).await, json!(["CLOSED", "c", "error: too many subscriptions"]));
+        client.send(Message::Text(json!(["REQ", "a", {"kinds": [1]}]).to_string())).await.unwrap();
+        assert_eq!(next_frame(&mut client).await, json!(["EOSE", "a"]));
+        
+        // A filter that doesn't parse is refused without touching the open ones
+        client.send(Message::Text(json!(["REQ", "d", {"kinds": "twenty"}]).to_string())).await.unwrap();
+        let closed = next_frame(&mut client).await;
+        assert_eq!(closed[0], "CLOSED");
+        assert_eq!(closed[1], "d");
+        assert!(closed[2].as_str().unwrap().starts_with("invalid: "));
+        client.send(Message::Text(json!(["REQ", 7, {"kinds": [KIND_TX_BROADCAST]}]).to_string())).await.unwrap();
+        assert_eq!(next_frame(&mut client).await, json!(["NOTICE", "invalid: subscription id must be a string"]));
+        assert_eq!(subscription_count(&server).await, 2);
+        assert!(server.subscriptions.read().await.values().all(|subscriptions| !subscriptions.contains_key("")));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_shares_event_across_clients() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        let mut client_a = server.tx_broadcaster.subscribe();
+        let mut client_b = server.tx_broadcaster.subscribe();
+        let mut client_c = server.tx_broadcaster.subscribe();
+        
+        // A large transaction so a deep clone per client would be costly
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let mut large_tx = tx.clone();
+        large_tx.output = vec![tx.output[0].clone(); 2000];
+        server.broadcast_transaction(&large_tx).await.unwrap();
+        
+        let event_a = client_a.recv().await.unwrap();
+        let event_b = client_b.recv().await.unwrap();
+        let event_c = client_c.recv().await.unwrap();
+        
+        // Every client received the very same allocation, not a copy
+        assert!(Arc::ptr_eq(&event_a, &event_b));
+        assert!(Arc::ptr_eq(&event_b, &event_c));
+        assert!(event_a.content.len() > 2000 * 10);
+        
+        // The strfry retry queue shares it too
+        let queued = server.strfry_retry_buffer.lock().await.pop_front().unwrap();
+        assert!(Arc::ptr_eq(&queued, &event_a));
+    }
+
+    #[tokio::test]
+    async fn test_script_filter_gates_broadcast() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let paid_script = tx.output[0].script_pubkey.clone();
+        
+        // Empty filter relays everything
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        assert!(server.matches_script_filter(&tx).await);
+        
+        let watching_paid = test_server(RelayConfig::for_network(Network::Regtest, 1)
+            .with_script_filter(crate::ScriptFilter::new().with_script(paid_script)));
+        assert!(watching_paid.matches_script_filter(&tx).await);
+        
+        let watching_other = test_server(RelayConfig::for_network(Network::Regtest, 1)
+            .with_script_filter(crate::ScriptFilter::new().with_script(bitcoin::ScriptBuf::from_bytes(vec![0x00, 0x14]))));
+        assert!(!watching_other.matches_script_filter(&tx).await);
+    }
+
+    #[tokio::test]
+    async fn test_discovery_populates_roster() {
+        let discovery = crate::DiscoveryConfig { enabled: true, ..Default::default() };
+        let relay_1 = test_server(RelayConfig::for_network(Network::Regtest, 1).with_discovery(discovery.clone()));
+        let relay_2 = test_server(RelayConfig::for_network(Network::Regtest, 2).with_discovery(discovery).with_relay_name("Alice's Miner Relay"));
+        
+        let announcement = relay_2.relay_discovery_event().unwrap();
+        let message = json!(["EVENT", "relay_discovery_1", announcement]);
+        relay_1.handle_strfry_message(&message.to_string()).await.unwrap();
+        
+        let peers = relay_1.known_peers().await;
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].relay_id, "2");
+        assert_eq!(peers[0].listen_addr, "127.0.0.1:7780");
+        assert_eq!(peers[0].networks, vec!["regtest".to_string()]);
+        assert_eq!(peers[0].relay_name.as_deref(), Some("Alice's Miner Relay"));
+        
+        // Our own announcement echoed back is ignored
+        let own = json!(["EVENT", "relay_discovery_1", relay_1.relay_discovery_event().unwrap()]);
+        relay_1.handle_strfry_message(&own.to_string()).await.unwrap();
+        assert_eq!(relay_1.known_peers().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_encoding_follows_peer_capabilities() {
+        let discovery = crate::DiscoveryConfig { enabled: true, ..Default::default() };
+        let relay_1 = test_server(RelayConfig::for_network(Network::Regtest, 1)
+            .with_discovery(discovery.clone())
+            .with_broadcast_encoding(BroadcastEncoding::Compact));
+        let relay_2 = test_server(RelayConfig::for_network(Network::Regtest, 2).with_discovery(discovery.clone()));
+        let mut config_3 = RelayConfig::for_network(Network::Regtest, 2).with_discovery(discovery);
+        config_3.relay_id = "3".to_string();
+        let relay_3 = test_server(config_3);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let mut client = relay_1.tx_broadcaster.subscribe();
+        relay_1.strfry_connected.store(true, Ordering::SeqCst);
+        // The copy gossiped to peers; local clients always get the verbose encoding
+        async fn broadcast_content(server: &RelayServer, tx: &Transaction, client: &mut broadcast::Receiver<SharedEvent>) -> Value {
+            server.broadcast_transaction(tx).await.unwrap();
+            let local: Value = serde_json::from_str(&client.recv().await.unwrap().content).unwrap();
+            assert!(local.get("vsize").is_some());
+            serde_json::from_str(&server.strfry_outbound.recv().now_or_never().unwrap().content).unwrap()
+        }
+        
+        // No peers known yet, so nobody is known to read compact broadcasts
+        assert!(broadcast_content(&relay_1, &tx, &mut client).await.get("vsize").is_some());
+        
+        let capabilities = relay_2.relay_capabilities_event().unwrap();
+        let content: CapabilityAnnouncement = serde_json::from_str(&capabilities.content).unwrap();
+        assert!(content.capabilities.contains(&Capability::CompactEncoding));
+        relay_1.handle_strfry_message(&json!(["EVENT", "relay_discovery_1", capabilities]).to_string()).await.unwrap();
+        relay_1.handle_strfry_message(&json!(["EVENT", "relay_discovery_1", relay_2.relay_discovery_event().unwrap()]).to_string()).await.unwrap();
+        assert_eq!(relay_1.known_peers().await[0].capabilities, content.capabilities);
+        let compact = broadcast_content(&relay_1, &tx, &mut client).await;
+        assert!(compact.get("vsize").is_none());
+        assert_eq!(compact["hex"], bitcoin::consensus::encode::serialize_hex(&tx));
+        
+        // A peer that hasn't advertised compact support sends us back to verbose
+        relay_1.handle_strfry_message(&json!(["EVENT", "relay_discovery_1", relay_3.relay_discovery_event().unwrap()]).to_string()).await.unwrap();
+        assert!(broadcast_content(&relay_1, &tx, &mut client).await.get("vsize").is_some());
+        
+        // Even if another key advertises compact support in its name
+        let spoofed = EventBuilder::new(
+            Kind::Ephemeral(KIND_RELAY_CAPABILITIES),
+            serde_json::to_string(&CapabilityAnnouncement { relay_id: "3".to_string(), capabilities: vec![Capability::CompactEncoding] }).unwrap(),
+            &[crate::TagNamespace::default().tag("relay_id", "3".to_string())]
+        ).to_event(&Keys::generate()).unwrap();
+        relay_1.handle_strfry_message(&json!(["EVENT", "relay_discovery_1", spoofed]).to_string()).await.unwrap();
+        assert!(broadcast_content(&relay_1, &tx, &mut client).await.get("vsize").is_some());
+    }
+
+    fn mock_node_config(port: u16) -> RelayConfig {
+        RelayConfig::new(
+            format!("http://127.0.0.1:{}", port),
+            "ws://127.0.0.1:7777",
+            "1",
+            "127.0.0.1:0".parse().unwrap(),
+        ).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_network_guard_rejects_mismatched_node() {
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": {"chain": "main", "blocks": 850000},
+            "error": null,
+            "id": 1
+        })).await;
+        let server = test_server(mock_node_config(port).with_network(Network::Regtest));
+        
+        let result = server.check_node_network().await;
+        assert!(matches!(
+            result,
+            Err(crate::RelayError::Config(crate::ConfigError::NetworkMismatch { configured: Network::Regtest, ref node })) if node == "main"
+        ));
+        
+        // run() refuses to start
+        assert!(server.run().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_network_guard_accepts_matching_node() {
+        let port = crate::test_utils::spawn_mock_rpc(json!({
+            "result": {"chain": "regtest", "blocks": 101},
+            "error": null,
+            "id": 1
+        })).await;
+        let server = test_server(mock_node_config(port).with_network(Network::Regtest));
+        assert!(server.check_node_network().await.is_ok());
+        
+        // No configured network means nothing to check
+        let unchecked = test_server(mock_node_config(1));
+        assert!(unchecked.check_node_network().await.is_ok());
+    }
+
+    #[test]
+    fn test_relay_id_conflict_detected() {
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_relay_id_check(RelayIdCheck::Warn);
+        let relay_a = test_server(config.clone());
+        let relay_b = test_server(config);
+        
+        let presence_a = relay_a.relay_presence_event().unwrap();
+        let presence_b = relay_b.relay_presence_event().unwrap();
+        
+        // Each relay sees the other's presence as a conflict
+        assert!(relay_a.is_relay_id_conflict(&presence_b));
+        assert!(relay_b.is_relay_id_conflict(&presence_a));
+        
+        // A relay's own presence echoed back by strfry is not a conflict
+        assert!(!relay_a.is_relay_id_conflict(&presence_a));
+    }
+
+    #[test]
+    fn test_presence_includes_relay_name() {
+        let unnamed = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        let content: Value = serde_json::from_str(&unnamed.relay_presence_event().unwrap().content).unwrap();
+        assert_eq!(content, json!({ "relay_id": "1" }));
+        
+        let named = test_server(RelayConfig::for_network(Network::Regtest, 1).with_relay_name("Alice's Miner Relay"));
+        let content: Value = serde_json::from_str(&named.relay_presence_event().unwrap().content).unwrap();
+        assert_eq!(content["relay_id"], "1");
+        assert_eq!(content["relay_name"], "Alice's Miner Relay");
+    }
+
+    #[test]
+    fn test_relay_id_no_conflict_for_distinct_ids() {
+        let relay_1 = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        let relay_2 = test_server(RelayConfig::for_network(Network::Regtest, 2));
+        
+        let presence_2 = relay_2.relay_presence_event().unwrap();
+        assert!(!relay_1.is_relay_id_conflict(&presence_2));
+        assert_eq!(relay_1.event_relay_id(&presence_2).as_deref(), Some("2"));
+    }
+
+    #[tokio::test]
+    async fn test_relay_id_conflict_fail_fast_notifies() {
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_relay_id_check(RelayIdCheck::FailFast);
+        let relay_a = test_server(config.clone());
+        let relay_b = test_server(config);
+        
+        let message = json!(["EVENT", "relay_presence_1", relay_b.relay_presence_event().unwrap()]);
+        relay_a.handle_strfry_message(&message.to_string()).await.unwrap();
+        
+        let notified = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            relay_a.relay_id_conflict_notify.notified(),
+        ).await;
+        assert!(notified.is_ok());
+        
+        // Conflicts are only reported once per remote relay
+        assert_eq!(relay_a.relay_id_conflicts.read().await.len(), 1);
+        relay_a.handle_strfry_message(&message.to_string()).await.unwrap();
+        assert_eq!(relay_a.relay_id_conflicts.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_broadcasts_queued_while_disconnected_are_sent_after_reconnect() {
+        let strfry = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let strfry_url = format!("ws://{}", strfry.local_addr().unwrap());
+        let config = RelayConfig::new(
+            "http://127.0.0.1:1",
+            strfry_url,
+            "1",
+            "127.0.0.1:0".parse().unwrap(),
+        ).unwrap();
+        let server = test_server(config);
+        
+        // Strfry is not connected yet, so the broadcast is held for retry
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let txid = tx.txid().to_string();
+        server.broadcast_transaction(&tx).await.unwrap();
+        assert_eq!(server.strfry_retry_buffer.lock().await.len(), 1);
+        
+        let connection = server.clone();
+        tokio::spawn(async move { connection.connect_to_strfry().await });
+        
+        let (stream, _) = strfry.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let received = tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let message: Value = serde_json::from_str(&text).unwrap();
+                if message[0] == "EVENT" && message[1]["kind"] == KIND_TX_BROADCAST {
+                    return Some(message[1].clone());
+                }
+            }
+            None
+        }).await.expect("queued broadcast is flushed").unwrap();
+        
+        let content: Value = serde_json::from_str(received["content"].as_str().unwrap()).unwrap();
+        assert_eq!(content["txid"], txid);
+        assert!(server.strfry_retry_buffer.lock().await.is_empty());
+    }
+
+    /// Read events of `kind` from a fake strfry connection until `count` have arrived
+    async fn read_strfry_events(
+        ws: &mut tokio_tungstenite::WebSocketStream<TcpStream>,
+        kind: u16,
+        count: usize,
+    ) -> Vec<Value> {
+        let mut events = Vec::new();
+        while events.len() < count {
+            match ws.next().await {
+                Some(Ok(Message::Text(text))) => {
+                    let message: Value = serde_json::from_str(&text).unwrap();
+                    if message[0] == "EVENT" && message[1]["kind"] == kind {
+                        events.push(message[1].clone());
+                    }
+                }
+                Some(Ok(_)) => {}
+                _ => break,
+            }
+        }
+        events
+    }
+
+    async fn wait_for_strfry_connected(server: &RelayServer, connected: bool) {
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while server.strfry_connected.load(Ordering::SeqCst) != connected {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }).await.expect("strfry connection state changes");
+    }
+
+    async fn next_strfry_state(states: &mut watch::Receiver<ConnectionState>) -> StrfryState {
+        tokio::time::timeout(std::time::Duration::from_secs(2), states.changed())
+            .await
+            .expect("connection state changes")
+            .unwrap();
+        states.borrow_and_update().strfry
+    }
+
+    #[tokio::test]
+    async fn test_connection_state_follows_strfry_cycle() {
+        let strfry = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = RelayConfig::new(
+            "http://127.0.0.1:1",
+            format!("ws://{}", strfry.local_addr().unwrap()),
+            "1",
+            "127.0.0.1:0".parse().unwrap(),
+        ).unwrap()
+            .with_strfry_reconnect_backoff(crate::BackoffConfig {
+                base_delay: std::time::Duration::from_millis(100),
+                jitter: 0.0,
+                ..Default::default()
+            });
+        let server = test_server(config);
+        let mut states = server.connection_state();
+        assert_eq!(states.borrow().strfry, StrfryState::Disconnected);
+        let started = states.borrow().last_change;
+        
+        let connection = server.clone();
+        tokio::spawn(async move { connection.connect_to_strfry().await });
+        
+        let (stream, _) = strfry.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert_eq!(next_strfry_state(&mut states).await, StrfryState::Connected);
+        assert!(states.borrow().last_change > started);
+        
+        ws.close(None).await.unwrap();
+        drop(ws);
+        assert_eq!(next_strfry_state(&mut states).await, StrfryState::Disconnected);
+        assert_eq!(next_strfry_state(&mut states).await, StrfryState::Reconnecting);
+        
+        let (stream, _) = strfry.accept().await.unwrap();
+        let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert_eq!(next_strfry_state(&mut states).await, StrfryState::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_connection_state_reports_bitcoin_health() {
+        let server = mock_server(fast_poll_config(), &crate::MockBitcoinRpc::new());
+        let mut states = server.connection_state();
+        assert_eq!(states.borrow().bitcoin, BitcoinState::Unhealthy);
+        
+        let monitor = server.clone();
+        tokio::spawn(async move { monitor.monitor_mempool().await });
+        tokio::time::timeout(std::time::Duration::from_secs(2), states.wait_for(|state| state.bitcoin == BitcoinState::Healthy))
+            .await
+            .expect("node reported healthy")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_drains_queued_outbound_events() {
+        let strfry = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = RelayConfig::new(
+            "http://127.0.0.1:1",
+            format!("ws://{}", strfry.local_addr().unwrap()),
+            "1",
+            "127.0.0.1:0".parse().unwrap(),
+        ).unwrap()
+            .with_strfry_reconnect_backoff(crate::BackoffConfig {
+                base_delay: std::time::Duration::from_millis(10),
+                jitter: 0.0,
+                ..Default::default()
+            });
+        let server = test_server(config);
+        
+        let connection = server.clone();
+        tokio::spawn(async move { connection.connect_to_strfry().await });
+        
+        // The first connection is dropped by strfry
+        let (stream, _) = strfry.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        wait_for_strfry_connected(&server, true).await;
+        ws.close(None).await.unwrap();
+        drop(ws);
+        wait_for_strfry_connected(&server, false).await;
+        
+        // Events queued on the outbound channel and in the retry buffer while disconnected
+        let note = Arc::new(EventBuilder::new_text_note("queued", &[]).to_event(&server.content_keys).unwrap());
+        server.strfry_outbound.push(Arc::clone(&note));
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        server.broadcast_transaction(&tx).await.unwrap();
+        
+        let (stream, _) = strfry.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let (broadcasts, notes) = tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            let broadcasts = read_strfry_events(&mut ws, KIND_TX_BROADCAST, 1).await;
+            let notes = read_strfry_events(&mut ws, 1, 1).await;
+            (broadcasts, notes)
+        }).await.expect("queued events are drained after reconnect");
+        
+        assert_eq!(broadcasts.len(), 1);
+        assert_eq!(notes[0]["id"], note.id.to_string());
+        
+        // The receiver belongs to the running connection task
+        assert!(server.connect_to_strfry().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_strfry_retry_buffer_drops_oldest() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1)
+            .with_strfry_retry_buffer_size(2));
+        let events: Vec<SharedEvent> = (0..3)
+            .map(|i| Arc::new(EventBuilder::new_text_note(i.to_string(), &[]).to_event(&server.content_keys).unwrap()))
+            .collect();
+        
+        for event in &events {
+            server.queue_strfry_retry(Arc::clone(event)).await;
+        }
+        
+        let buffer = server.strfry_retry_buffer.lock().await;
+        let ids: Vec<_> = buffer.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![events[1].id, events[2].id]);
+    }
+
+    #[tokio::test]
+    async fn test_full_outbound_queue_applies_policy() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let txs: Vec<Transaction> = (0..3).map(|i| {
+            let mut tx = tx.clone();
+            tx.lock_time = bitcoin::absolute::LockTime::from_consensus(i);
+            tx
+        }).collect();
+        
+        for policy in [crate::OverflowPolicy::DropOldest, crate::OverflowPolicy::Reject] {
+            let server = test_server(RelayConfig::for_network(Network::Regtest, 1)
+                .with_strfry_outbound_queue(2, policy));
+            // Connected, but nothing drains the queue
+            server.strfry_connected.store(true, Ordering::SeqCst);
+            
+            for tx in &txs {
+                server.broadcast_transaction(tx).await.unwrap();
+            }
+            
+            let queued: Vec<String> = std::iter::from_fn(|| {
+                (!server.strfry_outbound.is_empty()).then(|| server.strfry_outbound.recv().now_or_never().unwrap())
+            })
+            .map(|event| parse_tx_broadcast(&event).unwrap().txid)
+            .collect();
+            let expected = match policy {
+                crate::OverflowPolicy::DropOldest => &txs[1..],
+                crate::OverflowPolicy::Reject => &txs[..2],
+            };
+            assert_eq!(queued, expected.iter().map(|tx| tx.txid().to_string()).collect::<Vec<_>>());
+            assert_eq!(server.strfry_events_dropped(), 1);
+            // Overflow is not a disconnect, so nothing waits for a reconnect
+            assert!(server.strfry_retry_buffer.lock().await.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mempool_reconciliation_fetches_missing_transactions() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let mut only_on_a = tx.clone();
+        only_on_a.lock_time = bitcoin::absolute::LockTime::from_consensus(7);
+        
+        let (mock_a, mock_b) = (crate::MockBitcoinRpc::new(), crate::MockBitcoinRpc::new());
+        mock_a.add_mempool_tx(&crate::test_utils::sample_tx_hex());
+        mock_b.add_mempool_tx(&crate::test_utils::sample_tx_hex());
+        let missing_txid = mock_a.add_mempool_tx(&bitcoin::consensus::encode::serialize_hex(&only_on_a));
+        
+        let (keys_a, keys_b) = (Keys::generate(), Keys::generate());
+        let config = |id: u16, keys: &Keys, peer: &Keys| {
+            RelayConfig::for_network(Network::Regtest, id)
+                .with_mempool_reconciliation(true)
+                .with_identity_keys(keys.clone())
+                .with_peer_relay_allowlist([peer.public_key()])
+        };
+        let relay_a = mock_server(config(1, &keys_a, &keys_b), &mock_a);
+        let relay_b = mock_server(config(2, &keys_b, &keys_a), &mock_b);
+        for relay in [&relay_a, &relay_b] {
+            relay.strfry_connected.store(true, Ordering::SeqCst);
+        }
+        // Stands in for strfry: take what one relay published and hand it to the other
+        let deliver = |from: &RelayServer, to: &RelayServer| {
+            let event = from.strfry_outbound.recv().now_or_never().expect("an event was published");
+            let to = to.clone();
+            async move {
+                to.handle_strfry_message(&json!(["EVENT", "sub", event.as_ref()]).to_string()).await.unwrap();
+                event
+            }
+        };
+        
+        relay_b.request_mempool_summary(relay_a.identity_keys.public_key()).await.unwrap();
+        deliver(&relay_b, &relay_a).await;
+        let summary = deliver(&relay_a, &relay_b).await;
+        assert_eq!(summary.kind, Kind::Ephemeral(KIND_MEMPOOL_SUMMARY));
+        let request = deliver(&relay_b, &relay_a).await;
+        assert_eq!(serde_json::from_str::<Value>(&request.content).unwrap()["txids"], json!([missing_txid]));
+        
+        // Relay A gossips only the transaction relay B lacks
+        let broadcast = relay_a.strfry_outbound.recv().now_or_never().unwrap();
+        assert_eq!(parse_tx_broadcast(&broadcast).unwrap().txid, missing_txid);
+        assert!(relay_a.strfry_outbound.is_empty());
+        
+        // Events addressed to another relay are ignored
+        relay_a.request_mempool_summary(Keys::generate().public_key()).await.unwrap();
+        deliver(&relay_a, &relay_b).await;
+        assert!(relay_b.strfry_outbound.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_peer_requests_answered_only_for_trusted_peers_within_limit() {
+        let mock = crate::MockBitcoinRpc::new();
+        let txid = mock.add_mempool_tx(&crate::test_utils::sample_tx_hex());
+        let (peer, stranger) = (Keys::generate(), Keys::generate());
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_mempool_reconciliation(true)
+            .with_peer_relay_allowlist([peer.public_key()]);
+        let server = mock_server(config, &mock);
+        server.strfry_connected.store(true, Ordering::SeqCst);
+        let us = server.identity_keys.public_key();
+        let addressed = |kind: u16, content: Value, keys: &Keys| {
+            EventBuilder::new(Kind::Ephemeral(kind), content.to_string(), &[Tag::PubKey(us, None)]).to_event(keys).unwrap()
+        };
+        
+        let summary_request = addressed(KIND_MEMPOOL_SUMMARY_REQUEST, json!({ "relay_id": "x" }), &stranger);
+        server.handle_strfry_message(&json!(["EVENT", "sub", summary_request]).to_string()).await.unwrap();
+        assert!(server.strfry_outbound.is_empty());
+        
+        // A bad txid doesn't stop the rest of the request from being served
+        let tx_request = addressed(KIND_PEER_TX_REQUEST, json!({ "txids": ["zz", txid] }), &peer);
+        server.handle_strfry_message(&json!(["EVENT", "sub", tx_request]).to_string()).await.unwrap();
+        let broadcast = server.strfry_outbound.recv().now_or_never().unwrap();
+        assert_eq!(parse_tx_broadcast(&broadcast).unwrap().txid, txid);
+        
+        for _ in 0..MAX_PEER_REQUESTS_PER_MINUTE {
+            let summary_request = addressed(KIND_MEMPOOL_SUMMARY_REQUEST, json!({ "relay_id": "x" }), &peer);
+            server.handle_strfry_message(&json!(["EVENT", "sub", summary_request]).to_string()).await.unwrap();
+            assert_eq!(server.strfry_outbound.recv().now_or_never().unwrap().kind, Kind::Ephemeral(KIND_MEMPOOL_SUMMARY));
+        }
+        let summary_request = addressed(KIND_MEMPOOL_SUMMARY_REQUEST, json!({ "relay_id": "x" }), &peer);
+        server.handle_strfry_message(&json!(["EVENT", "sub", summary_request]).to_string()).await.unwrap();
+        assert!(server.strfry_outbound.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_announced_transaction_requested_and_served() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let (mock_a, mock_b) = (crate::MockBitcoinRpc::new(), crate::MockBitcoinRpc::new());
+        mock_a.add_mempool_tx(&crate::test_utils::sample_tx_hex());
+        
+        let (keys_a, keys_b) = (Keys::generate(), Keys::generate());
+        let relay_a = mock_server(
+            RelayConfig::for_network(Network::Regtest, 1)
+                .with_broadcast_encoding(BroadcastEncoding::AnnounceOnly)
+                .with_identity_keys(keys_a.clone())
+                .with_peer_relay_allowlist([keys_b.public_key()]),
+            &mock_a,
+        );
+        let validator = TransactionValidator::new(ValidationConfig { enable_validation: false, ..Default::default() }, 18332);
+        let config_b = RelayConfig::for_network(Network::Regtest, 2)
+            .with_identity_keys(keys_b)
+            .with_peer_relay_allowlist([keys_a.public_key()]);
+        let relay_b = RelayServer::with_rpc(Arc::new(mock_b.clone()), None, validator, config_b).unwrap();
+        for relay in [&relay_a, &relay_b] {
+            relay.strfry_connected.store(true, Ordering::SeqCst);
+        }
+        let deliver = |from: &RelayServer, to: &RelayServer| {
+            let event = from.strfry_outbound.recv().now_or_never().expect("an event was published");
+            let to = to.clone();
+            async move {
+                to.handle_strfry_message(&json!(["EVENT", "sub", event.as_ref()]).to_string()).await.unwrap();
+                event
+            }
+        };
+        
+        // Relay A announces the txid only; relay B asks relay A for the transaction
+        relay_a.broadcast_transaction(&tx).await.unwrap();
+        let announcement = deliver(&relay_a, &relay_b).await;
+        let payload = parse_tx_broadcast(&announcement).unwrap();
+        assert!(payload.is_announcement());
+        assert_eq!(payload.txid, tx.txid().to_string());
+        assert!(mock_b.sent_transactions().is_empty());
+        
+        // The request goes out once the announcement batch is due
+        let request = tokio::time::timeout(std::time::Duration::from_secs(2), relay_b.strfry_outbound.recv())
+            .await
+            .expect("request within timeout");
+        relay_a.handle_strfry_message(&json!(["EVENT", "sub", request.as_ref()]).to_string()).await.unwrap();
+        assert_eq!(request.kind, Kind::Ephemeral(KIND_PEER_TX_REQUEST));
+        assert!(request.tags.iter().any(|tag| matches!(tag, Tag::PubKey(pubkey, _) if *pubkey == relay_a.identity_keys.public_key())));
+        
+        // Relay A answers with the full transaction, which relay B submits to its node
+        let full = deliver(&relay_a, &relay_b).await;
+        assert!(!parse_tx_broadcast(&full).unwrap().is_announcement());
+        assert_eq!(mock_b.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        
+        // Announcing a transaction relay B already has doesn't request it again
+        relay_a.broadcast_transaction(&tx).await.unwrap();
+        deliver(&relay_a, &relay_b).await;
+        tokio::time::sleep(ANNOUNCEMENT_BATCH_DELAY * 2).await;
+        assert!(relay_b.strfry_outbound.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_announcements_batched_and_untrusted_ignored() {
+        let mock = crate::MockBitcoinRpc::new();
+        let (announcer, stranger) = (Keys::generate(), Keys::generate());
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_peer_relay_allowlist([announcer.public_key()]), &mock);
+        server.strfry_connected.store(true, Ordering::SeqCst);
+        let txids: Vec<Txid> = (1..=3u8).map(|i| Txid::from_byte_array([i; 32])).collect();
+        
+        for txid in &txids {
+            server.request_announced_transaction(*txid, Some(&announcer.public_key().to_string())).await.unwrap();
+        }
+        server.request_announced_transaction(Txid::from_byte_array([9; 32]), Some(&stranger.public_key().to_string())).await.unwrap();
+        
+        let request = tokio::time::timeout(std::time::Duration::from_secs(2), server.strfry_outbound.recv())
+            .await
+            .expect("request within timeout");
+        assert_eq!(request.kind, Kind::Ephemeral(KIND_PEER_TX_REQUEST));
+        let expected: Vec<String> = txids.iter().map(Txid::to_string).collect();
+        assert_eq!(serde_json::from_str::<Value>(&request.content).unwrap()["txids"], json!(expected));
+        // One mempool lookup for the whole batch
+        assert_eq!(mock.call_count("getrawmempool"), 1);
+        assert_eq!(mock.call_count("getmempoolentry"), 0);
+        tokio::time::sleep(ANNOUNCEMENT_BATCH_DELAY * 2).await;
+        assert!(server.strfry_outbound.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_identity_and_content_keys_sign_separately() {
+        let identity = Keys::generate();
+        let content = Keys::generate();
+        let mock = crate::MockBitcoinRpc::new();
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_identity_keys(identity.clone())
+            .with_content_keys(content.clone());
+        let server = mock_server(config.clone(), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        server.broadcast_transaction(&tx).await.unwrap();
+        let broadcast = client.recv().await.unwrap();
+        assert_eq!(broadcast.pubkey, content.public_key());
+        
+        assert_eq!(server.relay_presence_event().unwrap().pubkey, identity.public_key());
+        assert_eq!(server.relay_discovery_event().unwrap().pubkey, identity.public_key());
+        
+        // After rotating the content key, our earlier broadcasts are still recognized as our own
+        let rotated = mock_server(config.with_content_keys(Keys::generate()), &mock);
+        rotated.handle_remote_transaction((*broadcast).clone()).await.unwrap();
+        assert!(mock.sent_transactions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_response_to_disconnected_client_detected() {
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1), &mock);
+        
+        // The client's outgoing task has died, dropping its receiver
+        let (sender, receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        server.subscriptions.write().await.insert("client".to_string(), HashMap::new());
+        drop(receiver);
+        
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), "zz", &[]).to_event(&Keys::generate()).unwrap();
+        assert!(matches!(
+            server.handle_event(event, "client").await,
+            Err(crate::RelayError::Nostr(NostrError::ClientGone { .. }))
+        ));
+        assert!(!server.clients.read().await.contains_key("client"));
+        assert!(!server.subscriptions.read().await.contains_key("client"));
+    }
+
+    #[tokio::test]
+    async fn test_fee_estimate_request() {
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1), &mock);
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        let request = |content: &str| EventBuilder::new(Kind::Ephemeral(KIND_ESTIMATE_FEE), content, &[]).to_event(&Keys::generate()).unwrap();
+        
+        // Regtest nodes have no fee data
+        server.handle_event(request(""), "client").await.unwrap();
+        let response: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(response["conf_target"], DEFAULT_FEE_CONF_TARGET);
+        assert!(response["fee_rate"].is_null());
+        assert_eq!(response["errors"][0], "Insufficient data or no feerate found");
+        
+        mock.set_fee_rate(Some(0.00012));
+        server.handle_event(request(r#"{"conf_target": 2, "mode": "economical"}"#), "client").await.unwrap();
+        let response: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["fee_rate"], 0.00012);
+        assert_eq!(response["sat_per_vbyte"], 12.0);
+        assert_eq!(response["mode"], "economical");
+        assert_eq!(response["blocks"], 2);
+        
+        server.handle_event(request("next week"), "client").await.unwrap();
+        let response: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(mock.call_count("estimatesmartfee"), 2);
+    }
+
+    /// Submit `tx_hex` as a kind 20010 event signed by `keys`, returning the relay's response content
+    async fn submit_as(server: &RelayServer, keys: &Keys, tx_hex: &str) -> Value {
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), tx_hex, &[]).to_event(keys).unwrap();
+        server.handle_event(event, "client").await.unwrap();
+        
+        let response = receiver.recv().await.unwrap();
+        serde_json::from_str(&response.content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resubmission_on_same_connection_replays_response() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_client_resubmission_window(std::time::Duration::from_secs(60));
+        let server = mock_server(config, &mock);
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        let tx_hex = crate::test_utils::sample_tx_hex();
+        
+        // The retry arrives while the first submission is still at the node
+        mock.set_send_delay(Some(std::time::Duration::from_millis(100)));
+        let keys = Keys::generate();
+        let first = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), &tx_hex, &[]).to_event(&keys).unwrap();
+        let retry = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), &tx_hex, &[]).to_event(&keys).unwrap();
+        let (first_result, retry_result) = tokio::join!(
+            server.handle_event(first.clone(), "client"),
+            server.handle_event(retry.clone(), "client")
+        );
+        first_result.unwrap();
+        retry_result.unwrap();
+        
+        let mut responses = HashMap::new();
+        for _ in 0..2 {
+            let response = receiver.recv().await.unwrap();
+            let request_id = match &response.tags[0] {
+                Tag::Event(id, _, _) => *id,
+                tag => panic!("unexpected tag {:?}", tag),
+            };
+            responses.insert(request_id, serde_json::from_str::<Value>(&response.content).unwrap());
+        }
+        assert_eq!(responses[&first.id]["success"], true);
+        let txid = deserialize::<Transaction>(&hex::decode(&tx_hex).unwrap()).unwrap().txid();
+        assert_eq!(responses[&first.id]["txid"], txid.to_string());
+        assert_eq!(responses[&retry.id], responses[&first.id]);
+        assert_eq!(mock.sent_transactions().len(), 1);
+        
+        // Another connection submitting the same transaction is processed as usual
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("other".to_string(), sender);
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), &tx_hex, &[]).to_event(&keys).unwrap();
+        server.handle_event(event, "other").await.unwrap();
+        receiver.recv().await.unwrap();
+        assert_eq!(mock.sent_transactions().len(), 2);
+        
+        // A client reusing a disconnected client's id starts afresh
+        server.forget_client("client").await;
+        assert_eq!(server.client_submissions.lock().unwrap().len(), 1);
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), &tx_hex, &[]).to_event(&keys).unwrap();
+        server.handle_event(event, "client").await.unwrap();
+        receiver.recv().await.unwrap();
+        assert_eq!(mock.sent_transactions().len(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_residency_check_reports_evicted_submission() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let delay = std::time::Duration::from_millis(200);
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config.clone())
+            .with_residency_check(delay);
+        let server = mock_server(config, &mock);
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        let tx_hex = crate::test_utils::sample_tx_hex();
+        let submit = |tx_hex: &str| EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), tx_hex, &[]).to_event(&Keys::generate()).unwrap();
+        
+        // Accepted straight away, then gone from the mempool by the time the relay checks
+        mock.set_evict_on_send(true);
+        let event = submit(&tx_hex);
+        let started = tokio::time::Instant::now();
+        server.handle_event(event.clone(), "client").await.unwrap();
+        let accepted: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(started.elapsed(), std::time::Duration::ZERO);
+        assert_eq!(accepted["success"], true);
+        assert!(accepted.get("resident").is_none());
+        
+        let follow_up = receiver.recv().await.unwrap();
+        assert!(started.elapsed() >= delay);
+        assert_eq!(follow_up.tags[0], Tag::Event(event.id, None, None));
+        let follow_up: Value = serde_json::from_str(&follow_up.content).unwrap();
+        assert_eq!(follow_up["resident"], false);
+        assert_eq!(follow_up["success"], false);
+        assert_eq!(follow_up["code"], "EVICTED");
+        // One lookup for the broadcast's fee, one for the residency check
+        assert_eq!(mock.call_count("getmempoolentry"), 2);
+        
+        mock.set_evict_on_send(false);
+        server.handle_event(submit(&tx_hex), "client").await.unwrap();
+        receiver.recv().await.unwrap();
+        let follow_up: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(follow_up["resident"], true);
+        assert_eq!(follow_up["code"], "ACCEPTED");
+        
+        // A check that couldn't reach the node sends no follow-up
+        mock.set_mempool_entries_unavailable(true);
+        server.handle_event(submit(&tx_hex), "client").await.unwrap();
+        receiver.recv().await.unwrap();
+        assert!(tokio::time::timeout(delay * 2, receiver.recv()).await.is_err());
+        assert_eq!(mock.call_count("getmempoolentry"), 4);
+        mock.set_mempool_entries_unavailable(false);
+        
+        // Without the check there is no follow-up
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        server.handle_event(submit(&tx_hex), "client").await.unwrap();
+        let accepted: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert!(accepted.get("resident").is_none());
+        assert!(tokio::time::timeout(delay * 2, receiver.recv()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_submission_published_once_as_client() {
+        let mock = crate::MockBitcoinRpc::new();
+        let config = fast_poll_config().with_validation(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        });
+        let server = mock_server(config, &mock);
+        let mut relayed = server.subscribe_transactions(TxFilter::new());
+        
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        let tx_hex = crate::test_utils::sample_tx_hex();
+        let response = submit_as(&server, &Keys::generate(), &tx_hex).await;
+        assert_eq!(response["success"], true);
+        
+        // The monitor then finds the submission in the mempool without publishing it again
+        let polls = mock.call_count("getrawmempool");
+        while mock.call_count("getrawmempool") < polls + 3 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        let first = relayed.recv().await.unwrap();
+        assert_eq!(first.source, TxSource::Client);
+        assert_eq!(first.txid.to_string(), response["txid"]);
+        assert!(tokio::time::timeout(std::time::Duration::from_millis(50), relayed.recv()).await.is_err());
+        
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_embedder_submission_already_known_to_node() {
+        let mock = crate::MockBitcoinRpc::new();
+        let config = RelayConfig::for_network(Network::Regtest, 1).with_validation(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        });
+        let server = mock_server(config, &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        mock.set_send_error(Some((-27, "Transaction already in mempool")));
+        
+        // Not an error; the first submission still gossips it
+        let outcome = server.submit_transaction(&crate::test_utils::sample_tx_hex()).await.unwrap();
+        assert!(!outcome.submitted);
+        assert!(outcome.broadcast);
+        assert!(client.try_recv().is_ok());
+        
+        // Submitted again, it isn't gossiped a second time
+        let again = server.submit_transaction(&crate::test_utils::sample_tx_hex()).await.unwrap();
+        assert_eq!(again.txid, outcome.txid);
+        assert!(!again.submitted);
+        assert!(!again.broadcast);
+        assert!(client.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_embedder_submission_gossiped_once_across_monitor_ticks() {
+        let mock = crate::MockBitcoinRpc::new();
+        let config = fast_poll_config().with_validation(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        });
+        let server = mock_server(config, &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        // Several polls run while the node is still taking the transaction
+        mock.set_send_delay(Some(std::time::Duration::from_millis(50)));
+        let txid = server.submit_transaction(&crate::test_utils::sample_tx_hex()).await.unwrap().txid;
+        let polls = mock.call_count("getrawmempool");
+        while mock.call_count("getrawmempool") < polls + 3 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        let event = client.recv().await.unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&event.content).unwrap()["txid"], txid);
+        assert!(client.try_recv().is_err());
+        assert!(server.dedup_snapshot().await.broadcast_transactions.contains_key(&txid));
+        
+        handle.abort();
+    }
+
+    /// Forward `tx_hex` as a kind 20022 event signed by `keys`, returning the relay's response content
+    async fn forward_as(server: &RelayServer, keys: &Keys, tx_hex: &str) -> Value {
+        forward_with_tags(server, keys, tx_hex, &[]).await
+    }
+
+    async fn forward_with_tags(server: &RelayServer, keys: &Keys, tx_hex: &str, tags: &[Tag]) -> Value {
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("peer".to_string(), sender);
+        
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_FORWARD_TX), tx_hex, tags).to_event(keys).unwrap();
+        server.handle_event(event, "peer").await.unwrap();
+        
+        let response = receiver.recv().await.unwrap();
+        serde_json::from_str(&response.content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_tx_accepted_from_allowlisted_relay_only() {
+        let peer = Keys::generate();
+        let mock = crate::MockBitcoinRpc::new();
+        // Client policies don't apply to forwards
+        let config = allowlist_config(&Keys::generate())
+            .with_require_auth(true)
+            .with_peer_relay_allowlist([peer.public_key()]);
+        let server = mock_server(config, &mock);
+        let mut peer_txs = server.subscribe_transactions(TxFilter::new().with_source(TxSource::Peer));
+        let tx_hex = crate::test_utils::sample_tx_hex();
+        
+        let response = forward_as(&server, &Keys::generate(), &tx_hex).await;
+        assert_eq!(response["code"], "UNAUTHORIZED");
+        assert!(mock.sent_transactions().is_empty());
+        
+        let response = forward_as(&server, &peer, &tx_hex).await;
+        assert_eq!(response["success"], true);
+        assert_eq!(mock.sent_transactions(), vec![tx_hex.clone()]);
+        assert_eq!(peer_txs.recv().await.unwrap().txid.to_string(), response["txid"]);
+        
+        // Trust as a peer relay grants nothing on the client submission kind
+        let response = submit_as(&server, &peer, &tx_hex).await;
+        assert_eq!(response["code"], "UNAUTHORIZED");
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_tx_refused_from_discovered_relay() {
+        let discovery = crate::DiscoveryConfig { enabled: true, ..Default::default() };
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let relay_1 = mock_server(RelayConfig::for_network(Network::Regtest, 1)
+            .with_discovery(discovery.clone())
+            .with_validation(validation_config), &mock);
+        let relay_2 = test_server(RelayConfig::for_network(Network::Regtest, 2).with_discovery(discovery));
+        let tx_hex = crate::test_utils::sample_tx_hex();
+        
+        // Anyone can announce themselves, so the roster alone doesn't make a relay trusted
+        let announcement = json!(["EVENT", "relay_discovery_1", relay_2.relay_discovery_event().unwrap()]);
+        relay_1.handle_strfry_message(&announcement.to_string()).await.unwrap();
+        let pubkey = relay_2.identity_keys.public_key().to_string();
+        assert!(relay_1.known_peers().await.iter().any(|peer| peer.pubkey == pubkey));
+        
+        let response = forward_as(&relay_1, &relay_2.identity_keys, &tx_hex).await;
+        assert_eq!(response["code"], "UNAUTHORIZED");
+        assert!(mock.sent_transactions().is_empty());
+    }
+
+    fn allowlist_config(trusted: &Keys) -> RelayConfig {
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_submitter_allowlist([trusted.public_key()])
+    }
+
+    #[tokio::test]
+    async fn test_allowlisted_submitter_accepted() {
+        let trusted = Keys::generate();
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(allowlist_config(&trusted), &mock);
+        
+        let response = submit_as(&server, &trusted, &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(response["success"], true);
+        assert_eq!(response["code"], "ACCEPTED");
+        assert_eq!(mock.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_submitter_rejected() {
+        let trusted = Keys::generate();
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(allowlist_config(&trusted), &mock);
+        
+        let response = submit_as(&server, &Keys::generate(), &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(response["success"], false);
+        assert_eq!(response["code"], "UNAUTHORIZED");
+        assert!(response["message"].as_str().unwrap().contains("Unauthorized submitter"));
+        assert!(mock.sent_transactions().is_empty());
+        
+        // Claiming a trusted pubkey without its signature is rejected too
+        let mut forged = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), "00", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        forged.pubkey = trusted.public_key();
+        assert!(!server.is_authorized_submitter(&forged));
+    }
+
+    #[tokio::test]
+    async fn test_global_submission_limit_shared_across_sources() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_submission_rate_limit(crate::SubmissionRateLimit {
+                per_second: 0.1,
+                burst: 2,
+                max_queued: 1,
+                max_wait: std::time::Duration::from_millis(50),
+            });
+        let server = mock_server(config, &mock);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        
+        // WebSocket client submission takes the first token
+        let response = submit_as(&server, &Keys::generate(), &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(response["success"], true);
+        
+        // A remote relay's broadcast takes the second
+        let mut child = tx.clone();
+        child.input[0].previous_output = bitcoin::OutPoint::new(tx.txid(), 0);
+        let remote = test_server(RelayConfig::for_network(Network::Regtest, 2));
+        let mut remote_client = remote.tx_broadcaster.subscribe();
+        remote.broadcast_transaction(&child).await.unwrap();
+        let event = remote_client.recv().await.unwrap();
+        server.handle_strfry_message(&json!(["EVENT", "tx_relay_1", event.as_ref()]).to_string()).await.unwrap();
+        assert_eq!(mock.sent_transactions().len(), 2);
+        
+        // The bucket is now empty for every source
+        let response = submit_as(&server, &Keys::generate(), &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(response["success"], false);
+        assert_eq!(response["code"], "RATE_LIMITED");
+        assert!(response["message"].as_str().unwrap().contains("rate limit"));
+        assert_eq!(mock.sent_transactions().len(), 2);
+    }
+
+    /// Submit a transaction as `client` and return the channel its responses arrive on
+    async fn submit_from_client(server: &RelayServer, tx_hex: &str) -> broadcast::Receiver<SharedEvent> {
+        let (sender, receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), tx_hex, &[]).to_event(&Keys::generate()).unwrap();
+        server.handle_event(event, "client").await.unwrap();
+        receiver
+    }
+
+    async fn next_response(receiver: &mut broadcast::Receiver<SharedEvent>) -> Value {
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), receiver.recv()).await.unwrap().unwrap();
+        serde_json::from_str(&event.content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_propagation_confirmed_by_peer_acceptance() {
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let (mock_1, mock_2) = (crate::MockBitcoinRpc::new(), crate::MockBitcoinRpc::new());
+        let relay_1 = mock_server(
+            RelayConfig::for_network(Network::Regtest, 1)
+                .with_validation(validation_config.clone())
+                .with_propagation_ack(std::time::Duration::from_secs(2)),
+            &mock_1,
+        );
+        let relay_2 = mock_server(RelayConfig::for_network(Network::Regtest, 2).with_validation(validation_config), &mock_2);
+        for relay in [&relay_1, &relay_2] {
+            relay.strfry_connected.store(true, Ordering::SeqCst);
+        }
+        let deliver = |from: &RelayServer, to: &RelayServer| {
+            let event = from.strfry_outbound.recv().now_or_never().expect("an event was published");
+            let to = to.clone();
+            async move {
+                to.handle_strfry_message(&json!(["EVENT", "sub", event.as_ref()]).to_string()).await.unwrap();
+                event
+            }
+        };
+        
+        let mut responses = submit_from_client(&relay_1, &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(next_response(&mut responses).await["code"], "ACCEPTED");
+        
+        // Relay 2's node takes the broadcast, and relay 2 says so
+        let broadcast = deliver(&relay_1, &relay_2).await;
+        assert!(parse_tx_broadcast(&broadcast).unwrap().ack_requested);
+        assert_eq!(mock_2.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        let acceptance = deliver(&relay_2, &relay_1).await;
+        assert_eq!(acceptance.kind, Kind::Ephemeral(crate::nostr::KIND_TX_ACCEPTED));
+        
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let follow_up = next_response(&mut responses).await;
+        assert_eq!(follow_up["success"], true);
+        assert_eq!(follow_up["code"], "PROPAGATION_CONFIRMED");
+        assert_eq!(follow_up["propagation"], "confirmed");
+        assert_eq!(follow_up["peer_relay_id"], "2");
+        assert_eq!(follow_up["txid"], tx.txid().to_string());
+        assert!(relay_1.awaiting_acceptance.lock().unwrap().is_empty());
+        
+        // Broadcasts that don't ask for one get no acceptance notice
+        let mut other = tx.clone();
+        other.lock_time = bitcoin::absolute::LockTime::from_consensus(1);
+        relay_1.broadcast_transaction(&other).await.unwrap();
+        let broadcast = deliver(&relay_1, &relay_2).await;
+        assert!(!parse_tx_broadcast(&broadcast).unwrap().ack_requested);
+        assert_eq!(mock_2.sent_transactions().len(), 2);
+        assert!(relay_2.strfry_outbound.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_propagation_times_out_without_peer() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_propagation_ack(std::time::Duration::from_millis(50));
+        let server = mock_server(config, &mock);
+        
+        let mut responses = submit_from_client(&server, &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(next_response(&mut responses).await["success"], true);
+        
+        // A peer broadcasting the transaction doesn't say its node took it, and our own notice doesn't count
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let echo = crate::nostr::build_tx_broadcast(&tx, "2", Some(Network::Regtest), &Keys::generate()).unwrap();
+        server.handle_strfry_message(&json!(["EVENT", "tx_relay_1", echo]).to_string()).await.unwrap();
+        let own = crate::nostr::build_tx_acceptance_with_namespace(&[tx.txid()], "1", &crate::TagNamespace::default(), Some(Network::Regtest), &Keys::generate()).unwrap();
+        server.handle_strfry_message(&json!(["EVENT", "tx_relay_1", own]).to_string()).await.unwrap();
+        
+        let follow_up = next_response(&mut responses).await;
+        assert_eq!(follow_up["success"], false);
+        assert_eq!(follow_up["code"], "PROPAGATION_TIMED_OUT");
+        assert_eq!(follow_up["propagation"], "timed_out");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_submission_gets_busy_response() {
+        let mock = crate::MockBitcoinRpc::new();
+        mock.set_send_delay(Some(std::time::Duration::from_millis(500)));
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_inflight_submission_limit(crate::InflightSubmissionLimit {
+                max_inflight: 1,
+                acquire_timeout: std::time::Duration::from_millis(50),
+            });
+        let server = mock_server(config, &mock);
+        let (slow_sender, mut slow_receiver) = broadcast::channel(10);
+        let (busy_sender, mut busy_receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("slow".to_string(), slow_sender);
+        server.clients.write().await.insert("busy".to_string(), busy_sender);
+        
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let submit = |tx_hex: String, client_id: &'static str| {
+            let server = server.clone();
+            let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), tx_hex, &[]).to_event(&Keys::generate()).unwrap();
+            tokio::spawn(async move { server.handle_event(event, client_id).await })
+        };
+        
+        // The slow submission holds the only slot while the node takes its time
+        let slow = submit(crate::test_utils::sample_tx_hex(), "slow");
+        while mock.sent_transactions().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        let busy = submit(bitcoin::consensus::encode::serialize_hex(&child_of(&tx)), "busy");
+        busy.await.unwrap().unwrap();
+        
+        let response: Value = serde_json::from_str(&busy_receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], false);
+        assert!(response["message"].as_str().unwrap().contains("Server busy"));
+        assert_eq!(mock.sent_transactions().len(), 1);
+        
+        slow.await.unwrap().unwrap();
+        let response: Value = serde_json::from_str(&slow_receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_gossip_over_memory_transport() {
+        use crate::{MemoryTransport, NostrTransport};
+        
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let (mut strfry, relay_end) = MemoryTransport::pair();
+        let session = server.clone();
+        let handle = tokio::spawn(async move { session.run_strfry_session(relay_end).await });
+        
+        let subscription: Value = serde_json::from_str(&strfry.recv_message().await.unwrap().unwrap()).unwrap();
+        assert_eq!(subscription[0], "REQ");
+        assert_eq!(subscription[2]["kinds"], json!([KIND_TX_BROADCAST]));
+        wait_for_strfry_connected(&server, true).await;
+        
+        // Our broadcast goes out over the transport
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        server.broadcast_transaction(&tx).await.unwrap();
+        let sent: Value = serde_json::from_str(&strfry.recv_message().await.unwrap().unwrap()).unwrap();
+        assert_eq!(sent[0], "EVENT");
+        assert_eq!(parse_tx_broadcast(&serde_json::from_value(sent[1].clone()).unwrap()).unwrap().txid, tx.txid().to_string());
+        
+        // A peer's broadcast comes in and is submitted to the node
+        let peer_tx = child_of(&tx);
+        let peer_event = crate::nostr::build_tx_broadcast(&peer_tx, "2", Some(Network::Regtest), &Keys::generate()).unwrap();
+        strfry.send_message(json!(["EVENT", "tx_relay_1", peer_event]).to_string()).await.unwrap();
+        while mock.sent_transactions().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(mock.sent_transactions(), vec![bitcoin::consensus::encode::serialize_hex(&peer_tx)]);
+        
+        // Closing the transport ends the session
+        drop(strfry);
+        handle.await.unwrap().unwrap();
+        assert!(!server.strfry_connected.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_strfry_control_frames() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        let event_id = EventId::all_zeros().to_hex();
+        let frames = [
+            json!(["OK", event_id, true, ""]),
+            json!(["OK", event_id, false, "blocked: rate limited"]),
+            json!(["NOTICE", "restricted: we don't accept this kind"]),
+            json!(["EOSE", "someone_else"]),
+            json!(["CLOSED", "someone_else", "error: shutting down"]),
+            // Policy refusals of our own subscriptions are final
+            json!(["CLOSED", "tx_relay_1", "restricted: kind not allowed"]),
+            json!(["CLOSED", "tx_relay_1", "auth-required: sign in first"]),
+            // Partial and unknown frames are ignored
+            json!(["OK"]),
+            json!(["EOSE"]),
+            json!(["CLOSED"]),
+            json!(["EVENT", "tx_relay_1"]),
+            json!(["AUTH", "challenge"]),
+            json!([]),
+            json!({"not": "a frame"}),
+        ];
+        for frame in frames {
+            assert_eq!(server.handle_strfry_message(&frame.to_string()).await.unwrap(), None, "{}", frame);
+        }
+        assert!(!server.is_strfry_backfilled());
+        
+        server.handle_strfry_message(&json!(["EOSE", "tx_relay_1"]).to_string()).await.unwrap();
+        assert!(server.is_strfry_backfilled());
+        
+        let closed = json!(["CLOSED", "tx_relay_1", "error: shutting down"]).to_string();
+        assert_eq!(server.handle_strfry_message(&closed).await.unwrap(), Some("tx_relay_1".to_string()));
+    }
+
+    /// Sign an event with a chosen `created_at`
+    fn event_created_at(builder: EventBuilder, created_at: u64) -> Event {
+        let keys = Keys::generate();
+        let mut unsigned = builder.to_unsigned_event(keys.public_key());
+        unsigned.created_at = nostr::Timestamp::from(created_at);
+        unsigned.id = EventId::new(&unsigned.pubkey, unsigned.created_at, &unsigned.kind, &unsigned.tags, &unsigned.content);
+        unsigned.sign(&keys).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_events_outside_clock_skew_dropped() {
+        let mock = crate::MockBitcoinRpc::new();
+        let sink = Arc::new(RecordingSink::default());
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_metrics_sink(sink.clone())
+            .with_max_clock_skew(std::time::Duration::from_secs(60));
+        let server = mock_server(config, &mock);
+        let relay_tag = Tag::Generic(nostr::TagKind::Custom("relay_id".to_string()), vec!["2".to_string()]);
+        let now = unix_time_ms() / 1000;
+        
+        // Offsets from now in seconds, and whether the broadcast is accepted
+        let cases = [(-30, true), (30, true), (-61, false), (-86_400, false), (86_400, false)];
+        let mut tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        for (offset, accepted) in cases {
+            tx = child_of(&tx);
+            let payload = TxBroadcastPayload::from_transaction(&tx);
+            let builder = EventBuilder::new(
+                Kind::Ephemeral(KIND_TX_BROADCAST),
+                serde_json::to_string(&payload).unwrap(),
+                std::slice::from_ref(&relay_tag),
+            );
+            let event = event_created_at(builder, now.saturating_add_signed(offset));
+            server.handle_strfry_message(&json!(["EVENT", "tx_relay_1", event]).to_string()).await.unwrap();
+            assert_eq!(server.is_remote_transaction(&tx.txid()).await, accepted, "offset {}", offset);
+        }
+        
+        let dropped = sink.reports.lock().unwrap().iter().filter(|(metric, _)| *metric == Metric::ClockSkewDropped).count();
+        assert_eq!(dropped, 3);
+    }
+
+    #[tokio::test]
+    async fn test_closed_subscription_renewed() {
+        use crate::{MemoryTransport, NostrTransport};
+        
+        let base_delay = std::time::Duration::from_millis(50);
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_mempool_reconciliation(true)
+            .with_strfry_reconnect_backoff(crate::BackoffConfig {
+                base_delay,
+                jitter: 0.0,
+                ..Default::default()
+            });
+        let server = test_server(config);
+        let (mut strfry, relay_end) = MemoryTransport::pair();
+        let session = server.clone();
+        let handle = tokio::spawn(async move { session.run_strfry_session(relay_end).await });
+        
+        let mut subscription_ids = Vec::new();
+        for _ in 0..2 {
+            let req: Value = serde_json::from_str(&strfry.recv_message().await.unwrap().unwrap()).unwrap();
+            assert_eq!(req[0], "REQ");
+            subscription_ids.push(req[1].as_str().unwrap().to_string());
+        }
+        assert_eq!(subscription_ids, vec!["tx_relay_1", "relay_reconcile_1"]);
+        wait_for_strfry_connected(&server, true).await;
+        
+        strfry.send_message(json!(["EOSE", "tx_relay_1"]).to_string()).await.unwrap();
+        // Repeated closes are renewed after a growing delay
+        for delay in [base_delay, base_delay * 2] {
+            let closed_at = std::time::Instant::now();
+            strfry.send_message(json!(["CLOSED", "relay_reconcile_1", "error: too many subscriptions"]).to_string()).await.unwrap();
+            let renewed: Value = serde_json::from_str(&strfry.recv_message().await.unwrap().unwrap()).unwrap();
+            assert!(closed_at.elapsed() >= delay);
+            assert_eq!(renewed[0], "REQ");
+            assert_eq!(renewed[1], "relay_reconcile_1");
+        }
+        assert!(server.is_strfry_backfilled());
+        
+        // A policy refusal is not renewed
+        strfry.send_message(json!(["CLOSED", "relay_reconcile_1", "restricted: not allowed"]).to_string()).await.unwrap();
+        assert!(tokio::time::timeout(base_delay * 8, strfry.recv_message()).await.is_err());
+        
+        drop(strfry);
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_close_honored_during_slow_submission() {
+        let mock = crate::MockBitcoinRpc::new();
+        mock.set_send_delay(Some(std::time::Duration::from_secs(5)));
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = server.clone();
+        let connection = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            handler.handle_connection(stream, peer_addr).await
+        });
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), crate::test_utils::sample_tx_hex(), &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        client.send(Message::Text(json!(["EVENT", event]).to_string())).await.unwrap();
+        while mock.sent_transactions().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        
+        // The node is still working on the submission when the client leaves
+        client.send(Message::Close(None)).await.unwrap();
+        tokio::time::timeout(std::time::Duration::from_millis(500), connection)
+            .await
+            .expect("connection closed while the submission was in flight")
+            .unwrap()
+            .unwrap();
+        assert!(server.clients.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_submit_client_round_trip() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        
+        // An in-memory byte stream stands in for the TCP connection
+        let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+        let handler = server.clone();
+        tokio::spawn(async move { handler.serve_client(server_stream, "memory".to_string()).await });
+        let (ws, _) = tokio_tungstenite::client_async("ws://localhost/", client_stream).await.unwrap();
+        let client = crate::RelaySubmitClient::new(ws).with_timeout(std::time::Duration::from_secs(2));
+        
+        let tx_hex = crate::test_utils::sample_tx_hex();
+        let tx: Transaction = deserialize(&hex::decode(&tx_hex).unwrap()).unwrap();
+        let txid = tx.txid().to_string();
+        
+        let response = client.submit(&tx_hex).await.unwrap();
+        assert!(response.success, "{}", response.message);
+        assert_eq!(response.txid, txid);
+        assert_eq!(mock.sent_transactions(), vec![tx_hex.clone()]);
+        
+        let lookup = client.request_tx(&txid).await.unwrap();
+        assert!(lookup.success, "{}", lookup.message);
+        assert_eq!(lookup.hex, Some(tx_hex));
+        
+        let missing = client.request_tx(&"00".repeat(32)).await.unwrap();
+        assert!(!missing.success);
+        assert_eq!(missing.hex, None);
+    }
+
+    #[tokio::test]
+    async fn test_submission_requires_nip42_auth() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let trusted = Keys::generate();
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_require_auth(true)
+            .with_submitter_allowlist([trusted.public_key()]);
+        let server = mock_server(config, &mock);
+        
+        let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+        let handler = server.clone();
+        tokio::spawn(async move { handler.serve_client(server_stream, "memory".to_string()).await });
+        let (mut client, _) = tokio_tungstenite::client_async("ws://localhost/", client_stream).await.unwrap();
+        async fn next_frame(client: &mut tokio_tungstenite::WebSocketStream<tokio::io::DuplexStream>) -> Value {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(2), client.next()).await.unwrap().unwrap().unwrap();
+            serde_json::from_str(message.to_text().unwrap()).unwrap()
+        }
+        let auth = |challenge: &str, keys: &Keys| {
+            let event = EventBuilder::auth(challenge, nostr::Url::parse("ws://localhost/").unwrap()).to_event(keys).unwrap();
+            (event.id, Message::Text(json!(["AUTH", event]).to_string()))
+        };
+        let submit = |keys: &Keys| {
+            let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_TX), crate::test_utils::sample_tx_hex(), &[]).to_event(keys).unwrap();
+            Message::Text(json!(["EVENT", event]).to_string())
+        };
+        
+        let challenge_frame = next_frame(&mut client).await;
+        assert_eq!(challenge_frame[0], "AUTH");
+        let challenge = challenge_frame[1].as_str().unwrap().to_string();
+        
+        // Submitting before authenticating is refused
+        client.send(submit(&trusted)).await.unwrap();
+        let response = next_frame(&mut client).await;
+        let content: Value = serde_json::from_str(response[2]["content"].as_str().unwrap()).unwrap();
+        assert_eq!(content["code"], "UNAUTHORIZED");
+        assert!(content["message"].as_str().unwrap().starts_with("auth-required:"));
+        
+        // A response to the wrong challenge is rejected
+        let (id, message) = auth("not-the-challenge", &trusted);
+        client.send(message).await.unwrap();
+        let ok = next_frame(&mut client).await;
+        assert_eq!(ok[0], "OK");
+        assert_eq!(ok[1], id.to_hex());
+        assert_eq!(ok[2], false);
+        assert!(server.authenticated_clients.read().await.is_empty());
+        
+        // The correct response authenticates the connection, and then submissions go through
+        let (id, message) = auth(&challenge, &trusted);
+        client.send(message).await.unwrap();
+        assert_eq!(next_frame(&mut client).await, json!(["OK", id.to_hex(), true, ""]));
+        assert_eq!(server.authenticated_clients.read().await.get("memory"), Some(&trusted.public_key()));
+        
+        // The authenticated pubkey is the one checked against the allowlist, not the event's author
+        client.send(submit(&Keys::generate())).await.unwrap();
+        let response = next_frame(&mut client).await;
+        let content: Value = serde_json::from_str(response[2]["content"].as_str().unwrap()).unwrap();
+        assert_eq!(content["success"], true, "{}", content);
+        assert_eq!(mock.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+    }
+
+    #[tokio::test]
+    async fn test_idle_client_disconnected() {
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_auto_subscribe_new_clients(true)
+            .with_client_idle_timeout(std::time::Duration::from_millis(200));
+        let server = test_server(config);
+        let connect = |client_id: &str| {
+            let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+            let handler = server.clone();
+            let client_id = client_id.to_string();
+            tokio::spawn(async move { handler.serve_client(server_stream, client_id).await });
+            tokio_tungstenite::client_async("ws://localhost/", client_stream)
+        };
+        let (mut idle, _) = connect("idle").await.unwrap();
+        let (mut active, _) = connect("active").await.unwrap();
+        
+        // Broadcasts keep flowing to both clients, but only the active one sends anything
+        let keys = Keys::generate();
+        for i in 0..8 {
+            let note = EventBuilder::new_text_note(i.to_string(), &[]).to_event(&keys).unwrap();
+            let _ = server.tx_broadcaster.send(Arc::new(note));
+            active.send(Message::Ping(Vec::new())).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        
+        let clients = server.clients.read().await;
+        assert!(clients.contains_key("active"));
+        assert!(!clients.contains_key("idle"));
+        drop(clients);
+        
+        // The idle client's stream ends once the buffered broadcasts are read
+        let closed = tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while let Some(Ok(_)) = idle.next().await {}
+        });
+        closed.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_not_reading_replies_disconnected() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let handler = server.clone();
+        let served = tokio::spawn(async move { handler.serve_client(server_stream, "slow".to_string()).await });
+        let (mut client, _) = tokio_tungstenite::client_async("ws://localhost/", client_stream).await.unwrap();
+        
+        // Every bad REQ earns a NOTICE, which piles up once the client stops reading
+        for _ in 0..(CLIENT_FRAME_QUEUE * 4) {
+            if client.send(Message::Text(json!(["REQ", 7]).to_string())).await.is_err() {
+                break;
+            }
+        }
+        
+        tokio::time::timeout(std::time::Duration::from_secs(2), served).await.unwrap().unwrap().unwrap();
+        assert!(!server.clients.read().await.contains_key("slow"));
+    }
+
+    #[tokio::test]
+    async fn test_remote_broadcast_with_newer_schema_is_relayed() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let remote_keys = Keys::generate();
+        let relay_tag = Tag::Generic(nostr::TagKind::Custom("relay_id".to_string()), vec!["2".to_string()]);
+        
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let content = json!({
+            "schema_version": crate::relay::TX_BROADCAST_SCHEMA_VERSION + 1,
+            "txid": tx.txid().to_string(),
+            "hex": crate::test_utils::sample_tx_hex(),
+            "replaces": "something new"
+        });
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_TX_BROADCAST), content.to_string(), std::slice::from_ref(&relay_tag))
+            .to_event(&remote_keys)
+            .unwrap();
+        server.handle_remote_transaction(event).await.unwrap();
+        assert_eq!(mock.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        
+        // Content that doesn't fit the schema at all is ignored without an error
+        let malformed = EventBuilder::new(Kind::Ephemeral(KIND_TX_BROADCAST), "{\"schema_version\": 99}", &[relay_tag])
+            .to_event(&remote_keys)
+            .unwrap();
+        assert!(server.handle_remote_transaction(malformed).await.is_ok());
+        assert_eq!(mock.sent_transactions().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_package_submitted_to_node() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation = ValidationConfig { use_testmempoolaccept: false, ..Default::default() };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation), &mock);
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        
+        let parent: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let child = child_of(&parent);
+        let package = vec![
+            hex::encode(bitcoin::consensus::serialize(&parent)),
+            hex::encode(bitcoin::consensus::serialize(&child)),
+        ];
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_PACKAGE), json!(package).to_string(), &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        server.handle_event(event, "client").await.unwrap();
+        
+        let response: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(response["txid"], child.txid().to_string());
+        assert_eq!(mock.call_count("submitpackage"), 1);
+        assert_eq!(mock.sent_transactions(), package);
+        
+        // Malformed package content is rejected without reaching the node
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_PACKAGE), "not a package", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        server.handle_event(event, "client").await.unwrap();
+        let response: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(mock.call_count("submitpackage"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_package_validated_before_submitting() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation = ValidationConfig { use_testmempoolaccept: false, max_outputs: Some(1), ..Default::default() };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation), &mock);
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        
+        let parent: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let mut child = child_of(&parent);
+        child.output.push(child.output[0].clone());
+        let package = vec![
+            format!(" {}\n", hex::encode(bitcoin::consensus::serialize(&parent))),
+            hex::encode(bitcoin::consensus::serialize(&child)),
+        ];
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_PACKAGE), json!(package).to_string(), &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        server.handle_event(event, "client").await.unwrap();
+        
+        let response: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], false);
+        let message = response["message"].as_str().unwrap();
+        assert!(message.contains(&child.txid().to_string()));
+        assert!(!message.contains(&parent.txid().to_string()));
+        assert_eq!(mock.call_count("submitpackage"), 0);
+        
+        // Once valid, the trimmed hexes are what reaches the node
+        child.output.pop();
+        let package = vec![package[0].clone(), hex::encode(bitcoin::consensus::serialize(&child))];
+        let event = EventBuilder::new(Kind::Ephemeral(KIND_SUBMIT_PACKAGE), json!(package).to_string(), &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        server.handle_event(event, "client").await.unwrap();
+        
+        let response: Value = serde_json::from_str(&receiver.recv().await.unwrap().content).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(mock.sent_transactions(), vec![package[0].trim().to_string(), package[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_port_in_use_reported_as_bind_failure() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = taken.local_addr().unwrap();
+        let config = RelayConfig::new("http://127.0.0.1:1", "ws://127.0.0.1:1", "1", addr).unwrap();
+        let server = mock_server(config, &crate::MockBitcoinRpc::new());
+        let (logs, _guard) = crate::test_utils::capture_logs();
+        
+        let Err(crate::RelayError::Network(NetworkError::BindFailed { addr: failed })) = server.bind_listener() else {
+            panic!("expected a bind failure");
+        };
+        assert_eq!(failed, addr);
+        assert!(logs.contains("already in use"));
+        
+        let error = server.run().await.unwrap_err();
+        assert!(matches!(error, crate::RelayError::Network(NetworkError::BindFailed { .. })));
+        assert!(error.to_string().contains(&addr.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_fails_fast() {
+        let config = |strfry_url: &str| {
+            RelayConfig::new("http://127.0.0.1:1", strfry_url, "1", "127.0.0.1:0".parse().unwrap())
+                .unwrap()
+                .with_preflight_checks(true)
+        };
+        
+        let server = mock_server(config("http://127.0.0.1:7777"), &crate::MockBitcoinRpc::new());
+        let error = server.run().await.unwrap_err();
+        assert!(matches!(error, crate::RelayError::Config(crate::ConfigError::InvalidUrl { .. })));
+        
+        // Nothing listens on port 1
+        let server = test_server(config("ws://127.0.0.1:7777"));
+        let error = server.run().await.unwrap_err();
+        assert!(matches!(error, crate::RelayError::Config(crate::ConfigError::RpcUnreachable { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_startup_warns_about_isolated_node() {
+        let config = RelayConfig::new("http://127.0.0.1:1", "ws://127.0.0.1:1", "1", "127.0.0.1:0".parse().unwrap()).unwrap();
+        
+        // An isolated node is worth a warning but doesn't stop the relay, preflight checks or not
+        for connections in [0, 8] {
+            let (logs, _guard) = crate::test_utils::capture_logs();
+            let mock = crate::MockBitcoinRpc::new();
+            mock.set_connections(connections);
+            let server = mock_server(config.clone(), &mock);
+            let handle = tokio::spawn(server.clone().run());
+            let started = async {
+                while mock.call_count("getnetworkinfo") == 0 || !logs.contains("listening on") {
+                    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                }
+            };
+            tokio::time::timeout(std::time::Duration::from_secs(2), started).await.unwrap();
+            handle.abort();
+            assert_eq!(mock.call_count("getnetworkinfo"), 1);
+            assert_eq!(logs.contains("Bitcoin node has no p2p connections"), connections == 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accepted_stream_has_nodelay() {
+        for nodelay in [true, false] {
+            let config = RelayConfig::new(
+                "http://127.0.0.1:1",
+                "ws://127.0.0.1:1",
+                "1",
+                "127.0.0.1:0".parse().unwrap(),
+            ).unwrap()
+                .with_listen_backlog(16)
+                .with_tcp_nodelay(nodelay);
+            let server = test_server(config);
+            let listener = server.bind_listener().unwrap();
+            let addr = listener.local_addr().unwrap();
+            
+            let (accepted, _client) = tokio::join!(listener.accept(), TcpStream::connect(addr));
+            let (stream, peer_addr) = accepted.unwrap();
+            let std_stream = stream.into_std().unwrap();
+            let probe = TcpStream::from_std(std_stream.try_clone().unwrap()).unwrap();
+            
+            // The handshake fails on a plain TCP client, but socket options are applied first
+            let _ = tokio::time::timeout(
+                std::time::Duration::from_millis(100),
+                server.handle_connection(TcpStream::from_std(std_stream).unwrap(), peer_addr),
+            ).await;
+            assert_eq!(probe.nodelay().unwrap(), nodelay);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accepts_clients_over_ipv6() {
+        let mock = crate::MockBitcoinRpc::new();
+        // Hosts and containers without IPv6 can't bind the loopback address at all
+        let Ok(probe) = std::net::TcpListener::bind("[::1]:0") else {
+            return;
+        };
+        let port = probe.local_addr().unwrap().port();
+        drop(probe);
+        let addr = SocketAddr::from((std::net::Ipv6Addr::LOCALHOST, port));
+        let config = RelayConfig::new("http://127.0.0.1:1", "ws://127.0.0.1:1", "1", addr).unwrap();
+        let server = mock_server(config, &mock);
+        let running = tokio::spawn(server.clone().run());
+        
+        let mut client = None;
+        for _ in 0..100 {
+            if let Ok((stream, _)) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await {
+                client = Some(stream);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let _client = client.expect("relay accepts connections on [::1]");
+        while server.clients.read().await.is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert!(server.clients.read().await.keys().all(|client_id| client_id.starts_with("[::1]:")));
+        
+        server.shutdown();
+        tokio::time::timeout(std::time::Duration::from_secs(2), running).await.unwrap().unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_repeated_handshake_failures_ban_client() {
+        use tokio::io::AsyncWriteExt;
+        
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let addr = SocketAddr::from((std::net::Ipv4Addr::LOCALHOST, port));
+        let config = RelayConfig::new("http://127.0.0.1:1", "ws://127.0.0.1:1", "1", addr).unwrap()
+            .with_handshake_ban(crate::HandshakeBanPolicy { max_failures: 3, ..Default::default() });
+        let server = test_server(config);
+        let running = tokio::spawn(server.clone().run());
+        
+        let mut failures = 0;
+        while failures < 3 {
+            let Ok(mut scanner) = TcpStream::connect(addr).await else {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                continue;
+            };
+            scanner.write_all(b"SSH-2.0-scanner\r\n\r\n").await.unwrap();
+            scanner.shutdown().await.unwrap();
+            failures += 1;
+        }
+        let client_ip = SocketAddr::from((std::net::Ipv4Addr::LOCALHOST, 0));
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while !server.is_handshake_banned(client_ip) {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }).await.expect("scanner IP is banned");
+        
+        // Even a well-formed client from the banned IP is dropped before the handshake
+        assert!(tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.is_err());
+        assert!(server.clients.read().await.is_empty());
+        
+        server.shutdown();
+        tokio::time::timeout(std::time::Duration::from_secs(2), running).await.unwrap().unwrap().unwrap();
+    }
+
+    /// Metrics sink remembering every report in order
+    #[derive(Debug, Default)]
+    struct RecordingSink {
+        reports: std::sync::Mutex<Vec<(Metric, Option<f64>)>>,
+    }
+
+    impl crate::MetricsSink for RecordingSink {
+        fn incr(&self, metric: Metric) {
+            self.reports.lock().unwrap().push((metric, None));
+        }
+        
+        fn observe(&self, metric: Metric, value: f64) {
+            self.reports.lock().unwrap().push((metric, Some(value)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_reported_to_sink() {
+        let mock = crate::MockBitcoinRpc::new();
+        let sink = Arc::new(RecordingSink::default());
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_metrics_sink(sink.clone());
+        let server = mock_server(config, &mock);
+        
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        server.broadcast_transaction(&tx).await.unwrap();
+        assert_eq!(*sink.reports.lock().unwrap(), vec![(Metric::TxBroadcast, None)]);
+        sink.reports.lock().unwrap().clear();
+        
+        // A peer's broadcast, then a second peer delivering it again
+        let peer_tx = child_of(&tx);
+        let payload = TxBroadcastPayload::from_transaction(&peer_tx).with_first_seen(unix_time_ms() - 2_000);
+        for relay_id in ["2", "3"] {
+            let event = EventBuilder::new(
+                Kind::Ephemeral(KIND_TX_BROADCAST),
+                serde_json::to_string(&payload).unwrap(),
+                &[Tag::Generic(nostr::TagKind::Custom("relay_id".to_string()), vec![relay_id.to_string()])],
+            ).to_event(&Keys::generate()).unwrap();
+            server.handle_remote_transaction(event).await.unwrap();
+        }
+        
+        let reports = sink.reports.lock().unwrap().clone();
+        let metrics: Vec<Metric> = reports.iter().map(|(metric, _)| *metric).collect();
+        assert_eq!(metrics, vec![
+            Metric::TxReceived,
+            Metric::PropagationLatency,
+            Metric::NodeSubmissionAccepted,
+            Metric::TxReceived,
+            Metric::PropagationLatency,
+            Metric::DuplicateDropped,
+        ]);
+        let latency = reports[1].1.unwrap();
+        assert!((2.0..3.0).contains(&latency), "latency {}", latency);
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_processed_transactions() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        assert_eq!(server.stats(), RelayStats::default());
+        
+        // Client submissions: accepted and gossiped, undecodable, refused by the node
+        let accepted: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let refused = child_of(&accepted);
+        let mut responses = submit_from_client(&server, &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(next_response(&mut responses).await["code"], "ACCEPTED");
+        let mut responses = submit_from_client(&server, "zz").await;
+        assert_eq!(next_response(&mut responses).await["code"], "INVALID_HEX");
+        mock.set_send_error(Some((-26, "min relay fee not met")));
+        let mut responses = submit_from_client(&server, &bitcoin::consensus::encode::serialize_hex(&refused)).await;
+        assert_eq!(next_response(&mut responses).await["code"], "FEE_TOO_LOW");
+        mock.set_send_error(None);
+        
+        // An embedder submission, which is also gossiped
+        let embedded = child_of(&refused);
+        server.submit_transaction(&bitcoin::consensus::encode::serialize_hex(&embedded)).await.unwrap();
+        
+        // A peer broadcast, delivered twice
+        let remote = child_of(&embedded);
+        receive_from_peer(&server, &remote).await;
+        receive_from_peer(&server, &remote).await;
+        
+        assert_eq!(server.stats(), RelayStats {
+            broadcast: 2,
+            received: 2,
+            rejected: 2,
+            submitted: 3,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_propagation_latency_recorded() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let relay_tag = Tag::Generic(nostr::TagKind::Custom("relay_id".to_string()), vec!["2".to_string()]);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        
+        // Seen by relay 2 1.5s ago, and by a relay whose clock runs 1 minute ahead
+        for first_seen in [unix_time_ms() - 1_500, unix_time_ms() + 60_000] {
+            let payload = TxBroadcastPayload::from_transaction(&tx).with_first_seen(first_seen);
+            let event = EventBuilder::new(
+                Kind::Ephemeral(KIND_TX_BROADCAST),
+                serde_json::to_string(&payload).unwrap(),
+                std::slice::from_ref(&relay_tag),
+            ).to_event(&Keys::generate()).unwrap();
+            server.handle_remote_transaction(event).await.unwrap();
+        }
+        
+        let histogram = server.propagation_latency();
+        assert_eq!(histogram.count(), 2);
+        let buckets = histogram.buckets();
+        // Clock skew is clamped into the fastest bucket
+        assert_eq!(buckets[0], (Some(10), 1));
+        assert_eq!(buckets[6], (Some(2_500), 1));
+        
+        // With a clock skew limit, the relay running ahead is left out
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(ValidationConfig {
+                enable_validation: false,
+                ..Default::default()
+            })
+            .with_max_clock_skew(std::time::Duration::from_secs(10));
+        let server = mock_server(config, &mock);
+        for first_seen in [unix_time_ms() - 1_500, unix_time_ms() + 60_000] {
+            let payload = TxBroadcastPayload::from_transaction(&tx).with_first_seen(first_seen);
+            let event = EventBuilder::new(
+                Kind::Ephemeral(KIND_TX_BROADCAST),
+                serde_json::to_string(&payload).unwrap(),
+                std::slice::from_ref(&relay_tag),
+            ).to_event(&Keys::generate()).unwrap();
+            server.handle_remote_transaction(event).await.unwrap();
+        }
+        let histogram = server.propagation_latency();
+        assert_eq!(histogram.count(), 1);
+        assert_eq!(histogram.buckets()[6], (Some(2_500), 1));
+    }
+
+    #[tokio::test]
+    async fn test_ready_after_first_poll_with_strfry_connected() {
+        let mock = crate::MockBitcoinRpc::new();
+        let server = mock_server(fast_poll_config(), &mock);
+        server.strfry_connected.store(true, Ordering::SeqCst);
+        assert!(!server.is_ready());
+        
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        while mock.call_count("getrawmempool") < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert!(server.is_ready());
+        
+        // Losing strfry takes the relay out of rotation
+        server.strfry_connected.store(false, Ordering::SeqCst);
+        assert!(!server.is_ready());
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_while_node_unreachable() {
+        // Nothing listens on port 1
+        let mut config = fast_poll_config();
+        config.bitcoin_rpc_url = "http://127.0.0.1:1".to_string();
+        let server = test_server(config);
+        server.strfry_connected.store(true, Ordering::SeqCst);
+        
+        let monitor = server.clone();
+        let handle = tokio::spawn(async move { monitor.monitor_mempool().await });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!server.is_ready());
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_broadcasts_tagged_and_subscribed_by_network() {
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        let subscription = server.tx_broadcast_subscription(1_700_000_000);
+        assert_eq!(subscription[2]["#t"], json!(["regtest"]));
+        assert_eq!(subscription[2]["since"], 1_700_000_000);
+        
+        let mut client = server.tx_broadcaster.subscribe();
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        server.broadcast_transaction(&tx).await.unwrap();
+        let event = client.recv().await.unwrap();
+        assert!(event.tags.contains(&Tag::Hashtag("regtest".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_loop_metrics_count_dropped_broadcasts() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let broadcast_from = |relay_id: &str| {
+            let relay_tag = Tag::Generic(nostr::TagKind::Custom("relay_id".to_string()), vec![relay_id.to_string()]);
+            EventBuilder::new(
+                Kind::Ephemeral(KIND_TX_BROADCAST),
+                serde_json::to_string(&TxBroadcastPayload::from_transaction(&tx)).unwrap(),
+                &[relay_tag],
+            ).to_event(&Keys::generate()).unwrap()
+        };
+        
+        // Our own broadcast echoed back
+        server.handle_remote_transaction(broadcast_from("1")).await.unwrap();
+        assert_eq!(server.loop_metrics(), LoopMetrics { self_echoes_dropped: 1, duplicates_dropped: 0, hop_limit_dropped: 0 });
+        
+        // The first peer delivery is submitted, a second peer's copy is dropped
+        server.handle_remote_transaction(broadcast_from("2")).await.unwrap();
+        server.handle_remote_transaction(broadcast_from("3")).await.unwrap();
+        assert_eq!(server.loop_metrics(), LoopMetrics { self_echoes_dropped: 1, duplicates_dropped: 1, hop_limit_dropped: 0 });
+        assert_eq!(mock.sent_transactions().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_hop_limit_drops_far_travelled_transactions() {
+        let mock = crate::MockBitcoinRpc::new();
+        let peer = Keys::generate();
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(ValidationConfig {
+                enable_validation: false,
+                ..Default::default()
+            })
+            .with_peer_relay_allowlist([peer.public_key()])
+            .with_max_hops(2);
+        let server = mock_server(config, &mock);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let broadcast_after = |hops: u32| {
+            build_tx_broadcast_encoded(
+                &TxBroadcastPayload::from_transaction(&tx).with_hops(hops),
+                BroadcastEncoding::Verbose,
+                "2",
+                None,
+                &Keys::generate(),
+            ).unwrap()
+        };
+        
+        // Two relays before the sender plus the sender is one too many
+        server.handle_remote_transaction(broadcast_after(2)).await.unwrap();
+        assert_eq!(server.loop_metrics().hop_limit_dropped, 1);
+        assert!(mock.sent_transactions().is_empty());
+        
+        // One relay before the sender is within the limit, and is counted when gossiping on
+        server.handle_remote_transaction(broadcast_after(1)).await.unwrap();
+        assert_eq!(mock.sent_transactions().len(), 1);
+        let mut client = server.tx_broadcaster.subscribe();
+        server.broadcast_transaction(&tx).await.unwrap();
+        assert_eq!(parse_tx_broadcast(&client.recv().await.unwrap()).unwrap().hops, 2);
+        
+        // Forwarded transactions carry their hops in a tag
+        let tx_hex = bitcoin::consensus::encode::serialize_hex(&child_of(&tx));
+        let hops_tag = |hops: &str| Tag::Generic(nostr::TagKind::Custom(HOPS_TAG.to_string()), vec![hops.to_string()]);
+        let response = forward_with_tags(&server, &peer, &tx_hex, &[hops_tag("5")]).await;
+        assert_eq!(response["code"], "INVALID_REQUEST");
+        assert_eq!(server.loop_metrics().hop_limit_dropped, 2);
+        let response = forward_with_tags(&server, &peer, &tx_hex, &[hops_tag("1")]).await;
+        assert_eq!(response["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_hops_read_in_tag_namespace() {
+        let mock = crate::MockBitcoinRpc::new();
+        let peer = Keys::generate();
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(ValidationConfig {
+                enable_validation: false,
+                ..Default::default()
+            })
+            .with_tag_namespace("btcrelay")
+            .with_peer_relay_allowlist([peer.public_key()])
+            .with_max_hops(3);
+        let server = mock_server(config, &mock);
+        
+        let hops_tag = crate::TagNamespace::new("btcrelay").tag(HOPS_TAG, "5".to_string());
+        let response = forward_with_tags(&server, &peer, &crate::test_utils::sample_tx_hex(), &[hops_tag]).await;
+        assert_eq!(response["code"], "INVALID_REQUEST");
+        assert_eq!(server.loop_metrics().hop_limit_dropped, 1);
+        
+        // An un-namespaced hops tag isn't this deployment's, so doesn't count
+        let hops_tag = Tag::Generic(nostr::TagKind::Custom(HOPS_TAG.to_string()), vec!["5".to_string()]);
+        let response = forward_with_tags(&server, &peer, &crate::test_utils::sample_tx_hex(), &[hops_tag]).await;
+        assert_eq!(response["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_relays_gossip_over_event_bus() {
+        let bus = crate::EventBus::default();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let (mock_1, mock_2) = (crate::MockBitcoinRpc::new(), crate::MockBitcoinRpc::new());
+        let relay_1 = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_event_bus(bus.clone()), &mock_1);
+        let relay_2 = mock_server(RelayConfig::for_network(Network::Regtest, 2)
+            .with_event_bus(bus)
+            .with_validation(validation_config), &mock_2);
+        let tasks: Vec<_> = [&relay_1, &relay_2]
+            .into_iter()
+            .map(|relay| {
+                let relay = relay.clone();
+                tokio::spawn(async move { relay.connect_to_strfry().await })
+            })
+            .collect();
+        
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while !(relay_1.strfry_connected.load(Ordering::SeqCst) && relay_2.strfry_connected.load(Ordering::SeqCst)) {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }).await.expect("both relays on the bus");
+        
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        relay_1.broadcast_transaction(&tx).await.unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while mock_2.sent_transactions().is_empty() {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }).await.expect("relay 2 submits the broadcast");
+        assert_eq!(mock_2.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        
+        // Relay 1 recognizes its own broadcast coming back
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while relay_1.loop_metrics().self_echoes_dropped == 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        }).await.expect("relay 1 sees its echo");
+        assert!(mock_1.sent_transactions().is_empty());
+        
+        for task in tasks {
+            task.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redelivered_broadcast_handled_once() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let broadcast = build_tx_broadcast_encoded(
+            &TxBroadcastPayload::from_transaction(&tx),
+            BroadcastEncoding::Verbose,
+            "2",
+            None,
+            &Keys::generate(),
+        ).unwrap();
+        
+        // The same event arrives on two subscriptions
+        for subscription in ["tx_broadcasts_a", "tx_broadcasts_b"] {
+            let message = json!(["EVENT", subscription, broadcast]);
+            server.handle_strfry_message(&message.to_string()).await.unwrap();
+        }
+        assert_eq!(server.stats().received, 1);
+        assert_eq!(server.loop_metrics(), LoopMetrics { self_echoes_dropped: 0, duplicates_dropped: 0, hop_limit_dropped: 0 });
+        assert_eq!(mock.sent_transactions().len(), 1);
+        
+        // Another relay's event for the same transaction still counts as a duplicate
+        let other = build_tx_broadcast_encoded(
+            &TxBroadcastPayload::from_transaction(&tx),
+            BroadcastEncoding::Verbose,
+            "3",
+            None,
+            &Keys::generate(),
+        ).unwrap();
+        server.handle_strfry_message(&json!(["EVENT", "tx_broadcasts_a", other]).to_string()).await.unwrap();
+        assert_eq!(server.stats().received, 2);
+        assert_eq!(server.loop_metrics().duplicates_dropped, 1);
+        assert_eq!(mock.sent_transactions().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_broadcasts_read_only_with_shared_secret() {
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let plaintext = build_tx_broadcast_encoded(
+            &TxBroadcastPayload::from_transaction(&tx),
+            BroadcastEncoding::Verbose,
+            "2",
+            None,
+            &Keys::generate(),
+        ).unwrap();
+        let keys = Keys::generate();
+        let encrypted = EventBuilder::new(
+            plaintext.kind,
+            crate::BroadcastCipher::new("miners only").encrypt(&plaintext.content).unwrap(),
+            &plaintext.tags,
+        ).to_event(&keys).unwrap();
+        let relay = |secret: &str| {
+            let mock = crate::MockBitcoinRpc::new();
+            let config = RelayConfig::for_network(Network::Regtest, 1)
+                .with_validation(ValidationConfig {
+                    enable_validation: false,
+                    ..Default::default()
+                })
+                .with_broadcast_encryption(secret);
+            (mock_server(config, &mock), mock)
+        };
+        
+        let (server, mock) = relay("miners only");
+        server.handle_strfry_message(&json!(["EVENT", "tx_broadcasts", encrypted]).to_string()).await.unwrap();
+        assert_eq!(mock.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        
+        // Gossip leaves encrypted while local clients get plaintext
+        let mut client = server.tx_broadcaster.subscribe();
+        server.broadcast_transaction(&tx).await.unwrap();
+        let local = client.recv().await.unwrap();
+        let gossip = server.strfry_retry_buffer.lock().await.pop_front().unwrap();
+        assert_eq!(parse_tx_broadcast(&local).unwrap().txid, tx.txid().to_string());
+        assert!(parse_tx_broadcast(&gossip).is_err());
+        assert_eq!(gossip.tags, local.tags);
+        assert_eq!(crate::BroadcastCipher::new("miners only").decrypt(&gossip.content).unwrap(), local.content);
+        
+        // A relay with another secret, or a plaintext broadcast from outside the group, is ignored
+        let (server, mock) = relay("someone else");
+        server.handle_strfry_message(&json!(["EVENT", "tx_broadcasts", encrypted]).to_string()).await.unwrap();
+        let (plain_server, plain_mock) = relay("miners only");
+        plain_server.handle_strfry_message(&json!(["EVENT", "tx_broadcasts", plaintext]).to_string()).await.unwrap();
+        assert!(mock.sent_transactions().is_empty());
+        assert!(plain_mock.sent_transactions().is_empty());
+        // Dropped before being counted as received
+        assert_eq!(server.stats().received, 0);
+    }
+
+    #[tokio::test]
+    async fn test_peer_events_encrypted_with_shared_secret() {
+        let discovery = crate::DiscoveryConfig { enabled: true, ..Default::default() };
+        let relay = |relay_id: u16, secret: &str| test_server(RelayConfig::for_network(Network::Regtest, relay_id)
+            .with_discovery(discovery.clone())
+            .with_broadcast_encryption(secret));
+        let relay_1 = relay(1, "miners only");
+        let outsider = relay(1, "someone else");
+        let relay_2 = relay(2, "miners only");
+        
+        // Discovery, capabilities, presence and reconciliation events all leave encrypted
+        let peer = Keys::generate().public_key();
+        let events = [
+            relay_2.relay_discovery_event().unwrap(),
+            relay_2.relay_capabilities_event().unwrap(),
+            relay_2.relay_presence_event().unwrap(),
+            relay_2.reconciliation_event(KIND_MEMPOOL_SUMMARY_REQUEST, json!({ "relay_id": "2" }).to_string(), peer).unwrap(),
+        ];
+        for event in &events {
+            assert!(serde_json::from_str::<Value>(&event.content).is_err(), "kind {}", event.kind.as_u32());
+            assert!(crate::BroadcastCipher::new("miners only").decrypt(&event.content).is_ok());
+        }
+        
+        // Only a relay with the same secret reads them
+        for server in [&relay_1, &outsider] {
+            let message = json!(["EVENT", "relay_discovery_1", events[0]]);
+            server.handle_strfry_message(&message.to_string()).await.unwrap();
+        }
+        assert_eq!(relay_1.known_peers().await.len(), 1);
+        assert_eq!(relay_1.known_peers().await[0].relay_id, "2");
+        assert!(outsider.known_peers().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_remote_transaction_accepted_from_next_peer() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let broadcast_from = |relay_id: &str| {
+            let event = build_tx_broadcast_encoded(
+                &TxBroadcastPayload::from_transaction(&tx),
+                BroadcastEncoding::Verbose,
+                relay_id,
+                None,
+                &Keys::generate(),
+            ).unwrap();
+            json!(["EVENT", "tx_broadcasts", event]).to_string()
+        };
+        
+        // The node fails the first delivery, e.g. while it is still syncing
+        mock.set_send_error(Some((-28, "Loading block index...")));
+        server.handle_strfry_message(&broadcast_from("2")).await.unwrap();
+        assert!(mock.sent_transactions().is_empty());
+        
+        mock.set_send_error(None);
+        server.handle_strfry_message(&broadcast_from("3")).await.unwrap();
+        assert_eq!(mock.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        assert_eq!(server.loop_metrics().duplicates_dropped, 0);
+        
+        // Once the node has it, later broadcasts are duplicates
+        server.handle_strfry_message(&broadcast_from("4")).await.unwrap();
+        assert_eq!(server.loop_metrics().duplicates_dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_remote_transaction_ignored_from_next_peer() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let broadcast_from = |relay_id: &str| {
+            let event = build_tx_broadcast_encoded(
+                &TxBroadcastPayload::from_transaction(&tx),
+                BroadcastEncoding::Verbose,
+                relay_id,
+                None,
+                &Keys::generate(),
+            ).unwrap();
+            json!(["EVENT", "tx_broadcasts", event]).to_string()
+        };
+        
+        // The node turns it down for its fee, which another delivery won't change
+        mock.set_send_error(Some((-26, "min relay fee not met")));
+        server.handle_strfry_message(&broadcast_from("2")).await.unwrap();
+        assert_eq!(mock.call_count("sendrawtransaction"), 1);
+        
+        mock.set_send_error(None);
+        server.handle_strfry_message(&broadcast_from("3")).await.unwrap();
+        assert_eq!(mock.call_count("sendrawtransaction"), 1);
+        assert!(mock.sent_transactions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_remote_transaction_accepted_from_next_peer_after_validation() {
+        let mock = crate::MockBitcoinRpc::new();
+        let port = crate::test_utils::spawn_mock_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let validator = TransactionValidator::new(ValidationConfig::default(), port);
+        let server = RelayServer::with_rpc(Arc::new(mock.clone()), None, validator, RelayConfig::for_network(Network::Regtest, 1)).unwrap();
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let broadcast_from = |relay_id: &str| {
+            let event = build_tx_broadcast_encoded(
+                &TxBroadcastPayload::from_transaction(&tx),
+                BroadcastEncoding::Verbose,
+                relay_id,
+                None,
+                &Keys::generate(),
+            ).unwrap();
+            json!(["EVENT", "tx_broadcasts", event]).to_string()
+        };
+        
+        // The first delivery passes validation, caching the txid, but the node fails it
+        mock.set_send_error(Some((-28, "Loading block index...")));
+        server.handle_strfry_message(&broadcast_from("2")).await.unwrap();
+        assert!(mock.sent_transactions().is_empty());
+        
+        // The next peer's delivery is still submitted despite the cached validation
+        mock.set_send_error(None);
+        server.handle_strfry_message(&broadcast_from("3")).await.unwrap();
+        assert_eq!(mock.sent_transactions(), vec![crate::test_utils::sample_tx_hex()]);
+        assert_eq!(server.loop_metrics().duplicates_dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_self_echo_recognized_from_raw_tags() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        let content = serde_json::to_string(&TxBroadcastPayload::from_transaction(&tx)).unwrap();
+        
+        // However the tag was built, an event read off the wire keeps its relay_id
+        let tag_shapes = [
+            Tag::parse(vec!["relay_id", "1"]).unwrap(),
+            Tag::parse(vec!["relay_id", "1", "wss://relay.example"]).unwrap(),
+            Tag::Generic(nostr::TagKind::Custom("relay_id".to_string()), vec!["1".to_string()]),
+        ];
+        for tag in tag_shapes {
+            let event = EventBuilder::new(Kind::Ephemeral(KIND_TX_BROADCAST), content.clone(), &[tag])
+                .to_event(&Keys::generate())
+                .unwrap();
+            let event: Event = serde_json::from_str(&serde_json::to_string(&event).unwrap()).unwrap();
+            assert_eq!(server.event_relay_id(&event).as_deref(), Some("1"));
+            server.handle_remote_transaction(event).await.unwrap();
+        }
+        assert_eq!(server.loop_metrics().self_echoes_dropped, 3);
+        assert!(mock.sent_transactions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tag_namespace_used_in_and_out() {
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_tag_namespace("btcrelay"), &mock);
+        let mut client = server.tx_broadcaster.subscribe();
+        let tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        
+        server.broadcast_transaction(&tx).await.unwrap();
+        let broadcast = client.recv().await.unwrap();
+        assert!(broadcast.tags.iter().any(|tag| tag.as_vec() == vec!["btcrelay:relay_id".to_string(), "1".to_string()]));
+        assert!(!broadcast.tags.iter().any(|tag| tag.as_vec()[0] == "relay_id"));
+        let presence = server.relay_presence_event().unwrap();
+        assert_eq!(server.event_relay_id(&presence).as_deref(), Some("1"));
+        
+        // Our namespaced echo is recognized and dropped
+        server.handle_remote_transaction(broadcast.as_ref().clone()).await.unwrap();
+        assert_eq!(server.loop_metrics().self_echoes_dropped, 1);
+        
+        // Another app's un-namespaced `relay_id` tag is not mistaken for ours
+        let foreign = EventBuilder::new(
+            Kind::Ephemeral(KIND_TX_BROADCAST),
+            serde_json::to_string(&TxBroadcastPayload::from_transaction(&tx)).unwrap(),
+            &[Tag::Generic(nostr::TagKind::Custom("relay_id".to_string()), vec!["1".to_string()])],
+        ).to_event(&Keys::generate()).unwrap();
+        assert_eq!(server.event_relay_id(&foreign), None);
+        server.handle_remote_transaction(foreign).await.unwrap();
+        assert_eq!(server.loop_metrics().self_echoes_dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_underpriced_replacement_reported_to_client() {
+        let mock = crate::MockBitcoinRpc::new();
+        mock.set_send_error(Some((-26, "insufficient fee, rejecting replacement 5a1c..., less fees than conflicting txs; 0.00001 < 0.00002")));
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        
+        let response = submit_as(&server, &Keys::generate(), &crate::test_utils::sample_tx_hex()).await;
+        assert_eq!(response["success"], false);
+        assert_eq!(response["code"], "REPLACEMENT_UNDERPRICED");
+        assert!(response["message"].as_str().unwrap().starts_with("Replacement transaction underpriced"));
+    }
+
+    #[tokio::test]
+    async fn test_rejection_codes() {
+        let keys = Keys::generate();
+        
+        // Rejected by the relay's own validation
+        let server = test_server(RelayConfig::for_network(Network::Regtest, 1));
+        assert_eq!(submit_as(&server, &keys, "not hex").await["code"], "INVALID_HEX");
+        assert_eq!(submit_as(&server, &keys, "0200").await["code"], "INVALID_TRANSACTION");
+        
+        // Rejected by the node
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let server = mock_server(RelayConfig::for_network(Network::Regtest, 1).with_validation(validation_config), &mock);
+        let tx_hex = crate::test_utils::sample_tx_hex();
+        
+        mock.set_send_error(Some((-26, "min relay fee not met, 100 < 141")));
+        let response = submit_as(&server, &keys, &tx_hex).await;
+        assert_eq!(response["success"], false);
+        assert_eq!(response["code"], "FEE_TOO_LOW");
+        
+        mock.set_send_error(Some((-27, "Transaction already in block chain")));
+        assert_eq!(submit_as(&server, &keys, &tx_hex).await["code"], "ALREADY_KNOWN");
+        
+        mock.set_send_error(Some((-26, "bad-txns-inputs-missingorspent")));
+        assert_eq!(submit_as(&server, &keys, &tx_hex).await["code"], "NODE_REJECTED");
+        
+        // Transaction and fee estimate requests
+        let (sender, mut receiver) = broadcast::channel(10);
+        server.clients.write().await.insert("client".to_string(), sender);
+        let request = |kind: u16, content: String| {
+            let server = server.clone();
+            let event = EventBuilder::new(Kind::Ephemeral(kind), content, &[]).to_event(&keys).unwrap();
+            async move {
+                server.handle_event(event, "client").await.unwrap();
+            }
+        };
+        let known = mock.add_mempool_tx(&tx_hex);
+        let unknown = "ff".repeat(32);
+        request(KIND_REQUEST_TX, json!({ "txids": [known, unknown, "zz"] }).to_string()).await;
+        request(KIND_REQUEST_TX, "not json".to_string()).await;
+        request(KIND_ESTIMATE_FEE, String::new()).await;
+        request(KIND_ESTIMATE_FEE, "next week".to_string()).await;
+        
+        let mut codes = Vec::new();
+        while let Ok(response) = receiver.try_recv() {
+            let content: Value = serde_json::from_str(&response.content).unwrap();
+            assert_eq!(content["success"], content["code"] == "OK");
+            codes.push(content["code"].as_str().unwrap().to_string());
+        }
+        assert_eq!(codes, ["OK", "NOT_FOUND", "INVALID_REQUEST", "INVALID_REQUEST", "OK", "INVALID_REQUEST"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_submission_written_to_dead_letter_log() {
+        let path = std::env::temp_dir().join(format!("relay-dead-letters-{}.jsonl", std::process::id()));
+        let mock = crate::MockBitcoinRpc::new();
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_dead_letter_log(&path, 1024 * 1024)
+            .with_submission_rate_limit(crate::SubmissionRateLimit {
+                per_second: 0.001,
+                burst: 3,
+                max_queued: 0,
+                max_wait: std::time::Duration::from_millis(10),
+            });
+        let server = mock_server(config, &mock);
+        let tx_hex = crate::test_utils::sample_tx_hex();
+        let tx: Transaction = deserialize(&hex::decode(&tx_hex).unwrap()).unwrap();
+        
+        // Letters are written in the background
+        let letters_written = |count: usize| {
+            let path = path.clone();
+            async move {
+                tokio::time::timeout(std::time::Duration::from_secs(2), async {
+                    while DeadLetterLog::read(&path).map_or(0, |letters| letters.len()) < count {
+                        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                    }
+                }).await.expect("dead letters written");
+            }
+        };
+        
+        mock.set_send_error(Some((-26, "min relay fee not met, 100 < 141")));
+        submit_as(&server, &Keys::generate(), &tx_hex).await;
+        letters_written(1).await;
+        receive_from_peer(&server, &child_of(&tx)).await;
+        letters_written(2).await;
+        
+        // Transactions the node already has are not dead letters
+        mock.set_send_error(Some((-27, "Transaction already in block chain")));
+        submit_as(&server, &Keys::generate(), &tx_hex).await;
+        // Nor are submissions turned away by the relay's own rate limit
+        mock.set_send_error(None);
+        assert_eq!(submit_as(&server, &Keys::generate(), &tx_hex).await["code"], "RATE_LIMITED");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        
+        let letters = DeadLetterLog::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].txid, tx.txid().to_string());
+        assert_eq!(letters[0].hex, tx_hex);
+        assert_eq!(letters[0].source, TxSource::Client);
+        assert_eq!(letters[0].error, "Bitcoin RPC error: Bitcoin Core error: -26 - min relay fee not met, 100 < 141");
+        assert!(letters[0].timestamp > 0);
+        assert_eq!(letters[1].txid, child_of(&tx).txid().to_string());
+        assert_eq!(letters[1].source, TxSource::Peer);
+    }
+
+    /// A relay whose node already holds `local`, and a peer's double spend of it
+    fn conflict_scenario(policy: ConflictPolicy) -> (crate::MockBitcoinRpc, RelayServer, Transaction) {
+        let mock = crate::MockBitcoinRpc::new();
+        let local: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        mock.add_mempool_tx(&crate::test_utils::sample_tx_hex());
+        let validation_config = ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        };
+        let config = RelayConfig::for_network(Network::Regtest, 1)
+            .with_validation(validation_config)
+            .with_conflict_policy(policy);
+        let mut double_spend = local.clone();
+        double_spend.lock_time = bitcoin::absolute::LockTime::from_consensus(1);
+        (mock.clone(), mock_server(config, &mock), double_spend)
+    }
+
+    async fn receive_from_peer(server: &RelayServer, tx: &Transaction) {
+        let event = crate::nostr::build_tx_broadcast(tx, "2", Some(Network::Regtest), &Keys::generate()).unwrap();
+        server.handle_strfry_message(&json!(["EVENT", "tx_relay_1", event]).to_string()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_first_seen_policy_keeps_local_transaction() {
+        let (mock, server, double_spend) = conflict_scenario(ConflictPolicy::FirstSeen);
+        let mut peer_txs = server.subscribe_transactions(TxFilter::new());
+        
+        receive_from_peer(&server, &double_spend).await;
+        assert_eq!(mock.call_count("gettxspendingprevout"), 1);
+        assert!(mock.sent_transactions().is_empty());
+        
+        // A transaction spending nothing the mempool spends is still submitted, and is the
+        // first one subscribers see
+        let unrelated = child_of(&double_spend);
+        receive_from_peer(&server, &unrelated).await;
+        assert_eq!(mock.sent_transactions(), vec![bitcoin::consensus::encode::serialize_hex(&unrelated)]);
+        assert_eq!(peer_txs.recv().await.unwrap().txid, unrelated.txid());
+    }
+
+    #[tokio::test]
+    async fn test_node_authoritative_policy_attempts_replacement() {
+        let (mock, server, double_spend) = conflict_scenario(ConflictPolicy::NodeAuthoritative);
+        
+        // The node accepts the replacement
+        receive_from_peer(&server, &double_spend).await;
+        assert_eq!(mock.call_count("gettxspendingprevout"), 0);
+        assert_eq!(mock.sent_transactions(), vec![bitcoin::consensus::encode::serialize_hex(&double_spend)]);
+        
+        // The node refuses an underpriced replacement and keeps the local transaction
+        let (mock, server, double_spend) = conflict_scenario(ConflictPolicy::NodeAuthoritative);
+        mock.set_send_error(Some((-26, "insufficient fee, rejecting replacement 5a1c..., less fees than conflicting txs; 0.00001 < 0.00002")));
+        receive_from_peer(&server, &double_spend).await;
+        assert_eq!(mock.call_count("sendrawtransaction"), 1);
+        assert!(mock.sent_transactions().is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Instant;
+
+/// State of the connection to strfry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrfryState {
+    /// Subscribed and gossiping
+    Connected,
+    /// Not connected, either not yet attempted or waiting out the reconnect backoff
+    Disconnected,
+    /// Attempting to connect again after a failure or a dropped connection
+    Reconnecting,
+}
+
+/// Health of the Bitcoin node, from the latest mempool poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitcoinState {
+    Healthy,
+    Unhealthy,
+}
+
+/// Connection state published by a [`RelayServer`](super::RelayServer) for dashboards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionState {
+    pub strfry: StrfryState,
+    pub bitcoin: BitcoinState,
+    /// When either state last changed
+    pub last_change: Instant,
+}
+
+impl ConnectionState {
+    /// State of a relay that hasn't connected to anything yet
+    pub fn new() -> Self {
+        Self {
+            strfry: StrfryState::Disconnected,
+            bitcoin: BitcoinState::Unhealthy,
+            last_change: Instant::now(),
+        }
+    }
+    
+    /// Record the strfry state, returning whether it changed
+    pub fn set_strfry(&mut self, strfry: StrfryState) -> bool {
+        if self.strfry == strfry {
+            return false;
+        }
+        self.strfry = strfry;
+        self.last_change = Instant::now();
+        true
+    }
+    
+    /// Record the Bitcoin node state, returning whether it changed
+    pub fn set_bitcoin(&mut self, bitcoin: BitcoinState) -> bool {
+        if self.bitcoin == bitcoin {
+            return false;
+        }
+        self.bitcoin = bitcoin;
+        self.last_change = Instant::now();
+        true
+    }
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_changes_bump_last_change() {
+        let mut state = ConnectionState::new();
+        let created = state.last_change;
+        
+        assert!(!state.set_strfry(StrfryState::Disconnected));
+        assert!(!state.set_bitcoin(BitcoinState::Unhealthy));
+        assert_eq!(state.last_change, created);
+        
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(state.set_strfry(StrfryState::Connected));
+        assert!(state.last_change > created);
+        
+        let connected = state.last_change;
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(state.set_bitcoin(BitcoinState::Healthy));
+        assert!(state.last_change > connected);
+        assert_eq!(state.strfry, StrfryState::Connected);
+    }
+}
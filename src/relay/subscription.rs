@@ -0,0 +1,148 @@
+use crate::filter::ScriptFilter;
+use bitcoin::{ScriptBuf, Transaction, Txid};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// Where a relayed transaction came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxSource {
+    /// Found in the local node's mempool and gossiped to peers
+    Mempool,
+    /// Received in a broadcast from a peer relay
+    Peer,
+    /// Submitted by a WebSocket client and accepted by the node
+    Client,
+    /// Gossiped on seeing it confirmed in a block, having never been gossiped from the mempool
+    Block,
+}
+
+/// A transaction handled by the relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayedTx {
+    pub txid: Txid,
+    pub tx: Transaction,
+    pub source: TxSource,
+}
+
+/// Selects which transactions a [`TxSubscription`] yields
+///
+/// An empty filter matches every transaction.
+#[derive(Debug, Clone, Default)]
+pub struct TxFilter {
+    sources: Vec<TxSource>,
+    scripts: ScriptFilter,
+}
+
+impl TxFilter {
+    /// Create an empty filter that matches everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Match transactions from `source`; repeat to allow several sources
+    pub fn with_source(mut self, source: TxSource) -> Self {
+        if !self.sources.contains(&source) {
+            self.sources.push(source);
+        }
+        self
+    }
+    
+    /// Match transactions with an output paying to `script`; repeat to watch several scripts
+    pub fn with_script(mut self, script: ScriptBuf) -> Self {
+        self.scripts = self.scripts.with_script(script);
+        self
+    }
+    
+    /// Whether a relayed transaction passes the filter
+    pub fn matches(&self, relayed: &RelayedTx) -> bool {
+        (self.sources.is_empty() || self.sources.contains(&relayed.source))
+            && (self.scripts.is_empty() || self.scripts.matches_outputs(&relayed.tx))
+    }
+}
+
+/// Handle to the stream of transactions handled by a [`RelayServer`](super::RelayServer)
+///
+/// Only transactions matching the filter given at subscribe time are yielded.
+/// Dropping the handle unsubscribes.
+#[derive(Debug)]
+pub struct TxSubscription {
+    receiver: broadcast::Receiver<Arc<RelayedTx>>,
+    filter: TxFilter,
+}
+
+impl TxSubscription {
+    pub(crate) fn new(receiver: broadcast::Receiver<Arc<RelayedTx>>, filter: TxFilter) -> Self {
+        Self { receiver, filter }
+    }
+    
+    /// The filter this subscription was created with
+    pub fn filter(&self) -> &TxFilter {
+        &self.filter
+    }
+    
+    /// Wait for the next matching transaction
+    ///
+    /// Returns `None` once the server is gone. A subscriber that falls behind
+    /// skips the transactions it missed rather than failing.
+    pub async fn recv(&mut self) -> Option<Arc<RelayedTx>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(relayed) if self.filter.matches(&relayed) => return Some(relayed),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Transaction subscriber fell behind, skipped {} transactions", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::consensus::deserialize;
+
+    fn relayed(script: ScriptBuf, source: TxSource) -> Arc<RelayedTx> {
+        let mut tx: Transaction = deserialize(&hex::decode(crate::test_utils::sample_tx_hex()).unwrap()).unwrap();
+        tx.output[0].script_pubkey = script;
+        Arc::new(RelayedTx {
+            txid: tx.txid(),
+            tx,
+            source,
+        })
+    }
+
+    #[test]
+    fn test_filter_matching() {
+        let watched = ScriptBuf::from_bytes(vec![0x51]);
+        let other = ScriptBuf::from_bytes(vec![0x52]);
+        
+        assert!(TxFilter::new().matches(&relayed(other.clone(), TxSource::Peer)));
+        
+        let by_source = TxFilter::new().with_source(TxSource::Peer).with_source(TxSource::Client);
+        assert!(by_source.matches(&relayed(other.clone(), TxSource::Client)));
+        assert!(!by_source.matches(&relayed(other.clone(), TxSource::Mempool)));
+        
+        let by_both = TxFilter::new().with_source(TxSource::Peer).with_script(watched.clone());
+        assert!(by_both.matches(&relayed(watched.clone(), TxSource::Peer)));
+        assert!(!by_both.matches(&relayed(other, TxSource::Peer)));
+        assert!(!by_both.matches(&relayed(watched, TxSource::Mempool)));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_skips_unmatched_and_releases_receiver() {
+        let (sender, receiver) = broadcast::channel(10);
+        let mut subscription = TxSubscription::new(receiver, TxFilter::new().with_source(TxSource::Peer));
+        
+        sender.send(relayed(ScriptBuf::new(), TxSource::Mempool)).unwrap();
+        sender.send(relayed(ScriptBuf::new(), TxSource::Peer)).unwrap();
+        assert_eq!(subscription.recv().await.unwrap().source, TxSource::Peer);
+        
+        drop(subscription);
+        assert_eq!(sender.receiver_count(), 0);
+    }
+}
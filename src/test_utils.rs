@@ -0,0 +1,187 @@
+//! Shared helpers for unit tests
+
+/// Build a minimal valid-structure transaction hex for tests
+pub(crate) fn sample_tx_hex() -> String {
+    use bitcoin::{absolute::LockTime, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+    
+    let tx = Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: 50_000,
+            script_pubkey: ScriptBuf::from_bytes(vec![0x51]),
+        }],
+    };
+    hex::encode(bitcoin::consensus::serialize(&tx))
+}
+
+/// Spawn a one-shot-per-connection HTTP server answering every JSON-RPC call with `response`
+pub(crate) async fn spawn_mock_rpc(response: serde_json::Value) -> u16 {
+    spawn_recording_rpc(response).await.0
+}
+
+/// Like `spawn_mock_rpc`, but waiting `delay` before answering, like a hung node
+pub(crate) async fn spawn_slow_rpc(response: serde_json::Value, delay: std::time::Duration) -> u16 {
+    spawn_delayed_recording_rpc(response, delay).await.0
+}
+
+/// Like `spawn_mock_rpc`, also recording each request's `(headers, body)`
+pub(crate) async fn spawn_recording_rpc(
+    response: serde_json::Value,
+) -> (u16, std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>) {
+    spawn_delayed_recording_rpc(response, std::time::Duration::ZERO).await
+}
+
+async fn spawn_delayed_recording_rpc(
+    response: serde_json::Value,
+    delay: std::time::Duration,
+) -> (u16, std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>) {
+    use tokio::io::AsyncWriteExt;
+    
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = std::sync::Arc::clone(&requests);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let body = response.to_string();
+            let recorded = std::sync::Arc::clone(&recorded);
+            tokio::spawn(async move {
+                if let Ok(request) = read_http_request(&mut stream).await {
+                    recorded.lock().unwrap().push(request);
+                }
+                tokio::time::sleep(delay).await;
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(reply.as_bytes()).await;
+            });
+        }
+    });
+    (port, requests)
+}
+
+/// Read a full HTTP request (headers and `Content-Length` body), returning the header block and body
+async fn read_http_request(stream: &mut tokio::net::TcpStream) -> std::io::Result<(String, String)> {
+    use tokio::io::AsyncReadExt;
+    
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+        
+        let text = String::from_utf8_lossy(&data);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length").then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if data.len() >= header_end + 4 + content_length {
+                let body = String::from_utf8_lossy(&data[header_end + 4..]).to_string();
+                return Ok((text[..header_end].to_string(), body));
+            }
+        }
+    }
+    Ok((String::new(), String::new()))
+}
+
+/// Spawn a no-authentication SOCKS5 proxy that records each requested `host:port`
+///
+/// Targets are resolved locally, so tests can only reach local listeners.
+pub(crate) async fn spawn_socks5_proxy() -> (u16, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let targets = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = std::sync::Arc::clone(&targets);
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            let recorded = std::sync::Arc::clone(&recorded);
+            tokio::spawn(async move {
+                let mut greeting = [0u8; 2];
+                client.read_exact(&mut greeting).await?;
+                let mut methods = vec![0u8; usize::from(greeting[1])];
+                client.read_exact(&mut methods).await?;
+                client.write_all(&[0x05, 0x00]).await?;
+                
+                let mut request = [0u8; 4];
+                client.read_exact(&mut request).await?;
+                let host = match request[3] {
+                    0x01 => {
+                        let mut ip = [0u8; 4];
+                        client.read_exact(&mut ip).await?;
+                        std::net::Ipv4Addr::from(ip).to_string()
+                    }
+                    _ => {
+                        let mut name = vec![0u8; usize::from(client.read_u8().await?)];
+                        client.read_exact(&mut name).await?;
+                        String::from_utf8_lossy(&name).into_owned()
+                    }
+                };
+                let target = format!("{}:{}", host, client.read_u16().await?);
+                recorded.lock().unwrap().push(target.clone());
+                
+                let mut upstream = tokio::net::TcpStream::connect(&target).await?;
+                client.write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await?;
+                tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+                Ok::<_, std::io::Error>(())
+            });
+        }
+    });
+    (port, targets)
+}
+
+/// Log output captured by [`capture_logs`]
+#[derive(Clone, Default)]
+pub(crate) struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl CapturedLogs {
+    /// Whether any captured line contains `text`
+    pub(crate) fn contains(&self, text: &str) -> bool {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).contains(text)
+    }
+}
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl tracing_subscriber::fmt::MakeWriter<'_> for CapturedLogs {
+    type Writer = Self;
+    
+    fn make_writer(&self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Capture this thread's log output until the guard is dropped
+/// 
+/// Covers tasks spawned from a `#[tokio::test]`, which run on the test's thread.
+pub(crate) fn capture_logs() -> (CapturedLogs, tracing::subscriber::DefaultGuard) {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt().with_writer(logs.clone()).with_ansi(false).finish();
+    (logs, tracing::subscriber::set_default(subscriber))
+}
@@ -0,0 +1,201 @@
+use crate::error::{ConfigError, NostrError};
+use crate::Result;
+use futures_util::future::BoxFuture;
+use futures_util::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::protocol::{Message, WebSocketConfig};
+use tokio_tungstenite::{client_async_with_config, connect_async_with_config, MaybeTlsStream, WebSocketStream};
+use tracing::warn;
+use url::Url;
+
+/// A connection carrying Nostr protocol messages (JSON text frames)
+///
+/// Implemented for WebSocket streams and by [`MemoryTransport`] for tests.
+/// Method names avoid clashing with `SinkExt::send` on WebSocket streams.
+/// `recv_message` must be cancel-safe: dropping its future before completion loses no message.
+pub trait NostrTransport: Send {
+    /// Send one message
+    fn send_message(&mut self, message: String) -> BoxFuture<'_, Result<()>>;
+    
+    /// Receive the next message, or `None` once the connection is closed
+    fn recv_message(&mut self) -> BoxFuture<'_, Option<Result<String>>>;
+}
+
+impl<S> NostrTransport for WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    fn send_message(&mut self, message: String) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            SinkExt::send(self, Message::Text(message)).await?;
+            Ok(())
+        })
+    }
+    
+    fn recv_message(&mut self) -> BoxFuture<'_, Option<Result<String>>> {
+        Box::pin(async move {
+            loop {
+                match self.next().await? {
+                    Ok(Message::Text(text)) => return Some(Ok(text)),
+                    Ok(Message::Binary(_)) => warn!("Ignoring binary message from Nostr peer"),
+                    Ok(Message::Close(_)) => return None,
+                    Ok(_) => {}
+                    Err(e) => return Some(Err(e.into())),
+                }
+            }
+        })
+    }
+}
+
+/// One end of an in-process transport; messages sent on one end are received on the other
+#[derive(Debug)]
+pub struct MemoryTransport {
+    sender: mpsc::UnboundedSender<String>,
+    receiver: mpsc::UnboundedReceiver<String>,
+}
+
+impl MemoryTransport {
+    /// Create two connected ends
+    pub fn pair() -> (Self, Self) {
+        let (a_sender, b_receiver) = mpsc::unbounded_channel();
+        let (b_sender, a_receiver) = mpsc::unbounded_channel();
+        (
+            Self { sender: a_sender, receiver: a_receiver },
+            Self { sender: b_sender, receiver: b_receiver },
+        )
+    }
+}
+
+impl NostrTransport for MemoryTransport {
+    fn send_message(&mut self, message: String) -> BoxFuture<'_, Result<()>> {
+        let sent = self.sender.send(message).map_err(|_| NostrError::Disconnected.into());
+        Box::pin(async move { sent })
+    }
+    
+    fn recv_message(&mut self) -> BoxFuture<'_, Option<Result<String>>> {
+        Box::pin(async move { self.receiver.recv().await.map(Ok) })
+    }
+}
+
+/// Open a WebSocket connection to `url`, through a SOCKS5 proxy when one is given
+///
+/// Only `ws://` URLs can be proxied.
+pub async fn connect_websocket(
+    url: &str,
+    socks_proxy: Option<&str>,
+    config: Option<WebSocketConfig>,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let parsed = Url::parse(url)?;
+    let Some(proxy) = socks_proxy else {
+        return Ok(connect_async_with_config(parsed, config, false).await?.0);
+    };
+    
+    if parsed.scheme() != "ws" {
+        return Err(NostrError::proxy_failed(proxy, format!("cannot proxy {} URLs", parsed.scheme())).into());
+    }
+    let host = parsed.host_str().ok_or_else(|| ConfigError::invalid_url(url))?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let stream = connect_socks5(proxy, host, port).await?;
+    Ok(client_async_with_config(parsed, MaybeTlsStream::Plain(stream), config).await?.0)
+}
+
+/// Open a TCP connection to `host:port` through the SOCKS5 proxy at `proxy`
+///
+/// Only the no-authentication method is offered, as used by Tor. The host name
+/// is passed to the proxy unresolved, so `.onion` addresses work.
+pub async fn connect_socks5(proxy: &str, host: &str, port: u16) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy).await?;
+    
+    // Greeting: version 5, one method, no authentication
+    stream.write_all(&[0x05, 0x01, 0x00]).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice != [0x05, 0x00] {
+        return Err(NostrError::proxy_failed(proxy, "no supported authentication method").into());
+    }
+    
+    // CONNECT to a domain name; IPv6 literals arrive bracketed from URLs
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host_len = u8::try_from(host.len()).map_err(|_| NostrError::proxy_failed(proxy, "host name too long"))?;
+    let mut request = vec![0x05, 0x01, 0x00, 0x03, host_len];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+    
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != 0x05 || reply[1] != 0x00 {
+        return Err(NostrError::proxy_failed(proxy, format!("CONNECT to {}:{} failed with code {}", host, port, reply[1])).into());
+    }
+    
+    // Skip the bound address and port
+    let addr_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => usize::from(stream.read_u8().await?),
+        other => return Err(NostrError::proxy_failed(proxy, format!("unknown address type {}", other)).into()),
+    };
+    let mut bound = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_transport_pair() {
+        let (mut a, mut b) = MemoryTransport::pair();
+        a.send_message("ping".to_string()).await.unwrap();
+        b.send_message("pong".to_string()).await.unwrap();
+        
+        assert_eq!(b.recv_message().await.unwrap().unwrap(), "ping");
+        assert_eq!(a.recv_message().await.unwrap().unwrap(), "pong");
+        
+        // Dropping one end closes the connection for the other
+        drop(b);
+        assert!(a.recv_message().await.is_none());
+        assert!(matches!(
+            a.send_message("lost".to_string()).await,
+            Err(crate::RelayError::Nostr(NostrError::Disconnected))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_through_socks_proxy() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let message = ws.recv_message().await.unwrap().unwrap();
+            ws.send_message(message).await.unwrap();
+        });
+        let (proxy_port, targets) = crate::test_utils::spawn_socks5_proxy().await;
+        let proxy = format!("127.0.0.1:{}", proxy_port);
+        
+        let mut ws = connect_websocket(&format!("ws://{}", addr), Some(&proxy), None).await.unwrap();
+        ws.send_message("echo".to_string()).await.unwrap();
+        assert_eq!(ws.recv_message().await.unwrap().unwrap(), "echo");
+        assert_eq!(*targets.lock().unwrap(), vec![addr.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_socks_proxy_resolves_host_names() {
+        let (proxy_port, targets) = crate::test_utils::spawn_socks5_proxy().await;
+        let proxy = format!("127.0.0.1:{}", proxy_port);
+        let onion = "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion";
+        
+        // The test proxy can't reach onion services, but it receives the name unresolved
+        assert!(connect_socks5(&proxy, onion, 80).await.is_err());
+        assert_eq!(*targets.lock().unwrap(), vec![format!("{}:80", onion)]);
+        
+        assert!(matches!(
+            connect_websocket("wss://relay.example.com", Some(&proxy), None).await,
+            Err(crate::RelayError::Nostr(NostrError::ProxyFailed { .. }))
+        ));
+    }
+}
@@ -1,11 +1,18 @@
+use crate::bitcoin_rpc::RawTxInfo;
 use crate::error::ValidationError;
+use crate::networks::Network;
+use crate::relay::{CircuitBreaker, HttpPoolConfig, RpcAuth};
 use serde_json::{json, Value};
 use lru::LruCache;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use bitcoin::consensus::deserialize;
-use bitcoin::Transaction;
+use bitcoin::blockdata::opcodes::all::{OP_CHECKMULTISIG, OP_PUSHNUM_16};
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::{Script, Transaction, Txid};
+use tracing::debug;
 
 #[derive(Debug, Clone)]
 pub struct ValidationConfig {
@@ -14,6 +21,33 @@ pub struct ValidationConfig {
     pub validation_timeout_ms: u64,
     pub cache_ttl_seconds: u64,
     pub cache_size: usize,
+    pub use_testmempoolaccept: bool,
+    /// Minimum fee rate in sat/vB, checked against `testmempoolaccept` results (`None` leaves fees to the node)
+    pub min_fee_rate: Option<f64>,
+    /// Largest OP_RETURN output script in bytes, like Core's `-datacarriersize` (`None` for no limit)
+    pub max_op_return_bytes: Option<usize>,
+    /// Most OP_RETURN outputs allowed in one transaction (`None` for no limit)
+    pub max_op_return_outputs: Option<usize>,
+    /// Reject transactions paying to bare multisig scripts, like Core's `-permitbaremultisig=0`
+    pub reject_bare_multisig: bool,
+    /// Reject transaction versions other than 1 and 2, like Core's standardness rules
+    pub require_standard_version: bool,
+    /// Reject inputs whose scriptSig does anything but push data, like Core's standardness rules
+    pub require_push_only_script_sig: bool,
+    /// Smallest output value in satoshis, OP_RETURN outputs excepted (`None` leaves dust to the node)
+    pub dust_threshold_sats: Option<u64>,
+    /// Reject spends of coinbase outputs with fewer than 100 confirmations
+    /// 
+    /// Prevouts are looked up with `getrawtransaction`; inputs the node can't describe,
+    /// e.g. without `-txindex`, are let through.
+    pub reject_immature_coinbase_spends: bool,
+    /// Most inputs allowed in one transaction (`None` for no limit)
+    pub max_inputs: Option<usize>,
+    /// Most outputs allowed in one transaction (`None` for no limit)
+    pub max_outputs: Option<usize>,
+    /// Stages run by [`TransactionValidator::validate`] and [`TransactionValidator::validate_batch`],
+    /// in order; leave a stage out to skip it
+    pub stages: Vec<ValidationStage>,
 }
 
 impl Default for ValidationConfig {
@@ -24,31 +58,192 @@ impl Default for ValidationConfig {
             validation_timeout_ms: 5000,
             cache_ttl_seconds: 600,  // 10 minutes
             cache_size: 1000,        // ~116 KB
+            use_testmempoolaccept: true,
+            min_fee_rate: None,
+            max_op_return_bytes: None,
+            max_op_return_outputs: None,
+            reject_bare_multisig: false,
+            require_standard_version: false,
+            require_push_only_script_sig: false,
+            dust_threshold_sats: None,
+            reject_immature_coinbase_spends: false,
+            max_inputs: None,
+            max_outputs: None,
+            stages: ValidationStage::DEFAULT_PIPELINE.to_vec(),
         }
     }
 }
 
+impl ValidationConfig {
+    /// Validation defaults suited to a network
+    /// 
+    /// - Testnet4 is a public network and validates strictly: prechecks, `testmempoolaccept`
+    ///   and a 1 sat/vB fee floor, matching Bitcoin Core's default `minrelaytxfee`.
+    /// - Regtest has no fee floor, since local test transactions often pay minimal fees, and
+    ///   caches validations for only a minute so transactions can be resubmitted quickly after
+    ///   blocks are generated or invalidated.
+    pub fn for_network(network: Network) -> Self {
+        match network {
+            Network::Testnet4 => Self {
+                min_fee_rate: Some(1.0),
+                ..Self::default()
+            },
+            Network::Regtest => Self {
+                cache_ttl_seconds: 60,
+                min_fee_rate: None,
+                ..Self::default()
+            },
+        }
+    }
+    
+    /// Whether any output policy is configured
+    fn has_output_policy(&self) -> bool {
+        self.max_op_return_bytes.is_some() || self.max_op_return_outputs.is_some() || self.reject_bare_multisig
+    }
+    
+    /// Whether any standardness check is configured
+    fn has_standardness_checks(&self) -> bool {
+        self.require_standard_version || self.require_push_only_script_sig || self.dust_threshold_sats.is_some()
+    }
+}
+
+/// A step of single-transaction validation, run in the order of [`ValidationConfig::stages`]
+///
+/// The first failing stage ends validation with its error. Stages needing the decoded
+/// transaction decode it on first use, and the node's `testmempoolaccept` answer is
+/// shared by `Fee` and `NodeAccept`, so no stage has to come before another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationStage {
+    /// Hex format and size, before anything is decoded (off when `enable_precheck` is false)
+    Precheck,
+    /// Decode the transaction
+    Structure,
+    /// Stop early, reporting `from_cache`, if the transaction was validated recently
+    Cache,
+    /// The relay's own limits: input and output counts, output policy, standardness and coinbase maturity
+    Policy,
+    /// `min_fee_rate` against the fee `testmempoolaccept` reports (off when `use_testmempoolaccept` is false)
+    Fee,
+    /// Acceptance by Bitcoin Core's `testmempoolaccept` (off when `use_testmempoolaccept` is false)
+    NodeAccept,
+}
+
+impl ValidationStage {
+    /// Cheap local checks first, then the node
+    pub const DEFAULT_PIPELINE: [ValidationStage; 6] = [
+        ValidationStage::Precheck,
+        ValidationStage::Structure,
+        ValidationStage::Cache,
+        ValidationStage::Policy,
+        ValidationStage::NodeAccept,
+        ValidationStage::Fee,
+    ];
+}
+
+/// A validation check that was run against a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationCheck {
+    /// Transaction hex decoded and deserialized
+    Structure,
+    /// Cheap local checks on hex format and size
+    Precheck,
+    /// Limits on the number of inputs and outputs
+    InputOutputCount,
+    /// Relay policy on OP_RETURN and bare multisig outputs
+    OutputPolicy,
+    /// Local subset of Bitcoin Core standardness: version, scriptSig and dust
+    Standardness,
+    /// Prevout lookups for immature coinbase spends
+    CoinbaseMaturity,
+    /// Bitcoin Core `testmempoolaccept`
+    BitcoinCore,
+    /// Fee rate reported by `testmempoolaccept` against `min_fee_rate`
+    FeeRate,
+}
+
+/// Detailed result of a successful validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub txid: String,
+    /// Witness txid, which differs between witness variants of the same transaction
+    pub wtxid: String,
+    pub vsize: usize,
+    pub weight: u64,
+    pub checks_run: Vec<ValidationCheck>,
+    /// True when the transaction was recently validated and the checks were skipped
+    pub from_cache: bool,
+}
+
+/// A recently validated transaction
+/// 
+/// The cache is keyed by txid: witness variants share a txid and compete for the
+/// same mempool slot, so validating one of them covers the others. The wtxid of the
+/// variant that was validated is kept to tell them apart in logs.
+struct CacheEntry {
+    validated_at: Instant,
+    wtxid: String,
+}
+
+/// What the stages validating one transaction have learned so far
+struct StageContext<'a> {
+    tx_hex: &'a str,
+    tx: Option<Transaction>,
+    mempool_accept: Option<Value>,
+    from_cache: bool,
+}
+
+/// Validates transactions before they are submitted or relayed
+/// 
+/// Clones share the HTTP client, node URL and validation cache.
+#[derive(Clone)]
 pub struct TransactionValidator {
     config: ValidationConfig,
     bitcoin_client: reqwest::Client,
-    bitcoin_rpc_url: String,
-    tx_cache: RwLock<LruCache<String, Instant>>,
+    bitcoin_rpc_url: Arc<str>,
+    bitcoin_rpc_auth: RpcAuth,
+    tx_cache: Arc<RwLock<LruCache<String, CacheEntry>>>,
+    breaker: Option<Arc<CircuitBreaker>>,
 }
 
 impl TransactionValidator {
+    /// A validator for a node on localhost at `bitcoin_port`, with the default `user`/`password` credentials
     pub fn new(config: ValidationConfig, bitcoin_port: u16) -> Self {
-        let bitcoin_rpc_url = format!("http://127.0.0.1:{}", bitcoin_port);
+        Self::with_rpc_endpoint(
+            config,
+            format!("http://127.0.0.1:{}", bitcoin_port),
+            RpcAuth::new("user".to_string(), "password".to_string()),
+        )
+    }
+    
+    /// A validator calling the node at `bitcoin_rpc_url` with `auth`, e.g. a relay's `bitcoin_rpc_url` and `bitcoin_rpc_auth`
+    pub fn with_rpc_endpoint(config: ValidationConfig, bitcoin_rpc_url: impl Into<String>, auth: RpcAuth) -> Self {
         let cache_size = NonZeroUsize::new(config.cache_size).unwrap_or(NonZeroUsize::new(1000).unwrap());
-        let tx_cache = RwLock::new(LruCache::new(cache_size));
+        let tx_cache = Arc::new(RwLock::new(LruCache::new(cache_size)));
         
         Self {
             config,
             bitcoin_client: reqwest::Client::new(),
-            bitcoin_rpc_url,
+            bitcoin_rpc_url: bitcoin_rpc_url.into().into(),
+            bitcoin_rpc_auth: auth,
             tx_cache,
+            breaker: None,
         }
     }
     
+    /// Make node calls over an HTTP client built with `pool`, e.g. through its `socks_proxy`
+    /// 
+    /// Fails if `socks_proxy` is not a valid proxy address.
+    pub fn with_http_pool(mut self, pool: &HttpPoolConfig) -> crate::Result<Self> {
+        self.bitcoin_client = pool.apply(reqwest::Client::builder())?.build()?;
+        Ok(self)
+    }
+    
+    /// Send node calls through `breaker`, sharing its view of the node's health with other callers
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = Some(breaker);
+        self
+    }
+    
     /// Get the validation configuration
     pub fn config(&self) -> &ValidationConfig {
         &self.config
@@ -59,64 +254,307 @@ impl TransactionValidator {
             return Ok(());
         }
         
-        // Extract TXID first (needed for cache)
-        let txid = self.extract_txid(tx_hex)?;
-        
-        // Check cache for recent processing
-        if self.is_recently_processed(&txid) {
-            return Err(ValidationError::recently_processed(txid));
+        let report = self.validate_detailed(tx_hex).await?;
+        if report.from_cache {
+            return Err(ValidationError::recently_processed(report.txid));
         }
-        
-        // Phase 2: Quick pre-checks
-        if self.config.enable_precheck {
-            self.quick_validation_checks(tx_hex)?;
+        Ok(())
+    }
+    
+    /// Validate a transaction and report what was checked
+    /// 
+    /// Runs the configured [`stages`](ValidationConfig::stages) in order. Unlike
+    /// [`validate`](Self::validate), a recently processed transaction is reported
+    /// with `from_cache` set rather than as an error. Returns `ValidationError::Disabled`
+    /// when validation is turned off.
+    pub async fn validate_detailed(&self, tx_hex: &str) -> Result<ValidationReport, ValidationError> {
+        if !self.config.enable_validation {
+            return Err(ValidationError::Disabled);
         }
         
-        // Phase 1: Use Bitcoin Core validation
-        self.validate_with_bitcoin_core(tx_hex).await.map_err(|e| match e {
-            ValidationError::BitcoinCoreRejection { reason } => ValidationError::bitcoin_core_rejection(reason),
-            other => other,
-        })?;
+        let mut context = StageContext {
+            tx_hex,
+            tx: None,
+            mempool_accept: None,
+            from_cache: false,
+        };
+        let mut checks_run = Vec::new();
+        for stage in &self.config.stages {
+            self.run_stage(*stage, &mut context, &mut checks_run).await?;
+            if context.from_cache {
+                break;
+            }
+        }
         
-        // Cache successful validation
-        self.cache_transaction(&txid);
+        let tx = self.decoded(&mut context)?;
+        let report = ValidationReport {
+            txid: tx.txid().to_string(),
+            wtxid: tx.wtxid().to_string(),
+            vsize: tx.vsize(),
+            weight: tx.weight().to_wu(),
+            checks_run,
+            from_cache: context.from_cache,
+        };
+        if !report.from_cache {
+            self.cache_transaction(&report.txid, &report.wtxid);
+        }
+        Ok(report)
+    }
+    
+    /// Run one validation stage, recording the checks it made
+    async fn run_stage(&self, stage: ValidationStage, context: &mut StageContext<'_>, checks_run: &mut Vec<ValidationCheck>) -> Result<(), ValidationError> {
+        match stage {
+            ValidationStage::Precheck => {
+                if self.config.enable_precheck {
+                    precheck_hex(context.tx_hex)?;
+                    checks_run.push(ValidationCheck::Precheck);
+                }
+            }
+            ValidationStage::Structure => {
+                self.decoded(context)?;
+                checks_run.push(ValidationCheck::Structure);
+            }
+            ValidationStage::Cache => {
+                let tx = self.decoded(context)?;
+                let (txid, wtxid) = (tx.txid().to_string(), tx.wtxid().to_string());
+                if let Some(cached_wtxid) = self.cached_wtxid(&txid) {
+                    if cached_wtxid != wtxid {
+                        debug!(
+                            "Transaction {} (wtxid {}) is a witness variant of recently validated wtxid {}",
+                            txid, wtxid, cached_wtxid
+                        );
+                    }
+                    context.from_cache = true;
+                }
+            }
+            ValidationStage::Policy => {
+                let tx = self.decoded(context)?;
+                if self.config.max_inputs.is_some() || self.config.max_outputs.is_some() {
+                    check_input_output_count(tx, &self.config)?;
+                    checks_run.push(ValidationCheck::InputOutputCount);
+                }
+                if self.config.has_output_policy() {
+                    check_output_policy(tx, &self.config)?;
+                    checks_run.push(ValidationCheck::OutputPolicy);
+                }
+                if self.config.has_standardness_checks() {
+                    check_standardness(tx, &self.config)?;
+                    checks_run.push(ValidationCheck::Standardness);
+                }
+                if self.config.reject_immature_coinbase_spends {
+                    self.check_coinbase_maturity(tx).await?;
+                    checks_run.push(ValidationCheck::CoinbaseMaturity);
+                }
+            }
+            ValidationStage::Fee => {
+                if let (true, Some(min_fee_rate)) = (self.config.use_testmempoolaccept, self.config.min_fee_rate) {
+                    check_fee_floor(self.mempool_accept(context).await?, min_fee_rate)?;
+                    checks_run.push(ValidationCheck::FeeRate);
+                }
+            }
+            ValidationStage::NodeAccept => {
+                if self.config.use_testmempoolaccept {
+                    mempool_accept_result(self.mempool_accept(context).await?, None)?;
+                    checks_run.push(ValidationCheck::BitcoinCore);
+                }
+            }
+        }
         Ok(())
     }
     
-    fn quick_validation_checks(&self, tx_hex: &str) -> Result<(), ValidationError> {
-        if tx_hex.is_empty() {
-            return Err(ValidationError::EmptyTransaction);
+    /// The transaction being validated, decoding it on first use
+    fn decoded<'c>(&self, context: &'c mut StageContext<'_>) -> Result<&'c Transaction, ValidationError> {
+        if context.tx.is_none() {
+            context.tx = Some(self.decode_transaction(context.tx_hex)?);
         }
+        Ok(context.tx.as_ref().expect("decoded above"))
+    }
+    
+    /// The node's `testmempoolaccept` result for the transaction, asking on first use
+    async fn mempool_accept<'c>(&self, context: &'c mut StageContext<'_>) -> Result<&'c Value, ValidationError> {
+        if context.mempool_accept.is_none() {
+            let result = self.test_mempool_accept(&[context.tx_hex]).await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| ValidationError::bitcoin_core_rejection("Empty response"))?;
+            context.mempool_accept = Some(result);
+        }
+        Ok(context.mempool_accept.as_ref().expect("fetched above"))
+    }
+    
+    /// Validate several related transactions (e.g. a CPFP package) together
+    /// 
+    /// The configured local stages run per transaction, in order; the survivors are then
+    /// submitted in a single `testmempoolaccept` call so Bitcoin Core evaluates them as a
+    /// package, which the `Fee` and `NodeAccept` stages, when configured, are checked
+    /// against. Results are returned in input order.
+    pub async fn validate_batch(&self, tx_hexes: &[&str]) -> Vec<Result<(), ValidationError>> {
+        if !self.config.enable_validation {
+            return tx_hexes.iter().map(|_| Ok(())).collect();
+        }
+        
+        let mut results: Vec<Result<(), ValidationError>> = Vec::with_capacity(tx_hexes.len());
+        let mut txids: Vec<Option<(String, String)>> = Vec::with_capacity(tx_hexes.len());
         
-        if !tx_hex.chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err(ValidationError::InvalidHex);
+        for tx_hex in tx_hexes {
+            match self.run_local_stages(tx_hex).await {
+                Ok(ids) => {
+                    txids.push(Some(ids));
+                    results.push(Ok(()));
+                }
+                Err(e) => {
+                    txids.push(None);
+                    results.push(Err(e));
+                }
+            }
         }
         
-        let byte_len = tx_hex.len() / 2;
-        if byte_len < 60 || byte_len > 400_000 {
-            return Err(ValidationError::invalid_size(byte_len));
+        let node_accept = self.config.use_testmempoolaccept && self.config.stages.contains(&ValidationStage::NodeAccept);
+        let fee_floor = self.config.min_fee_rate
+            .filter(|_| self.config.use_testmempoolaccept && self.config.stages.contains(&ValidationStage::Fee));
+        let pending: Vec<usize> = (0..tx_hexes.len()).filter(|&i| results[i].is_ok()).collect();
+        if (node_accept || fee_floor.is_some()) && !pending.is_empty() {
+            let package: Vec<&str> = pending.iter().map(|&i| tx_hexes[i]).collect();
+            match self.test_mempool_accept(&package).await {
+                Ok(core_results) => {
+                    for (position, &i) in pending.iter().enumerate() {
+                        results[i] = match (core_results.get(position), node_accept) {
+                            (Some(result), true) => mempool_accept_result(result, fee_floor),
+                            (Some(result), false) => check_fee_floor(result, fee_floor.expect("checked above")),
+                            (None, _) => Err(ValidationError::bitcoin_core_rejection("Missing result")),
+                        };
+                    }
+                }
+                Err(e) => {
+                    for &i in &pending {
+                        results[i] = Err(ValidationError::bitcoin_core_rejection(e.to_string()));
+                    }
+                }
+            }
         }
         
+        for (result, txid) in results.iter().zip(&txids) {
+            if let (Ok(()), Some((txid, wtxid))) = (result, txid) {
+                self.cache_transaction(txid, wtxid);
+            }
+        }
+        
+        results
+    }
+    
+    /// Run the configured stages that don't need the node against one transaction of a batch
+    /// 
+    /// A recently processed transaction is an error here, as in [`validate`](Self::validate).
+    async fn run_local_stages(&self, tx_hex: &str) -> Result<(String, String), ValidationError> {
+        let mut context = StageContext {
+            tx_hex,
+            tx: None,
+            mempool_accept: None,
+            from_cache: false,
+        };
+        let mut checks_run = Vec::new();
+        for stage in &self.config.stages {
+            if matches!(stage, ValidationStage::Fee | ValidationStage::NodeAccept) {
+                continue;
+            }
+            self.run_stage(*stage, &mut context, &mut checks_run).await?;
+            if context.from_cache {
+                break;
+            }
+        }
+        
+        let from_cache = context.from_cache;
+        let tx = self.decoded(&mut context)?;
+        let txid = tx.txid().to_string();
+        if from_cache {
+            return Err(ValidationError::recently_processed(txid));
+        }
+        Ok((txid, tx.wtxid().to_string()))
+    }
+    
+    /// Reject inputs spending coinbase outputs that can't be mined in the next block
+    /// 
+    /// Prevouts the node can't describe are skipped rather than rejected.
+    async fn check_coinbase_maturity(&self, tx: &Transaction) -> Result<(), ValidationError> {
+        let mut looked_up: HashMap<String, Option<u64>> = HashMap::new();
+        for (index, input) in tx.input.iter().enumerate() {
+            if input.previous_output.is_null() {
+                continue;
+            }
+            let prev_txid = input.previous_output.txid.to_string();
+            let confirmations = match looked_up.get(&prev_txid) {
+                Some(confirmations) => *confirmations,
+                None => {
+                    let confirmations = self.coinbase_confirmations(&prev_txid).await;
+                    looked_up.insert(prev_txid, confirmations);
+                    confirmations
+                }
+            };
+            if let Some(confirmations) = confirmations.filter(|confirmations| *confirmations < COINBASE_MATURITY) {
+                return Err(ValidationError::immature_coinbase_spend(index, confirmations));
+            }
+        }
         Ok(())
     }
     
-    async fn validate_with_bitcoin_core(&self, tx_hex: &str) -> Result<(), ValidationError> {
+    /// Confirmations of `txid` if the node knows it as a confirmed coinbase transaction
+    async fn coinbase_confirmations(&self, txid: &str) -> Option<u64> {
+        let result = match self.rpc_call("getrawtransaction", json!([txid, true])).await {
+            Ok(result) => result,
+            Err(e) => {
+                debug!("Skipping coinbase maturity check for prevout {}: {}", txid, e);
+                return None;
+            }
+        };
+        let info: RawTxInfo = serde_json::from_value(result).ok()?;
+        let is_coinbase = info.vin.first().is_some_and(|input| input.coinbase.is_some());
+        info.confirmations.filter(|_| is_coinbase)
+    }
+    
+    /// Call `testmempoolaccept` with one or more transactions, returning the per-transaction results
+    async fn test_mempool_accept(&self, tx_hexes: &[&str]) -> Result<Vec<Value>, ValidationError> {
+        // Get the result array (testmempoolaccept returns array of results)
+        self.rpc_call("testmempoolaccept", json!([tx_hexes]))
+            .await?
+            .as_array()
+            .cloned()
+            .ok_or_else(|| ValidationError::bitcoin_core_rejection("Invalid response format"))
+    }
+    
+    /// Call a Bitcoin Core RPC method, returning its `result`
+    /// 
+    /// Fails with `ValidationError::Timeout` if the node hasn't answered within `validation_timeout_ms`,
+    /// and with `ValidationError::NodeUnavailable` while the circuit breaker is open.
+    async fn rpc_call(&self, method: &str, params: Value) -> Result<Value, ValidationError> {
         let request = json!({
             "jsonrpc": "2.0",
-            "method": "testmempoolaccept",
-            "params": [[tx_hex]],
+            "method": method,
+            "params": params,
             "id": "validation"
         });
         
-        let response: Value = self.bitcoin_client
-            .post(&self.bitcoin_rpc_url)
-            .basic_auth("user", Some("password"))
-            .timeout(std::time::Duration::from_millis(self.config.validation_timeout_ms))
-            .json(&request)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let call = async {
+            self.bitcoin_client
+                .post(&*self.bitcoin_rpc_url)
+                .basic_auth(&self.bitcoin_rpc_auth.username, Some(self.bitcoin_rpc_auth.password()))
+                .json(&request)
+                .send()
+                .await?
+                .json::<Value>()
+                .await
+        };
+        let timeout = Duration::from_millis(self.config.validation_timeout_ms);
+        let call = async {
+            tokio::time::timeout(timeout, call)
+                .await
+                .map_err(|_| ValidationError::Timeout)?
+                .map_err(ValidationError::from)
+        };
+        // Only a timeout or transport error means the node is unavailable; RPC errors come from a working node
+        let mut response = match &self.breaker {
+            Some(breaker) => breaker.call(call, |_| true).await.ok_or(ValidationError::NodeUnavailable)??,
+            None => call.await?,
+        };
         
         // Check for RPC error
         if let Some(error) = response.get("error") {
@@ -125,60 +563,243 @@ impl TransactionValidator {
             }
         }
         
-        // Get the result array (testmempoolaccept returns array of results)
-        let results = response["result"]
-            .as_array()
-            .ok_or_else(|| ValidationError::bitcoin_core_rejection("Invalid response format"))?;
-        
-        if results.is_empty() {
-            return Err(ValidationError::bitcoin_core_rejection("Empty response"));
-        }
-        
-        let result = &results[0];
-        
-        if result["allowed"].as_bool() == Some(true) {
-            Ok(())
-        } else {
-            let reason = result["reject-reason"]
-                .as_str()
-                .unwrap_or("unknown reason");
-            Err(ValidationError::bitcoin_core_rejection(reason))
-        }
+        Ok(response["result"].take())
     }
     
-    fn extract_txid(&self, tx_hex: &str) -> Result<String, ValidationError> {
+    fn decode_transaction(&self, tx_hex: &str) -> Result<Transaction, ValidationError> {
         let tx_bytes = hex::decode(tx_hex).map_err(|_| ValidationError::InvalidHex)?;
-        let tx = deserialize::<Transaction>(&tx_bytes)
-            .map_err(|_| ValidationError::InvalidStructure)?;
-        Ok(tx.txid().to_string())
+        deserialize::<Transaction>(&tx_bytes)
+            .map_err(|_| ValidationError::InvalidStructure)
+    }
+    
+    /// The wtxid validated for `txid`, if it was validated within the cache TTL
+    fn cached_wtxid(&self, txid: &str) -> Option<String> {
+        let cache = self.tx_cache.read().ok()?;
+        let entry = cache.peek(txid)?;
+        let ttl = Duration::from_secs(self.config.cache_ttl_seconds);
+        (entry.validated_at.elapsed() < ttl).then(|| entry.wtxid.clone())
     }
     
-    fn is_recently_processed(&self, txid: &str) -> bool {
-        if let Ok(cache) = self.tx_cache.read() {
-            if let Some(first_seen) = cache.peek(txid) {
-                let ttl = Duration::from_secs(self.config.cache_ttl_seconds);
-                return first_seen.elapsed() < ttl;
+    fn cache_transaction(&self, txid: &str, wtxid: &str) {
+        if let Ok(mut cache) = self.tx_cache.write() {
+            cache.put(txid.to_string(), CacheEntry {
+                validated_at: Instant::now(),
+                wtxid: wtxid.to_string(),
+            });
+        }
+    }
+}
+
+/// Smallest serialized transaction the precheck accepts, in bytes
+pub const MIN_TX_BYTES: usize = 60;
+
+/// Largest serialized transaction the precheck accepts, in bytes
+pub const MAX_TX_BYTES: usize = 400_000;
+
+/// Check a transaction's hex encoding and size without decoding it, returning the byte length
+/// 
+/// Makes a single pass over the input and allocates nothing. Rejects exactly the inputs
+/// `hex::decode` rejects, then sizes outside [`MIN_TX_BYTES`]..=[`MAX_TX_BYTES`].
+pub fn precheck_hex(tx_hex: &str) -> Result<usize, ValidationError> {
+    if tx_hex.is_empty() {
+        return Err(ValidationError::EmptyTransaction);
+    }
+    
+    if !tx_hex.len().is_multiple_of(2) || !tx_hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ValidationError::InvalidHex);
+    }
+    
+    let byte_len = tx_hex.len() / 2;
+    if !(MIN_TX_BYTES..=MAX_TX_BYTES).contains(&byte_len) {
+        return Err(ValidationError::invalid_size(byte_len));
+    }
+    
+    Ok(byte_len)
+}
+
+/// Confirmations a coinbase output needs before a spend of it can be mined
+const COINBASE_MATURITY: u64 = 100;
+
+/// Bitcoin Core error code for a transaction rejected by mempool policy
+const RPC_VERIFY_REJECTED: i32 = -26;
+
+/// Map a Core mempool rejection to a `ValidationError`, singling out replace-by-fee failures
+/// 
+/// `code` is the RPC error code when the rejection came from `sendrawtransaction`, and
+/// `None` for `testmempoolaccept` reject reasons.
+pub fn core_rejection(code: Option<i32>, reason: &str) -> ValidationError {
+    if code.is_some_and(|code| code != RPC_VERIFY_REJECTED) {
+        return ValidationError::bitcoin_core_rejection(reason);
+    }
+    
+    if reason.starts_with("txn-mempool-conflict")
+        || reason.starts_with("too many potential replacements")
+        || reason.starts_with("replacement-adds-unconfirmed")
+    {
+        ValidationError::mempool_conflict(reason)
+    } else if reason.starts_with("insufficient fee") {
+        // Core only reports "insufficient fee" for replacements that don't pay enough
+        ValidationError::replacement_underpriced(reason)
+    } else {
+        ValidationError::bitcoin_core_rejection(reason)
+    }
+}
+
+/// The replace-by-fee rejection behind a failed `sendrawtransaction`, if that's what it was
+pub fn replacement_rejection(err: &crate::RelayError) -> Option<ValidationError> {
+    match err {
+        crate::RelayError::BitcoinRpc(crate::BitcoinRpcError::BitcoinCore { code, message }) => {
+            match core_rejection(Some(*code), message) {
+                ValidationError::BitcoinCoreRejection { .. } => None,
+                rejection => Some(rejection),
             }
         }
-        false
+        _ => None,
+    }
+}
+
+/// Parse a txid received from a client, a peer or the node
+pub fn parse_txid(value: &str) -> Result<Txid, ValidationError> {
+    value.parse().map_err(|_| ValidationError::invalid_txid(value))
+}
+
+/// Reject transactions with more inputs or outputs than configured, before anything costlier
+fn check_input_output_count(tx: &Transaction, config: &ValidationConfig) -> Result<(), ValidationError> {
+    if let Some(max_inputs) = config.max_inputs.filter(|max| tx.input.len() > *max) {
+        return Err(ValidationError::too_many_inputs(tx.input.len(), max_inputs));
+    }
+    if let Some(max_outputs) = config.max_outputs.filter(|max| tx.output.len() > *max) {
+        return Err(ValidationError::too_many_outputs(tx.output.len(), max_outputs));
+    }
+    Ok(())
+}
+
+/// Apply the relay's own output policy, independent of the node's standardness rules
+fn check_output_policy(tx: &Transaction, config: &ValidationConfig) -> Result<(), ValidationError> {
+    let op_returns: Vec<&Script> = tx.output
+        .iter()
+        .map(|output| output.script_pubkey.as_script())
+        .filter(|script| script.is_op_return())
+        .collect();
+    
+    if let Some(max_outputs) = config.max_op_return_outputs {
+        if op_returns.len() > max_outputs {
+            return Err(ValidationError::output_policy(format!(
+                "{} OP_RETURN outputs exceed the limit of {}",
+                op_returns.len(), max_outputs
+            )));
+        }
     }
     
-    fn cache_transaction(&self, txid: &str) {
-        if let Ok(mut cache) = self.tx_cache.write() {
-            cache.put(txid.to_string(), Instant::now());
+    if let Some(max_bytes) = config.max_op_return_bytes {
+        if let Some(script) = op_returns.iter().find(|script| script.len() > max_bytes) {
+            return Err(ValidationError::output_policy(format!(
+                "{}-byte OP_RETURN output exceeds the limit of {} bytes",
+                script.len(), max_bytes
+            )));
         }
     }
+    
+    if config.reject_bare_multisig && tx.output.iter().any(|output| is_bare_multisig(&output.script_pubkey)) {
+        return Err(ValidationError::output_policy("bare multisig output"));
+    }
+    
+    Ok(())
 }
 
-impl Clone for TransactionValidator {
-    fn clone(&self) -> Self {
-        Self::new(self.config.clone(), self.bitcoin_rpc_url.strip_prefix("http://127.0.0.1:").and_then(|s| s.parse().ok()).unwrap_or(18332))
+/// Apply the configured subset of Bitcoin Core's standardness rules before asking the node
+fn check_standardness(tx: &Transaction, config: &ValidationConfig) -> Result<(), ValidationError> {
+    if config.require_standard_version && !(1..=2).contains(&tx.version) {
+        return Err(ValidationError::non_standard_version(tx.version));
+    }
+    
+    if config.require_push_only_script_sig {
+        if let Some(input) = tx.input.iter().position(|input| !is_push_only(&input.script_sig)) {
+            return Err(ValidationError::non_push_script_sig(input));
+        }
     }
+    
+    if let Some(threshold) = config.dust_threshold_sats {
+        let dust = tx.output
+            .iter()
+            .enumerate()
+            .find(|(_, output)| output.value < threshold && !output.script_pubkey.is_op_return());
+        if let Some((index, output)) = dust {
+            return Err(ValidationError::dust_output(index, output.value, threshold));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Whether `script` only pushes data, counting `OP_1NEGATE` through `OP_16` as pushes like Core does
+fn is_push_only(script: &Script) -> bool {
+    script.instructions().all(|instruction| match instruction {
+        Ok(Instruction::PushBytes(_)) => true,
+        Ok(Instruction::Op(op)) => op.to_u8() <= OP_PUSHNUM_16.to_u8(),
+        Err(_) => false,
+    })
+}
+
+/// Whether `script` is `OP_m <pubkey>... OP_n OP_CHECKMULTISIG`
+fn is_bare_multisig(script: &Script) -> bool {
+    let Ok(instructions) = script.instructions().collect::<Result<Vec<_>, _>>() else {
+        return false;
+    };
+    let [Instruction::Op(required), keys @ .., Instruction::Op(total), Instruction::Op(check)] = instructions.as_slice() else {
+        return false;
+    };
+    let pushnum = |op: &bitcoin::blockdata::opcodes::All| match op.to_u8() {
+        n @ 0x51..=0x60 => Some(usize::from(n - 0x50)),
+        _ => None,
+    };
+    
+    *check == OP_CHECKMULTISIG
+        && matches!((pushnum(required), pushnum(total)), (Some(m), Some(n)) if m <= n && n == keys.len())
+        && keys.iter().all(|key| matches!(key, Instruction::PushBytes(bytes) if bytes.len() == 33 || bytes.len() == 65))
+}
+
+/// Interpret a single `testmempoolaccept` result entry
+/// 
+/// The fee floor is only enforced when Core reports `fees` and `vsize` (Core 22+).
+fn mempool_accept_result(result: &Value, min_fee_rate: Option<f64>) -> Result<(), ValidationError> {
+    if result["allowed"].as_bool() == Some(true) {
+        match min_fee_rate {
+            Some(min_fee_rate) => check_fee_floor(result, min_fee_rate),
+            None => Ok(()),
+        }
+    } else {
+        // Package-level failures carry "package-error" instead of "reject-reason"
+        let reason = result["reject-reason"]
+            .as_str()
+            .or_else(|| result["package-error"].as_str())
+            .unwrap_or("unknown reason");
+        Err(core_rejection(None, reason))
+    }
+}
+
+/// Enforce the relay's fee floor on an accepted `testmempoolaccept` result
+/// 
+/// Rejected results and results without `fees` and `vsize` pass, leaving them to the node.
+fn check_fee_floor(result: &Value, min_fee_rate: f64) -> Result<(), ValidationError> {
+    if result["allowed"].as_bool() != Some(true) {
+        return Ok(());
+    }
+    let fee_btc = result["fees"]["base"].as_f64();
+    let vsize = result["vsize"].as_f64().filter(|vsize| *vsize > 0.0);
+    if let (Some(fee_btc), Some(vsize)) = (fee_btc, vsize) {
+        let fee_rate = (fee_btc * 100_000_000.0).round() / vsize;
+        if fee_rate < min_fee_rate {
+            return Err(ValidationError::fee_rate_too_low(fee_rate, min_fee_rate));
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{sample_tx_hex, spawn_mock_rpc};
 
     #[tokio::test]
     async fn test_validation_disabled() {
@@ -214,56 +835,86 @@ mod tests {
 
     #[test]
     fn test_quick_validation_empty_transaction() {
-        let config = ValidationConfig::default();
-        let validator = TransactionValidator::new(config, 18332);
-        
-        let result = validator.quick_validation_checks("");
+        let result = precheck_hex("");
         assert!(matches!(result, Err(ValidationError::EmptyTransaction)));
     }
 
     #[test]
     fn test_quick_validation_invalid_hex() {
-        let config = ValidationConfig::default();
-        let validator = TransactionValidator::new(config, 18332);
-        
         // Non-hex characters
-        let result = validator.quick_validation_checks("hello world");
+        let result = precheck_hex("hello world");
         assert!(matches!(result, Err(ValidationError::InvalidHex)));
         
         // Mixed case with invalid characters
-        let result = validator.quick_validation_checks("abcdefg");  // 'g' is not hex
+        let result = precheck_hex("abcdefg");  // 'g' is not hex
         assert!(matches!(result, Err(ValidationError::InvalidHex)));
     }
 
     #[test]
     fn test_quick_validation_invalid_size() {
-        let config = ValidationConfig::default();
-        let validator = TransactionValidator::new(config, 18332);
-        
         // Too small (less than 60 bytes = 120 hex chars)
         let small_tx = "a".repeat(118); // 59 bytes
-        let result = validator.quick_validation_checks(&small_tx);
+        let result = precheck_hex(&small_tx);
         assert!(matches!(result, Err(ValidationError::InvalidSize { size: 59 })));
         
         // Too large (more than 400KB = 800,000 hex chars)
         let large_tx = "a".repeat(800_002); // 400,001 bytes
-        let result = validator.quick_validation_checks(&large_tx);
+        let result = precheck_hex(&large_tx);
         assert!(matches!(result, Err(ValidationError::InvalidSize { size: 400_001 })));
     }
 
     #[test]
-    fn test_quick_validation_valid_hex() {
-        let config = ValidationConfig::default();
-        let validator = TransactionValidator::new(config, 18332);
+    fn test_precheck_agrees_with_decoding() {
+        // Reference precheck that decodes the whole transaction
+        fn decoding_precheck(tx_hex: &str) -> Result<usize, ValidationError> {
+            if tx_hex.is_empty() {
+                return Err(ValidationError::EmptyTransaction);
+            }
+            let byte_len = hex::decode(tx_hex).map_err(|_| ValidationError::InvalidHex)?.len();
+            if !(MIN_TX_BYTES..=MAX_TX_BYTES).contains(&byte_len) {
+                return Err(ValidationError::invalid_size(byte_len));
+            }
+            Ok(byte_len)
+        }
         
+        let cases = [
+            String::new(),
+            "a".to_string(),
+            "a".repeat(121),
+            "a".repeat(118),
+            "a".repeat(120),
+            "AbCdEf".repeat(20),
+            "a".repeat(MAX_TX_BYTES * 2),
+            "a".repeat(MAX_TX_BYTES * 2 + 1),
+            "a".repeat(MAX_TX_BYTES * 2 + 2),
+            format!("{}g", "a".repeat(119)),
+            format!("{}é", "a".repeat(118)),
+            format!(" {}", "a".repeat(119)),
+            sample_tx_hex(),
+        ];
+        for tx_hex in &cases {
+            assert_eq!(
+                format!("{:?}", precheck_hex(tx_hex)),
+                format!("{:?}", decoding_precheck(tx_hex)),
+                "input of length {}",
+                tx_hex.len()
+            );
+        }
+        
+        // Odd-length hex is malformed even when every character is a hex digit
+        assert!(matches!(precheck_hex(&"a".repeat(121)), Err(ValidationError::InvalidHex)));
+    }
+
+    #[test]
+    fn test_quick_validation_valid_hex() {
         // Valid hex string of appropriate length (60 bytes = 120 hex chars)
         let valid_hex = "a".repeat(120);
-        let result = validator.quick_validation_checks(&valid_hex);
+        let result = precheck_hex(&valid_hex);
         assert!(result.is_ok());
         
         // Test with actual hex characters
         let mixed_case_hex = "AbCdEf0123456789".repeat(8); // 128 hex chars = 64 bytes
-        let result = validator.quick_validation_checks(&mixed_case_hex);
+        let result = precheck_hex(&mixed_case_hex);
         assert!(result.is_ok());
     }
 
@@ -294,20 +945,270 @@ mod tests {
     }
     
     #[test]
-    fn test_extract_txid() {
+    fn test_decode_transaction_errors() {
         let config = ValidationConfig::default();
         let validator = TransactionValidator::new(config, 18332);
         
         // Test with invalid hex
-        let result = validator.extract_txid("invalid_hex");
+        let result = validator.decode_transaction("invalid_hex");
         assert!(matches!(result, Err(ValidationError::InvalidHex)));
         
         // Test with valid hex but invalid structure
         let invalid_tx_hex = "a".repeat(120);
-        let result = validator.extract_txid(&invalid_tx_hex);
+        let result = validator.decode_transaction(&invalid_tx_hex);
         assert!(matches!(result, Err(ValidationError::InvalidStructure)));
     }
     
+    #[tokio::test]
+    async fn test_hung_node_times_out() {
+        let response = json!({"result": [{"allowed": true}], "error": null, "id": "validation"});
+        let port = crate::test_utils::spawn_slow_rpc(response, Duration::from_secs(5)).await;
+        let config = ValidationConfig {
+            validation_timeout_ms: 50,
+            ..Default::default()
+        };
+        let validator = TransactionValidator::new(config, port);
+        
+        let started = Instant::now();
+        let result = validator.validate(&sample_tx_hex()).await;
+        assert!(matches!(result, Err(ValidationError::Timeout)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        
+        // Nothing is cached, so a retry asks the node again
+        assert!(matches!(validator.validate(&sample_tx_hex()).await, Err(ValidationError::Timeout)));
+    }
+    
+    #[tokio::test]
+    async fn test_unreachable_node_trips_circuit_breaker() {
+        let breaker = Arc::new(CircuitBreaker::new(crate::CircuitBreakerConfig {
+            failure_threshold: 2,
+            cool_down: Duration::from_secs(60),
+        }));
+        // Nothing listens on port 1
+        let validator = TransactionValidator::new(ValidationConfig::default(), 1).with_circuit_breaker(Arc::clone(&breaker));
+        
+        for _ in 0..2 {
+            assert!(matches!(validator.validate(&sample_tx_hex()).await, Err(ValidationError::BitcoinCoreRejection { .. })));
+        }
+        assert_eq!(breaker.state(), crate::BreakerState::Open);
+        assert!(matches!(validator.validate(&sample_tx_hex()).await, Err(ValidationError::NodeUnavailable)));
+    }
+
+    #[tokio::test]
+    async fn test_validator_with_socks_proxy() {
+        let rpc_port = spawn_mock_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let (proxy_port, targets) = crate::test_utils::spawn_socks5_proxy().await;
+        let pool = HttpPoolConfig {
+            socks_proxy: Some(format!("127.0.0.1:{}", proxy_port)),
+            ..Default::default()
+        };
+        let validator = TransactionValidator::new(ValidationConfig::default(), rpc_port).with_http_pool(&pool).unwrap();
+        
+        assert!(validator.validate(&sample_tx_hex()).await.is_ok());
+        assert_eq!(*targets.lock().unwrap(), vec![format!("127.0.0.1:{}", rpc_port)]);
+    }
+
+    #[tokio::test]
+    async fn test_validator_uses_configured_endpoint_and_auth() {
+        let (port, requests) = crate::test_utils::spawn_recording_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let validator = TransactionValidator::with_rpc_endpoint(
+            ValidationConfig::default(),
+            format!("http://localhost:{}/wallet/relay", port),
+            RpcAuth::new("alice".to_string(), "secret".to_string()),
+        );
+        
+        assert!(validator.validate(&sample_tx_hex()).await.is_ok());
+        let requests = requests.lock().unwrap();
+        let (headers, _) = &requests[0];
+        assert!(headers.starts_with("POST /wallet/relay "), "{}", headers);
+        // base64("alice:secret")
+        assert!(headers.lines().any(|line| line.eq_ignore_ascii_case("authorization: Basic YWxpY2U6c2VjcmV0")), "{}", headers);
+    }
+
+    #[tokio::test]
+    async fn test_cloned_validator_keeps_socks_proxy() {
+        let rpc_port = spawn_mock_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let (proxy_port, targets) = crate::test_utils::spawn_socks5_proxy().await;
+        let pool = HttpPoolConfig {
+            socks_proxy: Some(format!("127.0.0.1:{}", proxy_port)),
+            ..Default::default()
+        };
+        let validator = TransactionValidator::new(ValidationConfig::default(), rpc_port).with_http_pool(&pool).unwrap().clone();
+        
+        assert!(validator.validate(&sample_tx_hex()).await.is_ok());
+        assert_eq!(*targets.lock().unwrap(), vec![format!("127.0.0.1:{}", rpc_port)]);
+    }
+
+    #[tokio::test]
+    async fn test_validate_detailed_report() {
+        let port = spawn_mock_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let validator = TransactionValidator::new(ValidationConfig::default(), port);
+        
+        let tx_hex = sample_tx_hex();
+        let tx: Transaction = deserialize(&hex::decode(&tx_hex).unwrap()).unwrap();
+        
+        let report = validator.validate_detailed(&tx_hex).await.unwrap();
+        assert_eq!(report.txid, tx.txid().to_string());
+        assert_eq!(report.vsize, tx_hex.len() / 2); // No witness data
+        assert_eq!(report.weight, (tx_hex.len() / 2 * 4) as u64);
+        assert_eq!(report.checks_run, vec![
+            ValidationCheck::Precheck,
+            ValidationCheck::Structure,
+            ValidationCheck::BitcoinCore,
+        ]);
+        assert!(!report.from_cache);
+        
+        // Second call is served from the cache
+        let cached = validator.validate_detailed(&tx_hex).await.unwrap();
+        assert!(cached.from_cache);
+        assert_eq!(cached.checks_run, vec![ValidationCheck::Precheck, ValidationCheck::Structure]);
+        assert_eq!(cached.txid, report.txid);
+        
+        // The thin wrapper keeps reporting cache hits as errors
+        let result = validator.validate(&tx_hex).await;
+        assert!(matches!(result, Err(ValidationError::RecentlyProcessed { .. })));
+    }
+    
+    /// The sample transaction with a witness on its input
+    fn segwit_tx(witness: &[u8]) -> Transaction {
+        let mut tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        tx.input[0].witness = bitcoin::Witness::from_slice(&[witness]);
+        tx
+    }
+    
+    #[tokio::test]
+    async fn test_cache_covers_witness_variants() {
+        let port = spawn_mock_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let validator = TransactionValidator::new(ValidationConfig::default(), port);
+        
+        let original = segwit_tx(&[0x01; 72]);
+        let malleated = segwit_tx(&[0x02; 71]);
+        assert_eq!(original.txid(), malleated.txid());
+        assert_ne!(original.wtxid(), malleated.wtxid());
+        
+        let report = validator.validate_detailed(&bitcoin::consensus::encode::serialize_hex(&original)).await.unwrap();
+        assert!(!report.from_cache);
+        assert_eq!(report.txid, original.txid().to_string());
+        assert_eq!(report.wtxid, original.wtxid().to_string());
+        
+        // Same txid with a different witness is served from the cache but reports its own wtxid
+        let variant = validator.validate_detailed(&bitcoin::consensus::encode::serialize_hex(&malleated)).await.unwrap();
+        assert!(variant.from_cache);
+        assert_eq!(variant.txid, report.txid);
+        assert_eq!(variant.wtxid, malleated.wtxid().to_string());
+        assert_eq!(validator.cached_wtxid(&report.txid), Some(report.wtxid));
+    }
+    
+    #[tokio::test]
+    async fn test_cache_distinguishes_different_txids() {
+        let port = spawn_mock_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let validator = TransactionValidator::new(ValidationConfig::default(), port);
+        
+        let segwit = segwit_tx(&[0x01; 72]);
+        let mut other = segwit.clone();
+        other.lock_time = bitcoin::absolute::LockTime::from_consensus(1);
+        
+        validator.validate_detailed(&bitcoin::consensus::encode::serialize_hex(&segwit)).await.unwrap();
+        let report = validator.validate_detailed(&bitcoin::consensus::encode::serialize_hex(&other)).await.unwrap();
+        assert!(!report.from_cache);
+    }
+    
+    #[tokio::test]
+    async fn test_validate_detailed_without_precheck() {
+        let port = spawn_mock_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let config = ValidationConfig {
+            enable_precheck: false,
+            ..Default::default()
+        };
+        let validator = TransactionValidator::new(config, port);
+        
+        let report = validator.validate_detailed(&sample_tx_hex()).await.unwrap();
+        assert_eq!(report.checks_run, vec![ValidationCheck::Structure, ValidationCheck::BitcoinCore]);
+    }
+    
+    #[tokio::test]
+    async fn test_validate_detailed_rejection_and_disabled() {
+        let port = spawn_mock_rpc(json!({
+            "result": [{"allowed": false, "reject-reason": "bad-txns-inputs-missingorspent"}],
+            "error": null,
+            "id": "validation"
+        })).await;
+        let validator = TransactionValidator::new(ValidationConfig::default(), port);
+        
+        let result = validator.validate_detailed(&sample_tx_hex()).await;
+        assert!(matches!(result, Err(ValidationError::BitcoinCoreRejection { reason }) if reason == "bad-txns-inputs-missingorspent"));
+        
+        let disabled = TransactionValidator::new(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        }, port);
+        assert!(matches!(disabled.validate_detailed(&sample_tx_hex()).await, Err(ValidationError::Disabled)));
+    }
+    
+    #[tokio::test]
+    async fn test_validate_batch_mixed_prechecks() {
+        // Only the one transaction that survives the prechecks reaches Bitcoin Core
+        let port = spawn_mock_rpc(json!({"result": [{"allowed": true}], "error": null, "id": "validation"})).await;
+        let validator = TransactionValidator::new(ValidationConfig::default(), port);
+        
+        let valid = sample_tx_hex();
+        let too_small = "a".repeat(118);
+        let batch = [valid.as_str(), "", "not_hex", too_small.as_str()];
+        
+        let results = validator.validate_batch(&batch).await;
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(ValidationError::EmptyTransaction)));
+        assert!(matches!(results[2], Err(ValidationError::InvalidHex)));
+        assert!(matches!(results[3], Err(ValidationError::InvalidSize { size: 59 })));
+        
+        // Successful entries are cached like single validations
+        let again = validator.validate_batch(&[valid.as_str()]).await;
+        assert!(matches!(again[0], Err(ValidationError::RecentlyProcessed { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_validate_batch_package_results_in_order() {
+        let port = spawn_mock_rpc(json!({
+            "result": [
+                {"txid": "parent", "package-error": "package-mempool-limits"},
+                {"txid": "child", "allowed": true}
+            ],
+            "error": null,
+            "id": "validation"
+        })).await;
+        let validator = TransactionValidator::new(ValidationConfig::default(), port);
+        
+        let parent = sample_tx_hex();
+        let mut child_tx: Transaction = deserialize(&hex::decode(&parent).unwrap()).unwrap();
+        child_tx.lock_time = bitcoin::absolute::LockTime::from_consensus(1);
+        let child = hex::encode(bitcoin::consensus::serialize(&child_tx));
+        
+        let results = validator.validate_batch(&[parent.as_str(), child.as_str()]).await;
+        assert!(matches!(&results[0], Err(ValidationError::BitcoinCoreRejection { reason }) if reason == "package-mempool-limits"));
+        assert!(results[1].is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_validate_batch_without_testmempoolaccept() {
+        let config = ValidationConfig {
+            use_testmempoolaccept: false,
+            ..Default::default()
+        };
+        let validator = TransactionValidator::new(config, 1);
+        
+        let valid = sample_tx_hex();
+        let results = validator.validate_batch(&[valid.as_str(), "zz"]).await;
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(ValidationError::InvalidHex)));
+        
+        let disabled = TransactionValidator::new(ValidationConfig {
+            enable_validation: false,
+            ..Default::default()
+        }, 1);
+        assert!(disabled.validate_batch(&["zz", ""]).await.iter().all(|r| r.is_ok()));
+    }
+    
     #[test]
     fn test_cache_functionality() {
         let config = ValidationConfig::default();
@@ -316,13 +1217,13 @@ mod tests {
         let test_txid = "test_transaction_id";
         
         // Initially not in cache
-        assert!(!validator.is_recently_processed(test_txid));
+        assert!(validator.cached_wtxid(test_txid).is_none());
         
         // Add to cache
-        validator.cache_transaction(test_txid);
+        validator.cache_transaction(test_txid, test_txid);
         
         // Now should be in cache
-        assert!(validator.is_recently_processed(test_txid));
+        assert_eq!(validator.cached_wtxid(test_txid).as_deref(), Some(test_txid));
     }
     
     #[test] 
@@ -340,14 +1241,17 @@ mod tests {
     #[tokio::test]
     #[ignore] // Use `cargo test -- --ignored` to run this test
     async fn test_bitcoin_core_integration_valid_transaction() {
-        let config = ValidationConfig::default();
+        let config = ValidationConfig {
+            stages: vec![ValidationStage::NodeAccept],
+            ..Default::default()
+        };
         let validator = TransactionValidator::new(config, 18332);
         
         // This is a valid transaction hex from regtest (you'll need to replace with actual valid tx)
         // For now, this test is ignored and would need a real transaction hex
         let valid_tx_hex = "0200000001..."; // Replace with real transaction
         
-        let _result = validator.validate_with_bitcoin_core(valid_tx_hex).await;
+        let _result = validator.validate(valid_tx_hex).await;
         // This test requires actual Bitcoin Core running and a valid transaction
         // assert!(result.is_ok());
     }
@@ -355,13 +1259,16 @@ mod tests {
     #[tokio::test]
     #[ignore] // Use `cargo test -- --ignored` to run this test  
     async fn test_bitcoin_core_integration_invalid_transaction() {
-        let config = ValidationConfig::default();
+        let config = ValidationConfig {
+            stages: vec![ValidationStage::NodeAccept],
+            ..Default::default()
+        };
         let validator = TransactionValidator::new(config, 18332);
         
         // Invalid transaction hex (too short but valid hex)
         let invalid_tx_hex = "a".repeat(120);
         
-        let result = validator.validate_with_bitcoin_core(&invalid_tx_hex).await;
+        let result = validator.validate(&invalid_tx_hex).await;
         assert!(result.is_err());
         
         if let Err(ValidationError::BitcoinCoreRejection { reason }) = result {
@@ -379,20 +1286,419 @@ mod tests {
         let txid = "test_transaction_id";
         
         // First check - should not be in cache
-        assert!(!validator.is_recently_processed(txid));
+        assert!(validator.cached_wtxid(txid).is_none());
         
         // Mark as processed
-        validator.cache_transaction(txid);
+        validator.cache_transaction(txid, txid);
         
         // Second check - should now be in cache  
-        assert!(validator.is_recently_processed(txid));
+        assert!(validator.cached_wtxid(txid).is_some());
         
         // Should return RecentlyProcessed error
-        let _result = validator.quick_validation_checks("deadbeef"); // Valid hex to pass initial checks
-        // Then manually check cache (since quick_validation_checks doesn't check cache)
-        if validator.is_recently_processed("deadbeef") {
+        let _result = precheck_hex("deadbeef"); // Valid hex to pass initial checks
+        // Then manually check cache (since the precheck doesn't check cache)
+        if validator.cached_wtxid("deadbeef").is_some() {
             let cache_result: Result<(), ValidationError> = Err(ValidationError::recently_processed("deadbeef"));
             assert!(matches!(cache_result, Err(ValidationError::RecentlyProcessed { .. })));
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rbf_rejections_mapped() {
+        let underpriced = [
+            "insufficient fee, rejecting replacement 5a1c...; new feerate 0.00001000 BTC/kvB <= old feerate 0.00002000 BTC/kvB",
+            "insufficient fee, rejecting replacement 5a1c..., less fees than conflicting txs; 0.00001 < 0.00002",
+            "insufficient fee, rejecting replacement 5a1c..., not enough additional fees to relay; 0.00 < 0.00000141",
+        ];
+        for message in underpriced {
+            assert!(matches!(core_rejection(Some(-26), message), ValidationError::ReplacementUnderpriced { .. }));
+        }
+        
+        let conflicts = [
+            "txn-mempool-conflict",
+            "too many potential replacements, rejecting replacement 5a1c...; too many potential replacements (101 > 100)",
+        ];
+        for message in conflicts {
+            assert!(matches!(core_rejection(Some(-26), message), ValidationError::MempoolConflict { .. }));
+        }
+        
+        // Unrelated policy failures and other error codes are left alone
+        assert!(matches!(core_rejection(Some(-26), "min relay fee not met"), ValidationError::BitcoinCoreRejection { .. }));
+        assert!(matches!(core_rejection(Some(-25), "insufficient fee"), ValidationError::BitcoinCoreRejection { .. }));
+        
+        // testmempoolaccept reject reasons have no code
+        let result = json!({"allowed": false, "reject-reason": "insufficient fee, rejecting replacement 5a1c..."});
+        assert!(matches!(mempool_accept_result(&result, None), Err(ValidationError::ReplacementUnderpriced { .. })));
+    }
+
+    #[test]
+    fn test_replacement_rejection_from_rpc_error() {
+        let err: crate::RelayError = crate::BitcoinRpcError::bitcoin_core(-26, "txn-mempool-conflict").into();
+        assert!(matches!(replacement_rejection(&err), Some(ValidationError::MempoolConflict { .. })));
+        
+        let err: crate::RelayError = crate::BitcoinRpcError::bitcoin_core(-26, "bad-txns-inputs-missingorspent").into();
+        assert!(replacement_rejection(&err).is_none());
+    }
+
+    /// The sample transaction with extra outputs
+    fn tx_with_outputs(scripts: Vec<bitcoin::ScriptBuf>) -> Transaction {
+        let mut tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        for script_pubkey in scripts {
+            tx.output.push(bitcoin::TxOut { value: 0, script_pubkey });
+        }
+        tx
+    }
+    
+    fn op_return(data_len: usize) -> bitcoin::ScriptBuf {
+        let data = bitcoin::script::PushBytesBuf::try_from(vec![0x42; data_len]).unwrap();
+        bitcoin::ScriptBuf::new_op_return(&data)
+    }
+    
+    fn bare_multisig() -> bitcoin::ScriptBuf {
+        use bitcoin::blockdata::opcodes::all::{OP_PUSHNUM_1, OP_PUSHNUM_2};
+        bitcoin::ScriptBuf::builder()
+            .push_opcode(OP_PUSHNUM_1)
+            .push_slice([0x02; 33])
+            .push_slice([0x03; 33])
+            .push_opcode(OP_PUSHNUM_2)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script()
+    }
+    
+    #[test]
+    fn test_op_return_size_limit() {
+        let config = ValidationConfig {
+            max_op_return_bytes: Some(83),
+            ..Default::default()
+        };
+        
+        // 80 data bytes plus OP_RETURN and OP_PUSHDATA1 <len> is exactly 83 bytes
+        assert!(check_output_policy(&tx_with_outputs(vec![op_return(80)]), &config).is_ok());
+        assert!(matches!(
+            check_output_policy(&tx_with_outputs(vec![op_return(81)]), &config),
+            Err(ValidationError::OutputPolicy { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_op_return_count_limit() {
+        let config = ValidationConfig {
+            max_op_return_outputs: Some(1),
+            ..Default::default()
+        };
+        
+        assert!(check_output_policy(&tx_with_outputs(vec![op_return(8)]), &config).is_ok());
+        assert!(matches!(
+            check_output_policy(&tx_with_outputs(vec![op_return(8), op_return(8)]), &config),
+            Err(ValidationError::OutputPolicy { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_bare_multisig_rejection() {
+        let tx = tx_with_outputs(vec![bare_multisig()]);
+        assert!(check_output_policy(&tx, &ValidationConfig::default()).is_ok());
+        
+        let config = ValidationConfig {
+            reject_bare_multisig: true,
+            ..Default::default()
+        };
+        assert!(matches!(check_output_policy(&tx, &config), Err(ValidationError::OutputPolicy { .. })));
+        // OP_RETURN and ordinary outputs aren't multisig
+        assert!(check_output_policy(&tx_with_outputs(vec![op_return(8)]), &config).is_ok());
+    }
+    
+    fn standardness_config() -> ValidationConfig {
+        ValidationConfig {
+            require_standard_version: true,
+            require_push_only_script_sig: true,
+            dust_threshold_sats: Some(546),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_standard_transaction_passes() {
+        let tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        assert!(check_standardness(&tx, &standardness_config()).is_ok());
+        // Zero-value OP_RETURN outputs aren't dust
+        assert!(check_standardness(&tx_with_outputs(vec![op_return(8)]), &standardness_config()).is_ok());
+    }
+    
+    #[test]
+    fn test_version_3_rejected() {
+        let mut tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        tx.version = 3;
+        assert!(matches!(
+            check_standardness(&tx, &standardness_config()),
+            Err(ValidationError::NonStandardVersion { version: 3 })
+        ));
+        assert!(check_standardness(&tx, &ValidationConfig::default()).is_ok());
+    }
+    
+    #[test]
+    fn test_dust_output_rejected() {
+        let mut tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        let script_pubkey = tx.output[0].script_pubkey.clone();
+        tx.output.push(bitcoin::TxOut { value: 545, script_pubkey });
+        let dust_index = tx.output.len() - 1;
+        
+        assert!(matches!(
+            check_standardness(&tx, &standardness_config()),
+            Err(ValidationError::DustOutput { output, value: 545, threshold: 546 }) if output == dust_index
+        ));
+    }
+    
+    #[test]
+    fn test_non_push_script_sig_rejected() {
+        use bitcoin::blockdata::opcodes::all::{OP_DUP, OP_PUSHNUM_1};
+        let mut tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        tx.input[0].script_sig = bitcoin::ScriptBuf::builder().push_opcode(OP_PUSHNUM_1).push_slice([0x42; 4]).into_script();
+        assert!(check_standardness(&tx, &standardness_config()).is_ok());
+        
+        tx.input[0].script_sig = bitcoin::ScriptBuf::builder().push_slice([0x42; 4]).push_opcode(OP_DUP).into_script();
+        assert!(matches!(
+            check_standardness(&tx, &standardness_config()),
+            Err(ValidationError::NonPushScriptSig { input: 0 })
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_standardness_runs_before_node() {
+        // Nothing listens on port 1, so reaching testmempoolaccept would fail differently
+        let validator = TransactionValidator::new(standardness_config(), 1);
+        let mut tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        tx.version = 3;
+        
+        let tx_hex = bitcoin::consensus::encode::serialize_hex(&tx);
+        assert!(matches!(validator.validate(&tx_hex).await, Err(ValidationError::NonStandardVersion { .. })));
+    }
+    
+    /// The sample transaction with `inputs` inputs and `outputs` outputs
+    fn tx_with_counts(inputs: usize, outputs: usize) -> Transaction {
+        let mut tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        tx.input = vec![tx.input[0].clone(); inputs];
+        tx.output = vec![tx.output[0].clone(); outputs];
+        tx
+    }
+    
+    #[test]
+    fn test_input_limit() {
+        let config = ValidationConfig {
+            max_inputs: Some(3),
+            ..Default::default()
+        };
+        
+        assert!(check_input_output_count(&tx_with_counts(2, 1), &config).is_ok());
+        assert!(check_input_output_count(&tx_with_counts(3, 1), &config).is_ok());
+        assert!(matches!(
+            check_input_output_count(&tx_with_counts(4, 1), &config),
+            Err(ValidationError::TooManyInputs { count: 4, max: 3 })
+        ));
+    }
+    
+    #[test]
+    fn test_output_limit() {
+        let config = ValidationConfig {
+            max_outputs: Some(3),
+            ..Default::default()
+        };
+        
+        assert!(check_input_output_count(&tx_with_counts(1, 2), &config).is_ok());
+        assert!(check_input_output_count(&tx_with_counts(1, 3), &config).is_ok());
+        assert!(matches!(
+            check_input_output_count(&tx_with_counts(1, 4), &config),
+            Err(ValidationError::TooManyOutputs { count: 4, max: 3 })
+        ));
+        // Unset limits allow anything
+        assert!(check_input_output_count(&tx_with_counts(500, 500), &ValidationConfig::default()).is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_input_output_limits_run_before_node() {
+        // Nothing listens on port 1, so reaching testmempoolaccept would fail differently
+        let validator = TransactionValidator::new(ValidationConfig {
+            max_outputs: Some(10),
+            ..Default::default()
+        }, 1);
+        
+        let tx_hex = bitcoin::consensus::encode::serialize_hex(&tx_with_counts(1, 11));
+        assert!(matches!(validator.validate(&tx_hex).await, Err(ValidationError::TooManyOutputs { .. })));
+        let results = validator.validate_batch(&[&tx_hex]).await;
+        assert!(matches!(results[0], Err(ValidationError::TooManyOutputs { .. })));
+    }
+    
+    #[test]
+    fn test_parse_txid() {
+        let tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        assert_eq!(parse_txid(&tx.txid().to_string()).unwrap(), tx.txid());
+        
+        for invalid in ["", "remote", &"zz".repeat(32), &"aa".repeat(31), &"aa".repeat(33)] {
+            assert!(matches!(parse_txid(invalid), Err(ValidationError::InvalidTxid { .. })), "{:?} parsed", invalid);
+        }
+    }
+    
+    /// A verbose `getrawtransaction` response for a coinbase transaction
+    fn coinbase_info(confirmations: u64) -> Value {
+        json!({
+            "result": {
+                "txid": "aa".repeat(32),
+                "hash": "aa".repeat(32),
+                "hex": "00",
+                "size": 100,
+                "vsize": 100,
+                "weight": 400,
+                "version": 2,
+                "locktime": 0,
+                "confirmations": confirmations,
+                "vin": [{"coinbase": "0101", "sequence": 4294967295u32}],
+                "vout": [{"value": 50.0, "n": 0, "scriptPubKey": {"hex": "51", "type": "nonstandard"}}]
+            },
+            "error": null
+        })
+    }
+    
+    /// The sample transaction spending output 0 of the mocked prevout
+    fn coinbase_spend_hex() -> String {
+        let mut tx: Transaction = deserialize(&hex::decode(sample_tx_hex()).unwrap()).unwrap();
+        tx.input[0].previous_output = bitcoin::OutPoint::new("aa".repeat(32).parse().unwrap(), 0);
+        bitcoin::consensus::encode::serialize_hex(&tx)
+    }
+    
+    fn coinbase_maturity_config() -> ValidationConfig {
+        ValidationConfig {
+            reject_immature_coinbase_spends: true,
+            use_testmempoolaccept: false,
+            ..Default::default()
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_immature_coinbase_spend_rejected() {
+        let port = spawn_mock_rpc(coinbase_info(99)).await;
+        let validator = TransactionValidator::new(coinbase_maturity_config(), port);
+        
+        assert!(matches!(
+            validator.validate(&coinbase_spend_hex()).await,
+            Err(ValidationError::ImmatureCoinbaseSpend { input: 0, confirmations: 99 })
+        ));
+        let results = validator.validate_batch(&[&coinbase_spend_hex()]).await;
+        assert!(matches!(results[0], Err(ValidationError::ImmatureCoinbaseSpend { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_mature_coinbase_spend_allowed() {
+        let port = spawn_mock_rpc(coinbase_info(100)).await;
+        let validator = TransactionValidator::new(coinbase_maturity_config(), port);
+        
+        let report = validator.validate_detailed(&coinbase_spend_hex()).await.unwrap();
+        assert!(report.checks_run.contains(&ValidationCheck::CoinbaseMaturity));
+    }
+    
+    #[tokio::test]
+    async fn test_unknown_prevout_skipped() {
+        // Without -txindex the node can't find the prevout
+        let port = spawn_mock_rpc(json!({
+            "result": null,
+            "error": {"code": -5, "message": "No such mempool or blockchain transaction"}
+        })).await;
+        let validator = TransactionValidator::new(coinbase_maturity_config(), port);
+        
+        assert!(validator.validate(&coinbase_spend_hex()).await.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_output_policy_runs_before_node() {
+        // Nothing listens on port 1, so reaching testmempoolaccept would fail differently
+        let validator = TransactionValidator::new(ValidationConfig {
+            max_op_return_bytes: Some(83),
+            ..Default::default()
+        }, 1);
+        
+        let tx_hex = bitcoin::consensus::encode::serialize_hex(&tx_with_outputs(vec![op_return(200)]));
+        assert!(matches!(validator.validate(&tx_hex).await, Err(ValidationError::OutputPolicy { .. })));
+    }
+    
+    #[test]
+    fn test_fee_floor() {
+        // 61 vB paying 61 sat is exactly 1 sat/vB
+        let result = json!({"allowed": true, "vsize": 61, "fees": {"base": 0.00000061}});
+        assert!(mempool_accept_result(&result, Some(1.0)).is_ok());
+        assert!(matches!(
+            mempool_accept_result(&result, Some(2.0)),
+            Err(ValidationError::FeeRateTooLow { min_fee_rate, .. }) if min_fee_rate == 2.0
+        ));
+        assert!(mempool_accept_result(&result, None).is_ok());
+        
+        // Older nodes don't report fees, so the floor can't be checked
+        assert!(mempool_accept_result(&json!({"allowed": true}), Some(2.0)).is_ok());
+    }
+    
+    /// Validator running `stages` in order against a node answering `result`
+    async fn staged_validator(stages: Vec<ValidationStage>, result: Value) -> TransactionValidator {
+        let port = spawn_mock_rpc(json!({"result": [result], "error": null, "id": "validation"})).await;
+        TransactionValidator::new(ValidationConfig {
+            max_outputs: Some(10),
+            min_fee_rate: Some(5.0),
+            stages,
+            ..Default::default()
+        }, port)
+    }
+    
+    #[tokio::test]
+    async fn test_reordered_stages_fail_at_first_configured_failure() {
+        use ValidationStage::*;
+        let too_many_outputs = bitcoin::consensus::encode::serialize_hex(&tx_with_counts(1, 11));
+        let cheap = json!({"allowed": true, "vsize": 100, "fees": {"base": 0.00000100}});
+        let rejected = json!({"allowed": false, "reject-reason": "bad-txns-inputs-missingorspent"});
+        
+        // Both the fee floor and the output limit fail; whichever stage comes first decides
+        let fee_first = staged_validator(vec![Fee, Policy, Structure], cheap.clone()).await;
+        assert!(matches!(fee_first.validate(&too_many_outputs).await, Err(ValidationError::FeeRateTooLow { .. })));
+        let policy_first = staged_validator(vec![Structure, Policy, Fee], cheap).await;
+        assert!(matches!(policy_first.validate(&too_many_outputs).await, Err(ValidationError::TooManyOutputs { .. })));
+        
+        let node_first = staged_validator(vec![NodeAccept, Policy], rejected.clone()).await;
+        assert!(matches!(node_first.validate(&too_many_outputs).await, Err(ValidationError::BitcoinCoreRejection { .. })));
+        let policy_first = staged_validator(vec![Policy, NodeAccept], rejected).await;
+        assert!(matches!(policy_first.validate(&too_many_outputs).await, Err(ValidationError::TooManyOutputs { .. })));
+        
+        // Short hex fails both the precheck and decoding
+        let short = "00".repeat(30);
+        let structure_first = staged_validator(vec![Structure, Precheck], json!({"allowed": true})).await;
+        assert!(matches!(structure_first.validate(&short).await, Err(ValidationError::InvalidStructure)));
+        let precheck_first = staged_validator(vec![Precheck, Structure], json!({"allowed": true})).await;
+        assert!(matches!(precheck_first.validate(&short).await, Err(ValidationError::InvalidSize { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_validate_batch_follows_configured_stages() {
+        use ValidationStage::*;
+        let too_many_outputs = bitcoin::consensus::encode::serialize_hex(&tx_with_counts(1, 11));
+        let cheap = json!({"allowed": true, "vsize": 100, "fees": {"base": 0.00000100}});
+        
+        // Left-out stages are skipped for packages too
+        let node_only = staged_validator(vec![NodeAccept], cheap.clone()).await;
+        assert!(node_only.validate_batch(&[too_many_outputs.as_str()]).await[0].is_ok());
+        assert!(node_only.validate_batch(&[too_many_outputs.as_str()]).await[0].is_ok());
+        
+        let with_policy = staged_validator(vec![Structure, Policy, NodeAccept], cheap.clone()).await;
+        assert!(matches!(with_policy.validate_batch(&[too_many_outputs.as_str()]).await[0], Err(ValidationError::TooManyOutputs { .. })));
+        
+        let with_fee = staged_validator(vec![Structure, Cache, NodeAccept, Fee], cheap).await;
+        assert!(matches!(with_fee.validate_batch(&[too_many_outputs.as_str()]).await[0], Err(ValidationError::FeeRateTooLow { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_stages_left_out_are_skipped() {
+        use ValidationStage::*;
+        let validator = staged_validator(vec![Precheck, NodeAccept], json!({"allowed": true})).await;
+        let tx_hex = bitcoin::consensus::encode::serialize_hex(&tx_with_counts(1, 11));
+        
+        let report = validator.validate_detailed(&tx_hex).await.unwrap();
+        assert_eq!(report.checks_run, vec![ValidationCheck::Precheck, ValidationCheck::BitcoinCore]);
+        
+        // Without a cache stage, a repeat is validated again rather than reported from cache
+        assert!(!validator.validate_detailed(&tx_hex).await.unwrap().from_cache);
+    }
+}